@@ -1,9 +1,12 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use clap::Parser;
-use securefabric_sdk::{crypto::Keypair, Client};
+use securefabric_sdk::bench::{self, BenchConfig};
+use securefabric_sdk::{crypto::Keypair, Client, Envelope};
 use std::fs;
 use std::path::PathBuf;
+use std::time::Duration;
+use tokio_stream::StreamExt;
 
 #[derive(Parser)]
 #[command(name = "securefabric-demo")]
@@ -14,14 +17,14 @@ struct Args {
     endpoint: String,
 
     /// Bearer token for authentication
-    #[arg(long)]
-    token: String,
+    #[arg(long, required_unless_present = "self_test")]
+    token: Option<String>,
 
     /// Topic to send/receive messages
     #[arg(long, default_value = "demo.messages")]
     topic: String,
 
-    /// Mode: send or subscribe
+    /// Mode: send, subscribe, tail or bench
     #[arg(long, default_value = "send")]
     mode: String,
 
@@ -32,14 +35,55 @@ struct Args {
     /// Path to Ed25519 private key file (32 bytes hex). If not provided, generates a new key.
     #[arg(long)]
     key_path: Option<PathBuf>,
+
+    /// Keep tailing when the stream drops, resubscribing where it left off (only for tail mode)
+    #[arg(long)]
+    follow: bool,
+
+    /// Skip messages with a lower sequence number (only for tail mode)
+    #[arg(long)]
+    from_seq: Option<u64>,
+
+    /// Benchmark the SDK against an in-process fabric instead of the endpoint (only for bench mode)
+    #[arg(long)]
+    self_test: bool,
+
+    /// Concurrent publishers (only for bench mode)
+    #[arg(long, default_value_t = 1)]
+    producers: usize,
+
+    /// Concurrent subscriptions (only for bench mode)
+    #[arg(long, default_value_t = 1)]
+    subscribers: usize,
+
+    /// Messages published by each producer (only for bench mode)
+    #[arg(long, default_value_t = 1000)]
+    messages: usize,
+
+    /// Payload size in bytes, at least 16 (only for bench mode)
+    #[arg(long, default_value_t = 256)]
+    size: usize,
 }
 
+/// Longest wait between resubscribe attempts in tail mode
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Characters of payload shown per line in tail mode
+const PREVIEW_CHARS: usize = 48;
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
     println!("SecureFabric Rust SDK Demo");
     println!("==========================");
+
+    if args.mode == "bench" && args.self_test {
+        println!("Benchmarking against an in-process fabric...");
+        let report = bench::self_test(&bench_config(&args)).await?;
+        println!("{report}");
+        return Ok(());
+    }
     println!("Endpoint: {}", args.endpoint);
     println!("Topic: {}", args.topic);
 
@@ -59,8 +103,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let mut client = Client::new(&args.endpoint)
         .await?
-        .with_signing_key(keypair.signing_key)
-        .with_bearer(&args.token);
+        .with_signing_key(keypair.signing_key);
+    if let Some(token) = &args.token {
+        client = client.with_bearer(token);
+    }
 
     match args.mode.as_str() {
         "send" => {
@@ -71,29 +117,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
         "subscribe" => {
             println!("Subscribing to topic: {}", args.topic);
-            let mut stream = client.subscribe(args.topic.as_bytes()).await?;
+            let mut stream = client.subscribe_text(args.topic.as_bytes()).await?;
 
             println!("Waiting for messages (Ctrl+C to exit)...");
             println!();
 
-            use tokio_stream::StreamExt;
             while let Some(envelope) = stream.next().await {
                 match envelope {
-                    Ok(env) => {
-                        let payload = String::from_utf8_lossy(&env.payload);
+                    Ok((env, payload)) => {
                         println!("📨 Received message:");
                         println!("  Topic: {}", env.topic);
                         println!("  Message ID: {}", env.msg_id);
                         println!("  Sequence: {}", env.seq);
                         println!("  Payload: {}", payload);
 
-                        // Verify signature
-                        if let Ok(valid) = client.verify(&env) {
-                            println!("  Signature valid: {}", valid);
-                        }
-
-                        // Verify message ID
-                        println!("  Message ID valid: {}", client.verify_msg_id(&env));
+                        // Check signature and message ID together
+                        println!("  Validation: {}", client.validate(&env));
                         println!();
                     }
                     Err(e) => {
@@ -102,11 +141,142 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
         }
+        "tail" => tail(&mut client, &args).await?,
+        "bench" => {
+            println!("Benchmarking topic: {}", args.topic);
+            let report = client.bench(&bench_config(&args)).await?;
+            println!("{report}");
+        }
         _ => {
-            eprintln!("Invalid mode: {}. Use 'send' or 'subscribe'", args.mode);
+            eprintln!(
+                "Invalid mode: {}. Use 'send', 'subscribe', 'tail' or 'bench'",
+                args.mode
+            );
             std::process::exit(1);
         }
     }
 
     Ok(())
 }
+
+/// Load described by the bench mode arguments
+fn bench_config(args: &Args) -> BenchConfig {
+    BenchConfig {
+        topic: args.topic.clone(),
+        producers: args.producers,
+        subscribers: args.subscribers,
+        messages: args.messages,
+        message_size: args.size,
+        ..Default::default()
+    }
+}
+
+/// Print one line per envelope until the stream ends, or forever with `--follow`
+///
+/// After a dropped stream the client resubscribes with its consumed offsets,
+/// so envelopes already printed are not printed again.
+async fn tail(client: &mut Client, args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Tailing topic: {} (Ctrl+C to exit)", args.topic);
+    println!();
+    println!(
+        "{:<24} {:>8}  {:<12} {:<14} PAYLOAD",
+        "TIME (UTC)", "SEQ", "MSG ID", "STATUS"
+    );
+
+    let mut backoff = Duration::from_secs(1);
+    loop {
+        match client.subscribe(args.topic.as_bytes()).await {
+            Ok(mut stream) => {
+                while let Some(item) = stream.next().await {
+                    match item {
+                        Ok(envelope) => {
+                            backoff = Duration::from_secs(1);
+                            if args.from_seq.is_some_and(|from| envelope.seq < from) {
+                                continue;
+                            }
+                            println!("{}", tail_line(client, &envelope));
+                        }
+                        Err(e) => {
+                            eprintln!("Stream error: {}", e.message());
+                            break;
+                        }
+                    }
+                }
+            }
+            Err(e) if args.follow => eprintln!("Subscribe failed: {e:#}"),
+            Err(e) => return Err(e.into()),
+        }
+
+        if !args.follow {
+            return Ok(());
+        }
+        eprintln!("Stream dropped, resubscribing in {}s...", backoff.as_secs());
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+        // Carry the consumed offsets over so the node's replay is skipped
+        client.restore_state(client.export_state())?;
+    }
+}
+
+fn tail_line(client: &Client, envelope: &Envelope) -> String {
+    format!(
+        "{:<24} {:>8}  {:<12} {:<14} {}",
+        format_timestamp(envelope.timestamp_ms),
+        envelope.seq,
+        envelope.msg_id.get(..12).unwrap_or(&envelope.msg_id),
+        client.validate(envelope).to_string(),
+        preview(&envelope.payload),
+    )
+}
+
+/// Render milliseconds since the Unix epoch as `YYYY-MM-DD HH:MM:SS.mmm`
+fn format_timestamp(timestamp_ms: u64) -> String {
+    if timestamp_ms == 0 {
+        return "-".to_string();
+    }
+    let secs = timestamp_ms / 1000;
+    let (hour, minute, second) = (secs / 3600 % 24, secs / 60 % 60, secs % 60);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {hour:02}:{minute:02}:{second:02}.{:03}",
+        timestamp_ms % 1000
+    )
+}
+
+/// First few characters of a text payload, or a hex prefix of a binary one
+fn preview(payload: &[u8]) -> String {
+    match std::str::from_utf8(payload) {
+        Ok(text) => {
+            let mut shown: String = text
+                .chars()
+                .take(PREVIEW_CHARS)
+                .flat_map(char::escape_default)
+                .collect();
+            if text.chars().count() > PREVIEW_CHARS {
+                shown.push('…');
+            }
+            format!("\"{shown}\"")
+        }
+        Err(_) => {
+            let shown = hex::encode(&payload[..payload.len().min(PREVIEW_CHARS / 2)]);
+            let more = if payload.len() > PREVIEW_CHARS / 2 {
+                "…"
+            } else {
+                ""
+            };
+            format!("0x{shown}{more} ({} bytes)", payload.len())
+        }
+    }
+}
@@ -5,9 +5,79 @@ use securefabric_core::aead::{decrypt_chacha_with_aad, encrypt_chacha_with_aad};
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
+/// Largest AAD accepted by `encrypt` and `decrypt`, matching the Rust SDK's `crypto::MAX_AAD_LEN`
+pub const MAX_AAD_LEN: usize = 64 * 1024;
+
+/// Reject AAD over `max` bytes with an error starting `AadTooLarge`
+///
+/// The prefix is stable so JavaScript callers can tell this failure apart.
+pub fn check_aad_len(aad: &[u8], max: usize) -> Result<(), String> {
+    if aad.len() > max {
+        return Err(format!(
+            "AadTooLarge: AAD of {} bytes exceeds limit of {max} bytes",
+            aad.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Largest plaintext accepted by `encrypt` and `decrypt`
+///
+/// Larger data should go through `WasmSealStream` in chunks rather than one
+/// buffer.
+pub const MAX_PAYLOAD_LEN: usize = 16 * 1024 * 1024;
+
+/// Bytes the Poly1305 tag adds to a ciphertext
+pub const TAG_LEN: usize = 16;
+
+/// Reject a payload over `max` bytes with an error starting `PayloadTooLarge`
+///
+/// `what` names the payload in the message. The prefix is stable so
+/// JavaScript callers can tell this failure apart.
+pub fn check_payload_len(what: &str, len: usize, max: usize) -> Result<(), String> {
+    if len > max {
+        return Err(format!(
+            "PayloadTooLarge: {what} of {len} bytes exceeds limit of {max} bytes; \
+             encrypt larger data in chunks with WasmSealStream"
+        ));
+    }
+    Ok(())
+}
+
 #[cfg(feature = "wasm")]
 #[wasm_bindgen]
 pub fn encrypt(key: &[u8], nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, JsValue> {
+    encrypt_with_limits(key, nonce, aad, plaintext, MAX_AAD_LEN, MAX_PAYLOAD_LEN)
+}
+
+/// Like `encrypt`, accepting up to `max_aad_len` bytes of AAD
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = encryptWithMaxAad)]
+pub fn encrypt_with_max_aad(
+    key: &[u8],
+    nonce: &[u8],
+    aad: &[u8],
+    plaintext: &[u8],
+    max_aad_len: usize,
+) -> Result<Vec<u8>, JsValue> {
+    encrypt_with_limits(key, nonce, aad, plaintext, max_aad_len, MAX_PAYLOAD_LEN)
+}
+
+/// Like `encrypt`, accepting up to `max_aad_len` bytes of AAD and
+/// `max_payload_len` bytes of plaintext
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = encryptWithLimits)]
+pub fn encrypt_with_limits(
+    key: &[u8],
+    nonce: &[u8],
+    aad: &[u8],
+    plaintext: &[u8],
+    max_aad_len: usize,
+    max_payload_len: usize,
+) -> Result<Vec<u8>, JsValue> {
+    check_aad_len(aad, max_aad_len).map_err(|e| JsValue::from_str(&e))?;
+    check_payload_len("plaintext", plaintext.len(), max_payload_len)
+        .map_err(|e| JsValue::from_str(&e))?;
     if key.len() != 32 {
         return Err(JsValue::from_str("key must be32 bytes"));
     }
@@ -31,6 +101,44 @@ pub fn decrypt(
     aad: &[u8],
     ciphertext: &[u8],
 ) -> Result<Vec<u8>, JsValue> {
+    decrypt_with_limits(key, nonce, aad, ciphertext, MAX_AAD_LEN, MAX_PAYLOAD_LEN)
+}
+
+/// Like `decrypt`, accepting up to `max_aad_len` bytes of AAD
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = decryptWithMaxAad)]
+pub fn decrypt_with_max_aad(
+    key: &[u8],
+    nonce: &[u8],
+    aad: &[u8],
+    ciphertext: &[u8],
+    max_aad_len: usize,
+) -> Result<Vec<u8>, JsValue> {
+    decrypt_with_limits(key, nonce, aad, ciphertext, max_aad_len, MAX_PAYLOAD_LEN)
+}
+
+/// Like `decrypt`, accepting up to `max_aad_len` bytes of AAD and the
+/// ciphertext of up to `max_payload_len` bytes of plaintext
+///
+/// The ciphertext may be [`TAG_LEN`] bytes longer than `max_payload_len`, so
+/// whatever `encrypt_with_limits` produced under the same limit decrypts.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = decryptWithLimits)]
+pub fn decrypt_with_limits(
+    key: &[u8],
+    nonce: &[u8],
+    aad: &[u8],
+    ciphertext: &[u8],
+    max_aad_len: usize,
+    max_payload_len: usize,
+) -> Result<Vec<u8>, JsValue> {
+    check_aad_len(aad, max_aad_len).map_err(|e| JsValue::from_str(&e))?;
+    check_payload_len(
+        "ciphertext",
+        ciphertext.len(),
+        max_payload_len.saturating_add(TAG_LEN),
+    )
+    .map_err(|e| JsValue::from_str(&e))?;
     if key.len() != 32 {
         return Err(JsValue::from_str("key must be32 bytes"));
     }
@@ -66,3 +174,74 @@ pub fn decrypt(
 ) -> Result<Vec<u8>, String> {
     Err("wasm feature not enabled".to_string())
 }
+
+/// Plaintext bytes per sealed segment, matching the Rust SDK's `crypto::stream`
+#[cfg(feature = "wasm")]
+const SEGMENT_LEN: usize = 64 * 1024;
+#[cfg(feature = "wasm")]
+const NONCE_PREFIX_LEN: usize = 19;
+
+/// Incremental XChaCha20-Poly1305 encryptor for large uploads
+///
+/// Produces the same layout as `SealStream` in the Rust SDK, so the
+/// concatenated output opens with `crypto::stream::open_stream`. Bytes are
+/// released in whole segments; `finalize` must be called to get the last
+/// segment, which is flagged so a truncated upload fails to decrypt.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub struct WasmSealStream {
+    encryptor: chacha20poly1305::aead::stream::EncryptorBE32<chacha20poly1305::XChaCha20Poly1305>,
+    header: Option<[u8; NONCE_PREFIX_LEN]>,
+    pending: Vec<u8>,
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl WasmSealStream {
+    #[wasm_bindgen(constructor)]
+    pub fn new(key: &[u8]) -> Result<WasmSealStream, JsValue> {
+        use chacha20poly1305::aead::KeyInit;
+        if key.len() != 32 {
+            return Err(JsValue::from_str("key must be 32 bytes"));
+        }
+        let mut prefix = [0u8; NONCE_PREFIX_LEN];
+        getrandom::getrandom(&mut prefix).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let cipher = chacha20poly1305::XChaCha20Poly1305::new(key.into());
+        Ok(WasmSealStream {
+            encryptor: chacha20poly1305::aead::stream::EncryptorBE32::from_aead(
+                cipher,
+                prefix.as_slice().into(),
+            ),
+            header: Some(prefix),
+            pending: Vec::new(),
+        })
+    }
+
+    /// Add `chunk`, returning the sealed bytes that are ready (possibly none)
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<u8>, JsValue> {
+        let mut output = self.header.take().map(Vec::from).unwrap_or_default();
+        self.pending.extend_from_slice(chunk);
+        let mut sealed = 0;
+        while self.pending.len() - sealed > SEGMENT_LEN {
+            let ciphertext = self
+                .encryptor
+                .encrypt_next(&self.pending[sealed..sealed + SEGMENT_LEN])
+                .map_err(|_| JsValue::from_str("encryption failed"))?;
+            output.extend_from_slice(&ciphertext);
+            sealed += SEGMENT_LEN;
+        }
+        self.pending.drain(..sealed);
+        Ok(output)
+    }
+
+    /// Seal the remaining input as the last segment; the stream cannot be used after
+    pub fn finalize(mut self) -> Result<Vec<u8>, JsValue> {
+        let mut output = self.header.take().map(Vec::from).unwrap_or_default();
+        let last = self
+            .encryptor
+            .encrypt_last(self.pending.as_slice())
+            .map_err(|_| JsValue::from_str("encryption failed"))?;
+        output.extend_from_slice(&last);
+        Ok(output)
+    }
+}
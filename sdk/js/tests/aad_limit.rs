@@ -0,0 +1,49 @@
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+use securefabric_js::{decrypt, decrypt_with_max_aad, encrypt, encrypt_with_max_aad, MAX_AAD_LEN};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const KEY: [u8; 32] = [7; 32];
+const NONCE: [u8; 12] = [1; 12];
+
+fn is_aad_too_large(error: wasm_bindgen::JsValue) -> bool {
+    error
+        .as_string()
+        .is_some_and(|message| message.starts_with("AadTooLarge"))
+}
+
+#[wasm_bindgen_test]
+fn accepts_aad_at_limit_and_rejects_one_more() {
+    let at_limit = vec![b'a'; MAX_AAD_LEN];
+    let over = vec![b'a'; MAX_AAD_LEN + 1];
+
+    let ciphertext = encrypt(&KEY, &NONCE, &at_limit, b"payload").unwrap();
+    assert_eq!(
+        decrypt(&KEY, &NONCE, &at_limit, &ciphertext).unwrap(),
+        b"payload"
+    );
+    assert!(is_aad_too_large(
+        encrypt(&KEY, &NONCE, &over, b"payload").unwrap_err()
+    ));
+    assert!(is_aad_too_large(
+        decrypt(&KEY, &NONCE, &over, &ciphertext).unwrap_err()
+    ));
+}
+
+#[wasm_bindgen_test]
+fn limit_is_configurable() {
+    let aad = vec![b'a'; 64];
+    let ciphertext = encrypt_with_max_aad(&KEY, &NONCE, &aad, b"payload", 64).unwrap();
+    assert_eq!(
+        decrypt_with_max_aad(&KEY, &NONCE, &aad, &ciphertext, 64).unwrap(),
+        b"payload"
+    );
+    assert!(is_aad_too_large(
+        encrypt_with_max_aad(&KEY, &NONCE, &aad, b"payload", 63).unwrap_err()
+    ));
+    assert!(is_aad_too_large(
+        decrypt_with_max_aad(&KEY, &NONCE, &aad, &ciphertext, 63).unwrap_err()
+    ));
+}
@@ -0,0 +1,56 @@
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+use securefabric_js::{
+    decrypt, decrypt_with_limits, encrypt, encrypt_with_limits, MAX_AAD_LEN, MAX_PAYLOAD_LEN,
+    TAG_LEN,
+};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const KEY: [u8; 32] = [7; 32];
+const NONCE: [u8; 12] = [1; 12];
+
+fn is_payload_too_large(error: wasm_bindgen::JsValue) -> bool {
+    error
+        .as_string()
+        .is_some_and(|message| message.starts_with("PayloadTooLarge"))
+}
+
+#[wasm_bindgen_test]
+fn accepts_payload_at_limit_and_rejects_one_more() {
+    let at_limit = vec![b'p'; MAX_PAYLOAD_LEN];
+    let over = vec![b'p'; MAX_PAYLOAD_LEN + 1];
+
+    let ciphertext = encrypt(&KEY, &NONCE, b"aad", &at_limit).unwrap();
+    assert_eq!(ciphertext.len(), MAX_PAYLOAD_LEN + TAG_LEN);
+    assert_eq!(
+        decrypt(&KEY, &NONCE, b"aad", &ciphertext).unwrap(),
+        at_limit
+    );
+    assert!(is_payload_too_large(
+        encrypt(&KEY, &NONCE, b"aad", &over).unwrap_err()
+    ));
+
+    let mut too_long = ciphertext;
+    too_long.push(0);
+    assert!(is_payload_too_large(
+        decrypt(&KEY, &NONCE, b"aad", &too_long).unwrap_err()
+    ));
+}
+
+#[wasm_bindgen_test]
+fn limit_is_configurable() {
+    let payload = vec![b'p'; 64];
+    let ciphertext = encrypt_with_limits(&KEY, &NONCE, b"aad", &payload, MAX_AAD_LEN, 64).unwrap();
+    assert_eq!(
+        decrypt_with_limits(&KEY, &NONCE, b"aad", &ciphertext, MAX_AAD_LEN, 64).unwrap(),
+        payload
+    );
+    assert!(is_payload_too_large(
+        encrypt_with_limits(&KEY, &NONCE, b"aad", &payload, MAX_AAD_LEN, 63).unwrap_err()
+    ));
+    assert!(is_payload_too_large(
+        decrypt_with_limits(&KEY, &NONCE, b"aad", &ciphertext, MAX_AAD_LEN, 63).unwrap_err()
+    ));
+}
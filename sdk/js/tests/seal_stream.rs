@@ -0,0 +1,43 @@
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+use chacha20poly1305::aead::stream::DecryptorBE32;
+use chacha20poly1305::aead::KeyInit;
+use chacha20poly1305::XChaCha20Poly1305;
+use securefabric_js::WasmSealStream;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const KEY: [u8; 32] = [7; 32];
+const SEGMENT: usize = 64 * 1024 + 16;
+
+/// Opens the concatenated stream the way the Rust SDK's `open_stream` does
+fn open(sealed: &[u8]) -> Option<Vec<u8>> {
+    let (prefix, mut rest) = sealed.split_at(19);
+    let cipher = XChaCha20Poly1305::new(KEY.as_slice().into());
+    let mut decryptor = DecryptorBE32::from_aead(cipher, prefix.into());
+    let mut plaintext = Vec::new();
+    while rest.len() > SEGMENT {
+        let (segment, tail) = rest.split_at(SEGMENT);
+        plaintext.extend(decryptor.decrypt_next(segment).ok()?);
+        rest = tail;
+    }
+    plaintext.extend(decryptor.decrypt_last(rest).ok()?);
+    Some(plaintext)
+}
+
+#[wasm_bindgen_test]
+fn chunks_decrypt_as_concatenation() {
+    let plaintext: Vec<u8> = (0..200_000).map(|i| i as u8).collect();
+    let mut stream = WasmSealStream::new(&KEY).unwrap();
+    let mut sealed = Vec::new();
+    for chunk in plaintext.chunks(7_000) {
+        sealed.extend(stream.push(chunk).unwrap());
+    }
+    sealed.extend(stream.finalize().unwrap());
+    assert_eq!(open(&sealed).unwrap(), plaintext);
+
+    // Without the final segment the stream does not open
+    let cut = 19 + 3 * SEGMENT;
+    assert!(open(&sealed[..cut]).is_none());
+}
@@ -0,0 +1,49 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! One-shot encryption against a reused `AeadContext`
+//!
+//! Run with `cargo bench --bench aead`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use securefabric_sdk::crypto::{self, AeadContext};
+use std::hint::black_box;
+
+const KEY: [u8; crypto::KEY_LEN] = [7u8; crypto::KEY_LEN];
+const NONCE: [u8; crypto::NONCE_LEN] = [3u8; crypto::NONCE_LEN];
+const AAD: &[u8] = br#"{"key_version":1,"topic":"bench","ts":0}"#;
+
+fn seal(c: &mut Criterion) {
+    let mut group = c.benchmark_group("seal");
+    let context = AeadContext::new(&KEY);
+    for len in [64, 1024, 64 * 1024] {
+        let plaintext = vec![0x5a; len];
+        group.throughput(Throughput::Bytes(len as u64));
+        group.bench_with_input(BenchmarkId::new("one-shot", len), &plaintext, |b, pt| {
+            b.iter(|| crypto::encrypt(black_box(&KEY), &NONCE, black_box(pt), AAD).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("context", len), &plaintext, |b, pt| {
+            b.iter(|| context.seal(&NONCE, AAD, black_box(pt)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn open(c: &mut Criterion) {
+    let mut group = c.benchmark_group("open");
+    let context = AeadContext::new(&KEY);
+    for len in [64, 1024, 64 * 1024] {
+        let (ciphertext, tag) = crypto::encrypt(&KEY, &NONCE, &vec![0x5a; len], AAD).unwrap();
+        let sealed = [ciphertext.as_slice(), &tag].concat();
+        group.throughput(Throughput::Bytes(len as u64));
+        group.bench_with_input(BenchmarkId::new("one-shot", len), &len, |b, _| {
+            b.iter(|| crypto::decrypt(black_box(&KEY), &NONCE, &ciphertext, AAD, &tag).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("context", len), &sealed, |b, sealed| {
+            b.iter(|| context.open(&NONCE, AAD, black_box(sealed)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, seal, open);
+criterion_main!(benches);
@@ -3,8 +3,9 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path().unwrap());
 
+    // Server stubs are generated too so tests can stand up a mock node
     tonic_build::configure()
-        .build_server(false)
+        .build_server(true)
         .compile_protos(&["../../specs/securefabric.proto"], &["../../specs"])?;
     Ok(())
 }
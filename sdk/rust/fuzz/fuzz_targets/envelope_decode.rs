@@ -0,0 +1,20 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Fuzz the hardened envelope decoder used on the subscribe path.
+//!
+//! Run with `cargo +nightly fuzz run envelope_decode` from `sdk/rust`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use prost::Message;
+use securefabric_sdk::pb::Envelope;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(envelope) = Envelope::try_from_bytes(data) {
+        // Anything we accept must survive a re-encode unchanged
+        let encoded = envelope.encode_to_vec();
+        let decoded = Envelope::try_from_bytes(&encoded).expect("re-decode accepted envelope");
+        assert_eq!(envelope, decoded);
+    }
+});
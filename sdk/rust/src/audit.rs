@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Audit records for received envelopes
+//!
+//! [`Client::validate`] answers whether an envelope can be trusted;
+//! [`Client::audit`] also records who sent it and when, in one structured
+//! value that can be logged or serialized into an audit trail.
+
+use crate::pb::Envelope;
+use crate::Client;
+use serde::Serialize;
+use std::fmt;
+
+/// Everything known about an envelope after running every configured check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuditRecord {
+    pub msg_id: String,
+    pub topic: String,
+    /// [Fingerprint](crate::keyring::fingerprint) of the key that claims to have signed
+    pub sender: String,
+    pub seq: u64,
+    /// Sender's send time in milliseconds since the Unix epoch, if stamped
+    pub timestamp_ms: Option<u64>,
+    /// The signature verifies against the sender's key
+    pub signature: bool,
+    /// Why the signature could not be checked at all, e.g. an unknown sender
+    pub signature_error: Option<String>,
+    /// The msg_id matches the envelope's pubkey, seq and nonce
+    pub msg_id_valid: bool,
+    /// Older than [`Client::with_max_envelope_age`] allows
+    pub expired: bool,
+    /// Already seen within [`Client::with_replay_window`]
+    pub replayed: bool,
+}
+
+impl AuditRecord {
+    /// Whether every check passed
+    pub fn is_valid(&self) -> bool {
+        self.signature && self.msg_id_valid && !self.expired && !self.replayed
+    }
+}
+
+impl fmt::Display for AuditRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "msg_id={} topic={} sender={} seq={}",
+            self.msg_id, self.topic, self.sender, self.seq
+        )?;
+        if let Some(timestamp_ms) = self.timestamp_ms {
+            write!(f, " ts={timestamp_ms}")?;
+        }
+        let failed: Vec<&str> = [
+            (!self.signature, "bad signature"),
+            (!self.msg_id_valid, "bad msg_id"),
+            (self.expired, "expired"),
+            (self.replayed, "replayed"),
+        ]
+        .into_iter()
+        .filter_map(|(failed, name)| failed.then_some(name))
+        .collect();
+        if failed.is_empty() {
+            f.write_str(": valid")
+        } else {
+            write!(f, ": {}", failed.join(", "))?;
+            match &self.signature_error {
+                Some(error) => write!(f, " ({error})"),
+                None => Ok(()),
+            }
+        }
+    }
+}
+
+impl Client {
+    /// Run every configured check on an envelope and record its provenance
+    ///
+    /// Runs the same checks as [`Client::validate`], with the same effect on
+    /// the replay window.
+    pub fn audit(&self, envelope: &Envelope) -> AuditRecord {
+        let verified = self.verify(envelope);
+        let signature_error = verified.as_ref().err().map(|error| format!("{error:#}"));
+        let report = self.validate_with(envelope, verified);
+        AuditRecord {
+            msg_id: envelope.msg_id.clone(),
+            topic: envelope.topic.clone(),
+            sender: crate::keyring::fingerprint(&envelope.pubkey),
+            seq: envelope.seq,
+            timestamp_ms: (envelope.timestamp_ms != 0).then_some(envelope.timestamp_ms),
+            signature: report.signature,
+            signature_error,
+            msg_id_valid: report.msg_id,
+            expired: report.expired,
+            replayed: report.replayed,
+        }
+    }
+}
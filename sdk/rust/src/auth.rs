@@ -0,0 +1,137 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Bearer tokens read from a file
+//!
+//! Kubernetes and similar platforms mount a service account token into the
+//! container and replace it periodically. [`Client::with_bearer_file`] keeps
+//! the bearer in sync with such a file: before each request the file's
+//! metadata is checked and the token is re-read when it has changed.
+
+use crate::Client;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tonic::metadata::AsciiMetadataValue;
+
+/// Where Kubernetes mounts a pod's service account token
+pub const K8S_SERVICE_ACCOUNT_TOKEN: &str = "/var/run/secrets/kubernetes.io/serviceaccount/token";
+
+/// Source of the bearer token attached to every request
+#[derive(Clone, Debug)]
+pub(crate) enum Bearer {
+    Static(String),
+    File(Arc<TokenFile>),
+}
+
+impl Bearer {
+    /// `authorization` header to send now, refreshing a file-backed token if it was rotated
+    ///
+    /// Fails for a static token that cannot be sent as a header value.
+    pub(crate) fn header(&self) -> Result<AsciiMetadataValue> {
+        match self {
+            Self::Static(token) => header_value(token),
+            Self::File(file) => Ok(file.current()),
+        }
+    }
+}
+
+/// `Bearer <token>` as a header value
+///
+/// Fails if the token holds anything but visible ASCII and spaces, such as
+/// a newline. The error does not include the token.
+fn header_value(token: &str) -> Result<AsciiMetadataValue> {
+    format!("Bearer {token}")
+        .parse()
+        .ok()
+        .context("bearer token is not a valid header value")
+}
+
+/// Token file with the last token read from it
+#[derive(Debug)]
+pub(crate) struct TokenFile {
+    path: PathBuf,
+    cached: Mutex<CachedToken>,
+}
+
+#[derive(Debug)]
+struct CachedToken {
+    version: FileVersion,
+    header: AsciiMetadataValue,
+}
+
+/// Modification time and length, used to notice a rewritten file
+type FileVersion = (Option<SystemTime>, u64);
+
+impl TokenFile {
+    fn open(path: PathBuf) -> Result<Self> {
+        let (version, header) = read_token(&path)?;
+        Ok(Self {
+            path,
+            cached: Mutex::new(CachedToken { version, header }),
+        })
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Current token header, re-read if the file changed since the last read
+    ///
+    /// If the file is briefly missing mid-rotation, the last token is kept.
+    /// So it is if the new contents are unreadable, empty or not a valid
+    /// header value; that is logged once per change of the file.
+    fn current(&self) -> AsciiMetadataValue {
+        let mut cached = self.cached.lock().unwrap();
+        let changed = std::fs::metadata(&self.path)
+            .map(|metadata| version_of(&metadata))
+            .ok()
+            .filter(|version| *version != cached.version);
+        if let Some(changed) = changed {
+            match read_token(&self.path) {
+                Ok((version, header)) => *cached = CachedToken { version, header },
+                Err(error) => {
+                    tracing::warn!("keeping the previous bearer token: {error:#}");
+                    cached.version = changed;
+                }
+            }
+        }
+        cached.header.clone()
+    }
+}
+
+fn version_of(metadata: &std::fs::Metadata) -> FileVersion {
+    (metadata.modified().ok(), metadata.len())
+}
+
+fn read_token(path: &Path) -> Result<(FileVersion, AsciiMetadataValue)> {
+    let read = || -> Result<_> {
+        let metadata = std::fs::metadata(path)?;
+        let token = std::fs::read_to_string(path)?;
+        let token = token.trim();
+        anyhow::ensure!(!token.is_empty(), "file is empty");
+        Ok((version_of(&metadata), header_value(token)?))
+    };
+    read().with_context(|| format!("read bearer token from {}", path.display()))
+}
+
+impl Client {
+    /// Authenticate with the pod's Kubernetes service account token
+    ///
+    /// Reads [`K8S_SERVICE_ACCOUNT_TOKEN`]; see [`Client::with_bearer_file`].
+    pub fn with_k8s_service_account(self) -> Result<Self> {
+        self.with_bearer_file(K8S_SERVICE_ACCOUNT_TOKEN)
+    }
+
+    /// Authenticate with a bearer token read from `path`, following rotations
+    ///
+    /// Fails if the file is missing, unreadable, empty or holds a token that
+    /// cannot be sent as a header value, such as one with a newline inside.
+    /// Afterwards a rewritten file is picked up by the next request. Replaces any token set with
+    /// [`Client::with_bearer`].
+    pub fn with_bearer_file(mut self, path: impl Into<PathBuf>) -> Result<Self> {
+        let file = TokenFile::open(path.into())?;
+        self.bearer = Some(Bearer::File(Arc::new(file)));
+        Ok(self)
+    }
+}
@@ -0,0 +1,155 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Background sends and deliveries
+//!
+//! [`Client::send_async`] hands a message to a background task and returns a
+//! [`SendHandle`] at once, so callers such as UI threads can continue and
+//! collect the result later, or never. [`Client::pipe_to`] is the receiving
+//! counterpart: a background task pushes a topic's envelopes into a channel
+//! the application already reads from.
+
+use crate::pb::Envelope;
+use crate::{Client, Error, Outgoing};
+use anyhow::{Context as _, Result};
+use futures::StreamExt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Result of a send running in the background, from [`Client::send_async`]
+///
+/// Resolves to the message ID, or the error the send failed with. Dropping
+/// the handle does not cancel the send.
+#[derive(Debug)]
+pub struct SendHandle {
+    task: JoinHandle<Result<String>>,
+}
+
+impl SendHandle {
+    /// Whether the send has finished, successfully or not
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+impl Future for SendHandle {
+    type Output = Result<String>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.task)
+            .poll(cx)
+            .map(|joined| joined.context("background send panicked")?)
+    }
+}
+
+/// Forwarding task started by [`Client::pipe_to`]
+///
+/// Resolves when forwarding stops: to the number of envelopes forwarded if
+/// the subscription ended or the receiver was dropped, or to the error that
+/// ended the subscription. Dropping the handle leaves the task running.
+#[derive(Debug)]
+pub struct PipeHandle {
+    task: JoinHandle<Result<u64>>,
+}
+
+impl PipeHandle {
+    /// Whether forwarding has stopped
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Stop forwarding and close the subscription
+    pub fn abort(&self) {
+        self.task.abort();
+    }
+}
+
+impl Future for PipeHandle {
+    type Output = Result<u64>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.task)
+            .poll(cx)
+            .map(|joined| joined.context("pipe task failed")?)
+    }
+}
+
+impl Client {
+    /// Send a message in the background, returning a handle to its result
+    ///
+    /// The message is signed and delivered by a spawned task with the same
+    /// retries, ordering and metrics as [`Client::send_to`]; an empty `to`
+    /// broadcasts. With a [send queue](Client::with_send_queue), the message
+    /// takes its slot before this returns, so a full queue applies
+    /// backpressure here according to its [`OverflowPolicy`](crate::OverflowPolicy):
+    /// `Block` waits for a slot and `RejectNew` fails with
+    /// [`Error::QueueFull`]. Otherwise it returns at
+    /// once. Background sends may reach the node in any order relative to
+    /// each other; await each handle before the next send when order matters.
+    ///
+    /// Must be called within a Tokio runtime.
+    pub async fn send_async(&self, topic: &str, to: &[u8], payload: &[u8]) -> Result<SendHandle> {
+        let in_flight = self.lifecycle.begin_send()?;
+        let slot = in_flight.run(self.admit()).await?;
+        let mut client = self.clone();
+        let (topic, to, payload) = (topic.to_string(), to.to_vec(), payload.to_vec());
+        let task = tokio::spawn(async move {
+            let outgoing = Outgoing {
+                to: &to,
+                ..Default::default()
+            };
+            in_flight
+                .run(client.send_admitted(slot, &topic, outgoing, &payload))
+                .await
+        });
+        Ok(SendHandle { task })
+    }
+}
+
+impl Client {
+    /// Forward the verified envelopes of `topic` into `sender` from a background task
+    ///
+    /// Envelopes are verified as in [`Client::run`]; those that fail are
+    /// dropped. The task waits for room in the channel, so a slow receiver
+    /// slows the subscription rather than buffering it, and stops as soon as
+    /// the receiver is dropped, even while no envelopes arrive. Failing to
+    /// subscribe is reported here; errors after that end the task and are
+    /// reported through the returned [`PipeHandle`].
+    ///
+    /// Must be called within a Tokio runtime.
+    pub async fn pipe_to(
+        &mut self,
+        topic: &[u8],
+        sender: mpsc::Sender<Envelope>,
+    ) -> Result<PipeHandle> {
+        let mut stream = self.subscribe_verifying(topic).await?;
+        let client = self.clone();
+        let task = tokio::spawn(async move {
+            let mut forwarded = 0;
+            loop {
+                let item = tokio::select! {
+                    item = stream.next() => item,
+                    () = sender.closed() => break,
+                };
+                let Some(item) = item else {
+                    break;
+                };
+                let envelope = item.map_err(Error::from).context("subscription failed")?;
+                if !(client.verify(&envelope).unwrap_or(false) && client.verify_msg_id(&envelope)) {
+                    continue;
+                }
+                let Some(envelope) = client.post_receive.after_verification(envelope) else {
+                    continue;
+                };
+                if sender.send(envelope).await.is_err() {
+                    break;
+                }
+                forwarded += 1;
+            }
+            Ok(forwarded)
+        });
+        Ok(PipeHandle { task })
+    }
+}
@@ -0,0 +1,171 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Batch sends with per-message results
+//!
+//! [`Client::send_batch`] streams several envelopes over one `SendBatch` call.
+//! The node accepts or rejects each one independently, and the returned
+//! results line up with the input payloads so failures can be retried on their
+//! own with [`failures`].
+
+use crate::capabilities::{rpc_error, Feature};
+use crate::pb::{SendReq, SendResult};
+use crate::{Client, Error, Outgoing};
+use anyhow::{Context, Result};
+use prost::Message;
+use tonic::Code;
+
+/// Message ID of an accepted envelope, as returned by [`Client::send`]
+pub type MsgId = String;
+
+/// Why one message of a batch was not accepted
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum SendError {
+    /// The node rejected the envelope
+    #[error("rejected by node ({code:?}): {message}")]
+    Rejected { code: Code, message: String },
+
+    /// The node's response did not include a result for the envelope
+    #[error("no result returned for message")]
+    Missing,
+}
+
+/// Inputs whose batch result is an error, in their original order
+///
+/// Pass the returned items to another [`Client::send_batch`] call to retry only
+/// the messages that failed.
+pub fn failures<'a, T>(inputs: &'a [T], results: &[Result<MsgId, SendError>]) -> Vec<&'a T> {
+    inputs
+        .iter()
+        .zip(results)
+        .filter(|(_, result)| result.is_err())
+        .map(|(input, _)| input)
+        .collect()
+}
+
+impl Client {
+    /// Send several broadcast messages on `topic` in one client-streaming call
+    ///
+    /// Returns one result per payload, in input order. A rejected message fails
+    /// only its own entry; the outer error is reserved for failures of the
+    /// whole call, such as a missing signing key or a broken connection, after
+    /// which the caller cannot tell which messages were accepted.
+    ///
+    /// Every payload is assigned a sequence number, including ones the node
+    /// rejects. With ordered send enabled the whole batch takes one turn. The
+    /// batch is a single RPC and does not pass through the send queue, but
+    /// counts as one send for [`Client::with_circuit_breaker`], which fails it
+    /// with [`Error::CircuitOpen`] before any payload is sequenced, and gets one
+    /// send deadline under [`Client::with_adaptive_timeout`].
+    pub async fn send_batch(
+        &mut self,
+        topic: &str,
+        payloads: &[impl AsRef<[u8]>],
+    ) -> Result<Vec<Result<MsgId, SendError>>> {
+        self.require(Feature::SendBatch).await?;
+        let in_flight = self.lifecycle.begin_send()?;
+        in_flight.run(self.dispatch_batch(topic, payloads)).await
+    }
+
+    /// Sign, sequence and send a batch counted as in flight
+    async fn dispatch_batch(
+        &mut self,
+        topic: &str,
+        payloads: &[impl AsRef<[u8]>],
+    ) -> Result<Vec<Result<MsgId, SendError>>> {
+        // An open circuit fails the batch before it is signed or sequenced
+        let permit = match &self.circuit_breaker {
+            Some(breaker) => Some(breaker.admit()?),
+            None => None,
+        };
+        let outcome = self.dispatch_batch_admitted(topic, payloads).await;
+        if let Some(permit) = permit {
+            permit.finish(&outcome);
+        }
+        outcome
+    }
+
+    /// Sign, sequence and send a batch admitted by the circuit breaker
+    async fn dispatch_batch_admitted(
+        &mut self,
+        topic: &str,
+        payloads: &[impl AsRef<[u8]>],
+    ) -> Result<Vec<Result<MsgId, SendError>>> {
+        let _turn = match self.send_order.clone() {
+            Some(order) => Some(order.lock_owned().await),
+            None => None,
+        };
+        let envelopes = payloads
+            .iter()
+            .map(|payload| self.sign_envelope(topic, Outgoing::default(), payload.as_ref()))
+            .collect::<Result<Vec<_>>>()?;
+
+        let sent: Vec<(MsgId, Option<usize>)> = envelopes
+            .iter()
+            .map(|envelope| {
+                let len = self
+                    .instruments
+                    .size_histograms()
+                    .then(|| envelope.encoded_len());
+                (envelope.msg_id.clone(), len)
+            })
+            .collect();
+        let reqs: Vec<SendReq> = envelopes
+            .into_iter()
+            .map(|envelope| SendReq {
+                envelope: Some(envelope),
+            })
+            .collect();
+        let req = self.with_metadata(futures::stream::iter(reqs))?;
+
+        let deadline = self.send_timeout();
+        let start = std::time::Instant::now();
+        let call = self.inner.send_batch(req);
+        let response = match deadline {
+            Some(deadline) => match tokio::time::timeout(deadline, call).await {
+                Ok(response) => response,
+                Err(_) => {
+                    self.record_send_latency(deadline);
+                    return Err(Error::Timeout { after: deadline }.into());
+                }
+            },
+            None => call.await,
+        };
+        let response = response
+            .map_err(rpc_error(Feature::SendBatch))
+            .context("send batch")?
+            .into_inner();
+        self.record_send_latency(start.elapsed());
+
+        let mut results = response.results.into_iter();
+        let results = sent
+            .into_iter()
+            .map(|(msg_id, len)| {
+                let result = match results.next() {
+                    Some(result) => outcome(result, msg_id),
+                    None => Err(SendError::Missing),
+                };
+                if result.is_ok() {
+                    self.instruments.on_sent(len);
+                }
+                result
+            })
+            .collect();
+        Ok(results)
+    }
+}
+
+/// Map a node's per-message result, falling back to the local ID if none is echoed
+fn outcome(result: SendResult, msg_id: MsgId) -> Result<MsgId, SendError> {
+    if !result.ok {
+        return Err(SendError::Rejected {
+            code: Code::from_i32(result.code),
+            message: result.error,
+        });
+    }
+    if result.msg_id.is_empty() {
+        Ok(msg_id)
+    } else {
+        Ok(result.msg_id)
+    }
+}
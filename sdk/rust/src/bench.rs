@@ -0,0 +1,265 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Publish/subscribe load tests
+//!
+//! [`Client::bench`] measures the throughput and end-to-end latency this
+//! client achieves against its node: [`BenchConfig::producers`] tasks publish
+//! fixed-size messages on one topic while [`BenchConfig::subscribers`]
+//! subscriptions receive them. [`self_test`] runs the same load against an
+//! [`InMemoryFabric`], measuring the SDK itself without a node to start.
+//!
+//! Producers and subscribers run in this process, so latency is measured on
+//! one clock from just before a message is signed until a subscriber has
+//! received it, and decrypted it if a topic key is configured. Each payload
+//! starts with a random run id and its send time; subscribers skip envelopes
+//! from other runs, such as those a node replays from earlier ones.
+
+use crate::crypto::Keypair;
+use crate::fabric::InMemoryFabric;
+use crate::Client;
+use anyhow::{Context as _, Result};
+use std::fmt;
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+use tokio_stream::StreamExt;
+
+/// Bytes at the start of every payload carrying the run id and send time
+///
+/// [`BenchConfig::message_size`] must be at least this large.
+pub const HEADER_LEN: usize = 16;
+
+/// Load generated by [`Client::bench`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchConfig {
+    /// Topic published and subscribed on (default: `securefabric.bench`)
+    pub topic: String,
+    /// Concurrent publishing tasks (default: 1)
+    pub producers: usize,
+    /// Subscriptions receiving every message (default: 1)
+    pub subscribers: usize,
+    /// Messages published by each producer (default: 1000)
+    pub messages: usize,
+    /// Payload size in bytes (default: 256)
+    pub message_size: usize,
+    /// Longest the whole run may take before subscribers stop waiting (default: 30s)
+    pub timeout: Duration,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        Self {
+            topic: "securefabric.bench".to_string(),
+            producers: 1,
+            subscribers: 1,
+            messages: 1000,
+            message_size: 256,
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Percentiles (nearest rank) of the end-to-end latency of delivered messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Latency {
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl Latency {
+    fn from_samples(mut samples: Vec<Duration>) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort();
+        let rank = |percent: usize| samples[(samples.len() * percent).div_ceil(100) - 1];
+        Some(Self {
+            p50: rank(50),
+            p90: rank(90),
+            p99: rank(99),
+            max: samples[samples.len() - 1],
+        })
+    }
+}
+
+/// Outcome of a load test from [`Client::bench`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchReport {
+    /// Messages published
+    pub sent: u64,
+    /// Messages delivered, summed over subscribers
+    pub received: u64,
+    pub subscribers: usize,
+    pub message_size: usize,
+    /// From the first publish until every subscriber received every message,
+    /// or gave up at the timeout
+    pub elapsed: Duration,
+    /// `None` when nothing was delivered
+    pub latency: Option<Latency>,
+}
+
+impl BenchReport {
+    /// Messages published per second
+    pub fn messages_per_sec(&self) -> f64 {
+        self.sent as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// Payload bytes published per second
+    pub fn bytes_per_sec(&self) -> f64 {
+        self.messages_per_sec() * self.message_size as f64
+    }
+
+    /// Messages delivered per second, summed over subscribers
+    pub fn deliveries_per_sec(&self) -> f64 {
+        self.received as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// Whether every subscriber received every message
+    pub fn is_complete(&self) -> bool {
+        self.received == self.sent * self.subscribers as u64
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "sent {} x {} B in {:.3}s: {:.0} msg/s, {:.0} B/s",
+            self.sent,
+            self.message_size,
+            self.elapsed.as_secs_f64(),
+            self.messages_per_sec(),
+            self.bytes_per_sec()
+        )?;
+        write!(
+            f,
+            "received {} of {} by {} subscriber(s): {:.0} msg/s",
+            self.received,
+            self.sent * self.subscribers as u64,
+            self.subscribers,
+            self.deliveries_per_sec()
+        )?;
+        if let Some(latency) = &self.latency {
+            write!(
+                f,
+                "\nlatency p50 {:?}, p90 {:?}, p99 {:?}, max {:?}",
+                latency.p50, latency.p90, latency.p99, latency.max
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Run `config` against a fresh [`InMemoryFabric`] with a generated signing key
+///
+/// Needs no node, so it measures the SDK's own publish/subscribe path.
+pub async fn self_test(config: &BenchConfig) -> Result<BenchReport> {
+    let client = InMemoryFabric::new()
+        .client()
+        .await?
+        .with_signing_key(Keypair::generate().signing_key);
+    client.bench(config).await
+}
+
+impl Client {
+    /// Run a load test against the node and report what it achieved
+    ///
+    /// Producers and subscribers are clones of this client, with its signing
+    /// key, encryption and send settings. Subscribers are open before the
+    /// first message is published. A run whose subscribers miss messages
+    /// still reports once the timeout passes; see
+    /// [`BenchReport::is_complete`]. Fails if a publish fails.
+    pub async fn bench(&self, config: &BenchConfig) -> Result<BenchReport> {
+        anyhow::ensure!(
+            config.producers > 0 && config.messages > 0,
+            "Benchmark needs at least one producer and message"
+        );
+        anyhow::ensure!(
+            config.message_size >= HEADER_LEN,
+            "Benchmark messages must be at least {HEADER_LEN} bytes"
+        );
+        let mut run_id = [0u8; 8];
+        self.entropy.fill(&mut run_id);
+        let expected = (config.producers * config.messages) as u64;
+
+        let mut streams = Vec::with_capacity(config.subscribers);
+        for _ in 0..config.subscribers {
+            let mut client = self.clone();
+            let stream = client
+                .subscribe(config.topic.as_bytes())
+                .await
+                .context("open benchmark subscription")?;
+            streams.push((client, stream));
+        }
+
+        let start = Instant::now();
+        let deadline = tokio::time::Instant::from_std(start + config.timeout);
+        // Dropping a set aborts its tasks, so a failed publish stops the run
+        let mut receivers = JoinSet::new();
+        for (client, mut stream) in streams {
+            receivers.spawn(async move {
+                let mut latencies = Vec::new();
+                while (latencies.len() as u64) < expected {
+                    let Ok(Some(Ok(envelope))) =
+                        tokio::time::timeout_at(deadline, stream.next()).await
+                    else {
+                        break;
+                    };
+                    let payload = match envelope.key_version {
+                        0 => envelope.payload,
+                        _ => match client.decrypt(&envelope) {
+                            Ok(payload) => payload,
+                            Err(_) => continue,
+                        },
+                    };
+                    if payload.len() < HEADER_LEN || payload[..8] != run_id {
+                        continue;
+                    }
+                    let sent_at = u64::from_be_bytes(payload[8..16].try_into().unwrap());
+                    latencies.push(
+                        start
+                            .elapsed()
+                            .saturating_sub(Duration::from_nanos(sent_at)),
+                    );
+                }
+                latencies
+            });
+        }
+
+        let mut producers = JoinSet::new();
+        for _ in 0..config.producers {
+            let mut client = self.clone();
+            let topic = config.topic.clone();
+            let messages = config.messages;
+            let mut payload = vec![0u8; config.message_size];
+            payload[..8].copy_from_slice(&run_id);
+            producers.spawn(async move {
+                for _ in 0..messages {
+                    let sent_at = start.elapsed().as_nanos() as u64;
+                    payload[8..16].copy_from_slice(&sent_at.to_be_bytes());
+                    client.send(&topic, &payload).await?;
+                }
+                anyhow::Ok(())
+            });
+        }
+        while let Some(published) = producers.join_next().await {
+            published
+                .context("benchmark producer panicked")?
+                .context("benchmark publish failed")?;
+        }
+
+        let mut latencies = Vec::new();
+        while let Some(received) = receivers.join_next().await {
+            latencies.extend(received.context("benchmark subscriber panicked")?);
+        }
+        Ok(BenchReport {
+            sent: expected,
+            received: latencies.len() as u64,
+            subscribers: config.subscribers,
+            message_size: config.message_size,
+            elapsed: start.elapsed(),
+            latency: Latency::from_samples(latencies),
+        })
+    }
+}
@@ -0,0 +1,20 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Print the Rust SDK's conformance results for the test vectors on stdin
+//!
+//! This is the reference implementation of the command contract checked by
+//! `securefabric_sdk::conformance::cross`.
+
+use anyhow::{Context, Result};
+use std::io::Read;
+
+fn main() -> Result<()> {
+    let mut vectors = String::new();
+    std::io::stdin()
+        .read_to_string(&mut vectors)
+        .context("read test vectors from stdin")?;
+    let vectors = serde_json::from_str(&vectors).context("parse test vectors")?;
+    let results = securefabric_sdk::conformance::reference(&vectors)?;
+    println!("{}", serde_json::to_string_pretty(&results)?);
+    Ok(())
+}
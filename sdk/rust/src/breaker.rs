@@ -0,0 +1,191 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Circuit breaker around sends
+//!
+//! While a node is down, every send still waits out its timeout or retries
+//! before failing. [`Client::with_circuit_breaker`] stops that: after
+//! [`CbConfig::failure_threshold`] consecutive failed sends the circuit
+//! opens, and sends fail at once with [`Error::CircuitOpen`] for
+//! [`CbConfig::cooldown`]. The first send after the cooldown goes through as
+//! a trial (half-open): if it succeeds the circuit closes, if it fails the
+//! circuit opens for another cooldown.
+//!
+//! A send counts as failed when it times out or the node answers
+//! `UNAVAILABLE`, `DEADLINE_EXCEEDED`, `INTERNAL` or `UNKNOWN`, after any
+//! retries. Other errors, such as a rejected envelope, show the node is
+//! reachable and count as successes.
+
+use crate::error::Error;
+use crate::Client;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+use tonic::Code;
+
+/// Settings for [`Client::with_circuit_breaker`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CbConfig {
+    /// Consecutive failed sends that open the circuit (default: 5)
+    pub failure_threshold: u32,
+    /// How long the circuit stays open before a trial send (default: 30s)
+    pub cooldown: Duration,
+}
+
+impl Default for CbConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+/// State of a circuit breaker, from [`Client::circuit_state`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Sends go through
+    Closed,
+    /// Sends fail with [`Error::CircuitOpen`] until the cooldown ends
+    Open,
+    /// The cooldown ended; the next send is a trial, and others fail until it completes
+    HalfOpen,
+}
+
+#[derive(Debug)]
+enum State {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    HalfOpen { trial: bool },
+}
+
+/// Breaker shared by a client and its clones
+#[derive(Debug)]
+pub(crate) struct CircuitBreaker {
+    config: CbConfig,
+    state: Mutex<State>,
+}
+
+/// Admission of one send, settled with [`Permit::finish`]
+///
+/// A permit dropped unfinished, as when the send is cancelled, leaves the
+/// failure count alone and frees the trial slot.
+pub(crate) struct Permit {
+    breaker: Arc<CircuitBreaker>,
+    finished: bool,
+}
+
+impl CircuitBreaker {
+    fn new(config: CbConfig) -> Self {
+        assert!(
+            config.failure_threshold > 0,
+            "failure threshold must be non-zero"
+        );
+        Self {
+            config,
+            state: Mutex::new(State::Closed { failures: 0 }),
+        }
+    }
+
+    /// Let a send through, or fail with [`Error::CircuitOpen`]
+    pub(crate) fn admit(self: &Arc<Self>) -> Result<Permit, Error> {
+        let mut state = self.state.lock().unwrap();
+        match *state {
+            State::Closed { .. } => {}
+            State::Open { until } => {
+                let now = Instant::now();
+                if now < until {
+                    return Err(Error::CircuitOpen {
+                        retry_after: until - now,
+                    });
+                }
+                *state = State::HalfOpen { trial: true };
+            }
+            State::HalfOpen { trial: true } => {
+                return Err(Error::CircuitOpen {
+                    retry_after: Duration::ZERO,
+                })
+            }
+            State::HalfOpen { trial: false } => *state = State::HalfOpen { trial: true },
+        }
+        Ok(Permit {
+            breaker: self.clone(),
+            finished: false,
+        })
+    }
+
+    fn state(&self) -> CircuitState {
+        match *self.state.lock().unwrap() {
+            State::Closed { .. } => CircuitState::Closed,
+            State::Open { until } if Instant::now() < until => CircuitState::Open,
+            State::Open { .. } | State::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+}
+
+impl Permit {
+    /// Record how the admitted send ended
+    pub(crate) fn finish<T>(mut self, outcome: &anyhow::Result<T>) {
+        self.finished = true;
+        let failed = outcome.as_ref().err().is_some_and(is_failure);
+        let breaker = &self.breaker;
+        let mut state = breaker.state.lock().unwrap();
+        *state = match (&*state, failed) {
+            (_, false) => State::Closed { failures: 0 },
+            (State::Closed { failures }, true)
+                if failures + 1 < breaker.config.failure_threshold =>
+            {
+                State::Closed {
+                    failures: failures + 1,
+                }
+            }
+            (_, true) => State::Open {
+                until: Instant::now() + breaker.config.cooldown,
+            },
+        };
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        let mut state = self.breaker.state.lock().unwrap();
+        if let State::HalfOpen { trial: true } = *state {
+            *state = State::HalfOpen { trial: false };
+        }
+    }
+}
+
+/// Whether a send error suggests the node is unreachable or failing
+fn is_failure(error: &anyhow::Error) -> bool {
+    if let Some(Error::Timeout { .. }) = error.downcast_ref::<Error>() {
+        return true;
+    }
+    error.downcast_ref::<tonic::Status>().is_some_and(|status| {
+        matches!(
+            status.code(),
+            Code::Unavailable | Code::DeadlineExceeded | Code::Internal | Code::Unknown
+        )
+    })
+}
+
+impl Client {
+    /// Fail sends fast while the node keeps failing them
+    ///
+    /// See the [module docs](crate::breaker). The breaker is shared by this
+    /// client and its clones, and covers the sends of [`Client::send`] and
+    /// its variants; a batch from [`Client::send_batch`] counts as one send,
+    /// failed only when the whole call fails.
+    ///
+    /// Panics if `failure_threshold` is zero.
+    pub fn with_circuit_breaker(mut self, config: CbConfig) -> Self {
+        self.circuit_breaker = Some(Arc::new(CircuitBreaker::new(config)));
+        self
+    }
+
+    /// State of the circuit breaker, if one is configured
+    pub fn circuit_state(&self) -> Option<CircuitState> {
+        self.circuit_breaker.as_ref().map(|breaker| breaker.state())
+    }
+}
@@ -0,0 +1,287 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Client construction and connection warmup
+//!
+//! [`ClientBuilder`] gathers the transport options before a [`Client`] is
+//! connected. The `Client::new`/`with_tls`/`with_mtls`/`new_addr`
+//! constructors are shorthands for the common cases.
+
+use crate::capabilities::{is_unsupported, ProtocolVersion};
+use crate::config::Transport;
+use crate::diagnose::Diagnosis;
+use crate::peer;
+use crate::proxy::{Proxy, ProxyAuth};
+use crate::tls::TlsConfig;
+use crate::Client;
+use anyhow::{Context, Result};
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context as TaskContext, Poll};
+use tokio::net::TcpStream;
+use tonic::codegen::http::Uri;
+use tonic::transport::Channel;
+
+/// Builder for a [`Client`], created with [`Client::builder`]
+#[derive(Clone, Debug)]
+pub struct ClientBuilder {
+    endpoint: String,
+    tls: Option<TlsConfig>,
+    lazy: bool,
+    warmup: bool,
+    proxy: Option<(String, Option<ProxyAuth>)>,
+    env_proxy: bool,
+    nodelay: bool,
+    resolved_addr: Option<SocketAddr>,
+    protocol_version: Option<ProtocolVersion>,
+    pub(crate) connection_bound: bool,
+}
+
+impl ClientBuilder {
+    fn new(endpoint: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            tls: None,
+            lazy: false,
+            warmup: false,
+            proxy: None,
+            env_proxy: true,
+            nodelay: true,
+            resolved_addr: None,
+            protocol_version: None,
+            connection_bound: false,
+        }
+    }
+
+    /// Connect over TLS with the given configuration
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Defer connecting until the first RPC instead of connecting in `build`
+    pub fn connect_lazy(mut self, lazy: bool) -> Self {
+        self.lazy = lazy;
+        self
+    }
+
+    /// Run [`Client::warmup`] before `build` returns
+    ///
+    /// Takes precedence over [`ClientBuilder::connect_lazy`].
+    pub fn warmup(mut self, warmup: bool) -> Self {
+        self.warmup = warmup;
+        self
+    }
+
+    /// Tunnel the connection through the HTTP CONNECT proxy at `proxy_uri`
+    ///
+    /// `proxy_uri` is `http://host:port`; credentials embedded in it are used
+    /// when `auth` is `None`. TLS to the node, including mutual TLS, runs end
+    /// to end inside the tunnel. Overrides `HTTPS_PROXY` and `NO_PROXY`.
+    pub fn with_proxy(mut self, proxy_uri: impl Into<String>, auth: Option<ProxyAuth>) -> Self {
+        self.proxy = Some((proxy_uri.into(), auth));
+        self
+    }
+
+    /// Use the proxy named by `HTTPS_PROXY` unless `NO_PROXY` exempts the node
+    ///
+    /// On by default. Lowercase variable names are accepted as well. Has no
+    /// effect when a proxy is set with [`ClientBuilder::with_proxy`].
+    pub fn env_proxy(mut self, env_proxy: bool) -> Self {
+        self.env_proxy = env_proxy;
+        self
+    }
+
+    /// Set `TCP_NODELAY` on the connection to the node or proxy
+    ///
+    /// On by default, so small messages and request/response traffic are
+    /// written immediately instead of being held back by Nagle's algorithm.
+    /// Turn it off to trade latency for fewer, fuller packets on bulk
+    /// publishers.
+    pub fn with_nodelay(mut self, nodelay: bool) -> Self {
+        self.nodelay = nodelay;
+        self
+    }
+
+    /// Dial `addr` instead of resolving the endpoint's host
+    ///
+    /// No DNS lookup is made. The endpoint's host is still sent as the
+    /// `:authority` and, over TLS, used for SNI and to validate the node's
+    /// certificate unless [`TlsConfig::with_domain`] overrides it. Proxy
+    /// settings are ignored, since the address is dialled directly.
+    pub fn with_resolved_addr(mut self, addr: SocketAddr) -> Self {
+        self.resolved_addr = Some(addr);
+        self
+    }
+
+    /// Check each layer of the connection with this builder's TLS settings
+    ///
+    /// See [`Client::diagnose`]. The node is dialled directly, ignoring any
+    /// proxy settings.
+    pub async fn diagnose(&self) -> Diagnosis {
+        let mut builder = self.clone();
+        builder.proxy = None;
+        builder.env_proxy = false;
+        builder.lazy = false;
+        builder.warmup = false;
+        crate::diagnose::run(&self.endpoint, self.tls.as_ref(), builder).await
+    }
+
+    /// Pin the fabric protocol version the application was built against
+    ///
+    /// The version is sent on every request in `securefabric-protocol`
+    /// metadata. `build` then asks the node for its
+    /// [capabilities](Client::capabilities) and fails with
+    /// [`Error::ProtocolMismatch`](crate::Error::ProtocolMismatch) if the node
+    /// reports a different major version; nodes that report no version are
+    /// accepted. With [`ClientBuilder::connect_lazy`] the check is left to
+    /// [`Client::warmup`].
+    pub fn with_protocol_version(mut self, major: u32, minor: u32) -> Self {
+        self.protocol_version = Some(ProtocolVersion::new(major, minor));
+        self
+    }
+
+    /// Create the client, connecting and warming up as configured
+    pub async fn build(self) -> Result<Client> {
+        let mut endpoint = Channel::from_shared(self.endpoint.clone())?.tcp_nodelay(self.nodelay);
+        if let Some(tls) = self.tls.clone() {
+            endpoint = endpoint.tls_config(tls.into_tonic())?;
+        }
+
+        let proxy = match self.proxy {
+            _ if self.resolved_addr.is_some() => None,
+            Some((uri, auth)) => Some(Proxy::parse(&uri, auth)?),
+            None if self.env_proxy => Proxy::from_env(endpoint.uri())?,
+            None => None,
+        };
+        let peer_identity = match (&self.tls, self.connection_bound) {
+            (_, false) => None,
+            (Some(tls), true) => Some(
+                peer::probe(
+                    endpoint.uri(),
+                    tls,
+                    self.resolved_addr,
+                    proxy.as_ref(),
+                    self.nodelay,
+                )
+                .await
+                .context("read node identity")?,
+            ),
+            (None, true) => anyhow::bail!("Connection-bound signing requires TLS"),
+        };
+        let transport = Transport::new(
+            &self.endpoint,
+            self.tls.as_ref(),
+            proxy.as_ref().map(|proxy| proxy.authority().to_string()),
+            self.resolved_addr,
+        );
+        let lazy = self.lazy && !self.warmup;
+        let channel = match (self.resolved_addr, proxy) {
+            (Some(addr), _) if lazy => endpoint.connect_with_connector_lazy(FixedAddrConnector {
+                addr,
+                nodelay: self.nodelay,
+            }),
+            (Some(addr), _) => endpoint
+                .connect_with_connector(FixedAddrConnector {
+                    addr,
+                    nodelay: self.nodelay,
+                })
+                .await
+                .with_context(|| format!("connect to {addr}"))?,
+            (None, Some(proxy)) if lazy => {
+                endpoint.connect_with_connector_lazy(proxy.connector(self.nodelay))
+            }
+            (None, Some(proxy)) => endpoint
+                .connect_with_connector(proxy.connector(self.nodelay))
+                .await
+                .context("connect to endpoint through proxy")?,
+            (None, None) if lazy => endpoint.connect_lazy(),
+            (None, None) => endpoint.connect().await.context("connect to endpoint")?,
+        };
+
+        let mut client = Client::from_channel(channel, transport);
+        client.peer_identity = peer_identity;
+        client.protocol_version = self.protocol_version;
+        if self.warmup {
+            client.warmup().await?;
+        } else if !lazy {
+            client.check_protocol_version().await?;
+        }
+        Ok(client)
+    }
+}
+
+impl Client {
+    /// Start building a client for `endpoint`
+    pub fn builder(endpoint: impl Into<String>) -> ClientBuilder {
+        ClientBuilder::new(endpoint)
+    }
+
+    /// Create a Client connected to an already resolved address
+    ///
+    /// Skips DNS entirely: `addr` is dialled as is, while `tls_domain` names the
+    /// node in the `:authority` and, when `tls` is given, for SNI and
+    /// certificate validation. See [`ClientBuilder::with_resolved_addr`].
+    pub async fn new_addr(
+        addr: SocketAddr,
+        tls_domain: &str,
+        tls: Option<TlsConfig>,
+    ) -> Result<Self> {
+        let scheme = if tls.is_some() { "https" } else { "http" };
+        let mut builder = Self::builder(format!("{scheme}://{tls_domain}:{}", addr.port()))
+            .with_resolved_addr(addr);
+        if let Some(tls) = tls {
+            builder = builder.tls(tls.with_domain(tls_domain));
+        }
+        builder.build().await
+    }
+
+    /// Establish the connection ahead of the first real RPC
+    ///
+    /// Waits for the channel to be ready, which dials and completes the TLS and
+    /// HTTP/2 handshakes if they have not happened yet, then fetches the node's
+    /// [capabilities](Client::capabilities) and issues one `Ping` so the first
+    /// send does not pay for connection setup. Nodes without the `Ping` RPC are
+    /// still considered warm once the channel is ready. Fails with
+    /// [`Error::ProtocolMismatch`](crate::Error::ProtocolMismatch) if a
+    /// [pinned protocol version](ClientBuilder::with_protocol_version) is
+    /// incompatible with the node's.
+    pub async fn warmup(&mut self) -> Result<()> {
+        let mut grpc = tonic::client::Grpc::new(self.channel.clone());
+        grpc.ready().await.context("connect to endpoint")?;
+        self.check_protocol_version().await?;
+
+        match self.ping().await {
+            Ok(_) => Ok(()),
+            Err(error) if is_unsupported(&error) => Ok(()),
+            Err(error) => Err(error.context("warm up connection")),
+        }
+    }
+}
+
+/// Connector for tonic that dials a fixed address, whatever the endpoint's host
+#[derive(Clone, Copy)]
+struct FixedAddrConnector {
+    addr: SocketAddr,
+    nodelay: bool,
+}
+
+impl tower_service::Service<Uri> for FixedAddrConnector {
+    type Response = hyper_util::rt::TokioIo<TcpStream>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<Self::Response>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _: Uri) -> Self::Future {
+        let Self { addr, nodelay } = *self;
+        Box::pin(async move {
+            let stream = TcpStream::connect(addr).await?;
+            stream.set_nodelay(nodelay)?;
+            Ok(hyper_util::rt::TokioIo::new(stream))
+        })
+    }
+}
@@ -0,0 +1,227 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Detecting optional node features
+//!
+//! Older nodes answer optional RPCs with `UNIMPLEMENTED`. The client asks the
+//! node which features it supports through the `Capabilities` RPC once per
+//! connection, on [`Client::warmup`] or the first feature-dependent call, and
+//! fails such calls with [`Error::Unsupported`] instead of a raw status. Nodes
+//! that predate the RPC are assumed to support everything, and an
+//! `UNIMPLEMENTED` answer from the feature's own RPC is reported the same way.
+//!
+//! The same RPC reports the node's [`ProtocolVersion`], which clients pinned
+//! with [`ClientBuilder::with_protocol_version`](crate::ClientBuilder::with_protocol_version)
+//! check for compatibility when connecting.
+
+use crate::error::Error;
+use crate::pb::CapabilitiesReq;
+use crate::Client;
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::fmt;
+use tonic::{Code, Status};
+
+/// An optional node feature
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum Feature {
+    /// The `Ack` RPC
+    Ack,
+    /// The `Head` RPC, used by [`Client::lag`]
+    Head,
+    /// The `Ping` RPC
+    Ping,
+    /// Node time in `Ping` responses, used by [`Client::clock_skew`]
+    ServerTime,
+    /// The `SendBatch` RPC
+    SendBatch,
+    /// Node-side header filters on `Subscribe`
+    HeaderFilters,
+    /// The `GetMessage` RPC, used by [`Client::get_message`]
+    GetMessage,
+    /// The `SubscribeCredits` RPC, used by [`Client::subscribe_with_credits`]
+    Credits,
+    /// Node-side pacing on `Subscribe`, used by [`Client::subscribe_throttled`]
+    RateLimit,
+    /// The `SubscribeBatched` RPC, used by [`Client::subscribe_batched`]
+    BatchedSubscribe,
+    /// `SubscribeReq.topics`, used by [`Client::subscribe_many`]
+    MultiTopic,
+    /// `SubscribeReq.shard` and `shard_count`, used by [`Client::subscribe_sharded`]
+    Shards,
+}
+
+impl Feature {
+    /// Name of the feature in `CapabilitiesResp.features`
+    pub fn wire_name(self) -> &'static str {
+        match self {
+            Self::Ack => "ack",
+            Self::Head => "head",
+            Self::Ping => "ping",
+            Self::ServerTime => "server_time",
+            Self::SendBatch => "send_batch",
+            Self::HeaderFilters => "header_filters",
+            Self::GetMessage => "get_message",
+            Self::Credits => "credits",
+            Self::RateLimit => "rate_limit",
+            Self::BatchedSubscribe => "batched_subscribe",
+            Self::MultiTopic => "multi_topic",
+            Self::Shards => "shards",
+        }
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.wire_name())
+    }
+}
+
+/// Fabric protocol version, compatible across minor versions of one major
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ProtocolVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl ProtocolVersion {
+    /// Version of the protocol this SDK implements
+    pub const CURRENT: Self = Self { major: 1, minor: 0 };
+
+    pub const fn new(major: u32, minor: u32) -> Self {
+        Self { major, minor }
+    }
+
+    /// Whether a node speaking `other` is compatible with this version
+    pub fn is_compatible_with(self, other: Self) -> bool {
+        self.major == other.major
+    }
+
+    /// Parse `major.minor`, as carried in `CapabilitiesResp.protocol_version`
+    pub fn parse(version: &str) -> Option<Self> {
+        let (major, minor) = version.split_once('.')?;
+        Some(Self::new(major.parse().ok()?, minor.parse().ok()?))
+    }
+}
+
+impl fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// Features advertised by a node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    /// `None` for nodes without the `Capabilities` RPC
+    advertised: Option<BTreeSet<String>>,
+    protocol_version: Option<ProtocolVersion>,
+}
+
+impl Capabilities {
+    /// Whether the node advertised `feature`, or `None` if it cannot say
+    pub fn supports(&self, feature: Feature) -> Option<bool> {
+        self.advertised
+            .as_ref()
+            .map(|advertised| advertised.contains(feature.wire_name()))
+    }
+
+    /// Feature names the node advertised, including ones this SDK does not know
+    pub fn features(&self) -> impl Iterator<Item = &str> {
+        self.advertised.iter().flatten().map(String::as_str)
+    }
+
+    /// Whether the node answered the `Capabilities` RPC
+    pub fn is_known(&self) -> bool {
+        self.advertised.is_some()
+    }
+
+    /// Protocol version the node reported, if any
+    ///
+    /// `None` for nodes that predate the field or report it malformed.
+    pub fn protocol_version(&self) -> Option<ProtocolVersion> {
+        self.protocol_version
+    }
+}
+
+impl Client {
+    /// Features the node supports, fetched once per connection
+    ///
+    /// Clones of this client share the cached answer. A node without the
+    /// `Capabilities` RPC yields capabilities that are not
+    /// [known](Capabilities::is_known). Other failures are returned and not
+    /// cached.
+    pub async fn capabilities(&mut self) -> Result<Capabilities> {
+        self.lifecycle.check()?;
+        let req = self.with_metadata(CapabilitiesReq {})?;
+        let cache = self.capabilities.clone();
+        let capabilities = cache
+            .get_or_try_init(|| async {
+                match self.inner.capabilities(req).await {
+                    Ok(response) => {
+                        let response = response.into_inner();
+                        Ok(Capabilities {
+                            advertised: Some(response.features.into_iter().collect()),
+                            protocol_version: ProtocolVersion::parse(&response.protocol_version),
+                        })
+                    }
+                    Err(status) if status.code() == Code::Unimplemented => Ok(Capabilities {
+                        advertised: None,
+                        protocol_version: None,
+                    }),
+                    Err(status) => Err(status),
+                }
+            })
+            .await
+            .context("fetch node capabilities")?;
+        Ok(capabilities.clone())
+    }
+
+    /// Fail with [`Error::ProtocolMismatch`] if the node reports a protocol
+    /// version incompatible with the pinned one
+    ///
+    /// Nodes that do not report a version are given the benefit of the doubt.
+    pub(crate) async fn check_protocol_version(&mut self) -> Result<()> {
+        let Some(pinned) = self.protocol_version else {
+            return Ok(());
+        };
+        match self.capabilities().await?.protocol_version() {
+            Some(reported) if !pinned.is_compatible_with(reported) => {
+                Err(Error::ProtocolMismatch { pinned, reported }.into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Fail with [`Error::Unsupported`] if the node advertised that it lacks `feature`
+    ///
+    /// Failing to fetch capabilities is not an error here; the feature's own
+    /// RPC will report the underlying problem.
+    pub(crate) async fn require(&mut self, feature: Feature) -> Result<()> {
+        match self.capabilities().await {
+            Ok(capabilities) if capabilities.supports(feature) == Some(false) => {
+                Err(Error::Unsupported { feature }.into())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Report `UNIMPLEMENTED` from a feature's RPC as [`Error::Unsupported`]
+pub(crate) fn rpc_error(feature: Feature) -> impl FnOnce(Status) -> anyhow::Error {
+    move |status| {
+        if status.code() == Code::Unimplemented {
+            Error::Unsupported { feature }.into()
+        } else {
+            status.into()
+        }
+    }
+}
+
+/// Whether `error` is [`Error::Unsupported`]
+pub(crate) fn is_unsupported(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<Error>(),
+        Some(Error::Unsupported { .. })
+    )
+}
@@ -0,0 +1,207 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Per-sender sequence chain verification
+//!
+//! Every sender stamps its envelopes with a strictly increasing `seq` and binds
+//! it into the signed AAD. Tracking that sequence for one sender lets a
+//! consumer detect dropped messages (gaps), replayed or injected older
+//! messages (out of order), and conflicting messages claiming the same `seq`
+//! (forks). Only the signed seq counts, so a relay cannot renumber envelopes
+//! to hide a drop; envelopes without one are rejected.
+
+use crate::crypto::scheme::VerifyingKey;
+use crate::pb::Envelope;
+use crate::{signed_digest, signed_seq, Client, Verifier};
+use anyhow::Result;
+use futures::stream::{self, BoxStream, StreamExt};
+use std::collections::VecDeque;
+
+/// Number of recent envelopes remembered per seq for fork detection
+const FORK_WINDOW: usize = 64;
+
+/// A break in a sender's message chain
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ChainViolation {
+    /// One or more sequence numbers were skipped
+    #[error("gap in sender chain: expected seq {expected}, got {got}")]
+    Gap { expected: u64, got: u64 },
+
+    /// An envelope arrived with a seq older than the chain head
+    #[error("out-of-order envelope: seq {got} after chain head {head}")]
+    OutOfOrder { head: u64, got: u64 },
+
+    /// Two different envelopes claim the same seq
+    #[error("fork at seq {seq}: msg_id {first} conflicts with {second}")]
+    Fork {
+        seq: u64,
+        first: String,
+        second: String,
+    },
+
+    /// The envelope signature does not verify under the sender key
+    #[error("invalid signature at seq {seq}")]
+    BadSignature { seq: u64 },
+
+    /// The envelope msg_id does not match its pubkey, seq and nonce
+    #[error("invalid msg_id at seq {seq}")]
+    BadMsgId { seq: u64 },
+
+    /// The envelope's seq is not bound into its signed AAD
+    #[error("seq {seq} is not signed")]
+    UnsignedSeq { seq: u64 },
+}
+
+/// Errors yielded by [`Client::subscribe_chain_verified`]
+#[derive(Debug, thiserror::Error)]
+pub enum ChainError {
+    /// The sender's chain is broken
+    #[error(transparent)]
+    Violation(#[from] ChainViolation),
+
+    /// The underlying subscription failed
+    #[error("subscription error: {0}")]
+    Transport(#[from] tonic::Status),
+}
+
+/// Verifies that one sender's envelopes form an unbroken, validly signed sequence
+pub struct SenderChainVerifier {
+    sender: VerifyingKey,
+    verifier: Verifier,
+    next_seq: Option<u64>,
+    /// Seq, signed digest and msg_id of recent chain envelopes
+    recent: VecDeque<(u64, [u8; 32], String)>,
+}
+
+impl SenderChainVerifier {
+    /// Track `sender`, taking the first observed seq as the start of the chain
+    pub fn new(sender: impl Into<VerifyingKey>) -> Self {
+        Self {
+            sender: sender.into(),
+            verifier: Verifier::default(),
+            next_seq: None,
+            recent: VecDeque::with_capacity(FORK_WINDOW),
+        }
+    }
+
+    /// Track `sender`, requiring the chain to continue at `next_seq`
+    pub fn starting_at(sender: impl Into<VerifyingKey>, next_seq: u64) -> Self {
+        Self {
+            next_seq: Some(next_seq),
+            ..Self::new(sender)
+        }
+    }
+
+    /// Check signatures and msg_ids as `verifier` does
+    pub(crate) fn verifying_with(mut self, verifier: Verifier) -> Self {
+        self.verifier = verifier;
+        self
+    }
+
+    /// Whether the envelope was published by the tracked sender
+    pub fn is_from_sender(&self, envelope: &Envelope) -> bool {
+        envelope.pubkey == self.sender.to_bytes()
+    }
+
+    /// The seq the next envelope is expected to carry, if known
+    pub fn next_seq(&self) -> Option<u64> {
+        self.next_seq
+    }
+
+    /// Check the next envelope from the tracked sender
+    ///
+    /// On [`ChainViolation::Gap`] the envelope itself is valid and the chain is
+    /// resynchronised after it; every other violation leaves the chain head
+    /// untouched so the rejected envelope has no effect. Envelopes signed over
+    /// their plaintext fail with [`ChainViolation::BadSignature`] here; chains
+    /// from [`Client::subscribe_chain_verified`] decrypt them with the
+    /// client's key first.
+    pub fn check(&mut self, envelope: &Envelope) -> Result<(), ChainViolation> {
+        let seq = envelope.seq;
+
+        if !self.is_from_sender(envelope) || !self.verifier.verify(envelope).unwrap_or(false) {
+            return Err(ChainViolation::BadSignature { seq });
+        }
+        if !self.verifier.verify_msg_id(envelope) {
+            return Err(ChainViolation::BadMsgId { seq });
+        }
+        if signed_seq(envelope).is_none() {
+            return Err(ChainViolation::UnsignedSeq { seq });
+        }
+        let digest = signed_digest(envelope);
+
+        if let Some(expected) = self.next_seq {
+            if seq < expected {
+                if let Some((_, first_digest, first)) =
+                    self.recent.iter().find(|(s, _, _)| *s == seq)
+                {
+                    if *first_digest != digest {
+                        return Err(ChainViolation::Fork {
+                            seq,
+                            first: first.clone(),
+                            second: envelope.msg_id.clone(),
+                        });
+                    }
+                }
+                return Err(ChainViolation::OutOfOrder {
+                    head: expected - 1,
+                    got: seq,
+                });
+            }
+        }
+
+        let gap = match self.next_seq {
+            Some(expected) if seq > expected => Some(ChainViolation::Gap { expected, got: seq }),
+            _ => None,
+        };
+
+        self.next_seq = Some(seq.saturating_add(1));
+        if self.recent.len() == FORK_WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent
+            .push_back((seq, digest, envelope.msg_id.clone()));
+
+        gap.map_or(Ok(()), Err)
+    }
+}
+
+impl Client {
+    /// Subscribe to a topic and verify the chain of envelopes from one sender
+    ///
+    /// Envelopes from other senders are skipped. Every violation is yielded as an
+    /// error item; after a [`ChainViolation::Gap`] the envelope that revealed the
+    /// gap is still yielded, since it is itself valid. Signatures are checked
+    /// as [`Client::verify`] does, so envelopes signed over their plaintext
+    /// are verified with the configured encryption key.
+    pub async fn subscribe_chain_verified(
+        &mut self,
+        topic: &[u8],
+        sender: impl Into<VerifyingKey>,
+    ) -> Result<BoxStream<'static, Result<Envelope, ChainError>>> {
+        let inner = self.subscribe_verifying(topic).await?;
+        let mut verifier = SenderChainVerifier::new(sender).verifying_with(self.verifier());
+        let post_receive = self.post_receive.clone();
+
+        let stream = inner.flat_map(move |item| {
+            let items: Vec<Result<Envelope, ChainError>> = match item {
+                Err(status) => vec![Err(status.into())],
+                Ok(envelope) if !verifier.is_from_sender(&envelope) => Vec::new(),
+                Ok(envelope) => match verifier.check(&envelope) {
+                    Ok(()) => vec![Ok(envelope)],
+                    Err(gap @ ChainViolation::Gap { .. }) => vec![Err(gap.into()), Ok(envelope)],
+                    Err(violation) => vec![Err(violation.into())],
+                },
+            };
+            let items: Vec<_> = items
+                .into_iter()
+                .filter_map(|item| match item {
+                    Ok(envelope) => post_receive.after_verification(envelope).map(Ok),
+                    Err(error) => Some(Err(error)),
+                })
+                .collect();
+            stream::iter(items)
+        });
+
+        Ok(stream.boxed())
+    }
+}
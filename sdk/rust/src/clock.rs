@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Clock skew between client and node
+//!
+//! Envelope timestamps come from the sender's wall clock, so age limits and
+//! key validity windows judged by another party are only as good as the two
+//! clocks agree. [`Client::clock_skew`] estimates the offset of the local
+//! clock from the node's the way NTP does, assuming the node read its clock
+//! halfway through one `Ping` round trip. A bound set with
+//! [`Client::with_max_clock_skew`] decides what happens when the latest
+//! estimate exceeds it.
+
+use crate::capabilities::{rpc_error, Feature};
+use crate::error::Error;
+use crate::pb::PingReq;
+use crate::Client;
+use anyhow::{Context, Result};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Estimated offset of the node's clock from the local one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockSkew {
+    /// Node clock minus local clock in milliseconds, positive when the node is ahead
+    pub offset_ms: i64,
+    /// Round trip of the measuring ping; the offset is uncertain by up to half of it
+    pub round_trip: Duration,
+}
+
+impl ClockSkew {
+    /// Size of the offset regardless of direction
+    pub fn magnitude(&self) -> Duration {
+        Duration::from_millis(self.offset_ms.unsigned_abs())
+    }
+}
+
+/// What to do while the measured skew exceeds the bound
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkewAction {
+    /// Only call the [skew warning](Client::with_skew_warning) hooks
+    Warn,
+    /// Fail sends with [`Error::ClockSkew`]
+    Reject,
+    /// Shift the timestamps of sent envelopes by the measured offset
+    Adjust,
+}
+
+type SkewWarning = Arc<dyn Fn(&ClockSkew) + Send + Sync>;
+
+/// Skew bound of a client, and the latest measurement shared with its clones
+#[derive(Clone, Default)]
+pub(crate) struct ClockPolicy {
+    bound: Option<(Duration, SkewAction)>,
+    warnings: Vec<SkewWarning>,
+    measured: Arc<Mutex<Option<ClockSkew>>>,
+}
+
+impl ClockPolicy {
+    fn record(&self, skew: ClockSkew) {
+        *self.measured.lock().unwrap() = Some(skew);
+        if self.exceeded().is_some() {
+            for warning in &self.warnings {
+                warning(&skew);
+            }
+        }
+    }
+
+    /// Latest measurement, bound and action if the measurement exceeds the bound
+    fn exceeded(&self) -> Option<(ClockSkew, Duration, SkewAction)> {
+        let (max, action) = self.bound?;
+        let skew = (*self.measured.lock().unwrap())?;
+        (skew.magnitude() > max).then_some((skew, max, action))
+    }
+
+    /// Timestamp to send for local time `local_ms` under the skew bound
+    pub(crate) fn timestamp_ms(&self, local_ms: u64) -> Result<u64, Error> {
+        match self.exceeded() {
+            None | Some((_, _, SkewAction::Warn)) => Ok(local_ms),
+            Some((skew, max, SkewAction::Reject)) => Err(Error::ClockSkew {
+                offset_ms: skew.offset_ms,
+                max,
+            }),
+            Some((skew, _, SkewAction::Adjust)) => {
+                Ok(local_ms.saturating_add_signed(skew.offset_ms))
+            }
+        }
+    }
+}
+
+impl Client {
+    /// Current time on the node's clock
+    ///
+    /// Read from one `Ping`, so it is already stale by up to the round trip.
+    /// Fails with [`Error::Unsupported`] on nodes that do not report their
+    /// time, and with [`Error::Timeout`] after the ping timeout.
+    pub async fn server_time(&mut self) -> Result<SystemTime> {
+        let (server_ms, _, _) = self.read_server_clock().await?;
+        Ok(UNIX_EPOCH + Duration::from_millis(server_ms))
+    }
+
+    /// Measure the offset of the node's clock from the local one
+    ///
+    /// The measurement is shared with this client's clones and applies the
+    /// bound set with [`Client::with_max_clock_skew`] until the next one.
+    /// Fails like [`Client::server_time`].
+    pub async fn clock_skew(&mut self) -> Result<ClockSkew> {
+        let (server_ms, sent_ms, round_trip) = self.read_server_clock().await?;
+        let midpoint_ms = sent_ms + round_trip.as_millis() as u64 / 2;
+        let skew = ClockSkew {
+            offset_ms: server_ms as i64 - midpoint_ms as i64,
+            round_trip,
+        };
+        self.clock.record(skew);
+        Ok(skew)
+    }
+
+    /// Latest result of [`Client::clock_skew`] on this client or a clone
+    pub fn last_clock_skew(&self) -> Option<ClockSkew> {
+        *self.clock.measured.lock().unwrap()
+    }
+
+    /// Act on measured skew larger than `max`
+    ///
+    /// Skew is only known after [`Client::clock_skew`] has run, so call it
+    /// periodically to keep the bound meaningful. With
+    /// [`SkewAction::Adjust`], sends use the local clock shifted by the
+    /// measured offset as their timestamp.
+    pub fn with_max_clock_skew(mut self, max: Duration, action: SkewAction) -> Self {
+        self.clock.bound = Some((max, action));
+        self
+    }
+
+    /// Call `hook` with every measurement that exceeds the skew bound
+    ///
+    /// Runs whatever the bound's [`SkewAction`]. Hooks added by repeated calls
+    /// run in the order they were added.
+    pub fn with_skew_warning(mut self, hook: impl Fn(&ClockSkew) + Send + Sync + 'static) -> Self {
+        self.clock.warnings.push(Arc::new(hook));
+        self
+    }
+
+    /// Node time from one `Ping`, with the local time it was sent and its round trip
+    async fn read_server_clock(&mut self) -> Result<(u64, u64, Duration)> {
+        self.require(Feature::Ping).await?;
+        self.require(Feature::ServerTime).await?;
+        let timeout = self.ping_timeout;
+        let req = self.authorized(PingReq {})?;
+
+        let sent_ms = self.entropy.now_ms();
+        let start = Instant::now();
+        let response = match tokio::time::timeout(timeout, self.inner.ping(req)).await {
+            Ok(response) => response
+                .map_err(rpc_error(Feature::Ping))
+                .context("read node clock")?,
+            Err(_) => return Err(Error::Timeout { after: timeout }.into()),
+        };
+        let round_trip = start.elapsed();
+        match response.into_inner().server_time_ms {
+            0 => Err(Error::Unsupported {
+                feature: Feature::ServerTime,
+            }
+            .into()),
+            server_ms => Ok((server_ms, sent_ms, round_trip)),
+        }
+    }
+}
@@ -0,0 +1,148 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Shutting a client down
+//!
+//! [`Client::close`] stops a client and all its clones at once: open
+//! subscriptions end, sends already under way get a grace period to finish,
+//! and every call afterwards fails with [`Error::Closed`].
+
+use crate::error::Error;
+use crate::Client;
+use anyhow::Result;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tonic::transport::Endpoint;
+
+/// How long [`Client::close`] waits for sends under way
+pub const DEFAULT_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What [`Client::close`] stopped
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CloseSummary {
+    /// Subscriptions that were open and have been ended
+    pub subscriptions_cancelled: usize,
+    /// Sends under way that finished, successfully or not, within the timeout
+    pub sends_flushed: usize,
+    /// Sends still under way at the timeout, failed with [`Error::Closed`]
+    pub sends_cancelled: usize,
+}
+
+/// Open or closed state shared by a client and its clones
+pub(crate) struct Lifecycle {
+    closed: AtomicBool,
+    /// Sends started and not yet finished
+    in_flight: watch::Sender<usize>,
+    /// Set once close stops waiting for the sends still in flight
+    abandoned: watch::Sender<bool>,
+}
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        Self {
+            closed: AtomicBool::new(false),
+            in_flight: watch::Sender::new(0),
+            abandoned: watch::Sender::new(false),
+        }
+    }
+}
+
+/// A send counted by its [`Lifecycle`] until dropped
+pub(crate) struct InFlight {
+    lifecycle: Arc<Lifecycle>,
+}
+
+impl Lifecycle {
+    /// Fail with [`Error::Closed`] once the client is closed
+    pub(crate) fn check(&self) -> Result<(), Error> {
+        match self.closed.load(Ordering::SeqCst) {
+            true => Err(Error::Closed),
+            false => Ok(()),
+        }
+    }
+
+    /// Count a new send, unless the client is closed
+    pub(crate) fn begin_send(self: &Arc<Self>) -> Result<InFlight, Error> {
+        // Counted before the check, so close either sees the send or refuses it
+        self.in_flight.send_modify(|count| *count += 1);
+        let in_flight = InFlight {
+            lifecycle: self.clone(),
+        };
+        self.check()?;
+        Ok(in_flight)
+    }
+}
+
+impl InFlight {
+    /// Run the send, failing it with [`Error::Closed`] if close gives up on it
+    pub(crate) async fn run<T>(&self, send: impl Future<Output = Result<T>>) -> Result<T> {
+        let mut abandoned = self.lifecycle.abandoned.subscribe();
+        tokio::select! {
+            outcome = send => outcome,
+            _ = abandoned.wait_for(|abandoned| *abandoned) => Err(Error::Closed.into()),
+        }
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.lifecycle.in_flight.send_modify(|count| *count -= 1);
+    }
+}
+
+impl Client {
+    /// Close the client and its clones, waiting up to [`DEFAULT_CLOSE_TIMEOUT`] for sends
+    ///
+    /// See [`Client::close_with_timeout`].
+    pub async fn close(&mut self) -> Result<CloseSummary> {
+        self.close_with_timeout(DEFAULT_CLOSE_TIMEOUT).await
+    }
+
+    /// Close the client and its clones, waiting up to `timeout` for sends under way
+    ///
+    /// New calls on the client or any clone fail with [`Error::Closed`] from
+    /// the start. Open subscriptions end, including the ones behind shared
+    /// subscriptions and [`Client::pipe_to`]. Sends already under way, from
+    /// any clone, may finish until `timeout`; those still running then fail
+    /// with [`Error::Closed`]. This client's handle on the connection is
+    /// released; the connection closes once every clone is closed or dropped.
+    ///
+    /// Fails with [`Error::Closed`] if the client was already closed.
+    pub async fn close_with_timeout(&mut self, timeout: Duration) -> Result<CloseSummary> {
+        let lifecycle = self.lifecycle.clone();
+        let first = !lifecycle.closed.swap(true, Ordering::SeqCst);
+        self.release_channel();
+        if !first {
+            return Err(Error::Closed.into());
+        }
+
+        let subscriptions_cancelled = self.active_subscriptions.close_all();
+
+        let mut in_flight = lifecycle.in_flight.subscribe();
+        let started = *in_flight.borrow_and_update();
+        let drained = tokio::time::timeout(timeout, in_flight.wait_for(|count| *count == 0))
+            .await
+            .is_ok();
+        let sends_cancelled = match drained {
+            true => 0,
+            false => *in_flight.borrow(),
+        };
+        lifecycle.abandoned.send_replace(true);
+
+        Ok(CloseSummary {
+            subscriptions_cancelled,
+            sends_flushed: started.saturating_sub(sends_cancelled),
+            sends_cancelled,
+        })
+    }
+
+    /// Drop this client's handle on the connection
+    fn release_channel(&mut self) {
+        // Never connected: every call fails with Error::Closed before using it
+        let placeholder = Endpoint::from_static("http://[::1]:0").connect_lazy();
+        self.inner = crate::pb::fabric_node_client::FabricNodeClient::new(placeholder.clone());
+        self.channel = placeholder;
+    }
+}
@@ -0,0 +1,174 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Hardened envelope decoding
+//!
+//! Envelopes delivered on the subscribe path are attacker-influenceable bytes.
+//! This module decodes them with an explicit size limit and structural checks
+//! so that a malformed frame surfaces as an error instead of a panic or an
+//! oversized allocation.
+
+use crate::crypto::{self, SignatureScheme};
+use crate::pb::{Envelope, EnvelopeBatch, SubscribeReq};
+use prost::bytes::Buf;
+use prost::Message;
+use std::marker::PhantomData;
+use tonic::codec::{Codec, DecodeBuf, Decoder, ProstCodec};
+use tonic::Status;
+
+/// Maximum encoded envelope size accepted by the decoder (4 MiB, the tonic default)
+pub const MAX_ENVELOPE_LEN: usize = 4 * 1024 * 1024;
+
+/// Errors produced while decoding an envelope from the wire
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CodecError {
+    /// The encoded frame is larger than [`MAX_ENVELOPE_LEN`]
+    #[error("envelope of {len} bytes exceeds limit of {max} bytes")]
+    TooLarge { len: usize, max: usize },
+
+    /// The bytes are not a valid protobuf `Envelope`
+    #[error("malformed envelope: {0}")]
+    Malformed(#[from] prost::DecodeError),
+
+    /// A fixed-size field has an unexpected length
+    #[error("field `{field}` has invalid length {len}")]
+    InvalidLength { field: &'static str, len: usize },
+}
+
+impl Envelope {
+    /// Decode an envelope from untrusted bytes
+    ///
+    /// Rejects frames above [`MAX_ENVELOPE_LEN`] before decoding, and checks that
+    /// `pubkey`, `sig` and `nonce` are either empty or of their fixed protocol
+    /// length for the envelope's signature scheme, as are the ephemeral key and
+    /// wrapped keys of sealed envelopes, co-signatures and the payload digest.
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<Envelope, CodecError> {
+        if bytes.len() > MAX_ENVELOPE_LEN {
+            return Err(CodecError::TooLarge {
+                len: bytes.len(),
+                max: MAX_ENVELOPE_LEN,
+            });
+        }
+
+        let envelope = Envelope::decode(bytes)?;
+        envelope.check_lengths()?;
+        Ok(envelope)
+    }
+
+    /// Check the fixed-size fields of a decoded envelope, see [`Envelope::try_from_bytes`]
+    fn check_lengths(&self) -> Result<(), CodecError> {
+        // Unknown schemes are left to signature verification to reject
+        let scheme = SignatureScheme::of(self).unwrap_or_default();
+        check_len("pubkey", self.pubkey.len(), scheme.public_key_len())?;
+        check_len("sig", self.sig.len(), scheme.signature_len())?;
+        check_len("nonce", self.nonce.len(), 24)?;
+        check_len("ephemeral_key", self.ephemeral_key.len(), 32)?;
+        for slot in &self.wrapped_keys {
+            check_len("wrapped_keys.recipient", slot.recipient.len(), 32)?;
+            check_len(
+                "wrapped_keys.wrapped",
+                slot.wrapped.len(),
+                crypto::KEY_LEN + crypto::TAG_LEN,
+            )?;
+        }
+        for cosignature in &self.cosignatures {
+            check_len("cosignatures.sig", cosignature.sig.len(), 64)?;
+        }
+        check_len("payload_digest", self.payload_digest.len(), 32)
+    }
+}
+
+impl EnvelopeBatch {
+    /// Decode a batch of envelopes from untrusted bytes
+    ///
+    /// Each envelope is held to the same limits as [`Envelope::try_from_bytes`].
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<EnvelopeBatch, CodecError> {
+        let batch = EnvelopeBatch::decode(bytes)?;
+        for envelope in &batch.envelopes {
+            let len = envelope.encoded_len();
+            if len > MAX_ENVELOPE_LEN {
+                return Err(CodecError::TooLarge {
+                    len,
+                    max: MAX_ENVELOPE_LEN,
+                });
+            }
+            envelope.check_lengths()?;
+        }
+        Ok(batch)
+    }
+}
+
+fn check_len(field: &'static str, len: usize, expected: usize) -> Result<(), CodecError> {
+    if len == 0 || len == expected {
+        Ok(())
+    } else {
+        Err(CodecError::InvalidLength { field, len })
+    }
+}
+
+/// gRPC codec for the subscribe RPCs that decodes envelopes via [`Envelope::try_from_bytes`]
+///
+/// `Req` is the request message: [`SubscribeReq`], or `SubscribeControl` for
+/// credit-based subscriptions. `Dec` is [`EnvelopeDecoder`], or
+/// [`BatchDecoder`] for batched subscriptions.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SubscribeCodec<Req = SubscribeReq, Dec = EnvelopeDecoder>(
+    PhantomData<(Req, Dec)>,
+);
+
+impl<Req, Dec> Default for SubscribeCodec<Req, Dec> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<Req, Dec> Codec for SubscribeCodec<Req, Dec>
+where
+    Req: Message + Send + 'static,
+    Dec: Decoder<Error = Status> + Default + Send + 'static,
+    Dec::Item: Send + 'static,
+{
+    type Encode = Req;
+    type Decode = Dec::Item;
+    type Encoder = <ProstCodec<Req, Envelope> as Codec>::Encoder;
+    type Decoder = Dec;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        ProstCodec::<Req, Envelope>::default().encoder()
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        Dec::default()
+    }
+}
+
+/// Decoder half of [`SubscribeCodec`]
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct EnvelopeDecoder;
+
+impl Decoder for EnvelopeDecoder {
+    type Item = Envelope;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Envelope>, Status> {
+        let frame = src.copy_to_bytes(src.remaining());
+        Envelope::try_from_bytes(&frame)
+            .map(Some)
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+}
+
+/// Decoder for batched subscriptions, via [`EnvelopeBatch::try_from_bytes`]
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct BatchDecoder;
+
+impl Decoder for BatchDecoder {
+    type Item = EnvelopeBatch;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<EnvelopeBatch>, Status> {
+        let frame = src.copy_to_bytes(src.remaining());
+        EnvelopeBatch::try_from_bytes(&frame)
+            .map(Some)
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+}
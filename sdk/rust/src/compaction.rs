@@ -0,0 +1,136 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Keyed messages for log-compacted topics
+//!
+//! On a compacted topic only the latest envelope per compaction key matters,
+//! and a tombstone deletes the key. [`Client::send_keyed`] and
+//! [`Client::send_tombstone`] set `Envelope::key` and `Envelope::tombstone`
+//! and bind both into the signed AAD as `"key"` (hex) and `"tombstone"`.
+//! [`KeyedView`] folds a stream of envelopes into the resulting key/value
+//! state.
+
+use crate::pb::Envelope;
+use crate::{Client, Outgoing};
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use std::collections::HashMap;
+
+/// Latest envelope per compaction key, with tombstoned keys removed
+#[derive(Debug, Clone, Default)]
+pub struct KeyedView {
+    latest: HashMap<Vec<u8>, Envelope>,
+}
+
+impl KeyedView {
+    /// Empty view
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply the next envelope in delivery order
+    ///
+    /// A keyed envelope replaces the key's current value and a tombstone
+    /// removes it; unkeyed envelopes are ignored. Returns whether the envelope
+    /// was keyed. Fails if `key` or `tombstone` disagrees with the signed AAD,
+    /// so a rewritten key cannot overwrite another key's value. Verify the
+    /// envelope's signature before applying it.
+    pub fn apply(&mut self, envelope: Envelope) -> Result<bool> {
+        let (key, tombstone) = signed_compaction(&envelope);
+        anyhow::ensure!(
+            key == envelope.key && tombstone == envelope.tombstone,
+            "Compaction key of {} does not match its AAD",
+            envelope.msg_id
+        );
+        if envelope.key.is_empty() {
+            return Ok(false);
+        }
+        if envelope.tombstone {
+            self.latest.remove(&envelope.key);
+        } else {
+            self.latest.insert(envelope.key.clone(), envelope);
+        }
+        Ok(true)
+    }
+
+    /// Latest envelope for `key`, unless it was tombstoned
+    pub fn get(&self, key: &[u8]) -> Option<&Envelope> {
+        self.latest.get(key)
+    }
+
+    /// Number of live keys
+    pub fn len(&self) -> usize {
+        self.latest.len()
+    }
+
+    /// Whether no key is live
+    pub fn is_empty(&self) -> bool {
+        self.latest.is_empty()
+    }
+
+    /// Live keys with their latest envelopes, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], &Envelope)> {
+        self.latest
+            .iter()
+            .map(|(key, envelope)| (key.as_slice(), envelope))
+    }
+}
+
+/// Compaction key and tombstone flag as declared in the envelope's AAD
+fn signed_compaction(envelope: &Envelope) -> (Vec<u8>, bool) {
+    let aad = serde_json::from_slice::<serde_json::Value>(&envelope.aad).unwrap_or_default();
+    let key = aad
+        .get("key")
+        .and_then(|key| key.as_str())
+        .and_then(|key| hex::decode(key).ok())
+        .unwrap_or_default();
+    let tombstone = aad
+        .get("tombstone")
+        .and_then(|tombstone| tombstone.as_bool())
+        .unwrap_or(false);
+    (key, tombstone)
+}
+
+impl Client {
+    /// Send `payload` as the new value of `key` on a compacted topic
+    pub async fn send_keyed(&mut self, topic: &str, key: &[u8], payload: &[u8]) -> Result<String> {
+        anyhow::ensure!(!key.is_empty(), "Compaction key must be non-empty");
+        let outgoing = Outgoing {
+            key,
+            ..Default::default()
+        };
+        self.send_envelope(topic, outgoing, payload).await
+    }
+
+    /// Send a tombstone deleting `key` from a compacted topic
+    pub async fn send_tombstone(&mut self, topic: &str, key: &[u8]) -> Result<String> {
+        anyhow::ensure!(!key.is_empty(), "Compaction key must be non-empty");
+        let outgoing = Outgoing {
+            key,
+            tombstone: true,
+            ..Default::default()
+        };
+        self.send_envelope(topic, outgoing, &[]).await
+    }
+
+    /// Subscribe to a compacted topic and fold it into a [`KeyedView`]
+    ///
+    /// Consumes the subscription until the node ends it, verifying every
+    /// envelope first. Fails on the first envelope that does not verify or
+    /// whose compaction fields were tampered with.
+    pub async fn materialize(&mut self, topic: &[u8]) -> Result<KeyedView> {
+        let mut subscription = self.subscribe_verifying(topic).await?;
+        let mut view = KeyedView::new();
+        while let Some(envelope) = subscription.next().await {
+            let envelope = envelope.context("receive envelope")?;
+            anyhow::ensure!(
+                self.verify(&envelope)?,
+                "Invalid signature on {}",
+                envelope.msg_id
+            );
+            if let Some(envelope) = self.post_receive.after_verification(envelope) {
+                view.apply(envelope)?;
+            }
+        }
+        Ok(view)
+    }
+}
@@ -0,0 +1,44 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Compressed subscription streams
+//!
+//! A subscriber on a constrained link can let the node compress the envelopes
+//! it streams, while one on a fast local link keeps the stream raw. The
+//! accepted compressions are advertised in the `grpc-accept-encoding` header
+//! of `Subscribe`, and the node picks one of them or streams uncompressed.
+//! Messages are decompressed by the transport before they are decoded, so
+//! envelopes, signatures and size metrics are the same either way.
+
+use crate::Client;
+use tonic::codec::CompressionEncoding;
+
+/// Compression a subscription stream may use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Compression {
+    /// gzip; supported by most gRPC servers
+    Gzip,
+    /// Zstandard; better ratio and faster than gzip
+    Zstd,
+}
+
+impl Compression {
+    pub(crate) fn encoding(self) -> CompressionEncoding {
+        match self {
+            Self::Gzip => CompressionEncoding::Gzip,
+            Self::Zstd => CompressionEncoding::Zstd,
+        }
+    }
+}
+
+impl Client {
+    /// Let the node compress subscription streams with any of `accepted`
+    ///
+    /// The node chooses among them; if it supports none, or ignores the
+    /// header, the stream stays uncompressed. An empty list, the default,
+    /// asks for raw streams.
+    pub fn with_subscribe_compression(mut self, accepted: &[Compression]) -> Self {
+        self.subscribe_compression = accepted.to_vec();
+        self
+    }
+}
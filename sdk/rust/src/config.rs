@@ -0,0 +1,164 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Read-only view of a client's effective configuration
+//!
+//! Builder and `with_*` settings end up in private fields, so a chain that
+//! silently did something else is hard to spot. [`Client::config`] reports
+//! what the client will actually use, with secrets left out, so it can be
+//! logged or printed when debugging a deployment.
+
+use crate::auth::Bearer;
+use crate::crypto::{SignOrder, VerifyMode};
+use crate::peer::PeerIdentity;
+use crate::tls::TlsConfig;
+use crate::Client;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How the connection to the node is secured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlsMode {
+    /// Plaintext HTTP/2
+    None,
+    /// TLS validating the node's certificate
+    ServerAuth,
+    /// TLS with a client certificate presented to the node
+    Mutual,
+}
+
+/// How requests are authenticated to the node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthMode {
+    /// No `authorization` metadata
+    None,
+    /// A fixed bearer token, shown as `****` and at most its last four characters
+    Bearer { masked: String },
+    /// A bearer token re-read from a file
+    BearerFile { path: PathBuf },
+}
+
+/// Retry settings from [`Client::with_send_retries`], [`Client::with_retry_jitter`]
+/// and [`Client::with_retry_budget`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryView {
+    pub max_retries: u32,
+    pub backoff: Duration,
+    /// Bound of the initial random delay, if retry waits are jittered
+    pub jitter: Option<Duration>,
+    /// Budget ratio and token cap, if a retry budget is set
+    pub budget: Option<(f64, u32)>,
+}
+
+/// Transport settings fixed when the client was built
+#[derive(Debug, Clone)]
+pub(crate) struct Transport {
+    endpoint: String,
+    tls: TlsMode,
+    tls_domain: Option<String>,
+    proxy: Option<String>,
+    resolved_addr: Option<SocketAddr>,
+}
+
+impl Transport {
+    pub(crate) fn new(
+        endpoint: &str,
+        tls: Option<&TlsConfig>,
+        proxy: Option<String>,
+        resolved_addr: Option<SocketAddr>,
+    ) -> Self {
+        Self {
+            endpoint: crate::proxy::redact(endpoint),
+            tls: match tls {
+                None => TlsMode::None,
+                Some(tls) if tls.has_identity() => TlsMode::Mutual,
+                Some(_) => TlsMode::ServerAuth,
+            },
+            tls_domain: tls.and_then(TlsConfig::domain).map(str::to_owned),
+            proxy,
+            resolved_addr,
+        }
+    }
+}
+
+/// Snapshot of a client's effective configuration, from [`Client::config`]
+///
+/// Never holds key material or tokens: keys appear as their
+/// [fingerprint](crate::keyring::fingerprint) and a bearer token masked.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct ClientConfigView {
+    /// Endpoint URI, with any credentials in it masked
+    pub endpoint: String,
+    pub tls: TlsMode,
+    /// Name the node's certificate is validated against, if overridden
+    pub tls_domain: Option<String>,
+    /// `host:port` of the HTTP CONNECT proxy in use
+    pub proxy: Option<String>,
+    /// Address dialled instead of resolving the endpoint host
+    pub resolved_addr: Option<SocketAddr>,
+    /// Node identity signatures are bound to
+    pub peer_identity: Option<PeerIdentity>,
+    pub auth: AuthMode,
+    /// Fingerprint of the signing key; `None` means sends will fail
+    pub signing_key: Option<String>,
+    pub sign_order: SignOrder,
+    /// Version of the end-to-end topic key, if encryption is enabled
+    pub encryption_key_version: Option<u32>,
+    /// Number of trusted keys, if verification is restricted to a keyring
+    pub keyring_len: Option<usize>,
+    pub verify_mode: VerifyMode,
+    pub ping_timeout: Duration,
+    /// Deadline the next send attempt gets, if adaptive timeouts are enabled
+    pub send_timeout: Option<Duration>,
+    pub retry: Option<RetryView>,
+    /// Capacity of the send queue, if one is configured
+    pub send_queue: Option<usize>,
+    pub ordered_send: bool,
+}
+
+/// `****` followed by the last four characters of tokens long enough to spare them
+fn mask(token: &str) -> String {
+    let chars = token.chars().count();
+    if chars < 16 {
+        return "****".to_string();
+    }
+    let tail: String = token.chars().skip(chars - 4).collect();
+    format!("****{tail}")
+}
+
+impl Client {
+    /// The configuration this client is running with, secrets masked
+    pub fn config(&self) -> ClientConfigView {
+        let transport = &self.transport;
+        ClientConfigView {
+            endpoint: transport.endpoint.clone(),
+            tls: transport.tls,
+            tls_domain: transport.tls_domain.clone(),
+            proxy: transport.proxy.clone(),
+            resolved_addr: transport.resolved_addr,
+            peer_identity: self.peer_identity,
+            auth: match &self.bearer {
+                None => AuthMode::None,
+                Some(Bearer::Static(token)) => AuthMode::Bearer {
+                    masked: mask(token),
+                },
+                Some(Bearer::File(file)) => AuthMode::BearerFile {
+                    path: file.path().to_path_buf(),
+                },
+            },
+            signing_key: self
+                .verifying_key
+                .map(|key| crate::keyring::fingerprint(&key.to_bytes())),
+            sign_order: self.sign_order,
+            encryption_key_version: self.encryption.as_ref().map(|key| key.version),
+            keyring_len: self.keyring.as_ref().map(|keyring| keyring.len()),
+            verify_mode: self.verify_mode,
+            ping_timeout: self.ping_timeout,
+            send_timeout: self.send_timeout(),
+            retry: self.retry.view(),
+            send_queue: self.send_queue.as_ref().map(|queue| queue.capacity()),
+            ordered_send: self.send_order.is_some(),
+        }
+    }
+}
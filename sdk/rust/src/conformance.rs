@@ -0,0 +1,532 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Cross-SDK conformance checks
+//!
+//! [`reference`] computes this crate's results for the signature scheme
+//! vectors in `sdk/tests/test_vectors.json`, and [`cross`] runs another SDK's
+//! conformance command on the same vectors and compares its output against
+//! them. The `securefabric-conformance` binary prints [`reference`] for the
+//! vectors on its stdin, which is the contract every external command must
+//! follow:
+//!
+//! ```text
+//! {"signature_schemes": {"ed25519": [{"description": ..., "public_key": hex,
+//!   "signature": hex, "verified": bool}, ...], "secp256k1": [...], "ed25519ph": [...]}}
+//! ```
+//!
+//! `public_key` is derived from `secret_key`, `signature` is signed over
+//! `message` (under `context` for Ed25519ph) and `verified` is whether the
+//! vector's own signature verifies against its own public key.
+//!
+//! [`run`] checks this crate against the remaining sections of the vectors
+//! file, `encryption`, `signatures`, `msg_id`, `replay_protection` and
+//! `tamper_detection`, and returns a [`ConformanceReport`] with a result per
+//! vector instead of stopping at the first mismatch.
+//!
+//! The canonical vectors are compiled into the crate as [`VECTORS`], so
+//! [`run_embedded`] works from any working directory and from dependents of
+//! the crate; [`run`] checks a vectors file given by path instead.
+
+use crate::crypto::scheme::{
+    sign_prehashed, verify_prehashed, Digest, Sha512, SigningKey, VerifyingKey,
+};
+use crate::crypto::{SignatureScheme, VerifyMode};
+use anyhow::{Context, Result};
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// The canonical vectors file, `sdk/tests/test_vectors.json`, as of this build
+pub const VECTORS: &str = include_str!("../../tests/test_vectors.json");
+
+/// Replay window used by `replay_protection` vectors that do not set one
+const DEFAULT_REPLAY_WINDOW: u64 = 64;
+
+/// Schemes covered by the comparison, by their key in the vectors file
+const SCHEMES: [(&str, SignatureScheme); 3] = [
+    ("ed25519", SignatureScheme::Ed25519),
+    ("secp256k1", SignatureScheme::Secp256k1),
+    ("ed25519ph", SignatureScheme::Ed25519ph),
+];
+
+/// This crate's results for the vectors file `vectors`
+///
+/// Fails if a vector is malformed, for example holds a key that does not
+/// parse.
+pub fn reference(vectors: &Value) -> Result<Value> {
+    let mut schemes = Map::new();
+    for (name, scheme) in SCHEMES {
+        let Some(tests) = vectors["signature_schemes"][name].as_array() else {
+            continue;
+        };
+        let results = tests
+            .iter()
+            .map(|vector| {
+                let description = vector["description"].as_str().unwrap_or_default();
+                signature_result(scheme, vector).with_context(|| format!("{name}: {description}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        schemes.insert(name.to_string(), Value::Array(results));
+    }
+    Ok(json!({ "signature_schemes": schemes }))
+}
+
+fn signature_result(scheme: SignatureScheme, vector: &Value) -> Result<Value> {
+    let field = |key: &str| -> Result<Vec<u8>> {
+        let value = vector[key]
+            .as_str()
+            .with_context(|| format!("missing {key}"))?;
+        hex::decode(value).with_context(|| format!("invalid hex in {key}"))
+    };
+    let secret = field("secret_key")?;
+    let message = field("message")?;
+    let public_key = field("public_key")?;
+    let signature = field("signature")?;
+
+    let (derived, signed, verified) = match scheme {
+        SignatureScheme::Ed25519 | SignatureScheme::Secp256k1 => {
+            let key = match scheme {
+                SignatureScheme::Secp256k1 => SigningKey::from(
+                    k256::ecdsa::SigningKey::from_slice(&secret).context("invalid secret_key")?,
+                ),
+                _ => SigningKey::from(ed25519_secret(&secret)?),
+            };
+            let verified = VerifyingKey::from_bytes(scheme, &public_key)
+                .is_ok_and(|public| public.verify(&message, &signature));
+            (key.verifying_key().to_bytes(), key.sign(&message), verified)
+        }
+        SignatureScheme::Ed25519ph => {
+            let key = ed25519_secret(&secret)?;
+            let context = field("context")?;
+            let prehash = || Sha512::new_with_prefix(&message);
+            let verified = public_key
+                .as_slice()
+                .try_into()
+                .ok()
+                .and_then(|bytes| ed25519_dalek::VerifyingKey::from_bytes(bytes).ok())
+                .is_some_and(|public| {
+                    verify_prehashed(&public, prehash(), &context, &signature, VerifyMode::Strict)
+                });
+            let signed = sign_prehashed(&key, prehash(), &context);
+            (key.verifying_key().to_bytes().to_vec(), signed, verified)
+        }
+    };
+
+    Ok(json!({
+        "description": vector["description"],
+        "public_key": hex::encode(derived),
+        "signature": hex::encode(signed),
+        "verified": verified,
+    }))
+}
+
+fn ed25519_secret(secret: &[u8]) -> Result<ed25519_dalek::SigningKey> {
+    let secret = secret.try_into().context("secret_key must be 32 bytes")?;
+    Ok(ed25519_dalek::SigningKey::from_bytes(secret))
+}
+
+/// Run an external SDK's conformance `command` and compare it with [`reference`]
+///
+/// `vectors` is the contents of the vectors file; it is written to the
+/// command's stdin, and the command must print its results as JSON on stdout.
+/// Fails if the command cannot run, exits unsuccessfully or prints anything
+/// that differs from this crate's results, listing every differing field.
+pub fn cross(mut command: Command, vectors: &str) -> Result<()> {
+    let parsed: Value = serde_json::from_str(vectors).context("parse test vectors")?;
+    let expected = reference(&parsed)?;
+
+    let program = command.get_program().to_string_lossy().into_owned();
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("run {program}"))?;
+    let mut stdin = child.stdin.take().context("no stdin")?;
+    let input = vectors.to_string();
+    // Written on another thread so a command that prints before reading all
+    // of stdin cannot deadlock against a full pipe.
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("wait for {program}"))?;
+    // A command that exits without reading stdin breaks the pipe; its exit
+    // status and output say more than the write error would.
+    let _ = writer.join();
+
+    anyhow::ensure!(
+        output.status.success(),
+        "{program} failed ({}): {}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    let actual: Value = serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("{program} did not print JSON"))?;
+
+    let mut differences = Vec::new();
+    diff("", &expected, &actual, &mut differences);
+    anyhow::ensure!(
+        differences.is_empty(),
+        "{program} disagrees with the Rust SDK on {} field(s):\n{}",
+        differences.len(),
+        differences.join("\n")
+    );
+    Ok(())
+}
+
+/// Append a line for each field where `actual` differs from `expected`
+fn diff(path: &str, expected: &Value, actual: &Value, differences: &mut Vec<String>) {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            for (key, value) in expected {
+                let path = format!("{path}.{key}");
+                match actual.get(key) {
+                    Some(actual) => diff(&path, value, actual, differences),
+                    None => differences.push(format!("  {path}: missing, expected {value}")),
+                }
+            }
+            for key in actual.keys().filter(|key| !expected.contains_key(*key)) {
+                differences.push(format!("  {path}.{key}: unexpected"));
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) if expected.len() == actual.len() => {
+            for (index, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+                diff(&format!("{path}[{index}]"), expected, actual, differences);
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) => differences.push(format!(
+            "  {path}: expected {} entries, got {}",
+            expected.len(),
+            actual.len()
+        )),
+        _ if expected != actual => {
+            differences.push(format!("  {path}: expected {expected}, got {actual}"))
+        }
+        _ => {}
+    }
+}
+
+/// Outcome of checking one vector
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaseResult {
+    /// The vector's `description`
+    pub description: String,
+    /// Why the vector failed, or `None` if it passed
+    pub failure: Option<String>,
+}
+
+impl CaseResult {
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+/// Results for one section of the vectors file, in file order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SectionReport {
+    pub cases: Vec<CaseResult>,
+}
+
+impl SectionReport {
+    /// Number of vectors that passed
+    pub fn passed(&self) -> usize {
+        self.cases.iter().filter(|case| case.passed()).count()
+    }
+
+    /// Number of vectors that failed
+    pub fn failed(&self) -> usize {
+        self.cases.len() - self.passed()
+    }
+
+    /// Vectors that failed
+    pub fn failures(&self) -> impl Iterator<Item = &CaseResult> {
+        self.cases.iter().filter(|case| !case.passed())
+    }
+
+    fn from_vectors(vectors: &Value, check: impl Fn(&Value) -> Result<()>) -> Self {
+        let cases = vectors
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|vector| CaseResult {
+                description: vector["description"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                failure: check(vector).err().map(|error| format!("{error:#}")),
+            })
+            .collect();
+        Self { cases }
+    }
+}
+
+/// Results of [`run`] for every section of a vectors file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConformanceReport {
+    /// `encryption.xchacha20_poly1305`
+    pub encryption: SectionReport,
+    /// `signatures.ed25519`
+    pub signatures: SectionReport,
+    /// `msg_id.tests`
+    pub msg_id: SectionReport,
+    /// `replay_protection.tests`
+    pub replay: SectionReport,
+    /// `tamper_detection.tests`
+    pub tamper: SectionReport,
+}
+
+impl ConformanceReport {
+    /// Sections with their names in the vectors file
+    pub fn sections(&self) -> [(&'static str, &SectionReport); 5] {
+        [
+            ("encryption", &self.encryption),
+            ("signatures", &self.signatures),
+            ("msg_id", &self.msg_id),
+            ("replay_protection", &self.replay),
+            ("tamper_detection", &self.tamper),
+        ]
+    }
+
+    /// Number of vectors that passed across all sections
+    pub fn passed(&self) -> usize {
+        self.sections()
+            .iter()
+            .map(|(_, section)| section.passed())
+            .sum()
+    }
+
+    /// Number of vectors that failed across all sections
+    pub fn failed(&self) -> usize {
+        self.sections()
+            .iter()
+            .map(|(_, section)| section.failed())
+            .sum()
+    }
+
+    /// Whether every vector passed
+    pub fn all_passed(&self) -> bool {
+        self.failed() == 0
+    }
+}
+
+impl fmt::Display for ConformanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} passed, {} failed", self.passed(), self.failed())?;
+        for (name, section) in self.sections() {
+            for case in section.failures() {
+                let reason = case.failure.as_deref().unwrap_or_default();
+                write!(f, "\n  {name}: {}: {reason}", case.description)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check this crate against the vectors file at `path`
+///
+/// Fails only if the file cannot be read or is not JSON. A vector that does
+/// not pass, including one that is malformed, is recorded as a failed case
+/// and the remaining vectors are still checked.
+pub fn run(path: impl AsRef<Path>) -> Result<ConformanceReport> {
+    let path = path.as_ref();
+    let vectors = std::fs::read_to_string(path)
+        .with_context(|| format!("read test vectors from {}", path.display()))?;
+    let vectors = serde_json::from_str(&vectors).context("parse test vectors")?;
+    Ok(report(&vectors))
+}
+
+/// Check this crate against the embedded [`VECTORS`]
+pub fn run_embedded() -> Result<ConformanceReport> {
+    let vectors = serde_json::from_str(VECTORS).context("parse embedded test vectors")?;
+    Ok(report(&vectors))
+}
+
+/// Check this crate against already parsed vectors, as [`run`] does
+pub fn report(vectors: &Value) -> ConformanceReport {
+    ConformanceReport {
+        encryption: SectionReport::from_vectors(
+            &vectors["encryption"]["xchacha20_poly1305"],
+            check_encryption,
+        ),
+        signatures: SectionReport::from_vectors(&vectors["signatures"]["ed25519"], check_signature),
+        msg_id: SectionReport::from_vectors(&vectors["msg_id"]["tests"], check_msg_id),
+        replay: SectionReport::from_vectors(&vectors["replay_protection"]["tests"], check_replay),
+        tamper: SectionReport::from_vectors(&vectors["tamper_detection"]["tests"], check_tamper),
+    }
+}
+
+/// Decode the hex string `vector[key]`
+fn hex_field(vector: &Value, key: &str) -> Result<Vec<u8>> {
+    let value = vector[key]
+        .as_str()
+        .with_context(|| format!("missing {key}"))?;
+    hex::decode(value).with_context(|| format!("invalid hex in {key}"))
+}
+
+fn check_encryption(vector: &Value) -> Result<()> {
+    let key = hex_field(vector, "key")?;
+    let nonce = hex_field(vector, "nonce")?;
+    let plaintext = hex_field(vector, "plaintext")?;
+    let aad = hex_field(vector, "aad")?;
+    let expected_ciphertext = hex_field(vector, "ciphertext")?;
+    let expected_tag = hex_field(vector, "tag")?;
+
+    let (ciphertext, tag) = crate::crypto::encrypt(&key, &nonce, &plaintext, &aad)?;
+    anyhow::ensure!(
+        ciphertext == expected_ciphertext,
+        "ciphertext mismatch: got {}",
+        hex::encode(ciphertext)
+    );
+    anyhow::ensure!(
+        tag == expected_tag,
+        "tag mismatch: got {}",
+        hex::encode(tag)
+    );
+    let decrypted = crate::crypto::decrypt(&key, &nonce, &ciphertext, &aad, &tag)?;
+    anyhow::ensure!(decrypted == plaintext, "round trip changed the plaintext");
+    Ok(())
+}
+
+fn check_signature(vector: &Value) -> Result<()> {
+    let secret = hex_field(vector, "secret_key")?;
+    let public_key = hex_field(vector, "public_key")?;
+    let message = hex_field(vector, "message")?;
+    let expected_signature = hex_field(vector, "signature")?;
+
+    let key = SigningKey::from(ed25519_secret(&secret)?);
+    let derived = key.verifying_key().to_bytes();
+    anyhow::ensure!(
+        derived == public_key,
+        "public key mismatch: got {}",
+        hex::encode(derived)
+    );
+    let signature = key.sign(&message);
+    anyhow::ensure!(
+        signature == expected_signature,
+        "signature mismatch: got {}",
+        hex::encode(signature)
+    );
+    let public = VerifyingKey::from_bytes(SignatureScheme::Ed25519, &public_key)?;
+    anyhow::ensure!(
+        public.verify(&message, &expected_signature),
+        "signature does not verify"
+    );
+    Ok(())
+}
+
+fn check_msg_id(vector: &Value) -> Result<()> {
+    let pubkey = hex_field(vector, "pubkey")?;
+    let nonce = hex_field(vector, "nonce")?;
+    let seq = vector["seq"].as_u64().context("missing seq")?;
+    let id = vector["msg_id_hash"]
+        .as_u64()
+        .context("missing msg_id_hash")?;
+    let expected = vector["msg_id"].as_str().context("missing msg_id")?;
+
+    let hasher = u32::try_from(id)
+        .ok()
+        .and_then(crate::msg_id::builtin)
+        .with_context(|| format!("unknown msg_id_hash {id}"))?;
+    let msg_id = hex::encode(hasher.hash(&crate::msg_id::preimage(&pubkey, seq, &nonce)));
+    anyhow::ensure!(msg_id == expected, "msg_id mismatch: got {msg_id}");
+    Ok(())
+}
+
+fn check_replay(vector: &Value) -> Result<()> {
+    let counters = vector["counters"]
+        .as_array()
+        .context("missing counters")?
+        .iter()
+        .map(|counter| counter.as_u64().context("counter is not an integer"))
+        .collect::<Result<Vec<_>>>()?;
+    let expected = vector["expected"]
+        .as_array()
+        .context("missing expected")?
+        .iter()
+        .map(|accepted| accepted.as_bool().context("expected is not a boolean"))
+        .collect::<Result<Vec<_>>>()?;
+    anyhow::ensure!(
+        counters.len() == expected.len(),
+        "{} counters but {} expectations",
+        counters.len(),
+        expected.len()
+    );
+    let window = vector["window_size"]
+        .as_u64()
+        .unwrap_or(DEFAULT_REPLAY_WINDOW);
+    anyhow::ensure!(
+        (1..=64).contains(&window),
+        "unsupported window_size {window}"
+    );
+
+    let mut replay = CounterWindow::new(window);
+    for (index, (&counter, &expected)) in counters.iter().zip(&expected).enumerate() {
+        let accepted = replay.accept(counter);
+        anyhow::ensure!(
+            accepted == expected,
+            "counter {counter} at index {index} was {}",
+            if accepted { "accepted" } else { "rejected" }
+        );
+    }
+    Ok(())
+}
+
+fn check_tamper(vector: &Value) -> Result<()> {
+    let key = hex_field(vector, "key")?;
+    let nonce = hex_field(vector, "nonce")?;
+    let should_fail = vector["should_fail"]
+        .as_bool()
+        .context("missing should_fail")?;
+    let either = |plain: &str, tampered: &str| match vector.get(tampered) {
+        Some(_) => hex_field(vector, tampered),
+        None => hex_field(vector, plain),
+    };
+    let ciphertext = either("ciphertext", "tampered_ciphertext")?;
+    let tag = either("tag", "tampered_tag")?;
+
+    let opened = crate::crypto::decrypt(&key, &nonce, &ciphertext, &[], &tag).is_ok();
+    anyhow::ensure!(
+        opened != should_fail,
+        "decryption {}",
+        if opened { "succeeded" } else { "failed" }
+    );
+    Ok(())
+}
+
+/// Sliding-window anti-replay check over counters, as in IPsec
+struct CounterWindow {
+    size: u64,
+    highest: u64,
+    /// Bit `i` is set if `highest - i` was accepted
+    seen: u64,
+}
+
+impl CounterWindow {
+    fn new(size: u64) -> Self {
+        Self {
+            size,
+            highest: 0,
+            seen: 0,
+        }
+    }
+
+    /// Accept `counter` unless it was seen before or is too far behind
+    fn accept(&mut self, counter: u64) -> bool {
+        if counter > self.highest {
+            let shift = counter - self.highest;
+            self.seen = if shift >= 64 { 0 } else { self.seen << shift };
+            self.seen |= 1;
+            self.highest = counter;
+            return true;
+        }
+        let behind = self.highest - counter;
+        if behind >= self.size || self.seen & (1 << behind) != 0 {
+            return false;
+        }
+        self.seen |= 1 << behind;
+        true
+    }
+}
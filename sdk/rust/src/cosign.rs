@@ -0,0 +1,163 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Envelopes signed by several parties
+//!
+//! Some workflows accept a message only once several parties have signed it.
+//! Besides the sender's signature an envelope can carry [`Cosignature`]s, each
+//! over the [co-signing preimage](cosigning_preimage), and a receiver checks
+//! them against a [`CosignPolicy`]: a set of keys and how many of them must
+//! have signed. Co-signatures do not cover each other, so parties can add
+//! theirs in any order, before or after the envelope is sent.
+
+use crate::crypto::scheme::{SignatureScheme, SigningKey, VerifyMode, VerifyingKey};
+use crate::keyring::fingerprint;
+use crate::pb::{Cosignature, Envelope};
+use crate::{signing_preimage, verify_signature, Client};
+
+/// Domain separation prefix of the co-signing preimage
+pub const COSIGN_CONTEXT: &[u8] = b"securefabric/cosign/v1";
+
+/// Why a co-signed envelope was rejected
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum CosignError {
+    /// A key in the policy co-signed, but its signature does not verify
+    #[error("invalid co-signature from {signer}")]
+    BadSignature { signer: String },
+
+    /// Too few keys in the policy signed the envelope
+    #[error("{valid} of {required} required signatures, missing {missing:?}")]
+    BelowThreshold {
+        valid: usize,
+        required: usize,
+        /// Fingerprints of the policy keys that did not sign
+        missing: Vec<String>,
+    },
+}
+
+/// Bytes covered by a co-signature
+///
+/// `COSIGN_CONTEXT || u32_be(len(pubkey)) || pubkey` followed by the bytes the
+/// sender signs, computed over the payload as carried in the envelope. Binding
+/// the sender's key keeps a co-signature from being moved onto the same
+/// message published by someone else.
+pub fn cosigning_preimage(envelope: &Envelope) -> Vec<u8> {
+    let signed = signing_preimage(
+        &envelope.aad,
+        &envelope.nonce,
+        &envelope.payload,
+        envelope.key_version,
+    );
+    let mut message =
+        Vec::with_capacity(COSIGN_CONTEXT.len() + 4 + envelope.pubkey.len() + signed.len());
+    message.extend_from_slice(COSIGN_CONTEXT);
+    message.extend_from_slice(&(envelope.pubkey.len() as u32).to_be_bytes());
+    message.extend_from_slice(&envelope.pubkey);
+    message.extend_from_slice(&signed);
+    message
+}
+
+/// Add `key`'s co-signature to `envelope`, replacing an earlier one by the same key
+///
+/// The envelope must be complete: changing anything the sender signed
+/// afterwards invalidates every co-signature.
+pub fn cosign(envelope: &mut Envelope, key: &SigningKey) {
+    let signer = fingerprint(&key.verifying_key().to_bytes());
+    let sig = key.sign(&cosigning_preimage(envelope));
+    envelope.cosignatures.retain(|c| c.signer != signer);
+    envelope.cosignatures.push(Cosignature {
+        signer,
+        sig,
+        sig_scheme: key.scheme().wire_value(),
+    });
+}
+
+/// Keys whose signatures an envelope needs, and how many of them
+#[derive(Debug, Clone)]
+pub struct CosignPolicy {
+    signers: Vec<(String, VerifyingKey)>,
+    threshold: usize,
+}
+
+impl CosignPolicy {
+    /// Require a signature from every key in `signers`
+    pub fn all(signers: impl IntoIterator<Item = VerifyingKey>) -> Self {
+        let signers = Self::fingerprinted(signers);
+        Self {
+            threshold: signers.len(),
+            signers,
+        }
+    }
+
+    /// Require signatures from at least `threshold` of `signers`
+    ///
+    /// Panics if `threshold` is zero or exceeds the number of distinct keys.
+    pub fn threshold(threshold: usize, signers: impl IntoIterator<Item = VerifyingKey>) -> Self {
+        let signers = Self::fingerprinted(signers);
+        assert!(
+            (1..=signers.len()).contains(&threshold),
+            "threshold {threshold} out of range for {} signers",
+            signers.len()
+        );
+        Self { signers, threshold }
+    }
+
+    fn fingerprinted(
+        signers: impl IntoIterator<Item = VerifyingKey>,
+    ) -> Vec<(String, VerifyingKey)> {
+        let mut fingerprinted: Vec<(String, VerifyingKey)> = Vec::new();
+        for key in signers {
+            let fp = fingerprint(&key.to_bytes());
+            if !fingerprinted.iter().any(|(known, _)| *known == fp) {
+                fingerprinted.push((fp, key));
+            }
+        }
+        fingerprinted
+    }
+
+    /// Check that enough keys of the policy signed `envelope`
+    ///
+    /// The sender counts when its key is in the policy and its signature
+    /// verifies; every other key counts through a valid co-signature.
+    /// Co-signatures from keys outside the policy are ignored.
+    pub fn check(&self, envelope: &Envelope) -> Result<(), CosignError> {
+        let preimage = cosigning_preimage(envelope);
+        let mut missing = Vec::new();
+        for (fp, key) in &self.signers {
+            if key.to_bytes() == envelope.pubkey && verify_signature(envelope).unwrap_or(false) {
+                continue;
+            }
+            let Some(cosignature) = envelope.cosignatures.iter().find(|c| c.signer == *fp) else {
+                missing.push(fp.clone());
+                continue;
+            };
+            let valid = SignatureScheme::from_wire(cosignature.sig_scheme).is_some_and(|scheme| {
+                key.supports(scheme)
+                    && key.verify_scheme(scheme, &preimage, &cosignature.sig, VerifyMode::Strict)
+            });
+            if !valid {
+                return Err(CosignError::BadSignature { signer: fp.clone() });
+            }
+        }
+
+        let valid = self.signers.len() - missing.len();
+        if valid < self.threshold {
+            return Err(CosignError::BelowThreshold {
+                valid,
+                required: self.threshold,
+                missing,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Client {
+    /// Co-sign every envelope this client sends with `key` as well
+    ///
+    /// Can be called repeatedly to add several co-signers.
+    pub fn with_cosigner(mut self, key: impl Into<SigningKey>) -> Self {
+        self.cosigners.push(key.into());
+        self
+    }
+}
@@ -0,0 +1,109 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Credit-based flow control for subscriptions
+//!
+//! HTTP/2 flow control bounds the bytes in flight per stream, not the number
+//! of envelopes a slow consumer has yet to process. With
+//! [`Client::subscribe_with_credits`] the node sends no more envelopes than the
+//! subscriber has granted, and the SDK grants more only as the application
+//! takes envelopes off the stream.
+
+use crate::capabilities::{rpc_error, Feature};
+use crate::pb::{SubscribeControl, SubscribeReq};
+use crate::subscription::Subscription;
+use crate::{codec, Client};
+use anyhow::{Context, Result};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tonic::codegen::http::uri::PathAndQuery;
+
+/// Credits a subscription has left, replenished as envelopes are consumed
+pub(crate) struct Credits {
+    grants: mpsc::UnboundedSender<SubscribeControl>,
+    remaining: u32,
+    initial: u32,
+    low_watermark: u32,
+}
+
+impl Credits {
+    /// Account for one delivered envelope, topping credits up at the low watermark
+    pub(crate) fn consumed(&mut self) {
+        self.remaining = self.remaining.saturating_sub(1);
+        if self.remaining <= self.low_watermark {
+            let credits = self.initial - self.remaining;
+            // A closed upstream means the call ended; the stream reports why
+            let _ = self.grants.send(SubscribeControl {
+                subscribe: None,
+                credits,
+            });
+            self.remaining = self.initial;
+        }
+    }
+}
+
+impl Client {
+    /// Subscribe to a topic, letting the node send at most `initial` envelopes ahead
+    ///
+    /// Each envelope taken from the returned stream uses up one credit. When
+    /// no more than `low_watermark` are left, the spent credits are granted
+    /// back, so a consumer that stops reading stops the node after at most
+    /// `initial` envelopes. Fails if `initial` is zero or `low_watermark` is
+    /// not below it, and with [`Error::Unsupported`](crate::Error::Unsupported)
+    /// on nodes without `SubscribeCredits`.
+    pub async fn subscribe_with_credits(
+        &mut self,
+        topic: &[u8],
+        initial: u32,
+        low_watermark: u32,
+    ) -> Result<Subscription> {
+        anyhow::ensure!(
+            initial > 0 && low_watermark < initial,
+            "low watermark {low_watermark} must be below {initial} initial credits"
+        );
+        self.check_topic(topic)?;
+        self.require(Feature::Credits).await?;
+
+        let (grants, upstream) = mpsc::unbounded_channel();
+        let open = SubscribeControl {
+            subscribe: Some(SubscribeReq {
+                topic: topic.to_vec(),
+                ..Default::default()
+            }),
+            credits: initial,
+        };
+        grants.send(open).expect("upstream receiver is alive");
+        let req = self.authorized(UnboundedReceiverStream::new(upstream))?;
+
+        let mut grpc = self.subscribe_grpc();
+        grpc.ready().await.context("subscribe to topic")?;
+        let path = PathAndQuery::from_static("/securefabric.FabricNode/SubscribeCredits");
+        let stream = grpc
+            .streaming(
+                req,
+                path,
+                codec::SubscribeCodec::<SubscribeControl>::default(),
+            )
+            .await
+            .map_err(rpc_error(Feature::Credits))
+            .context("subscribe to topic")?
+            .into_inner();
+
+        let resume = self.resume_offsets.get(topic).cloned().unwrap_or_default();
+        let credits = Credits {
+            grants,
+            remaining: initial,
+            initial,
+            low_watermark,
+        };
+        Ok(Subscription::new(
+            stream,
+            topic.to_vec(),
+            self.stats.clone(),
+            self.instruments.clone(),
+            self.verifier(),
+        )
+        .skipping_through(resume)
+        .tracked(&self.active_subscriptions)
+        .with_credits(credits))
+    }
+}
@@ -0,0 +1,382 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Crypto helpers
+
+use crate::pb::Envelope;
+use anyhow::{Context, Result};
+use chacha20poly1305::aead::{AeadInPlace, KeyInit};
+use chacha20poly1305::{Key, Tag, XChaCha20Poly1305, XNonce};
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use rand::rngs::OsRng;
+use zeroize::Zeroizing;
+
+pub mod capability;
+pub mod keyfile;
+pub mod openssh;
+pub mod pipeline;
+pub mod scheme;
+pub mod stream;
+
+pub use scheme::{SignatureScheme, VerifyMode};
+
+/// Which bytes the signature covers when end-to-end encryption is enabled
+///
+/// - `EncryptThenSign` (default) signs the ciphertext. Anyone holding only the
+///   sender's public key, including the node, can authenticate the envelope
+///   without the topic key, and tampered ciphertext is rejected before any
+///   decryption is attempted. The signature does, however, reveal which sender
+///   produced a ciphertext, and a key holder could re-encrypt the plaintext
+///   under a fresh envelope of their own without it being signed by the sender.
+/// - `SignThenEncrypt` signs the plaintext, so the signature attests to the
+///   content itself and stays meaningful after decryption. Verification then
+///   requires the topic key: the node cannot check these envelopes on ingress,
+///   and receivers must decrypt before they can verify.
+///
+/// In both orders the AAD and nonce are signed and the AAD is bound to the
+/// AEAD. Envelopes signed over the plaintext carry `"signed":"plaintext"` in
+/// their AAD so receivers apply the matching inverse order.
+///
+/// The default is `EncryptThenSign` rather than `SignThenEncrypt`. Encrypted
+/// envelopes were already signed over their ciphertext before the order was
+/// configurable, and a node that verifies signatures on ingress cannot check
+/// plaintext signatures, so a `SignThenEncrypt` default would get every
+/// encrypted send from an upgraded client rejected by such nodes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SignOrder {
+    #[default]
+    EncryptThenSign,
+    SignThenEncrypt,
+}
+
+impl SignOrder {
+    /// The order an envelope was signed in, as declared by its AAD
+    pub fn of(envelope: &Envelope) -> SignOrder {
+        if envelope.key_version == 0 {
+            return SignOrder::EncryptThenSign;
+        }
+        let signed = serde_json::from_slice::<serde_json::Value>(&envelope.aad)
+            .ok()
+            .and_then(|aad| aad.get("signed")?.as_str().map(str::to_owned));
+        match signed.as_deref() {
+            Some("plaintext") => SignOrder::SignThenEncrypt,
+            _ => SignOrder::EncryptThenSign,
+        }
+    }
+}
+
+/// XChaCha20-Poly1305 key length in bytes
+pub const KEY_LEN: usize = 32;
+/// XChaCha20-Poly1305 nonce length in bytes, matching `Envelope.nonce`
+pub const NONCE_LEN: usize = 24;
+/// Poly1305 authentication tag length in bytes
+pub const TAG_LEN: usize = 16;
+/// `key_version` of envelopes sealed to individual recipients rather than a topic key
+pub const SEALED_KEY_VERSION: u32 = u32::MAX;
+/// Default limit on the associated data accepted for encryption and decryption
+///
+/// Envelope AAD grows with bound headers and recipients; the limit keeps an
+/// oversized one from costing unbounded memory. Larger AAD fails with
+/// [`Error::AadTooLarge`](crate::Error::AadTooLarge).
+pub const MAX_AAD_LEN: usize = 64 * 1024;
+
+/// Encrypt with XChaCha20-Poly1305, returning `(ciphertext, tag)`
+pub fn encrypt(
+    key: &[u8],
+    nonce: &[u8],
+    plaintext: &[u8],
+    aad: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    encrypt_with(&cipher(key)?, nonce, plaintext, aad, MAX_AAD_LEN)
+}
+
+/// Decrypt and authenticate XChaCha20-Poly1305 ciphertext with a detached tag
+pub fn decrypt(
+    key: &[u8],
+    nonce: &[u8],
+    ciphertext: &[u8],
+    aad: &[u8],
+    tag: &[u8],
+) -> Result<Vec<u8>> {
+    decrypt_with(&cipher(key)?, nonce, ciphertext, aad, tag, MAX_AAD_LEN)
+}
+
+/// Encrypt into the envelope payload layout `ciphertext || tag`
+pub(crate) fn seal(key: &[u8], nonce: &[u8], plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+    seal_with(&cipher(key)?, nonce, plaintext, aad, MAX_AAD_LEN)
+}
+
+/// Decrypt an envelope payload laid out as `ciphertext || tag`
+pub(crate) fn open(key: &[u8], nonce: &[u8], sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+    open_with(&cipher(key)?, nonce, sealed, aad, MAX_AAD_LEN)
+}
+
+/// XChaCha20-Poly1305 cipher keyed once and reused across messages
+///
+/// [`seal`](AeadContext::seal) and [`open`](AeadContext::open) produce and
+/// accept the envelope payload layout `ciphertext || tag`, exactly like
+/// one-shot encryption under the same key, without setting up the cipher for
+/// every message.
+#[derive(Clone)]
+pub struct AeadContext {
+    cipher: XChaCha20Poly1305,
+    max_aad_len: usize,
+}
+
+impl AeadContext {
+    /// Set up the cipher for `key`, accepting up to [`MAX_AAD_LEN`] bytes of AAD
+    pub fn new(key: &[u8; KEY_LEN]) -> Self {
+        Self {
+            cipher: XChaCha20Poly1305::new(Key::from_slice(key)),
+            max_aad_len: MAX_AAD_LEN,
+        }
+    }
+
+    /// Fail with [`Error::AadTooLarge`](crate::Error::AadTooLarge) for AAD over `max` bytes
+    pub fn with_max_aad_len(mut self, max: usize) -> Self {
+        self.max_aad_len = max;
+        self
+    }
+
+    /// Encrypt `plaintext` under `nonce`, binding `aad`, into `ciphertext || tag`
+    pub fn seal(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        seal_with(&self.cipher, nonce, plaintext, aad, self.max_aad_len)
+    }
+
+    /// Decrypt and authenticate `ciphertext || tag` sealed under `nonce` and `aad`
+    pub fn open(&self, nonce: &[u8], aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>> {
+        open_with(&self.cipher, nonce, sealed, aad, self.max_aad_len)
+    }
+}
+
+/// Reject `aad` longer than `max` bytes
+pub(crate) fn check_aad_len(aad: &[u8], max: usize) -> Result<()> {
+    if aad.len() > max {
+        return Err(crate::Error::AadTooLarge {
+            len: aad.len(),
+            max,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+fn encrypt_with(
+    cipher: &XChaCha20Poly1305,
+    nonce: &[u8],
+    plaintext: &[u8],
+    aad: &[u8],
+    max_aad_len: usize,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    check_aad_len(aad, max_aad_len)?;
+    let nonce = xnonce(nonce)?;
+
+    let mut buffer = plaintext.to_vec();
+    let tag = cipher
+        .encrypt_in_place_detached(nonce, aad, &mut buffer)
+        .map_err(|_| anyhow::anyhow!("encryption failed"))?;
+    Ok((buffer, tag.to_vec()))
+}
+
+fn decrypt_with(
+    cipher: &XChaCha20Poly1305,
+    nonce: &[u8],
+    ciphertext: &[u8],
+    aad: &[u8],
+    tag: &[u8],
+    max_aad_len: usize,
+) -> Result<Vec<u8>> {
+    check_aad_len(aad, max_aad_len)?;
+    let nonce = xnonce(nonce)?;
+    if tag.len() != TAG_LEN {
+        anyhow::bail!("Expected {TAG_LEN}-byte tag, got {}", tag.len());
+    }
+
+    let mut buffer = ciphertext.to_vec();
+    cipher
+        .decrypt_in_place_detached(nonce, aad, &mut buffer, Tag::from_slice(tag))
+        .map_err(|_| anyhow::anyhow!("decryption failed: authentication tag mismatch"))?;
+    Ok(buffer)
+}
+
+fn seal_with(
+    cipher: &XChaCha20Poly1305,
+    nonce: &[u8],
+    plaintext: &[u8],
+    aad: &[u8],
+    max_aad_len: usize,
+) -> Result<Vec<u8>> {
+    let (mut sealed, tag) = encrypt_with(cipher, nonce, plaintext, aad, max_aad_len)?;
+    sealed.extend_from_slice(&tag);
+    Ok(sealed)
+}
+
+fn open_with(
+    cipher: &XChaCha20Poly1305,
+    nonce: &[u8],
+    sealed: &[u8],
+    aad: &[u8],
+    max_aad_len: usize,
+) -> Result<Vec<u8>> {
+    if sealed.len() < TAG_LEN {
+        anyhow::bail!("Ciphertext shorter than {TAG_LEN}-byte tag");
+    }
+    let (ciphertext, tag) = sealed.split_at(sealed.len() - TAG_LEN);
+    decrypt_with(cipher, nonce, ciphertext, aad, tag, max_aad_len)
+}
+
+fn cipher(key: &[u8]) -> Result<XChaCha20Poly1305> {
+    if key.len() != KEY_LEN {
+        anyhow::bail!("Expected {KEY_LEN}-byte key, got {}", key.len());
+    }
+    Ok(XChaCha20Poly1305::new(Key::from_slice(key)))
+}
+
+fn xnonce(nonce: &[u8]) -> Result<&XNonce> {
+    if nonce.len() != NONCE_LEN {
+        anyhow::bail!("Expected {NONCE_LEN}-byte nonce, got {}", nonce.len());
+    }
+    Ok(XNonce::from_slice(nonce))
+}
+
+/// Ed25519 keypair
+///
+/// Loaders wipe the buffers they decode the seed through: the hex decoded by
+/// [`Keypair::from_hex`], the file read by [`Keypair::load_from_file`] and the
+/// base64 decoded by [`Keypair::from_openssh`]. Some copies stay out of reach:
+/// the operating system keeps the file's contents in its page cache after it
+/// is read, and strings passed in are the caller's to wipe.
+pub struct Keypair {
+    pub signing_key: SigningKey,
+    pub verifying_key: VerifyingKey,
+}
+
+impl Keypair {
+    /// Generate a new random keypair
+    pub fn generate() -> Self {
+        use rand::RngCore;
+        let mut seed = Zeroizing::new([0u8; 32]);
+        OsRng.fill_bytes(&mut *seed);
+        let signing_key = SigningKey::from_bytes(&seed);
+        let verifying_key = signing_key.verifying_key();
+        Self {
+            signing_key,
+            verifying_key,
+        }
+    }
+
+    /// Load keypair from 32-byte seed
+    pub fn from_bytes(bytes: &[u8; 32]) -> Self {
+        let signing_key = SigningKey::from_bytes(bytes);
+        let verifying_key = signing_key.verifying_key();
+        Self {
+            signing_key,
+            verifying_key,
+        }
+    }
+
+    /// Load keypair from hex string
+    ///
+    /// The decoded seed is wiped from memory once the key is built; the
+    /// string itself belongs to the caller.
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        let bytes = Zeroizing::new(hex::decode(hex).context("decode hex")?);
+        if bytes.len() != 32 {
+            anyhow::bail!("Expected 32 bytes, got {}", bytes.len());
+        }
+        let mut arr = Zeroizing::new([0u8; 32]);
+        arr.copy_from_slice(&bytes);
+        Ok(Self::from_bytes(&arr))
+    }
+
+    /// Export signing key as hex
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.signing_key.to_bytes())
+    }
+
+    /// Export verifying key as hex
+    pub fn verifying_key_hex(&self) -> String {
+        hex::encode(self.verifying_key.to_bytes())
+    }
+
+    /// Public half of the keypair, for handing out to subscribers
+    pub fn public_only(&self) -> PublicKeyBundle {
+        PublicKeyBundle::from(scheme::VerifyingKey::Ed25519(self.verifying_key))
+    }
+}
+
+/// Generate `n` independent random keypairs, in parallel across cores
+///
+/// Each worker thread draws seeds from its own ChaCha12 generator seeded from
+/// `OsRng`, so threads neither contend on the OS RNG nor share a stream. The
+/// keys are returned in a `Vec` of exactly `n` with distinct public keys;
+/// their order carries no meaning.
+pub fn generate_keypairs(n: usize) -> Vec<Keypair> {
+    use rand::{RngCore, SeedableRng};
+    use rayon::prelude::*;
+
+    let from_rng = |rng: &mut rand::rngs::StdRng| {
+        let mut seed = [0u8; 32];
+        rng.fill_bytes(&mut seed);
+        Keypair::from_bytes(&seed)
+    };
+    let mut keypairs: Vec<Keypair> = (0..n)
+        .into_par_iter()
+        .map_init(rand::rngs::StdRng::from_entropy, |rng, _| from_rng(rng))
+        .collect();
+
+    // A repeat among 256-bit seeds would mean a broken RNG, but uniqueness is
+    // promised, so replace any rather than hand out a shared key
+    let mut seen = std::collections::HashSet::with_capacity(n);
+    let mut rng = rand::rngs::StdRng::from_entropy();
+    for keypair in &mut keypairs {
+        while !seen.insert(keypair.verifying_key.to_bytes()) {
+            *keypair = from_rng(&mut rng);
+        }
+    }
+    keypairs
+}
+
+/// Public key prepared for distribution
+///
+/// Holds only the verifying key, so nothing exported from it can reveal a
+/// seed. Each export is read back by the matching `VerifyingKey::from_*`
+/// importer in [`scheme`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicKeyBundle {
+    key: scheme::VerifyingKey,
+}
+
+impl PublicKeyBundle {
+    /// The verifying key itself
+    pub fn verifying_key(&self) -> scheme::VerifyingKey {
+        self.key
+    }
+
+    /// [Keyring fingerprint](crate::keyring::fingerprint) of the key
+    pub fn fingerprint(&self) -> String {
+        crate::keyring::fingerprint(&self.key.to_bytes())
+    }
+
+    /// Hex encoding, read by [`VerifyingKey::from_hex`](scheme::VerifyingKey::from_hex)
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.key.to_bytes())
+    }
+
+    /// Standard base64 encoding, read by
+    /// [`VerifyingKey::from_base64`](scheme::VerifyingKey::from_base64)
+    pub fn to_base64(&self) -> String {
+        use base64::Engine;
+        base64::engine::general_purpose::STANDARD.encode(self.key.to_bytes())
+    }
+
+    /// PEM `PUBLIC KEY` block, read by [`VerifyingKey::from_pem`](scheme::VerifyingKey::from_pem)
+    pub fn to_pem(&self) -> String {
+        self.key.to_pem()
+    }
+}
+
+impl From<scheme::VerifyingKey> for PublicKeyBundle {
+    fn from(key: scheme::VerifyingKey) -> Self {
+        Self { key }
+    }
+}
@@ -0,0 +1,199 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Self-signed capability tokens
+//!
+//! A capability asserts "the holder of this key may perform these operations on
+//! this topic until this time". Nodes that authorize by capability rather than
+//! bearer token check it with [`verify_capability`]. The wire format is
+//! specified in `specs/api.md` so that every SDK mints identical bytes.
+
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use std::ops::BitOr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Current token format version
+pub const VERSION: u8 = 1;
+
+/// Domain separation prefix signed ahead of the token body
+pub const SIGNING_CONTEXT: &[u8] = b"securefabric/capability/v1";
+
+/// Longest topic a token can name, matching the API topic rules
+pub const MAX_TOPIC_LEN: usize = 255;
+
+const HEADER_LEN: usize = 1 + 32 + 1 + 8 + 2;
+const SIG_LEN: usize = 64;
+
+/// Set of operations granted by a capability
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Ops(u8);
+
+impl Ops {
+    /// Send envelopes on the topic
+    pub const PUBLISH: Ops = Ops(0x01);
+    /// Subscribe to the topic
+    pub const SUBSCRIBE: Ops = Ops(0x02);
+
+    const ALL: u8 = 0x03;
+
+    /// Whether every operation in `other` is granted
+    pub fn contains(self, other: Ops) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Bitmask as encoded in the token
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+}
+
+impl BitOr for Ops {
+    type Output = Ops;
+
+    fn bitor(self, rhs: Ops) -> Ops {
+        Ops(self.0 | rhs.0)
+    }
+}
+
+/// Errors minting or verifying a capability
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CapabilityError {
+    /// The token is truncated, has trailing bytes or unknown operation bits
+    #[error("malformed capability token")]
+    Malformed,
+
+    /// The token uses a format version this SDK does not understand
+    #[error("unsupported capability version {0}")]
+    UnsupportedVersion(u8),
+
+    /// The signature does not verify under the embedded public key
+    #[error("invalid capability signature")]
+    BadSignature,
+
+    /// The token's expiry has passed
+    #[error("capability expired")]
+    Expired,
+
+    /// The token grants a different topic
+    #[error("capability is for topic {granted}, not {requested}")]
+    TopicMismatch { granted: String, requested: String },
+
+    /// The token does not grant the requested operation
+    #[error("capability does not grant the requested operation")]
+    NotPermitted,
+
+    /// The topic is longer than [`MAX_TOPIC_LEN`] bytes
+    #[error("topic of {0} bytes exceeds capability limit")]
+    TopicTooLong(usize),
+}
+
+/// The verified contents of a capability token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capability {
+    /// Key that minted the token and to which it grants access
+    pub issuer: VerifyingKey,
+    /// Topic the token is scoped to
+    pub topic: String,
+    /// Operations granted
+    pub ops: Ops,
+    /// Instant after which the token is no longer valid
+    pub expiry: SystemTime,
+}
+
+/// Mint a capability for `topic` signed by `signing_key`
+///
+/// The expiry is encoded with millisecond precision.
+pub fn mint(
+    signing_key: &SigningKey,
+    topic: &str,
+    ops: Ops,
+    expiry: SystemTime,
+) -> Result<Vec<u8>, CapabilityError> {
+    if topic.len() > MAX_TOPIC_LEN {
+        return Err(CapabilityError::TopicTooLong(topic.len()));
+    }
+    let expiry_ms = expiry
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64);
+
+    let mut token = Vec::with_capacity(HEADER_LEN + topic.len() + SIG_LEN);
+    token.push(VERSION);
+    token.extend_from_slice(signing_key.verifying_key().as_bytes());
+    token.push(ops.bits());
+    token.extend_from_slice(&expiry_ms.to_le_bytes());
+    token.extend_from_slice(&(topic.len() as u16).to_le_bytes());
+    token.extend_from_slice(topic.as_bytes());
+
+    let signature = signing_key.sign(&signed_bytes(&token));
+    token.extend_from_slice(&signature.to_bytes());
+    Ok(token)
+}
+
+/// Verify that `token` grants `op` on `topic` at time `now`
+pub fn verify_capability(
+    token: &[u8],
+    topic: &str,
+    op: Ops,
+    now: SystemTime,
+) -> Result<Capability, CapabilityError> {
+    let capability = decode(token)?;
+
+    if now >= capability.expiry {
+        return Err(CapabilityError::Expired);
+    }
+    if capability.topic != topic {
+        return Err(CapabilityError::TopicMismatch {
+            granted: capability.topic,
+            requested: topic.to_string(),
+        });
+    }
+    if !capability.ops.contains(op) {
+        return Err(CapabilityError::NotPermitted);
+    }
+    Ok(capability)
+}
+
+/// Parse a token and check its signature, without checking expiry or scope
+fn decode(token: &[u8]) -> Result<Capability, CapabilityError> {
+    let version = *token.first().ok_or(CapabilityError::Malformed)?;
+    if version != VERSION {
+        return Err(CapabilityError::UnsupportedVersion(version));
+    }
+    if token.len() < HEADER_LEN + SIG_LEN {
+        return Err(CapabilityError::Malformed);
+    }
+
+    let topic_len = u16::from_le_bytes([token[42], token[43]]) as usize;
+    if token.len() != HEADER_LEN + topic_len + SIG_LEN {
+        return Err(CapabilityError::Malformed);
+    }
+    let (body, sig) = token.split_at(HEADER_LEN + topic_len);
+
+    let issuer = VerifyingKey::from_bytes(body[1..33].try_into().unwrap())
+        .map_err(|_| CapabilityError::BadSignature)?;
+    let signature = Signature::from_bytes(sig.try_into().unwrap());
+    issuer
+        .verify_strict(&signed_bytes(body), &signature)
+        .map_err(|_| CapabilityError::BadSignature)?;
+
+    let ops = body[33];
+    if ops & !Ops::ALL != 0 {
+        return Err(CapabilityError::Malformed);
+    }
+    let expiry_ms = u64::from_le_bytes(body[34..42].try_into().unwrap());
+    let topic =
+        String::from_utf8(body[HEADER_LEN..].to_vec()).map_err(|_| CapabilityError::Malformed)?;
+
+    Ok(Capability {
+        issuer,
+        topic,
+        ops: Ops(ops),
+        expiry: UNIX_EPOCH + Duration::from_millis(expiry_ms),
+    })
+}
+
+fn signed_bytes(body: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(SIGNING_CONTEXT.len() + body.len());
+    message.extend_from_slice(SIGNING_CONTEXT);
+    message.extend_from_slice(body);
+    message
+}
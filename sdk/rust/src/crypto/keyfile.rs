@@ -0,0 +1,183 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checksummed key files
+//!
+//! A bare 32-byte seed on disk gives no way to notice corruption: a flipped
+//! byte is still a valid seed, just for a different key. The key file format
+//! wraps the seed so damage is detected on load:
+//!
+//! ```text
+//! "SFKP" || version (1 byte, = 1) || flags (1 byte) || seed (32 bytes)
+//!        || public key (32 bytes, if flags bit 0 is set) || checksum (32 bytes)
+//! ```
+//!
+//! The checksum is BLAKE3 over every preceding byte. The optional Ed25519
+//! public key is checked against the one derived from the seed, which also
+//! catches files assembled from mismatched parts. The layout is frozen:
+//! changes get a new version byte.
+
+use super::Keypair;
+use anyhow::{Context, Result};
+use std::io::Read;
+use std::path::Path;
+use zeroize::{Zeroize, Zeroizing};
+
+/// First bytes of every key file
+pub const MAGIC: &[u8; 4] = b"SFKP";
+/// Key file version written by [`Keypair::to_file_bytes`]
+pub const VERSION: u8 = 1;
+
+/// Flag bit set when the file embeds the public key
+const FLAG_PUBLIC_KEY: u8 = 1;
+const SEED_LEN: usize = 32;
+const PUBLIC_KEY_LEN: usize = 32;
+const CHECKSUM_LEN: usize = 32;
+const HEADER_LEN: usize = MAGIC.len() + 2;
+
+/// Why a key file was rejected
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum KeyFileError {
+    /// The file does not start with [`MAGIC`]
+    #[error("not a SecureFabric key file")]
+    BadMagic,
+
+    /// The file was written by a newer or unknown format version
+    #[error("unsupported key file version {version}")]
+    UnsupportedVersion { version: u8 },
+
+    /// Flags this version does not define are set
+    #[error("unknown key file flags {flags:#04x}")]
+    UnknownFlags { flags: u8 },
+
+    /// The file is shorter or longer than its header says
+    #[error("key file has length {len}, expected {expected}")]
+    InvalidLength { len: usize, expected: usize },
+
+    /// The contents do not match the checksum
+    #[error("key file checksum mismatch")]
+    BadChecksum,
+
+    /// The embedded public key is not the one derived from the seed
+    #[error("key file public key does not match its seed")]
+    PublicKeyMismatch,
+}
+
+impl Keypair {
+    /// Encode the keypair in the key file format
+    ///
+    /// With `embed_public_key`, the public key is stored next to the seed and
+    /// cross-checked on load.
+    pub fn to_file_bytes(&self, embed_public_key: bool) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(file_len(embed_public_key));
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(if embed_public_key { FLAG_PUBLIC_KEY } else { 0 });
+        bytes.extend_from_slice(&self.signing_key.to_bytes());
+        if embed_public_key {
+            bytes.extend_from_slice(self.verifying_key.as_bytes());
+        }
+        let checksum = blake3::hash(&bytes);
+        bytes.extend_from_slice(checksum.as_bytes());
+        bytes
+    }
+
+    /// Decode a keypair written by [`Keypair::to_file_bytes`]
+    pub fn from_file_bytes(bytes: &[u8]) -> Result<Self, KeyFileError> {
+        if bytes.len() < HEADER_LEN || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(KeyFileError::BadMagic);
+        }
+        let version = bytes[MAGIC.len()];
+        if version != VERSION {
+            return Err(KeyFileError::UnsupportedVersion { version });
+        }
+        let flags = bytes[MAGIC.len() + 1];
+        if flags & !FLAG_PUBLIC_KEY != 0 {
+            return Err(KeyFileError::UnknownFlags { flags });
+        }
+        let embed_public_key = flags & FLAG_PUBLIC_KEY != 0;
+        let expected = file_len(embed_public_key);
+        if bytes.len() != expected {
+            return Err(KeyFileError::InvalidLength {
+                len: bytes.len(),
+                expected,
+            });
+        }
+
+        let (body, checksum) = bytes.split_at(expected - CHECKSUM_LEN);
+        if blake3::hash(body).as_bytes() != checksum {
+            return Err(KeyFileError::BadChecksum);
+        }
+        let seed: &[u8; SEED_LEN] = body[HEADER_LEN..HEADER_LEN + SEED_LEN].try_into().unwrap();
+        let keypair = Self::from_bytes(seed);
+        if embed_public_key && body[HEADER_LEN + SEED_LEN..] != keypair.verifying_key.to_bytes() {
+            return Err(KeyFileError::PublicKeyMismatch);
+        }
+        Ok(keypair)
+    }
+
+    /// Decode a keypair from a buffer holding a key file, then wipe the buffer
+    ///
+    /// Like [`Keypair::from_file_bytes`], for callers that read the file
+    /// themselves; the buffer is zeroized whether or not it decodes.
+    pub fn from_file_buffer<B>(mut buffer: B) -> Result<Self, KeyFileError>
+    where
+        B: AsRef<[u8]> + Zeroize,
+    {
+        let keypair = Self::from_file_bytes(buffer.as_ref());
+        buffer.zeroize();
+        keypair
+    }
+
+    /// Write the keypair to `path` in the key file format, embedding the public key
+    ///
+    /// On Unix a new file is created with mode 0600; an existing file keeps
+    /// its permissions.
+    pub fn save_to_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        use std::io::Write;
+
+        let path = path.as_ref();
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options
+            .open(path)
+            .and_then(|mut file| {
+                file.write_all(&self.to_file_bytes(true))?;
+                file.sync_all()
+            })
+            .with_context(|| format!("write key file {}", path.display()))
+    }
+
+    /// Read a keypair from a file written by [`Keypair::save_to_file`]
+    ///
+    /// Format errors are [`KeyFileError`]s and can be recovered with
+    /// `err.downcast_ref::<KeyFileError>()`. The bytes read are wiped from
+    /// memory once decoded, but the operating system may keep the file's
+    /// contents in its page cache; keep key files on encrypted storage.
+    pub fn load_from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes =
+            read_wiped(path).with_context(|| format!("read key file {}", path.display()))?;
+        Self::from_file_buffer(bytes)
+            .map_err(anyhow::Error::from)
+            .with_context(|| format!("load key file {}", path.display()))
+    }
+}
+
+/// Read a whole file into a buffer that is wiped when dropped
+///
+/// Sized from the file's metadata up front so reading does not reallocate
+/// and leave partial copies behind.
+fn read_wiped(path: &Path) -> std::io::Result<Zeroizing<Vec<u8>>> {
+    let mut file = std::fs::File::open(path)?;
+    let len = file.metadata()?.len() as usize;
+    let mut bytes = Zeroizing::new(Vec::with_capacity(len + 1));
+    file.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn file_len(embed_public_key: bool) -> usize {
+    HEADER_LEN + SEED_LEN + if embed_public_key { PUBLIC_KEY_LEN } else { 0 } + CHECKSUM_LEN
+}
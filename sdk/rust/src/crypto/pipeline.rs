@@ -0,0 +1,118 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Bulk verification for archived envelopes
+//!
+//! [`verify_dedup`] checks a stream of envelopes, for example one read back
+//! from an archive dump, in a single pass: each msg_id is recomputed, each
+//! signature is checked against a [`Keyring`], and repeats of an envelope
+//! already accepted are dropped. Envelopes are pulled from the input in
+//! fixed-size batches, so memory use does not grow with the size of the dump
+//! beyond a 32-byte digest per accepted envelope.
+
+use crate::keyring::Keyring;
+use crate::pb::Envelope;
+use crate::{msg_id_matches, signed_digest, verify_signature_with};
+use std::collections::{HashSet, VecDeque};
+
+/// Envelopes pulled from the input per batch
+const BATCH_SIZE: usize = 256;
+
+/// Why [`verify_dedup`] rejected an envelope
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum RejectReason {
+    /// The msg_id does not match the envelope's pubkey, seq and nonce
+    #[error("msg_id {msg_id} does not match envelope contents")]
+    BadMsgId { msg_id: String },
+
+    /// The sender is not in the keyring
+    #[error("envelope {msg_id} from unknown sender {fingerprint}")]
+    UnknownSender { msg_id: String, fingerprint: String },
+
+    /// The signature does not verify against the sender's key
+    #[error("envelope {msg_id} has a bad signature")]
+    BadSignature { msg_id: String },
+}
+
+/// Verify, check and dedup `envelopes` against the senders in `keyring`
+///
+/// Yields each envelope that passes, or the reason it was rejected, in input
+/// order. An envelope repeating one already accepted, meaning the same sender
+/// and the same signed bytes, is dropped without a result. Copies differing
+/// only in `seq`, `nonce` or msg_id, none of which are necessarily signed,
+/// count as repeats. Rejected envelopes never count as seen, so a forgery
+/// cannot suppress the genuine envelope it imitates.
+///
+/// Like [`Keyring::verify`], signatures are checked over the payload as
+/// carried, so plaintext-signed encrypted envelopes are rejected.
+pub fn verify_dedup(
+    envelopes: impl Iterator<Item = Envelope>,
+    keyring: &Keyring,
+) -> impl Iterator<Item = Result<Envelope, RejectReason>> {
+    VerifyDedup {
+        envelopes,
+        keyring: keyring.clone(),
+        seen: HashSet::new(),
+        ready: VecDeque::with_capacity(BATCH_SIZE),
+    }
+}
+
+struct VerifyDedup<I> {
+    envelopes: I,
+    keyring: Keyring,
+    /// Signed digests of the envelopes accepted so far
+    seen: HashSet<[u8; 32]>,
+    ready: VecDeque<Result<Envelope, RejectReason>>,
+}
+
+impl<I: Iterator<Item = Envelope>> VerifyDedup<I> {
+    /// Check the next batch of input, returning false once the input is exhausted
+    fn fill(&mut self) -> bool {
+        let batch: Vec<Envelope> = self.envelopes.by_ref().take(BATCH_SIZE).collect();
+        if batch.is_empty() {
+            return false;
+        }
+
+        let keys = self.keyring.sender_keys(&batch);
+        for (envelope, (fingerprint, key)) in batch.into_iter().zip(keys) {
+            if !msg_id_matches(&envelope) {
+                self.ready.push_back(Err(RejectReason::BadMsgId {
+                    msg_id: envelope.msg_id,
+                }));
+                continue;
+            }
+            let Some(key) = key else {
+                self.ready.push_back(Err(RejectReason::UnknownSender {
+                    msg_id: envelope.msg_id,
+                    fingerprint,
+                }));
+                continue;
+            };
+            if !verify_signature_with(&envelope, &key).unwrap_or(false) {
+                self.ready.push_back(Err(RejectReason::BadSignature {
+                    msg_id: envelope.msg_id,
+                }));
+                continue;
+            }
+            if self.seen.insert(signed_digest(&envelope)) {
+                self.ready.push_back(Ok(envelope));
+            }
+        }
+        true
+    }
+}
+
+impl<I: Iterator<Item = Envelope>> Iterator for VerifyDedup<I> {
+    type Item = Result<Envelope, RejectReason>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(result) = self.ready.pop_front() {
+                return Some(result);
+            }
+            if !self.fill() {
+                return None;
+            }
+        }
+    }
+}
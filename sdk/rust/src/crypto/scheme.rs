@@ -0,0 +1,391 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Envelope signature schemes
+//!
+//! Envelopes are signed with Ed25519 unless the sender opts into secp256k1.
+//! The scheme travels in `Envelope::sig_scheme`, so a verifier knows how to parse
+//! `pubkey` and `sig` before checking anything:
+//!
+//! | scheme      | wire | `pubkey`                   | `sig`                          |
+//! |-------------|------|----------------------------|--------------------------------|
+//! | `Ed25519`   | 0    | 32 bytes                   | 64 bytes                       |
+//! | `Secp256k1` | 1    | 33 bytes, SEC1 compressed  | 64 bytes `r \|\| s`, low-S, ECDSA over SHA-256 |
+//! | `Ed25519ph` | 2    | 32 bytes                   | 64 bytes, over SHA-512 of the preimage |
+//!
+//! Every scheme signs the same preimage. Ed25519ph (RFC 8032) signs its
+//! SHA-512 digest instead, with [`ED25519PH_CONTEXT`] as context, so large
+//! preimages can be hashed incrementally or the digest handed to an HSM. An
+//! Ed25519 key can sign with either Ed25519 variant.
+
+use crate::pb::Envelope;
+use anyhow::{Context, Result};
+use base64::Engine as _;
+use ed25519_dalek::pkcs8::spki::der::pem::LineEnding;
+use ed25519_dalek::pkcs8::{DecodePublicKey as _, EncodePublicKey as _};
+use ed25519_dalek::Signer as _;
+use k256::ecdsa::signature::Verifier as _;
+pub use sha2::{Digest, Sha512};
+
+/// Context bound into every Ed25519ph envelope signature
+pub const ED25519PH_CONTEXT: &[u8] = b"securefabric/envelope/v1";
+
+/// Sign a SHA-512 `prehash` with Ed25519ph under `context`
+///
+/// `prehash` is the hasher after the whole message was fed to it.
+pub fn sign_prehashed(key: &ed25519_dalek::SigningKey, prehash: Sha512, context: &[u8]) -> Vec<u8> {
+    key.sign_prehashed(prehash, Some(context))
+        .expect("context is at most 255 bytes")
+        .to_bytes()
+        .to_vec()
+}
+
+/// SHA-512 hasher fed `parts` one after another
+fn prehash(parts: &[&[u8]]) -> Sha512 {
+    parts
+        .iter()
+        .fold(Sha512::new(), |hasher, part| hasher.chain_update(part))
+}
+
+/// Check an Ed25519ph signature over a SHA-512 `prehash` under `context`
+pub fn verify_prehashed(
+    key: &ed25519_dalek::VerifyingKey,
+    prehash: Sha512,
+    context: &[u8],
+    signature: &[u8],
+    mode: VerifyMode,
+) -> bool {
+    let Ok(signature) = ed25519_dalek::Signature::from_slice(signature) else {
+        return false;
+    };
+    match mode {
+        VerifyMode::Strict => key
+            .verify_prehashed_strict(prehash, Some(context), &signature)
+            .is_ok(),
+        VerifyMode::Permissive => key
+            .verify_prehashed(prehash, Some(context), &signature)
+            .is_ok(),
+    }
+}
+
+/// How strictly signatures are checked
+///
+/// `Strict`, the default, rejects every signature that is not in canonical
+/// form: Ed25519 signatures are checked with `verify_strict`, which refuses
+/// small-order public keys and `R` points, and secp256k1 signatures must be
+/// low-S. `Permissive` accepts what the underlying algorithms accept: plain
+/// cofactorless Ed25519 verification and high-S secp256k1 signatures.
+///
+/// Only use `Permissive` to interoperate with signers that do not produce
+/// canonical signatures. It allows signature malleability, so the same
+/// message can verify under several distinct signatures, and with Ed25519 it
+/// accepts signatures under weak keys that verify for many messages at once.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum VerifyMode {
+    #[default]
+    Strict,
+    Permissive,
+}
+
+/// Signature algorithm of an envelope
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SignatureScheme {
+    /// Ed25519 (EdDSA on Curve25519)
+    #[default]
+    Ed25519,
+    /// ECDSA on secp256k1 with SHA-256
+    Secp256k1,
+    /// Ed25519ph: Ed25519 over the SHA-512 digest of the preimage
+    Ed25519ph,
+}
+
+impl SignatureScheme {
+    /// Value carried in `Envelope::sig_scheme`
+    pub const fn wire_value(self) -> u32 {
+        match self {
+            Self::Ed25519 => 0,
+            Self::Secp256k1 => 1,
+            Self::Ed25519ph => 2,
+        }
+    }
+
+    /// Scheme for a wire value, or `None` if it is not supported
+    pub const fn from_wire(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(Self::Ed25519),
+            1 => Some(Self::Secp256k1),
+            2 => Some(Self::Ed25519ph),
+            _ => None,
+        }
+    }
+
+    /// Scheme an envelope declares, or `None` if it is not supported
+    pub fn of(envelope: &Envelope) -> Option<Self> {
+        Self::from_wire(envelope.sig_scheme)
+    }
+
+    /// Length of an encoded public key
+    pub const fn public_key_len(self) -> usize {
+        match self {
+            Self::Ed25519 | Self::Ed25519ph => 32,
+            Self::Secp256k1 => 33,
+        }
+    }
+
+    /// Length of an encoded signature
+    pub const fn signature_len(self) -> usize {
+        64
+    }
+}
+
+/// Private key envelopes are signed with
+#[derive(Clone)]
+pub enum SigningKey {
+    Ed25519(ed25519_dalek::SigningKey),
+    Secp256k1(k256::ecdsa::SigningKey),
+    /// An Ed25519 key signing with Ed25519ph
+    Ed25519ph(ed25519_dalek::SigningKey),
+}
+
+impl SigningKey {
+    /// Scheme this key signs with
+    pub fn scheme(&self) -> SignatureScheme {
+        match self {
+            Self::Ed25519(_) => SignatureScheme::Ed25519,
+            Self::Secp256k1(_) => SignatureScheme::Secp256k1,
+            Self::Ed25519ph(_) => SignatureScheme::Ed25519ph,
+        }
+    }
+
+    /// Matching public key
+    pub fn verifying_key(&self) -> VerifyingKey {
+        match self {
+            Self::Ed25519(key) | Self::Ed25519ph(key) => VerifyingKey::Ed25519(key.verifying_key()),
+            Self::Secp256k1(key) => VerifyingKey::Secp256k1(*key.verifying_key()),
+        }
+    }
+
+    /// Sign `message`, returning the encoded signature
+    pub fn sign(&self, message: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Ed25519(key) => key.sign(message).to_bytes().to_vec(),
+            Self::Secp256k1(key) => {
+                let signature: k256::ecdsa::Signature = key.sign(message);
+                signature.to_bytes().to_vec()
+            }
+            Self::Ed25519ph(key) => {
+                sign_prehashed(key, Sha512::new_with_prefix(message), ED25519PH_CONTEXT)
+            }
+        }
+    }
+
+    /// Sign the concatenation of `parts`, returning the encoded signature
+    ///
+    /// Ed25519ph hashes the parts in turn instead of joining them first.
+    pub fn sign_parts(&self, parts: &[&[u8]]) -> Vec<u8> {
+        match self {
+            Self::Ed25519ph(key) => sign_prehashed(key, prehash(parts), ED25519PH_CONTEXT),
+            _ => self.sign(&parts.concat()),
+        }
+    }
+}
+
+impl From<ed25519_dalek::SigningKey> for SigningKey {
+    fn from(key: ed25519_dalek::SigningKey) -> Self {
+        Self::Ed25519(key)
+    }
+}
+
+impl From<k256::ecdsa::SigningKey> for SigningKey {
+    fn from(key: k256::ecdsa::SigningKey) -> Self {
+        Self::Secp256k1(key)
+    }
+}
+
+/// Public key envelope signatures are checked against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyingKey {
+    Ed25519(ed25519_dalek::VerifyingKey),
+    Secp256k1(k256::ecdsa::VerifyingKey),
+}
+
+impl VerifyingKey {
+    /// Parse a public key encoded for `scheme`
+    pub fn from_bytes(scheme: SignatureScheme, bytes: &[u8]) -> Result<Self> {
+        match scheme {
+            SignatureScheme::Ed25519 | SignatureScheme::Ed25519ph => {
+                let bytes = <&[u8; 32]>::try_from(bytes).context("Invalid Ed25519 public key")?;
+                Ok(Self::Ed25519(
+                    ed25519_dalek::VerifyingKey::from_bytes(bytes)
+                        .context("Invalid Ed25519 public key")?,
+                ))
+            }
+            SignatureScheme::Secp256k1 => {
+                if bytes.len() != SignatureScheme::Secp256k1.public_key_len() {
+                    anyhow::bail!("Invalid secp256k1 public key");
+                }
+                Ok(Self::Secp256k1(
+                    k256::ecdsa::VerifyingKey::from_sec1_bytes(bytes)
+                        .context("Invalid secp256k1 public key")?,
+                ))
+            }
+        }
+    }
+
+    /// Parse an encoded public key, telling the scheme from its length
+    ///
+    /// 32 bytes are an Ed25519 key and 33 bytes a compressed secp256k1 key, as
+    /// produced by [`VerifyingKey::to_bytes`].
+    pub fn from_encoded(bytes: &[u8]) -> Result<Self> {
+        let scheme = match bytes.len() {
+            32 => SignatureScheme::Ed25519,
+            33 => SignatureScheme::Secp256k1,
+            len => anyhow::bail!("Expected a 32- or 33-byte public key, got {len} bytes"),
+        };
+        Self::from_bytes(scheme, bytes)
+    }
+
+    /// Parse a hex-encoded public key, see [`VerifyingKey::from_encoded`]
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        Self::from_encoded(&hex::decode(hex.trim()).context("decode hex")?)
+    }
+
+    /// Parse a standard base64-encoded public key, see [`VerifyingKey::from_encoded`]
+    pub fn from_base64(base64: &str) -> Result<Self> {
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(base64.trim())
+            .context("decode base64")?;
+        Self::from_encoded(&bytes)
+    }
+
+    /// Parse a PEM `PUBLIC KEY` (SubjectPublicKeyInfo) block
+    pub fn from_pem(pem: &str) -> Result<Self> {
+        if let Ok(key) = ed25519_dalek::VerifyingKey::from_public_key_pem(pem) {
+            return Ok(Self::Ed25519(key));
+        }
+        k256::ecdsa::VerifyingKey::from_public_key_pem(pem)
+            .map(Self::Secp256k1)
+            .map_err(|_| anyhow::anyhow!("Expected an Ed25519 or secp256k1 PEM public key"))
+    }
+
+    /// Encode as a PEM `PUBLIC KEY` (SubjectPublicKeyInfo) block
+    pub fn to_pem(&self) -> String {
+        match self {
+            Self::Ed25519(key) => key.to_public_key_pem(LineEnding::LF),
+            Self::Secp256k1(key) => key.to_public_key_pem(LineEnding::LF),
+        }
+        .expect("public keys always encode")
+    }
+
+    /// Default scheme for this key's algorithm
+    pub fn scheme(&self) -> SignatureScheme {
+        match self {
+            Self::Ed25519(_) => SignatureScheme::Ed25519,
+            Self::Secp256k1(_) => SignatureScheme::Secp256k1,
+        }
+    }
+
+    /// Whether this key can verify signatures made with `scheme`
+    ///
+    /// Ed25519 keys verify both Ed25519 and Ed25519ph signatures.
+    pub fn supports(&self, scheme: SignatureScheme) -> bool {
+        matches!(
+            (self, scheme),
+            (
+                Self::Ed25519(_),
+                SignatureScheme::Ed25519 | SignatureScheme::Ed25519ph
+            ) | (Self::Secp256k1(_), SignatureScheme::Secp256k1)
+        )
+    }
+
+    /// Encoded public key, as carried in `Envelope::pubkey`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Ed25519(key) => key.to_bytes().to_vec(),
+            Self::Secp256k1(key) => key.to_encoded_point(true).as_bytes().to_vec(),
+        }
+    }
+
+    /// Check an encoded signature over `message`
+    ///
+    /// Ed25519 signatures are verified strictly and secp256k1 signatures must be
+    /// low-S normalised, so neither scheme accepts a malleated signature.
+    pub fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        self.verify_with_mode(message, signature, VerifyMode::Strict)
+    }
+
+    /// Check a signature over `message` made with `scheme`, under `mode`
+    ///
+    /// Fails if the key does not [support](VerifyingKey::supports) `scheme`.
+    pub fn verify_scheme(
+        &self,
+        scheme: SignatureScheme,
+        message: &[u8],
+        signature: &[u8],
+        mode: VerifyMode,
+    ) -> bool {
+        match (self, scheme) {
+            (Self::Ed25519(key), SignatureScheme::Ed25519ph) => verify_prehashed(
+                key,
+                Sha512::new_with_prefix(message),
+                ED25519PH_CONTEXT,
+                signature,
+                mode,
+            ),
+            _ if self.supports(scheme) => self.verify_with_mode(message, signature, mode),
+            _ => false,
+        }
+    }
+
+    /// [`VerifyingKey::verify_scheme`] over the concatenation of `parts`
+    ///
+    /// Ed25519ph hashes the parts in turn instead of joining them first.
+    pub fn verify_scheme_parts(
+        &self,
+        scheme: SignatureScheme,
+        parts: &[&[u8]],
+        signature: &[u8],
+        mode: VerifyMode,
+    ) -> bool {
+        match (self, scheme) {
+            (Self::Ed25519(key), SignatureScheme::Ed25519ph) => {
+                verify_prehashed(key, prehash(parts), ED25519PH_CONTEXT, signature, mode)
+            }
+            _ => self.verify_scheme(scheme, &parts.concat(), signature, mode),
+        }
+    }
+
+    /// Check an encoded signature over `message` under `mode`
+    pub fn verify_with_mode(&self, message: &[u8], signature: &[u8], mode: VerifyMode) -> bool {
+        match self {
+            Self::Ed25519(key) => {
+                ed25519_dalek::Signature::from_slice(signature).is_ok_and(|sig| match mode {
+                    VerifyMode::Strict => key.verify_strict(message, &sig).is_ok(),
+                    VerifyMode::Permissive => {
+                        ed25519_dalek::Verifier::verify(key, message, &sig).is_ok()
+                    }
+                })
+            }
+            Self::Secp256k1(key) => {
+                k256::ecdsa::Signature::from_slice(signature).is_ok_and(|sig| {
+                    let sig = match mode {
+                        VerifyMode::Strict => sig,
+                        VerifyMode::Permissive => sig.normalize_s().unwrap_or(sig),
+                    };
+                    key.verify(message, &sig).is_ok()
+                })
+            }
+        }
+    }
+}
+
+impl From<ed25519_dalek::VerifyingKey> for VerifyingKey {
+    fn from(key: ed25519_dalek::VerifyingKey) -> Self {
+        Self::Ed25519(key)
+    }
+}
+
+impl From<k256::ecdsa::VerifyingKey> for VerifyingKey {
+    fn from(key: k256::ecdsa::VerifyingKey) -> Self {
+        Self::Secp256k1(key)
+    }
+}
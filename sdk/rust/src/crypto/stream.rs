@@ -0,0 +1,109 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Streaming XChaCha20-Poly1305 for payloads too large to seal in one call
+//!
+//! [`SealStream`] encrypts its input incrementally with the STREAM construction
+//! (big-endian 32-bit counter and last-segment flag in the nonce). Input is cut
+//! into [`SEGMENT_LEN`]-byte segments, each sealed with its own tag, and the
+//! final segment is flagged as last, so [`open_stream`] detects a truncated
+//! stream as well as tampered or reordered segments. The sealed layout is
+//!
+//! ```text
+//! nonce_prefix (19 bytes) || segment_0 || ... || last_segment
+//! ```
+//!
+//! where every segment but the last holds exactly `SEGMENT_LEN` plaintext
+//! bytes plus a [`TAG_LEN`]-byte tag, and the last holds 1 to `SEGMENT_LEN`
+//! bytes, or none if the input was empty.
+
+use super::{cipher, TAG_LEN};
+use anyhow::Result;
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::XChaCha20Poly1305;
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+/// Plaintext bytes per sealed segment
+pub const SEGMENT_LEN: usize = 64 * 1024;
+/// Length of the random nonce prefix that starts a sealed stream
+pub const NONCE_PREFIX_LEN: usize = 19;
+
+/// Incremental encryptor producing the layout described in the module docs
+pub struct SealStream {
+    encryptor: EncryptorBE32<XChaCha20Poly1305>,
+    /// Nonce prefix still to be written, until the first output
+    header: Option<[u8; NONCE_PREFIX_LEN]>,
+    /// Plaintext not yet sealed; at most one segment is held back so the last
+    /// segment can be flagged by [`SealStream::finalize`]
+    pending: Vec<u8>,
+}
+
+impl SealStream {
+    /// Start a stream under `key` with a random nonce prefix
+    pub fn new(key: &[u8]) -> Result<Self> {
+        let mut prefix = [0u8; NONCE_PREFIX_LEN];
+        OsRng.fill_bytes(&mut prefix);
+        Ok(Self {
+            encryptor: EncryptorBE32::from_aead(cipher(key)?, prefix.as_slice().into()),
+            header: Some(prefix),
+            pending: Vec::new(),
+        })
+    }
+
+    /// Add `chunk` to the stream, returning whatever sealed bytes are ready
+    ///
+    /// The output may be empty; bytes are only released in whole segments.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<u8>> {
+        let mut output = self.take_header();
+        self.pending.extend_from_slice(chunk);
+        let mut sealed = 0;
+        while self.pending.len() - sealed > SEGMENT_LEN {
+            let segment = &self.pending[sealed..sealed + SEGMENT_LEN];
+            let ciphertext = self
+                .encryptor
+                .encrypt_next(segment)
+                .map_err(|_| anyhow::anyhow!("encryption failed"))?;
+            output.extend_from_slice(&ciphertext);
+            sealed += SEGMENT_LEN;
+        }
+        self.pending.drain(..sealed);
+        Ok(output)
+    }
+
+    /// Seal the remaining input as the last segment, ending the stream
+    pub fn finalize(mut self) -> Result<Vec<u8>> {
+        let mut output = self.take_header();
+        let last = self
+            .encryptor
+            .encrypt_last(self.pending.as_slice())
+            .map_err(|_| anyhow::anyhow!("encryption failed"))?;
+        output.extend_from_slice(&last);
+        Ok(output)
+    }
+
+    fn take_header(&mut self) -> Vec<u8> {
+        self.header.take().map(Vec::from).unwrap_or_default()
+    }
+}
+
+/// Decrypt a whole stream produced by [`SealStream`]
+///
+/// Fails if any segment was altered, reordered or dropped, including the last.
+pub fn open_stream(key: &[u8], sealed: &[u8]) -> Result<Vec<u8>> {
+    anyhow::ensure!(
+        sealed.len() >= NONCE_PREFIX_LEN + TAG_LEN,
+        "Sealed stream too short"
+    );
+    let (prefix, mut rest) = sealed.split_at(NONCE_PREFIX_LEN);
+    let mut decryptor = DecryptorBE32::from_aead(cipher(key)?, prefix.into());
+    let failed = || anyhow::anyhow!("decryption failed: stream truncated or tampered");
+
+    let mut plaintext = Vec::with_capacity(rest.len());
+    while rest.len() > SEGMENT_LEN + TAG_LEN {
+        let (segment, tail) = rest.split_at(SEGMENT_LEN + TAG_LEN);
+        plaintext.extend(decryptor.decrypt_next(segment).map_err(|_| failed())?);
+        rest = tail;
+    }
+    plaintext.extend(decryptor.decrypt_last(rest).map_err(|_| failed())?);
+    Ok(plaintext)
+}
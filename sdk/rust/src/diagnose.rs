@@ -0,0 +1,275 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Layer-by-layer connection diagnostics
+//!
+//! A failed connect surfaces as one error from deep inside the transport,
+//! which rarely says whether the problem is DNS, the network, certificates or
+//! the node itself. [`Client::diagnose`] walks the layers one at a time and
+//! reports the first that fails, so field reports can name the broken layer.
+
+use crate::tls::TlsConfig;
+use crate::{Client, ClientBuilder};
+use std::fmt;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tonic::codegen::http::Uri;
+
+/// Longest each stage may take before it is reported as failed
+pub const STAGE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// HTTP/2 client connection preface (RFC 9113, section 3.4)
+const H2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+/// An empty SETTINGS frame: zero length, type 0x4, no flags, stream 0
+const H2_EMPTY_SETTINGS: [u8; 9] = [0, 0, 0, 4, 0, 0, 0, 0, 0];
+const H2_SETTINGS_TYPE: u8 = 0x4;
+
+/// One layer of the connection, in the order they are checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    /// Resolving the endpoint host to addresses
+    Resolve,
+    /// Opening a TCP connection to one of the addresses
+    Connect,
+    /// TLS handshake, certificate validation and `h2` ALPN; skipped for `http://`
+    Tls,
+    /// Exchanging the HTTP/2 connection preface and SETTINGS
+    Http2,
+    /// Connecting a client and calling `Ping`
+    Rpc,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Resolve => "resolve",
+            Self::Connect => "connect",
+            Self::Tls => "tls",
+            Self::Http2 => "http2",
+            Self::Rpc => "rpc",
+        })
+    }
+}
+
+/// Outcome of one stage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StageReport {
+    pub stage: Stage,
+    /// Time the stage took, including a timeout
+    pub elapsed: Duration,
+    /// What the stage found on success, or why it failed
+    pub result: Result<String, String>,
+}
+
+/// Report from [`Client::diagnose`]
+///
+/// Lists the stages that ran, in order. Checking stops at the first failure, so
+/// only the last stage can have failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnosis {
+    pub endpoint: String,
+    pub stages: Vec<StageReport>,
+}
+
+impl Diagnosis {
+    /// The stage that failed, if any
+    pub fn failed_stage(&self) -> Option<Stage> {
+        self.stages
+            .iter()
+            .find(|report| report.result.is_err())
+            .map(|report| report.stage)
+    }
+
+    /// Whether every stage passed
+    pub fn is_healthy(&self) -> bool {
+        self.failed_stage().is_none()
+    }
+}
+
+impl fmt::Display for Diagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.endpoint)?;
+        for report in &self.stages {
+            let (mark, text) = match &report.result {
+                Ok(detail) => ("ok", detail),
+                Err(error) => ("FAILED", error),
+            };
+            writeln!(
+                f,
+                "  {:<8} {mark:<6} {text} ({:?})",
+                report.stage, report.elapsed
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Client {
+    /// Check each layer of the connection to `endpoint` and report which fails
+    ///
+    /// `https://` endpoints are checked against the platform's trust store; use
+    /// [`ClientBuilder::diagnose`] to check with a specific [`TlsConfig`]. The
+    /// node is dialled directly, without a proxy.
+    pub async fn diagnose(endpoint: impl Into<String>) -> Diagnosis {
+        Client::builder(endpoint).diagnose().await
+    }
+}
+
+/// Run every stage against `endpoint`, stopping at the first failure
+pub(crate) async fn run(
+    endpoint: &str,
+    tls: Option<&TlsConfig>,
+    builder: ClientBuilder,
+) -> Diagnosis {
+    let mut diagnosis = Diagnosis {
+        endpoint: endpoint.to_string(),
+        stages: Vec::new(),
+    };
+    let _ = stages(endpoint, tls, builder, &mut diagnosis.stages).await;
+    diagnosis
+}
+
+/// Marker for a stage that failed and ended the run
+struct Stopped;
+
+async fn stages(
+    endpoint: &str,
+    tls: Option<&TlsConfig>,
+    builder: ClientBuilder,
+    reports: &mut Vec<StageReport>,
+) -> Result<(), Stopped> {
+    let uri = endpoint.parse::<Uri>();
+    let (host, addrs) = stage(reports, Stage::Resolve, async {
+        let uri = uri
+            .as_ref()
+            .map_err(|e| format!("invalid endpoint URI: {e}"))?;
+        let host = uri.host().ok_or("endpoint URI has no host")?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let port = uri.port_u16().unwrap_or(match uri.scheme_str() {
+            Some("https") => 443,
+            _ => 80,
+        });
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|e| format!("cannot resolve {host}: {e}"))?
+            .collect();
+        if addrs.is_empty() {
+            return Err(format!("{host} resolved to no addresses"));
+        }
+        let detail = format!("{host} -> {}", join(&addrs));
+        Ok(((host.to_string(), addrs), detail))
+    })
+    .await?;
+
+    let tcp = stage(reports, Stage::Connect, async {
+        let mut errors = Vec::new();
+        for addr in &addrs {
+            match TcpStream::connect(addr).await {
+                Ok(stream) => return Ok((stream, format!("connected to {addr}"))),
+                Err(e) => errors.push(format!("{addr}: {e}")),
+            }
+        }
+        Err(errors.join("; "))
+    })
+    .await?;
+
+    let is_https = uri
+        .as_ref()
+        .is_ok_and(|uri| uri.scheme_str() == Some("https"));
+    if is_https {
+        let tls_stream = stage(reports, Stage::Tls, async {
+            let config = match tls {
+                Some(tls) => tls.rustls_config(),
+                None => TlsConfig::new()
+                    .with_native_roots()
+                    .and_then(|tls| tls.rustls_config()),
+            }
+            .map_err(|e| format!("invalid TLS configuration: {e:#}"))?;
+            let name = tls.and_then(TlsConfig::domain).unwrap_or(&host).to_string();
+            let server_name = rustls::pki_types::ServerName::try_from(name.clone())
+                .map_err(|e| format!("invalid TLS server name {name}: {e}"))?;
+
+            let connector = tokio_rustls::TlsConnector::from(Arc::new(config));
+            let stream = connector
+                .connect(server_name, tcp)
+                .await
+                .map_err(|e| format!("handshake with {name} failed: {e}"))?;
+            let (_, session) = stream.get_ref();
+            if session.alpn_protocol() != Some(b"h2") {
+                return Err("server did not negotiate h2 via ALPN".to_string());
+            }
+            let version = session
+                .protocol_version()
+                .map_or("TLS".to_string(), |v| format!("{v:?}"));
+            Ok((stream, format!("{version} with {name}, ALPN h2")))
+        })
+        .await?;
+        stage(reports, Stage::Http2, http2_preface(tls_stream)).await?;
+    } else {
+        stage(reports, Stage::Http2, http2_preface(tcp)).await?;
+    }
+
+    stage(reports, Stage::Rpc, async {
+        let mut client = builder.build().await.map_err(|e| format!("{e:#}"))?;
+        client.warmup().await.map_err(|e| format!("{e:#}"))?;
+        Ok(((), "Ping answered".to_string()))
+    })
+    .await?;
+    Ok(())
+}
+
+/// Run one stage under [`STAGE_TIMEOUT`] and record its report
+async fn stage<T>(
+    reports: &mut Vec<StageReport>,
+    stage: Stage,
+    check: impl Future<Output = Result<(T, String), String>>,
+) -> Result<T, Stopped> {
+    let started = Instant::now();
+    let outcome = tokio::time::timeout(STAGE_TIMEOUT, check)
+        .await
+        .unwrap_or_else(|_| Err(format!("timed out after {STAGE_TIMEOUT:?}")));
+    let (value, result) = match outcome {
+        Ok((value, detail)) => (Some(value), Ok(detail)),
+        Err(error) => (None, Err(error)),
+    };
+    reports.push(StageReport {
+        stage,
+        elapsed: started.elapsed(),
+        result,
+    });
+    value.ok_or(Stopped)
+}
+
+/// Send the HTTP/2 preface and expect the server's SETTINGS frame in reply
+async fn http2_preface(
+    mut stream: impl AsyncRead + AsyncWrite + Unpin,
+) -> Result<((), String), String> {
+    let io_error = |e: std::io::Error| format!("connection failed during HTTP/2 preface: {e}");
+    stream.write_all(H2_PREFACE).await.map_err(io_error)?;
+    stream
+        .write_all(&H2_EMPTY_SETTINGS)
+        .await
+        .map_err(io_error)?;
+    stream.flush().await.map_err(io_error)?;
+
+    let mut header = [0u8; 9];
+    stream.read_exact(&mut header).await.map_err(io_error)?;
+    if header[3] != H2_SETTINGS_TYPE {
+        return Err(format!(
+            "server did not speak HTTP/2, replied {:?}",
+            String::from_utf8_lossy(&header)
+        ));
+    }
+    Ok(((), "server sent SETTINGS".to_string()))
+}
+
+fn join(addrs: &[SocketAddr]) -> String {
+    addrs
+        .iter()
+        .map(SocketAddr::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,103 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Payload digests
+//!
+//! An envelope can carry `payload_digest`, the BLAKE3 hash of its payload as
+//! carried, so consumers can check a large payload's integrity without a
+//! signature check, store only the digest, or catch corruption of unsigned
+//! envelopes. The digest is not signed: it detects corruption, not forgery.
+
+use crate::pb::Envelope;
+use crate::Client;
+use anyhow::Result;
+use futures::stream::{BoxStream, StreamExt};
+
+/// Length of a payload digest in bytes
+pub const DIGEST_LEN: usize = 32;
+
+/// BLAKE3 digest of `payload`
+pub fn payload_digest(payload: &[u8]) -> [u8; DIGEST_LEN] {
+    *blake3::hash(payload).as_bytes()
+}
+
+/// Set `envelope.payload_digest` to the digest of its current payload
+///
+/// Attach the digest after encrypting, since it covers the payload bytes
+/// carried in the envelope. The signature is unaffected.
+pub fn attach_payload_digest(envelope: &mut Envelope) {
+    envelope.payload_digest = payload_digest(&envelope.payload).to_vec();
+}
+
+/// Check an envelope's payload against its digest
+///
+/// Returns `None` if the envelope carries no digest.
+pub fn payload_digest_matches(envelope: &Envelope) -> Option<bool> {
+    if envelope.payload_digest.is_empty() {
+        return None;
+    }
+    Some(envelope.payload_digest == payload_digest(&envelope.payload))
+}
+
+/// Per-envelope errors yielded by [`Client::subscribe_digest_checked`]
+#[derive(Debug, thiserror::Error)]
+pub enum DigestError {
+    /// The payload does not match the envelope's digest
+    #[error("payload of {msg_id} does not match its digest")]
+    Mismatch { msg_id: String },
+
+    /// The envelope carries no digest and one is required
+    #[error("envelope {msg_id} has no payload digest")]
+    Missing { msg_id: String },
+
+    /// The underlying subscription failed
+    #[error("subscription error: {0}")]
+    Transport(Box<tonic::Status>),
+}
+
+impl From<tonic::Status> for DigestError {
+    fn from(status: tonic::Status) -> Self {
+        Self::Transport(Box::new(status))
+    }
+}
+
+impl Client {
+    /// Attach a [payload digest](attach_payload_digest) to every envelope sent
+    ///
+    /// Off by default. Costs one BLAKE3 pass over each payload and 32 bytes
+    /// per envelope.
+    pub fn with_payload_digest(mut self, enabled: bool) -> Self {
+        self.payload_digest = enabled;
+        self
+    }
+
+    /// Subscribe to a topic, checking each payload against its digest
+    ///
+    /// An envelope whose payload does not match its digest is yielded as
+    /// [`DigestError::Mismatch`] and the stream continues. Envelopes without a
+    /// digest are passed through, or yielded as [`DigestError::Missing`] if
+    /// `require` is set. Like [`Client::subscribe`], signatures are not
+    /// verified here; the check holds for unsigned envelopes too.
+    pub async fn subscribe_digest_checked(
+        &mut self,
+        topic: &[u8],
+        require: bool,
+    ) -> Result<BoxStream<'static, Result<Envelope, DigestError>>> {
+        let inner = self.subscribe(topic).await?;
+
+        let stream = inner.map(move |item| {
+            let envelope = item?;
+            match payload_digest_matches(&envelope) {
+                Some(true) => Ok(envelope),
+                None if !require => Ok(envelope),
+                None => Err(DigestError::Missing {
+                    msg_id: envelope.msg_id,
+                }),
+                Some(false) => Err(DigestError::Mismatch {
+                    msg_id: envelope.msg_id,
+                }),
+            }
+        });
+
+        Ok(stream.boxed())
+    }
+}
@@ -0,0 +1,106 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Clock and randomness used when building envelopes
+//!
+//! Sends read the wall clock for `timestamp_ms` and the OS RNG for nonces,
+//! which in turn determine `msg_id`. With the `test-determinism` feature,
+//! tests can replace both so envelopes are reproducible byte for byte. The
+//! feature refuses to compile without debug assertions, so it cannot end up
+//! in a release build by accident.
+
+#[cfg(all(feature = "test-determinism", not(debug_assertions)))]
+compile_error!(
+    "the `test-determinism` feature makes nonces predictable and must not be enabled in release builds"
+);
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Source of timestamps and nonces for a client
+#[derive(Clone, Default)]
+pub(crate) struct Entropy {
+    #[cfg(feature = "test-determinism")]
+    fixed: Option<std::sync::Arc<deterministic::Fixed>>,
+}
+
+impl Entropy {
+    /// Current time in milliseconds since the Unix epoch, 0 if before it
+    pub(crate) fn now_ms(&self) -> u64 {
+        #[cfg(feature = "test-determinism")]
+        if let Some(fixed) = &self.fixed {
+            return fixed.now_ms();
+        }
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64)
+    }
+
+    /// Fill `buf` with random bytes
+    pub(crate) fn fill(&self, buf: &mut [u8]) {
+        #[cfg(feature = "test-determinism")]
+        if let Some(fixed) = &self.fixed {
+            return fixed.fill(buf);
+        }
+        use rand::RngCore;
+        rand::rngs::OsRng.fill_bytes(buf);
+    }
+
+    /// Uniformly random duration in `[0, max]`
+    pub(crate) fn up_to(&self, max: Duration) -> Duration {
+        let mut bytes = [0u8; 8];
+        self.fill(&mut bytes);
+        let nanos = max.as_nanos().min(u64::MAX as u128 - 1) as u64;
+        Duration::from_nanos(u64::from_le_bytes(bytes) % (nanos + 1))
+    }
+}
+
+#[cfg(feature = "test-determinism")]
+mod deterministic {
+    use super::Entropy;
+    use crate::Client;
+    use rand::{RngCore, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+    use std::sync::{Arc, Mutex};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    type Clock = Box<dyn Fn() -> SystemTime + Send + Sync>;
+
+    pub(super) struct Fixed {
+        clock: Clock,
+        rng: Mutex<ChaCha20Rng>,
+    }
+
+    impl Fixed {
+        pub(super) fn now_ms(&self) -> u64 {
+            (self.clock)()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis() as u64)
+        }
+
+        pub(super) fn fill(&self, buf: &mut [u8]) {
+            self.rng.lock().unwrap().fill_bytes(buf);
+        }
+    }
+
+    impl Client {
+        /// Stamp envelopes from `clock` and draw nonces from ChaCha20 seeded
+        /// with `seed`
+        ///
+        /// Two clients with the same signing key, clock, seed and starting
+        /// sequence produce identical envelopes for identical sends, across
+        /// `rand` upgrades too. Only available with the `test-determinism`
+        /// feature.
+        pub fn with_deterministic_entropy(
+            mut self,
+            clock: impl Fn() -> SystemTime + Send + Sync + 'static,
+            seed: u64,
+        ) -> Self {
+            self.entropy = Entropy {
+                fixed: Some(Arc::new(Fixed {
+                    clock: Box::new(clock),
+                    rng: Mutex::new(ChaCha20Rng::seed_from_u64(seed)),
+                })),
+            };
+            self
+        }
+    }
+}
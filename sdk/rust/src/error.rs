@@ -0,0 +1,124 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Typed SDK errors
+//!
+//! Client methods return `anyhow::Result`; failures that callers are expected
+//! to branch on are raised as [`Error`] and can be recovered with
+//! `err.downcast_ref::<securefabric_sdk::Error>()`.
+//!
+//! Streams that yield [`Error`] directly convert gRPC statuses from the node
+//! with its `From<tonic::Status>` impl.
+
+/// Errors callers can match on
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// The send queue is at capacity and the overflow policy rejected the send
+    #[error("send queue full (capacity {capacity})")]
+    QueueFull { capacity: usize },
+
+    /// The send was evicted from the queue by a newer one under `DropOldest`
+    #[error("send dropped from full queue")]
+    Dropped,
+
+    /// The envelope's sender is not in the configured keyring
+    #[error("unknown sender {fingerprint}")]
+    UnknownSender { fingerprint: String },
+
+    /// The envelope was signed outside its sender key's validity window
+    #[error("key {fingerprint} was not valid at {timestamp_ms} ms")]
+    KeyNotValid {
+        fingerprint: String,
+        timestamp_ms: u64,
+    },
+
+    /// The measured clock skew exceeds the bound and sends are rejected
+    #[error("clock skew of {offset_ms} ms exceeds bound of {max:?}")]
+    ClockSkew {
+        offset_ms: i64,
+        max: std::time::Duration,
+    },
+
+    /// An envelope reuses a nonce already decrypted under the same key version
+    #[error("nonce {nonce} reused under key version {key_version}")]
+    NonceReused { key_version: u32, nonce: String },
+
+    /// The node answered with a gRPC error status
+    #[error("node returned {code:?}: {message}")]
+    Rpc { code: tonic::Code, message: String },
+
+    /// The node did not answer in time
+    #[error("timed out after {after:?}")]
+    Timeout { after: std::time::Duration },
+
+    /// The circuit breaker is open and the send was not attempted
+    ///
+    /// `retry_after` is the rest of the cooldown, or zero while a trial send
+    /// is in flight.
+    #[error("circuit open, retry after {retry_after:?}")]
+    CircuitOpen { retry_after: std::time::Duration },
+
+    /// The client was closed with `Client::close`
+    #[error("client is closed")]
+    Closed,
+
+    /// A consumer of a shared subscription fell behind and missed envelopes
+    #[error("consumer lagged behind and skipped {skipped} envelope(s)")]
+    Lagged { skipped: u64 },
+
+    /// A topic is not valid UTF-8 and strict UTF-8 topics are enabled
+    #[error("topic is not valid UTF-8 (invalid byte at offset {valid_up_to})")]
+    InvalidTopic { valid_up_to: usize },
+
+    /// The node does not support an optional feature the call depends on
+    #[error("node does not support {feature}")]
+    Unsupported {
+        feature: crate::capabilities::Feature,
+    },
+
+    /// The node speaks a protocol version incompatible with the pinned one
+    #[error("node speaks protocol {reported}, incompatible with pinned protocol {pinned}")]
+    ProtocolMismatch {
+        pinned: crate::capabilities::ProtocolVersion,
+        reported: crate::capabilities::ProtocolVersion,
+    },
+
+    /// An envelope fetched from the node failed signature verification
+    #[error("envelope {msg_id} has an invalid signature")]
+    InvalidSignature { msg_id: String },
+
+    /// An envelope carries no signature
+    #[error("envelope {msg_id} is unsigned")]
+    Unsigned { msg_id: String },
+
+    /// An envelope's msg_id does not match its pubkey, seq and nonce
+    #[error("envelope {msg_id} has an invalid msg_id")]
+    InvalidMsgId { msg_id: String },
+
+    /// Associated data exceeds the configured limit for encryption or decryption
+    #[error("AAD of {len} bytes exceeds limit of {max} bytes")]
+    AadTooLarge { len: usize, max: usize },
+
+    /// A sealed envelope has no wrapped key for the client's recipient key
+    #[error("envelope is not sealed to this recipient")]
+    NotRecipient,
+
+    /// An envelope's `to` field is malformed or does not fit how it is encrypted
+    #[error("invalid recipient: {reason}")]
+    InvalidRecipient { reason: String },
+
+    /// An envelope failed one or more checks in `Client::validate_strict`
+    #[error("envelope failed validation: {report}")]
+    Invalid {
+        report: crate::validate::ValidationReport,
+    },
+}
+
+impl From<tonic::Status> for Error {
+    fn from(status: tonic::Status) -> Self {
+        Self::Rpc {
+            code: status.code(),
+            message: status.message().to_string(),
+        }
+    }
+}
@@ -0,0 +1,439 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! In-memory loopback fabric
+//!
+//! [`InMemoryFabric`] is a tiny node living inside the process. It serves the
+//! `FabricNode` service over in-memory pipes rather than sockets, so the
+//! clients from [`InMemoryFabric::client`] run the full publish/subscribe
+//! path, from signing through gRPC to verification, without a node to start.
+//! It is meant for integration tests and local development.
+//!
+//! Like a node, the fabric checks envelopes on ingress, retains everything
+//! published on a topic and fans each envelope out to every subscription on
+//! it, applying shards and header filters. New subscriptions replay the
+//! retained envelopes that have not been acknowledged on the topic, so
+//! [`Client::ack`] and the resume offsets of [`Client::restore_state`] behave
+//! as against a real node. Topics are matched exactly, nothing is persisted
+//! and there are no peers to join.
+
+use crate::capabilities::{Feature, ProtocolVersion};
+use crate::crypto::SignOrder;
+use crate::headers::HeaderFilter;
+use crate::pb::fabric_node_server::{FabricNode, FabricNodeServer};
+use crate::pb::{
+    AckReq, AckResp, CapabilitiesReq, CapabilitiesResp, Envelope, EnvelopeBatch, GetMessageReq,
+    GetMessageResp, HeadReq, HeadResp, JoinResp, NodeId, NodeInfo, PingReq, PingResp,
+    SendBatchResp, SendReq, SendResp, SendResult, StatsReq, StatsResp, SubscribeControl,
+    SubscribeReq,
+};
+use crate::subscription::shard_for;
+use crate::{msg_id_matches, verify_signature, Client};
+use anyhow::{Context as _, Result};
+use futures::stream::{self, BoxStream};
+use futures::StreamExt;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::DuplexStream;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tonic::codegen::http::Uri;
+use tonic::transport::{Endpoint, Server};
+use tonic::{Request, Response, Status, Streaming};
+
+/// URI clients of an [`InMemoryFabric`] report as their endpoint
+const ENDPOINT: &str = "http://in-memory.fabric";
+
+/// Buffer size of each in-memory connection
+const PIPE_CAPACITY: usize = 64 * 1024;
+
+/// Most envelopes pushed in one `SubscribeBatched` batch
+const MAX_BATCH: usize = 64;
+
+/// Loopback node for tests and local development
+///
+/// Clones share the same topics, so clients connected through any clone see
+/// each other's messages.
+#[derive(Clone, Default)]
+pub struct InMemoryFabric {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Retained envelopes per topic, in the order they were published
+    topics: HashMap<Vec<u8>, Vec<Envelope>>,
+    /// msg_id of the envelope that used each sender's seq
+    seqs: HashMap<(Vec<u8>, u64), String>,
+    /// Acknowledged msg_ids per topic
+    acked: HashMap<Vec<u8>, HashSet<String>>,
+    subscribers: Vec<Subscriber>,
+}
+
+/// Live subscription waiting for newly published envelopes
+struct Subscriber {
+    topics: Vec<Vec<u8>>,
+    shard: u32,
+    shard_count: u32,
+    filter: HeaderFilter,
+    tx: mpsc::UnboundedSender<Envelope>,
+}
+
+impl Subscriber {
+    fn wants(&self, envelope: &Envelope) -> bool {
+        self.topics
+            .iter()
+            .any(|topic| envelope.topic.as_bytes() == topic)
+            && (self.shard_count == 0
+                || shard_for(&envelope.pubkey, self.shard_count) == self.shard)
+            && self.filter.matches(envelope)
+    }
+}
+
+impl InMemoryFabric {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Connect a new client to the fabric
+    ///
+    /// Must be called within a Tokio runtime. The client is configured like
+    /// one from [`Client::new`] and can be set up with the usual `with_*`
+    /// methods.
+    pub async fn client(&self) -> Result<Client> {
+        let channel = Endpoint::from_static(ENDPOINT)
+            .connect_with_connector(Pipe {
+                fabric: self.clone(),
+            })
+            .await
+            .context("connect to in-memory fabric")?;
+        let transport = crate::config::Transport::new(ENDPOINT, None, None, None);
+        Ok(Client::from_channel(channel, transport))
+    }
+
+    /// Envelopes retained on `topic`, in the order they were published
+    pub fn retained(&self, topic: &[u8]) -> Vec<Envelope> {
+        let state = self.state.lock().unwrap();
+        state.topics.get(topic).cloned().unwrap_or_default()
+    }
+
+    /// Validate and publish one envelope, returning its msg_id
+    ///
+    /// Resending an envelope already published succeeds without delivering
+    /// it again, so retried sends are idempotent.
+    fn publish(&self, envelope: Option<Envelope>) -> Result<String, Box<Status>> {
+        let envelope =
+            envelope.ok_or_else(|| Box::new(Status::invalid_argument("missing envelope")))?;
+        if envelope.topic.is_empty() {
+            return Err(Box::new(Status::invalid_argument("missing topic")));
+        }
+        if !msg_id_matches(&envelope) {
+            return Err(Box::new(Status::invalid_argument(
+                "msg_id does not match pubkey, seq and nonce",
+            )));
+        }
+        // Plaintext-signed envelopes can only be verified with the topic key
+        if SignOrder::of(&envelope) == SignOrder::EncryptThenSign
+            && !verify_signature(&envelope).unwrap_or(false)
+        {
+            return Err(Box::new(Status::invalid_argument("invalid signature")));
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let sender_seq = (envelope.pubkey.clone(), envelope.seq);
+        match state.seqs.get(&sender_seq) {
+            Some(msg_id) if *msg_id == envelope.msg_id => return Ok(envelope.msg_id),
+            Some(_) => {
+                return Err(Box::new(Status::already_exists(format!(
+                    "seq {} was already used by this sender",
+                    envelope.seq
+                ))))
+            }
+            None => {}
+        }
+        state.seqs.insert(sender_seq, envelope.msg_id.clone());
+        state
+            .subscribers
+            .retain(|subscriber| !subscriber.tx.is_closed());
+        for subscriber in &state.subscribers {
+            if subscriber.wants(&envelope) {
+                let _ = subscriber.tx.send(envelope.clone());
+            }
+        }
+        let msg_id = envelope.msg_id.clone();
+        state
+            .topics
+            .entry(envelope.topic.clone().into_bytes())
+            .or_default()
+            .push(envelope);
+        Ok(msg_id)
+    }
+
+    /// Unacknowledged retained envelopes for `req`, followed by live ones
+    fn open(&self, req: SubscribeReq) -> Result<BoxStream<'static, Envelope>, Box<Status>> {
+        if req.shard_count > 0 && req.shard >= req.shard_count {
+            return Err(Box::new(Status::invalid_argument("shard out of range")));
+        }
+        let topics = match (req.topic.is_empty(), req.topics.is_empty()) {
+            (_, true) => vec![req.topic],
+            (true, false) => req.topics,
+            (false, false) => {
+                return Err(Box::new(Status::invalid_argument(
+                    "topic and topics are mutually exclusive",
+                )))
+            }
+        };
+        let (tx, rx) = mpsc::unbounded_channel();
+        let subscriber = Subscriber {
+            topics,
+            shard: req.shard,
+            shard_count: req.shard_count,
+            filter: HeaderFilter::from(req.filter),
+            tx,
+        };
+
+        // Snapshot and register under one lock so nothing is missed or repeated
+        let mut state = self.state.lock().unwrap();
+        let mut backlog = Vec::new();
+        for topic in &subscriber.topics {
+            let acked = state.acked.get(topic);
+            backlog.extend(
+                state
+                    .topics
+                    .get(topic)
+                    .into_iter()
+                    .flatten()
+                    .filter(|e| {
+                        subscriber.wants(e) && !acked.is_some_and(|acked| acked.contains(&e.msg_id))
+                    })
+                    .cloned(),
+            );
+        }
+        state.subscribers.push(subscriber);
+        Ok(stream::iter(backlog)
+            .chain(UnboundedReceiverStream::new(rx))
+            .boxed())
+    }
+}
+
+#[tonic::async_trait]
+impl FabricNode for InMemoryFabric {
+    async fn send(&self, request: Request<SendReq>) -> Result<Response<SendResp>, Status> {
+        let msg_id = self
+            .publish(request.into_inner().envelope)
+            .map_err(|status| *status)?;
+        Ok(Response::new(SendResp { ok: true, msg_id }))
+    }
+
+    async fn send_batch(
+        &self,
+        request: Request<Streaming<SendReq>>,
+    ) -> Result<Response<SendBatchResp>, Status> {
+        let mut stream = request.into_inner();
+        let mut results = Vec::new();
+        while let Some(req) = stream.message().await? {
+            results.push(match self.publish(req.envelope) {
+                Ok(msg_id) => SendResult {
+                    ok: true,
+                    msg_id,
+                    ..Default::default()
+                },
+                Err(status) => SendResult {
+                    ok: false,
+                    code: status.code() as i32,
+                    error: status.message().to_string(),
+                    ..Default::default()
+                },
+            });
+        }
+        Ok(Response::new(SendBatchResp { results }))
+    }
+
+    type SubscribeStream = BoxStream<'static, Result<Envelope, Status>>;
+
+    async fn subscribe(
+        &self,
+        request: Request<SubscribeReq>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let req = request.into_inner();
+        let max_rate = req.max_rate;
+        let envelopes = self.open(req).map_err(|status| *status)?.map(Ok);
+        if max_rate == 0 {
+            return Ok(Response::new(envelopes.boxed()));
+        }
+        let interval = Duration::from_secs(1) / max_rate;
+        let paced = envelopes.then(move |item| async move {
+            tokio::time::sleep(interval).await;
+            item
+        });
+        Ok(Response::new(paced.boxed()))
+    }
+
+    type SubscribeBatchedStream = BoxStream<'static, Result<EnvelopeBatch, Status>>;
+
+    async fn subscribe_batched(
+        &self,
+        request: Request<SubscribeReq>,
+    ) -> Result<Response<Self::SubscribeBatchedStream>, Status> {
+        // Batch whatever is ready, so a backlog goes out in few frames
+        let batches = self
+            .open(request.into_inner())
+            .map_err(|status| *status)?
+            .ready_chunks(MAX_BATCH)
+            .map(|envelopes| EnvelopeBatch { envelopes })
+            .map(Ok);
+        Ok(Response::new(batches.boxed()))
+    }
+
+    type SubscribeCreditsStream = BoxStream<'static, Result<Envelope, Status>>;
+
+    async fn subscribe_credits(
+        &self,
+        request: Request<Streaming<SubscribeControl>>,
+    ) -> Result<Response<Self::SubscribeCreditsStream>, Status> {
+        let mut control = request.into_inner();
+        let first = control
+            .message()
+            .await?
+            .ok_or_else(|| Status::invalid_argument("empty control stream"))?;
+        let req = first
+            .subscribe
+            .ok_or_else(|| Status::invalid_argument("first message has no subscribe"))?;
+        let mut envelopes = self.open(req).map_err(|status| *status)?;
+
+        // Forward while credits last, then wait for the next grant
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut credits = first.credits;
+            loop {
+                while credits > 0 {
+                    let Some(envelope) = envelopes.next().await else {
+                        return;
+                    };
+                    if tx.send(Ok(envelope)).is_err() {
+                        return;
+                    }
+                    credits -= 1;
+                }
+                match control.message().await {
+                    Ok(Some(grant)) => credits += grant.credits,
+                    _ => return,
+                }
+            }
+        });
+        Ok(Response::new(UnboundedReceiverStream::new(rx).boxed()))
+    }
+
+    async fn ack(&self, request: Request<AckReq>) -> Result<Response<AckResp>, Status> {
+        let req = request.into_inner();
+        let mut state = self.state.lock().unwrap();
+        state
+            .acked
+            .entry(req.topic)
+            .or_default()
+            .extend(req.msg_ids);
+        Ok(Response::new(AckResp { ok: true }))
+    }
+
+    async fn head(&self, request: Request<HeadReq>) -> Result<Response<HeadResp>, Status> {
+        let req = request.into_inner();
+        let latest_seq = self
+            .retained(&req.topic)
+            .iter()
+            .filter(|e| e.pubkey == req.pubkey)
+            .map(|e| e.seq)
+            .max()
+            .unwrap_or(0);
+        Ok(Response::new(HeadResp { latest_seq }))
+    }
+
+    async fn get_message(
+        &self,
+        request: Request<GetMessageReq>,
+    ) -> Result<Response<GetMessageResp>, Status> {
+        let req = request.into_inner();
+        let envelope = self
+            .retained(&req.topic)
+            .into_iter()
+            .find(|e| e.msg_id == req.msg_id);
+        Ok(Response::new(GetMessageResp { envelope }))
+    }
+
+    async fn ping(&self, _request: Request<PingReq>) -> Result<Response<PingResp>, Status> {
+        let server_time_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        Ok(Response::new(PingResp { server_time_ms }))
+    }
+
+    async fn capabilities(
+        &self,
+        _request: Request<CapabilitiesReq>,
+    ) -> Result<Response<CapabilitiesResp>, Status> {
+        let features = [
+            Feature::Ack,
+            Feature::Head,
+            Feature::Ping,
+            Feature::ServerTime,
+            Feature::SendBatch,
+            Feature::HeaderFilters,
+            Feature::GetMessage,
+            Feature::Credits,
+            Feature::RateLimit,
+            Feature::BatchedSubscribe,
+            Feature::MultiTopic,
+            Feature::Shards,
+        ]
+        .into_iter()
+        .map(|feature| feature.wire_name().to_string())
+        .collect();
+        Ok(Response::new(CapabilitiesResp {
+            features,
+            protocol_version: ProtocolVersion::CURRENT.to_string(),
+        }))
+    }
+
+    async fn stats(&self, _request: Request<StatsReq>) -> Result<Response<StatsResp>, Status> {
+        Ok(Response::new(StatsResp {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            ..Default::default()
+        }))
+    }
+
+    async fn join(&self, _request: Request<NodeInfo>) -> Result<Response<JoinResp>, Status> {
+        Err(Status::unimplemented("the in-memory fabric has no peers"))
+    }
+
+    async fn unjoin(&self, _request: Request<NodeId>) -> Result<Response<JoinResp>, Status> {
+        Err(Status::unimplemented("the in-memory fabric has no peers"))
+    }
+}
+
+/// Connector serving each new connection of a channel from the fabric
+struct Pipe {
+    fabric: InMemoryFabric,
+}
+
+impl tower_service::Service<Uri> for Pipe {
+    type Response = hyper_util::rt::TokioIo<DuplexStream>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<Self::Response>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _: Uri) -> Self::Future {
+        let (client, server) = tokio::io::duplex(PIPE_CAPACITY);
+        tokio::spawn(
+            Server::builder()
+                .add_service(FabricNodeServer::new(self.fabric.clone()))
+                .serve_with_incoming(stream::iter([Ok::<_, io::Error>(server)])),
+        );
+        Box::pin(async move { Ok(hyper_util::rt::TokioIo::new(client)) })
+    }
+}
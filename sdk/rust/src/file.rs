@@ -0,0 +1,248 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Streaming files over a topic in ordered chunks
+//!
+//! [`Client::send_file`] splits a file into envelopes of at most `chunk_size`
+//! bytes and [`Client::receive_file`] puts them back together, for log
+//! shipping and firmware-sized payloads that do not fit one envelope. Chunks
+//! describe their place in the transfer in signed headers:
+//!
+//! - `file-id`: msg_id of the first chunk, which identifies the transfer and
+//!   is absent from the first chunk itself
+//! - `file-chunk`: zero-based index of the chunk
+//! - `file-chunks`: total number of chunks
+//! - `file-blake3`: hex BLAKE3 of the whole file, on the last chunk only
+//!
+//! A sender's envelopes arrive in order, so the receiver treats a skipped
+//! index as a lost chunk rather than waiting for it.
+
+use crate::error::Error;
+use crate::{Client, Outgoing};
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// How long [`Client::receive_file`] waits for each chunk
+pub const DEFAULT_CHUNK_TIMEOUT: Duration = Duration::from_secs(30);
+
+const FILE_ID: &str = "file-id";
+const CHUNK: &str = "file-chunk";
+const CHUNKS: &str = "file-chunks";
+const DIGEST: &str = "file-blake3";
+
+impl Client {
+    /// Send the file at `path` on `topic` as chunks of at most `chunk_size` bytes
+    ///
+    /// Each chunk is signed, and encrypted if a topic key is configured, like
+    /// any other send; `to` addresses every chunk to one recipient, or is
+    /// empty to broadcast. Returns the msg_id of the first chunk, which
+    /// identifies the transfer. Fails if the file changes size while being
+    /// sent; chunks sent before a failure are not withdrawn.
+    pub async fn send_file(
+        &mut self,
+        topic: &str,
+        to: &[u8],
+        path: impl AsRef<Path>,
+        chunk_size: usize,
+    ) -> Result<String> {
+        anyhow::ensure!(chunk_size > 0, "Chunk size must be non-zero");
+        let path = path.as_ref();
+        let mut file = File::open(path).with_context(|| format!("open {}", path.display()))?;
+        let len = file.metadata()?.len();
+        let chunks = len.div_ceil(chunk_size as u64).max(1);
+
+        let mut buffer = vec![0u8; chunk_size];
+        let mut digest = blake3::Hasher::new();
+        let mut file_id: Option<String> = None;
+        for index in 0..chunks {
+            let read = read_full(&mut file, &mut buffer)
+                .with_context(|| format!("read {}", path.display()))?;
+            let expected = (len - index * chunk_size as u64).min(chunk_size as u64) as usize;
+            anyhow::ensure!(read == expected, "{} changed while sending", path.display());
+            let chunk = &buffer[..read];
+            digest.update(chunk);
+
+            let mut headers = BTreeMap::new();
+            headers.insert(CHUNK.to_string(), index.to_string());
+            headers.insert(CHUNKS.to_string(), chunks.to_string());
+            if let Some(file_id) = &file_id {
+                headers.insert(FILE_ID.to_string(), file_id.clone());
+            }
+            if index + 1 == chunks {
+                headers.insert(DIGEST.to_string(), digest.finalize().to_hex().to_string());
+            }
+            let outgoing = Outgoing {
+                to,
+                headers: &headers,
+                ..Default::default()
+            };
+            let msg_id = self
+                .send_envelope(topic, outgoing, chunk)
+                .await
+                .with_context(|| format!("send chunk {index} of {chunks}"))?;
+            file_id.get_or_insert(msg_id);
+        }
+        Ok(file_id.expect("a file has at least one chunk"))
+    }
+
+    /// Receive the next file sent on `topic` and write it to `out_path`
+    ///
+    /// Waits up to [`DEFAULT_CHUNK_TIMEOUT`] for each chunk; see
+    /// [`Client::receive_file_within`].
+    pub async fn receive_file(
+        &mut self,
+        topic: &[u8],
+        out_path: impl AsRef<Path>,
+    ) -> Result<String> {
+        self.receive_file_within(topic, out_path, DEFAULT_CHUNK_TIMEOUT)
+            .await
+    }
+
+    /// Receive the next file sent on `topic`, waiting up to `chunk_timeout` per chunk
+    ///
+    /// Starts at the first verified chunk 0 on the topic and follows that
+    /// transfer only, ignoring other envelopes and envelopes that fail
+    /// verification. Encrypted chunks are decrypted with the configured topic
+    /// key. The file is assembled next to `out_path` and moved there once the
+    /// last chunk arrives and the whole file matches its digest, so a failed
+    /// transfer never leaves a partial file at `out_path`. Returns the
+    /// transfer's file id.
+    ///
+    /// Fails when a chunk is skipped, with [`Error::Timeout`] when the next
+    /// chunk does not arrive in time, and when the subscription ends first.
+    pub async fn receive_file_within(
+        &mut self,
+        topic: &[u8],
+        out_path: impl AsRef<Path>,
+        chunk_timeout: Duration,
+    ) -> Result<String> {
+        let out_path = out_path.as_ref();
+        let mut partial = out_path.as_os_str().to_owned();
+        partial.push(".part");
+        let mut stream = self.subscribe_verifying(topic).await?;
+        let mut transfer: Option<Transfer> = None;
+
+        let result = async {
+            loop {
+                let envelope = match tokio::time::timeout(chunk_timeout, stream.next()).await {
+                    Err(_) => {
+                        return Err(Error::Timeout {
+                            after: chunk_timeout,
+                        }
+                        .into())
+                    }
+                    Ok(None) => anyhow::bail!("Subscription ended before the file was complete"),
+                    Ok(Some(item)) => item.context("subscription failed")?,
+                };
+                let headers = envelope.headers();
+                let Some(index) = headers.get(CHUNK).and_then(|i| i.parse::<u64>().ok()) else {
+                    continue;
+                };
+                if !(self.verify(&envelope).unwrap_or(false) && self.verify_msg_id(&envelope)) {
+                    continue;
+                }
+                let Some(envelope) = self.post_receive.after_verification(envelope) else {
+                    continue;
+                };
+
+                if transfer.is_none() {
+                    if index != 0 {
+                        continue;
+                    }
+                    transfer = Some(Transfer {
+                        file_id: envelope.msg_id.clone(),
+                        sender: envelope.pubkey.clone(),
+                        chunks: parse(&headers, CHUNKS)?,
+                        next: 0,
+                        digest: blake3::Hasher::new(),
+                        out: File::create(&partial)
+                            .with_context(|| format!("create {}", partial.to_string_lossy()))?,
+                    });
+                }
+                let transfer = transfer.as_mut().expect("transfer started above");
+                let first = index == 0 && envelope.msg_id == transfer.file_id;
+                let later = envelope.pubkey == transfer.sender
+                    && headers.get(FILE_ID) == Some(&transfer.file_id);
+                if !(first || later) {
+                    continue;
+                }
+                if index < transfer.next {
+                    continue;
+                }
+                anyhow::ensure!(
+                    index == transfer.next,
+                    "File {}: chunk {} of {} is missing",
+                    transfer.file_id,
+                    transfer.next,
+                    transfer.chunks
+                );
+
+                let chunk = if envelope.key_version != 0 {
+                    self.decrypt(&envelope)?
+                } else {
+                    envelope.payload
+                };
+                transfer.digest.update(&chunk);
+                transfer.out.write_all(&chunk)?;
+                transfer.next += 1;
+                if transfer.next == transfer.chunks {
+                    let expected = headers.get(DIGEST).context("Last chunk has no digest")?;
+                    anyhow::ensure!(
+                        transfer.digest.finalize().to_hex().as_str() == expected,
+                        "File {} does not match its digest",
+                        transfer.file_id
+                    );
+                    transfer.out.sync_all()?;
+                    return Ok(transfer.file_id.clone());
+                }
+            }
+        }
+        .await;
+
+        // Close the partial file before moving or removing it
+        let started = transfer.take().is_some();
+        match &result {
+            Ok(_) => std::fs::rename(&partial, out_path)
+                .with_context(|| format!("move file to {}", out_path.display()))?,
+            Err(_) if started => {
+                let _ = std::fs::remove_file(&partial);
+            }
+            Err(_) => {}
+        }
+        result
+    }
+}
+
+/// Transfer being reassembled by [`Client::receive_file_within`]
+struct Transfer {
+    file_id: String,
+    sender: Vec<u8>,
+    chunks: u64,
+    /// Index of the next chunk expected
+    next: u64,
+    digest: blake3::Hasher,
+    out: File,
+}
+
+fn parse(headers: &BTreeMap<String, String>, name: &str) -> Result<u64> {
+    headers
+        .get(name)
+        .and_then(|value| value.parse().ok())
+        .with_context(|| format!("Chunk has no valid {name} header"))
+}
+
+/// Read until `buf` is full or the file ends, returning the bytes read
+fn read_full(file: &mut File, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match file.read(&mut buf[filled..])? {
+            0 => break,
+            read => filled += read,
+        }
+    }
+    Ok(filled)
+}
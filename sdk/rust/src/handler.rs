@@ -0,0 +1,254 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Handler-based subscription dispatch
+//!
+//! Instead of looping over a subscription stream by hand, implement
+//! [`MessageHandler`] and hand it to [`Client::run`]. The loop verifies each
+//! envelope before dispatch and isolates handler failures, so one bad message
+//! does not stop the subscription. [`Client::subscribe_autoack`] runs the same
+//! loop for at-least-once consumers, acknowledging processed envelopes in
+//! batches.
+
+use crate::pb::Envelope;
+use crate::subscription::StreamEnd;
+use crate::Client;
+use anyhow::Result;
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio_stream::StreamExt;
+
+pub use async_trait::async_trait;
+
+/// Callback invoked for every envelope received by [`Client::run`]
+#[async_trait]
+pub trait MessageHandler: Send + Sync {
+    /// Process one envelope; an error is reported to [`MessageHandler::on_error`]
+    async fn handle(&self, envelope: Envelope) -> Result<()>;
+
+    /// Called when [`MessageHandler::handle`] fails; the loop continues afterwards
+    async fn on_error(&self, _msg_id: &str, _error: anyhow::Error) {}
+}
+
+/// Options for [`Client::run_with`]
+#[derive(Debug, Clone)]
+pub struct RunOptions {
+    /// Drop envelopes whose signature or msg_id does not verify (default: true)
+    pub verify: bool,
+    /// Acknowledge each envelope after its handler succeeds (default: false)
+    pub ack_on_success: bool,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        Self {
+            verify: true,
+            ack_on_success: false,
+        }
+    }
+}
+
+/// When [`Client::subscribe_autoack`] acknowledges processed envelopes
+///
+/// A batch is acknowledged once it holds `every_n` message IDs or
+/// `every_duration` after its first one was added, whichever comes first, and
+/// when the subscription ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AckPolicy {
+    /// Largest batch of message IDs per `Ack` call (default: 100)
+    pub every_n: usize,
+    /// Longest time a processed envelope waits to be acknowledged (default: 1s)
+    pub every_duration: Duration,
+}
+
+impl Default for AckPolicy {
+    fn default() -> Self {
+        Self {
+            every_n: 100,
+            every_duration: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Counters reported when a dispatch loop finishes
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RunSummary {
+    /// Envelopes the handler processed successfully
+    pub handled: u64,
+    /// Envelopes the handler returned an error for
+    pub failed: u64,
+    /// Envelopes dropped because they failed verification
+    pub rejected: u64,
+    /// Envelopes acknowledged to the node
+    pub acked: u64,
+    /// Envelopes handled successfully whose acknowledgement failed
+    pub ack_failed: u64,
+    /// Why the node ended the subscription, if it ended it on purpose
+    pub end: Option<StreamEnd>,
+}
+
+impl Client {
+    /// Dispatch every envelope on `topic` to `handler` with default [`RunOptions`]
+    pub async fn run(&mut self, topic: &[u8], handler: impl MessageHandler) -> Result<RunSummary> {
+        self.run_with(topic, handler, RunOptions::default()).await
+    }
+
+    /// Dispatch every envelope on `topic` to `handler`
+    ///
+    /// Runs until the server ends the stream, returning the accumulated
+    /// [`RunSummary`] with the reason the node gave, if any; drop the future
+    /// to cancel the loop. A transport error ends the loop with that error. A
+    /// failed acknowledgement under [`RunOptions::ack_on_success`] is logged
+    /// and counted in [`RunSummary::ack_failed`], and the loop continues; the
+    /// node redelivers the envelope.
+    pub async fn run_with(
+        &mut self,
+        topic: &[u8],
+        handler: impl MessageHandler,
+        options: RunOptions,
+    ) -> Result<RunSummary> {
+        let mut stream = self.subscribe_verifying(topic).await?;
+        let mut summary = RunSummary::default();
+
+        while let Some(item) = stream.next().await {
+            let envelope = item?;
+
+            if options.verify
+                && !(self.verify(&envelope).unwrap_or(false) && self.verify_msg_id(&envelope))
+            {
+                summary.rejected += 1;
+                continue;
+            }
+            let Some(envelope) = self.post_receive.after_verification(envelope) else {
+                continue;
+            };
+
+            let msg_id = envelope.msg_id.clone();
+            match handler.handle(envelope).await {
+                Ok(()) => {
+                    summary.handled += 1;
+                    if options.ack_on_success {
+                        match self.ack(topic, vec![msg_id]).await {
+                            Ok(()) => summary.acked += 1,
+                            Err(error) => {
+                                tracing::warn!("acknowledging a handled message failed: {error:#}");
+                                summary.ack_failed += 1;
+                            }
+                        }
+                    }
+                }
+                Err(error) => {
+                    summary.failed += 1;
+                    handler.on_error(&msg_id, error).await;
+                }
+            }
+        }
+
+        summary.end = stream.stream_end().cloned();
+        Ok(summary)
+    }
+
+    /// Dispatch every envelope on `topic` to `handler`, acknowledging in batches
+    ///
+    /// Envelopes are verified as in [`Client::run`], and those handled
+    /// successfully are acknowledged according to `policy`. Once the handler
+    /// fails for an envelope, no later envelope from the same sender is
+    /// acknowledged in this run, so what is acknowledged for each sender is
+    /// always the contiguous run of seqs up to its first failure; the node
+    /// redelivers the rest. Envelopes rejected by verification are neither
+    /// acknowledged nor treated as failures. Pending acknowledgements are sent
+    /// when the stream ends, also on a transport error, which is then returned.
+    ///
+    /// Fails with [`Error::Unsupported`](crate::Error::Unsupported) on nodes
+    /// without the `Ack` RPC.
+    pub async fn subscribe_autoack(
+        &mut self,
+        topic: &[u8],
+        handler: impl MessageHandler,
+        policy: AckPolicy,
+    ) -> Result<RunSummary> {
+        self.require(crate::capabilities::Feature::Ack).await?;
+        let mut stream = self.subscribe_verifying(topic).await?;
+        let mut summary = RunSummary::default();
+        let mut pending = Vec::new();
+        let mut deadline = None;
+        let mut failed_senders = HashSet::new();
+
+        loop {
+            let flush_timer = async {
+                match deadline {
+                    Some(deadline) => tokio::time::sleep_until(deadline).await,
+                    None => std::future::pending().await,
+                }
+            };
+            let item = tokio::select! {
+                item = stream.next() => item,
+                () = flush_timer => {
+                    self.flush_acks(topic, &mut pending, &mut summary).await?;
+                    deadline = None;
+                    continue;
+                }
+            };
+            let envelope = match item {
+                Some(Ok(envelope)) => envelope,
+                Some(Err(status)) => {
+                    self.flush_acks(topic, &mut pending, &mut summary).await?;
+                    return Err(status.into());
+                }
+                None => break,
+            };
+
+            if !(self.verify(&envelope).unwrap_or(false) && self.verify_msg_id(&envelope)) {
+                summary.rejected += 1;
+                continue;
+            }
+            let Some(envelope) = self.post_receive.after_verification(envelope) else {
+                continue;
+            };
+
+            let msg_id = envelope.msg_id.clone();
+            let sender = envelope.pubkey.clone();
+            match handler.handle(envelope).await {
+                Ok(()) => {
+                    summary.handled += 1;
+                    if failed_senders.contains(&sender) {
+                        continue;
+                    }
+                    pending.push(msg_id);
+                    deadline.get_or_insert_with(|| Instant::now() + policy.every_duration);
+                    if pending.len() >= policy.every_n {
+                        self.flush_acks(topic, &mut pending, &mut summary).await?;
+                        deadline = None;
+                    }
+                }
+                Err(error) => {
+                    summary.failed += 1;
+                    failed_senders.insert(sender);
+                    handler.on_error(&msg_id, error).await;
+                }
+            }
+        }
+
+        self.flush_acks(topic, &mut pending, &mut summary).await?;
+        summary.end = stream.stream_end().cloned();
+        Ok(summary)
+    }
+
+    /// Acknowledge and clear `pending`, if it holds anything
+    ///
+    /// `pending` is left as it was if the ack fails.
+    async fn flush_acks(
+        &mut self,
+        topic: &[u8],
+        pending: &mut Vec<String>,
+        summary: &mut RunSummary,
+    ) -> Result<()> {
+        if pending.is_empty() {
+            return Ok(());
+        }
+        self.ack(topic, pending.clone()).await?;
+        summary.acked += pending.len() as u64;
+        pending.clear();
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Application headers and header-filtered subscriptions
+//!
+//! Headers are string key/value pairs carried in the envelope AAD under
+//! `"headers"`, so they are signed along with the rest of the AAD. A
+//! [`HeaderFilter`] selects envelopes by their headers; where the node supports
+//! it the filter is evaluated server-side so non-matching envelopes never cross
+//! the wire.
+
+use crate::capabilities::Feature;
+use crate::pb::header_predicate::Op;
+use crate::pb::{Envelope, HeaderPredicate, SubscribeReq};
+use crate::{Client, Outgoing};
+use anyhow::Result;
+use futures::stream::{BoxStream, StreamExt};
+use std::collections::BTreeMap;
+use tonic::{Code, Status};
+
+impl Envelope {
+    /// Headers carried in the envelope's AAD
+    ///
+    /// Returns an empty map if the AAD has no headers or is not a JSON object.
+    /// Headers are only authentic once the envelope has been verified.
+    pub fn headers(&self) -> BTreeMap<String, String> {
+        let Ok(aad) = serde_json::from_slice::<serde_json::Value>(&self.aad) else {
+            return BTreeMap::new();
+        };
+        let Some(headers) = aad.get("headers").and_then(|h| h.as_object()) else {
+            return BTreeMap::new();
+        };
+        headers
+            .iter()
+            .filter_map(|(name, value)| Some((name.clone(), value.as_str()?.to_string())))
+            .collect()
+    }
+}
+
+/// Conjunction of predicates over envelope headers
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HeaderFilter {
+    predicates: Vec<HeaderPredicate>,
+}
+
+impl HeaderFilter {
+    /// Filter matching every envelope; add predicates to narrow it
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require header `name` to equal `value`
+    pub fn equals(self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.with(name, Op::Equals, [value.into()])
+    }
+
+    /// Require header `name` to equal one of `values`
+    pub fn one_of(
+        self,
+        name: impl Into<String>,
+        values: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.with(name, Op::In, values.into_iter().map(Into::into))
+    }
+
+    /// Require header `name` to start with `prefix`
+    pub fn prefix(self, name: impl Into<String>, prefix: impl Into<String>) -> Self {
+        self.with(name, Op::Prefix, [prefix.into()])
+    }
+
+    fn with(
+        mut self,
+        name: impl Into<String>,
+        op: Op,
+        values: impl IntoIterator<Item = String>,
+    ) -> Self {
+        self.predicates.push(HeaderPredicate {
+            name: name.into(),
+            op: op.into(),
+            values: values.into_iter().collect(),
+        });
+        self
+    }
+
+    /// Predicates in wire form, as sent in `SubscribeReq.filter`
+    pub fn predicates(&self) -> &[HeaderPredicate] {
+        &self.predicates
+    }
+
+    /// Whether the envelope's headers satisfy every predicate
+    pub fn matches(&self, envelope: &Envelope) -> bool {
+        if self.predicates.is_empty() {
+            return true;
+        }
+        let headers = envelope.headers();
+        self.predicates.iter().all(|predicate| {
+            let Some(value) = headers.get(&predicate.name) else {
+                return false;
+            };
+            match Op::try_from(predicate.op) {
+                Ok(Op::Equals) => predicate.values.first() == Some(value),
+                Ok(Op::In) => predicate.values.contains(value),
+                Ok(Op::Prefix) => predicate
+                    .values
+                    .first()
+                    .is_some_and(|prefix| value.starts_with(prefix.as_str())),
+                Err(_) => false,
+            }
+        })
+    }
+}
+
+impl From<Vec<HeaderPredicate>> for HeaderFilter {
+    fn from(predicates: Vec<HeaderPredicate>) -> Self {
+        Self { predicates }
+    }
+}
+
+impl Client {
+    /// Send a broadcast message carrying application headers
+    pub async fn send_with_headers(
+        &mut self,
+        topic: &str,
+        headers: &BTreeMap<String, String>,
+        payload: &[u8],
+    ) -> Result<String> {
+        let outgoing = Outgoing {
+            headers,
+            ..Default::default()
+        };
+        self.send_envelope(topic, outgoing, payload).await
+    }
+
+    /// Subscribe to envelopes on a topic whose headers match `filter`
+    ///
+    /// The filter is sent to the node so it forwards only matching envelopes,
+    /// unless its [capabilities](Client::capabilities) lack header filters. If
+    /// the node answers `UNIMPLEMENTED`, the subscription is reopened without it.
+    /// Either way the filter is also applied to every received envelope, so a node
+    /// that ignores it cannot leak non-matching envelopes to the consumer.
+    pub async fn subscribe_matching(
+        &mut self,
+        topic: &[u8],
+        filter: HeaderFilter,
+    ) -> Result<BoxStream<'static, Result<Envelope, Status>>> {
+        let filtering = self.capabilities().await.map_or(true, |caps| {
+            caps.supports(Feature::HeaderFilters) != Some(false)
+        });
+        let req = SubscribeReq {
+            topic: topic.to_vec(),
+            filter: filter.predicates.clone(),
+            ..Default::default()
+        };
+        let opened = if filtering {
+            self.open_subscription(req).await
+        } else {
+            self.subscribe(topic).await
+        };
+        let subscription = match opened {
+            Ok(subscription) => subscription,
+            Err(error)
+                if error
+                    .downcast_ref::<Status>()
+                    .is_some_and(|status| status.code() == Code::Unimplemented) =>
+            {
+                self.subscribe(topic).await?
+            }
+            Err(error) => return Err(error),
+        };
+
+        let stream = subscription.filter(move |item| {
+            let keep = match item {
+                Ok(envelope) => filter.matches(envelope),
+                Err(_) => true,
+            };
+            futures::future::ready(keep)
+        });
+        Ok(stream.boxed())
+    }
+}
@@ -0,0 +1,140 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Message transformation hooks
+//!
+//! A pre-send hook sees every outgoing message before it is encrypted and
+//! signed, so cross-cutting concerns such as correlation ids, schema versions
+//! or redaction live in one place instead of at every call site. Post-receive
+//! hooks are the consumer-side counterpart: they rewrite or drop received
+//! envelopes, either as they arrive or after the SDK has verified and
+//! decrypted them, as chosen with [`ReceiveOrder`].
+
+use crate::pb::Envelope;
+use crate::Client;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// An outgoing message as seen by a pre-send hook
+///
+/// Changes made by the hook are what gets encrypted, signed and sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SendContext {
+    pub topic: String,
+    /// Application headers, carried in the signed AAD
+    pub headers: BTreeMap<String, String>,
+    /// Plaintext payload, before any encryption
+    pub payload: Vec<u8>,
+}
+
+pub(crate) type PreSend = Arc<dyn Fn(&mut SendContext) + Send + Sync>;
+
+/// When post-receive hooks run relative to the SDK's own verification
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReceiveOrder {
+    /// Run as envelopes arrive, before any verification or decryption
+    ///
+    /// Verifying subscriptions check what the hooks return, so a hook that
+    /// rewrites signed fields makes those envelopes fail verification.
+    BeforeVerification,
+    /// Run after verification and decryption, on authentic envelopes only
+    ///
+    /// Applies to [`Client::subscribe_decrypted`], [`Client::subscribe_windowed`],
+    /// [`Client::subscribe_chain_verified`], [`Client::run`],
+    /// [`Client::subscribe_autoack`] and [`Client::materialize`]. Streams that
+    /// do not verify, such as [`Client::subscribe`], run hooks as envelopes
+    /// arrive either way.
+    #[default]
+    AfterVerification,
+}
+
+/// Post-receive hooks of a client and when they run
+#[derive(Clone, Default)]
+pub(crate) struct PostReceive {
+    hooks: Vec<Arc<dyn Fn(Envelope) -> Option<Envelope> + Send + Sync>>,
+    order: ReceiveOrder,
+}
+
+impl PostReceive {
+    /// Run every hook in registration order, stopping at the first drop
+    pub(crate) fn apply(&self, envelope: Envelope) -> Option<Envelope> {
+        self.hooks
+            .iter()
+            .try_fold(envelope, |envelope, hook| hook(envelope))
+    }
+
+    /// The hooks a verifying subscription runs on arrival
+    pub(crate) fn before_verification(&self) -> Self {
+        match self.order {
+            ReceiveOrder::BeforeVerification => self.clone(),
+            ReceiveOrder::AfterVerification => Self::default(),
+        }
+    }
+
+    /// Run the hooks ordered after verification on a verified envelope
+    pub(crate) fn after_verification(&self, envelope: Envelope) -> Option<Envelope> {
+        match self.order {
+            ReceiveOrder::BeforeVerification => Some(envelope),
+            ReceiveOrder::AfterVerification => self.apply(envelope),
+        }
+    }
+}
+
+/// Run `hooks` in registration order over a copy of the outgoing message
+pub(crate) fn run_pre_send(
+    hooks: &[PreSend],
+    topic: &str,
+    headers: &BTreeMap<String, String>,
+    payload: &[u8],
+) -> SendContext {
+    let mut context = SendContext {
+        topic: topic.to_string(),
+        headers: headers.clone(),
+        payload: payload.to_vec(),
+    };
+    for hook in hooks {
+        hook(&mut context);
+    }
+    context
+}
+
+impl Client {
+    /// Transform every outgoing message before it is signed
+    ///
+    /// `hook` may rewrite the topic, headers and payload of each message sent
+    /// by this client and its clones, including batches. It runs before
+    /// encryption and signing, so its changes are covered by the signature.
+    /// Hooks added by repeated calls run in the order they were added.
+    pub fn with_pre_send(
+        mut self,
+        hook: impl Fn(&mut SendContext) + Send + Sync + 'static,
+    ) -> Self {
+        self.pre_send.push(Arc::new(hook));
+        self
+    }
+
+    /// Transform or drop every received envelope
+    ///
+    /// `hook` returns the envelope to deliver, possibly rewritten, or `None`
+    /// to drop it, for every subscription opened by this client and its clones.
+    /// By default it runs after the SDK's verification and decryption; see
+    /// [`Client::with_receive_order`]. Hooks added by repeated calls run in the
+    /// order they were added, and a drop skips the remaining hooks. Dropped
+    /// envelopes still count as consumed in
+    /// [`SubscriptionStats`](crate::subscription::SubscriptionStats) but are
+    /// never handed to a handler or acknowledged.
+    pub fn with_post_receive(
+        mut self,
+        hook: impl Fn(Envelope) -> Option<Envelope> + Send + Sync + 'static,
+    ) -> Self {
+        self.post_receive.hooks.push(Arc::new(hook));
+        self
+    }
+
+    /// Choose whether post-receive hooks run before or after verification
+    ///
+    /// Defaults to [`ReceiveOrder::AfterVerification`].
+    pub fn with_receive_order(mut self, order: ReceiveOrder) -> Self {
+        self.post_receive.order = order;
+        self
+    }
+}
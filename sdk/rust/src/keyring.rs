@@ -0,0 +1,276 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Trusted sender keys
+//!
+//! An envelope carries its sender's public key, so a bare signature check only
+//! proves the envelope is self-consistent. A [`Keyring`] restricts
+//! verification to known senders and keeps their keys parsed, so verifying
+//! traffic from many senders does not re-decode a key per message.
+//!
+//! A [`KeyHistory`] additionally records when each key was in use, so that
+//! after a sender rotates keys its old messages still verify under the key it
+//! held at the time, while anything newly signed with a retired key is refused.
+
+use crate::crypto::scheme::VerifyingKey;
+use crate::error::Error;
+use crate::pb::Envelope;
+use crate::{verify_signature_with, Client};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Fingerprint of a sender public key: the first 16 bytes of `blake3(pubkey)`, hex-encoded
+pub fn fingerprint(pubkey: &[u8]) -> String {
+    hex::encode(&blake3::hash(pubkey).as_bytes()[..16])
+}
+
+/// Set of trusted sender keys indexed by [`fingerprint`]
+///
+/// Clones share the same set, so keys added, removed or reloaded through any
+/// clone take effect for every client using the keyring.
+#[derive(Clone, Debug, Default)]
+pub struct Keyring {
+    keys: Arc<RwLock<HashMap<String, VerifyingKey>>>,
+}
+
+impl Keyring {
+    /// Empty keyring
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust `key`, returning its fingerprint
+    ///
+    /// Accepts an Ed25519 or secp256k1 public key; envelopes from the sender
+    /// must declare the matching signature scheme.
+    pub fn insert(&self, key: impl Into<VerifyingKey>) -> String {
+        let key = key.into();
+        let fp = fingerprint(&key.to_bytes());
+        self.keys.write().unwrap().insert(fp.clone(), key);
+        fp
+    }
+
+    /// Stop trusting the key with `fingerprint`, returning it if present
+    pub fn remove(&self, fingerprint: &str) -> Option<VerifyingKey> {
+        self.keys.write().unwrap().remove(fingerprint)
+    }
+
+    /// Replace the whole set of trusted keys at once
+    pub fn reload<K: Into<VerifyingKey>>(&self, keys: impl IntoIterator<Item = K>) {
+        let keys = keys
+            .into_iter()
+            .map(|key| {
+                let key = key.into();
+                (fingerprint(&key.to_bytes()), key)
+            })
+            .collect();
+        *self.keys.write().unwrap() = keys;
+    }
+
+    /// Trusted key with `fingerprint`
+    pub fn get(&self, fingerprint: &str) -> Option<VerifyingKey> {
+        self.keys.read().unwrap().get(fingerprint).copied()
+    }
+
+    /// Number of trusted keys
+    pub fn len(&self) -> usize {
+        self.keys.read().unwrap().len()
+    }
+
+    /// Whether no keys are trusted
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Verify an envelope's signature against the trusted key for its sender
+    ///
+    /// Checks the signature over the payload as carried, so plaintext-signed
+    /// envelopes must go through [`Client::verify`] instead. Always uses
+    /// [`VerifyMode::Strict`](crate::crypto::VerifyMode::Strict). Fails with [`Error::UnknownSender`] if the sender is not in the keyring.
+    pub fn verify(&self, envelope: &Envelope) -> Result<bool> {
+        verify_signature_with(envelope, &self.sender_key(envelope)?)
+    }
+
+    /// Trusted key of the envelope's sender
+    pub(crate) fn sender_key(&self, envelope: &Envelope) -> Result<VerifyingKey, Error> {
+        let fp = fingerprint(&envelope.pubkey);
+        lookup(&self.keys.read().unwrap(), &fp, envelope)
+            .ok_or(Error::UnknownSender { fingerprint: fp })
+    }
+
+    /// Trusted keys of several envelopes' senders under a single lock, with
+    /// each sender's fingerprint
+    pub(crate) fn sender_keys(
+        &self,
+        envelopes: &[Envelope],
+    ) -> Vec<(String, Option<VerifyingKey>)> {
+        let keys = self.keys.read().unwrap();
+        envelopes
+            .iter()
+            .map(|envelope| {
+                let fp = fingerprint(&envelope.pubkey);
+                let key = lookup(&keys, &fp, envelope);
+                (fp, key)
+            })
+            .collect()
+    }
+}
+
+fn lookup(
+    keys: &HashMap<String, VerifyingKey>,
+    fp: &str,
+    envelope: &Envelope,
+) -> Option<VerifyingKey> {
+    keys.get(fp)
+        .copied()
+        .filter(|key| key.to_bytes() == envelope.pubkey)
+}
+
+/// Period during which a key in a [`KeyHistory`] was allowed to sign
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Validity {
+    /// First instant the key signed with
+    pub from: SystemTime,
+    /// Instant the key was retired, or `None` while it is still in use
+    pub until: Option<SystemTime>,
+}
+
+impl Validity {
+    /// Whether `at` falls in `from..until`
+    pub fn contains(&self, at: SystemTime) -> bool {
+        at >= self.from && self.until.is_none_or(|until| at < until)
+    }
+}
+
+/// Sender keys with the period each one was valid, indexed by [`fingerprint`]
+///
+/// Clones share the same history, like [`Keyring`].
+#[derive(Clone, Debug, Default)]
+pub struct KeyHistory {
+    keys: Arc<RwLock<HashMap<String, (VerifyingKey, Validity)>>>,
+}
+
+impl KeyHistory {
+    /// Empty history
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust `key` for messages signed in `valid_from..valid_until`, returning
+    /// its fingerprint
+    ///
+    /// `valid_until` of `None` leaves the window open. Inserting a key already
+    /// in the history replaces its window.
+    pub fn insert(
+        &self,
+        key: impl Into<VerifyingKey>,
+        valid_from: SystemTime,
+        valid_until: Option<SystemTime>,
+    ) -> String {
+        let key = key.into();
+        let fp = fingerprint(&key.to_bytes());
+        let validity = Validity {
+            from: valid_from,
+            until: valid_until,
+        };
+        self.keys
+            .write()
+            .unwrap()
+            .insert(fp.clone(), (key, validity));
+        fp
+    }
+
+    /// Retire every key still in use at `at` and trust `key` from then on,
+    /// returning its fingerprint
+    pub fn rotate(&self, key: impl Into<VerifyingKey>, at: SystemTime) -> String {
+        let key = key.into();
+        let fp = fingerprint(&key.to_bytes());
+        let mut keys = self.keys.write().unwrap();
+        for (_, validity) in keys.values_mut() {
+            if validity.contains(at) {
+                validity.until = Some(at);
+            }
+        }
+        let validity = Validity {
+            from: at,
+            until: None,
+        };
+        keys.insert(fp.clone(), (key, validity));
+        fp
+    }
+
+    /// Key with `fingerprint` and its validity window
+    pub fn get(&self, fingerprint: &str) -> Option<(VerifyingKey, Validity)> {
+        self.keys.read().unwrap().get(fingerprint).copied()
+    }
+
+    /// Key with `fingerprint`, if it was valid at `at`
+    pub fn key_at(&self, fingerprint: &str, at: SystemTime) -> Option<VerifyingKey> {
+        self.get(fingerprint)
+            .filter(|(_, validity)| validity.contains(at))
+            .map(|(key, _)| key)
+    }
+
+    /// Number of keys in the history
+    pub fn len(&self) -> usize {
+        self.keys.read().unwrap().len()
+    }
+
+    /// Whether the history holds no keys
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Verify an envelope's signature against the key its sender held when
+    /// signing it
+    ///
+    /// The signing time is the `ts` bound into the signed AAD, not the
+    /// envelope's unsigned `timestamp_ms`; envelopes without one count as
+    /// signed at the Unix epoch. Returns `Ok(false)` for a bad signature, and
+    /// fails with [`Error::UnknownSender`] if the sender's key is not in the
+    /// history or [`Error::KeyNotValid`] if the envelope is authentic but was
+    /// signed outside the key's window. Like [`Keyring::verify`], checks the
+    /// signature over the payload as carried.
+    pub fn verify(&self, envelope: &Envelope) -> Result<bool> {
+        let fp = fingerprint(&envelope.pubkey);
+        let Some((key, validity)) = self
+            .get(&fp)
+            .filter(|(key, _)| key.to_bytes() == envelope.pubkey)
+        else {
+            return Err(Error::UnknownSender { fingerprint: fp }.into());
+        };
+        if !verify_signature_with(envelope, &key)? {
+            return Ok(false);
+        }
+        let timestamp_ms = signed_timestamp_ms(envelope);
+        if !validity.contains(UNIX_EPOCH + Duration::from_millis(timestamp_ms)) {
+            return Err(Error::KeyNotValid {
+                fingerprint: fp,
+                timestamp_ms,
+            }
+            .into());
+        }
+        Ok(true)
+    }
+}
+
+/// Send time in the envelope's signed AAD, 0 if absent
+fn signed_timestamp_ms(envelope: &Envelope) -> u64 {
+    serde_json::from_slice::<serde_json::Value>(&envelope.aad)
+        .ok()
+        .and_then(|aad| aad["ts"].as_u64())
+        .unwrap_or(0)
+}
+
+impl Client {
+    /// Verify envelopes only against senders in `keyring`
+    ///
+    /// With a keyring installed, [`Client::verify`] rejects envelopes from
+    /// unknown senders with [`Error::UnknownSender`] instead of trusting the
+    /// public key embedded in the envelope.
+    pub fn with_keyring(mut self, keyring: Keyring) -> Self {
+        self.keyring = Some(keyring);
+        self
+    }
+}
@@ -5,43 +5,196 @@
 //! Provides high-level client API for publishing and subscribing to SecureFabric nodes.
 
 use anyhow::{Context, Result};
-use ed25519_dalek::{Signer, SigningKey, VerifyingKey};
+use prost::Message;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tonic::transport::{Certificate, Channel, ClientTlsConfig, Identity};
-use tonic::{Request, Streaming};
+use subscription::Subscription;
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::transport::Channel;
+use tonic::Request;
 
 pub mod pb {
     tonic::include_proto!("securefabric");
 }
 
+pub mod audit;
+pub mod auth;
+pub mod background;
+pub mod batch;
+pub mod bench;
+pub mod breaker;
+pub mod builder;
+pub mod capabilities;
+pub mod chain;
+pub mod clock;
+pub mod close;
+pub mod codec;
+pub mod compaction;
+pub mod compression;
+pub mod config;
+pub mod conformance;
+pub mod cosign;
+pub mod credits;
+pub mod crypto;
+pub mod diagnose;
+pub mod digest;
+mod entropy;
+mod error;
+pub mod fabric;
+pub mod file;
+pub mod handler;
+pub mod headers;
+pub mod hooks;
+pub mod keyring;
+pub mod lookup;
+pub mod metrics;
+pub mod msg_id;
+pub mod negative_cache;
+pub mod nonce;
+pub mod peer;
+pub mod ping;
+pub mod proxy;
+mod queue;
+pub mod recipient;
+pub mod request;
+mod retry;
+pub mod rotator;
+pub mod sealed;
+pub mod session;
+pub mod shared;
+pub mod subscription;
+pub mod throttle;
+pub mod timeout;
+pub mod tls;
+pub mod topic;
+pub mod topic_tree;
+pub mod validate;
+pub mod window;
+pub mod writer;
+
+pub use builder::ClientBuilder;
+pub use error::Error;
+/// Wire types that are part of the stable API
+///
+/// [`Envelope`] is the signed message unit delivered by subscriptions,
+/// [`SendReq`] wraps one for the `Send` RPC and [`SubscribeReq`] selects what a
+/// subscription receives. Prefer these paths to `pb`, which mirrors the proto
+/// file and may be reorganised. Field semantics are documented on each type.
+pub use pb::{Envelope, SendReq, SubscribeReq};
+pub use proxy::ProxyAuth;
+pub use queue::OverflowPolicy;
+pub use recipient::Recipient;
+pub use tls::TlsConfig;
+
 use pb::fabric_node_client::FabricNodeClient;
-use pb::{Envelope, SendReq, SubscribeReq};
+use pb::AckReq;
 
 /// High-level client for SecureFabric
+///
+/// Clones share the connection, sequence counter and subscription stats, so a
+/// clone can be moved into another task to send concurrently.
+#[derive(Clone)]
 pub struct Client {
+    channel: Channel,
     inner: FabricNodeClient<Channel>,
-    signing_key: Option<SigningKey>,
-    verifying_key: Option<VerifyingKey>,
-    bearer: Option<String>,
+    signing_key: Option<crypto::scheme::SigningKey>,
+    verifying_key: Option<crypto::scheme::VerifyingKey>,
+    signing_key_rotator: Option<rotator::SigningKeyRotator>,
+    bearer: Option<auth::Bearer>,
     sequence: Arc<AtomicU64>,
+    default_recipients: HashMap<String, Vec<u8>>,
+    encryption: Option<TopicKey>,
+    stats: subscription::StatsRegistry,
+    active_subscriptions: Arc<subscription::ActiveSubscriptions>,
+    send_order: Option<Arc<tokio::sync::Mutex<()>>>,
+    send_queue: Option<Arc<queue::SendQueue>>,
+    resume_offsets: HashMap<Vec<u8>, HashMap<Vec<u8>, u64>>,
+    instruments: metrics::Instruments,
+    keyring: Option<keyring::Keyring>,
+    ping_timeout: std::time::Duration,
+    sign_order: crypto::SignOrder,
+    verify_mode: crypto::VerifyMode,
+    strict_utf8_topics: bool,
+    validation: validate::Checks,
+    retry: retry::Retry,
+    entropy: entropy::Entropy,
+    recipient_key: Option<sealed::StaticSecret>,
+    capabilities: Arc<tokio::sync::OnceCell<capabilities::Capabilities>>,
+    cosigners: Vec<crypto::scheme::SigningKey>,
+    subscribe_compression: Vec<compression::Compression>,
+    negative_cache: Option<negative_cache::NegativeCache>,
+    payload_digest: bool,
+    max_aad_len: usize,
+    pre_send: Vec<hooks::PreSend>,
+    post_receive: hooks::PostReceive,
+    send_timeout: Option<timeout::SharedEstimator>,
+    circuit_breaker: Option<Arc<breaker::CircuitBreaker>>,
+    clock: clock::ClockPolicy,
+    nonces: nonce::NonceTracker,
+    peer_identity: Option<peer::PeerIdentity>,
+    protocol_version: Option<capabilities::ProtocolVersion>,
+    msg_id_hasher: Arc<dyn msg_id::MsgIdHasher>,
+    transport: Arc<config::Transport>,
+    lifecycle: Arc<close::Lifecycle>,
+}
+
+/// Per-message options for signing an envelope
+#[derive(Clone, Copy)]
+pub(crate) struct Outgoing<'a> {
+    /// Wire encoding of the [`Recipient`](recipient::Recipient), empty for broadcast
+    pub(crate) to: &'a [u8],
+    pub(crate) headers: &'a BTreeMap<String, String>,
+    /// Compaction key, and whether this is a tombstone for it
+    pub(crate) key: &'a [u8],
+    pub(crate) tombstone: bool,
+    /// Seal the payload to these recipients instead of the topic key
+    pub(crate) sealed_for: &'a [sealed::PublicKey],
+}
+
+static NO_HEADERS: BTreeMap<String, String> = BTreeMap::new();
+
+impl Default for Outgoing<'_> {
+    fn default() -> Self {
+        Self {
+            to: &[],
+            headers: &NO_HEADERS,
+            key: &[],
+            tombstone: false,
+            sealed_for: &[],
+        }
+    }
+}
+
+/// Symmetric end-to-end key shared by a topic's publishers and subscribers
+#[derive(Clone)]
+struct TopicKey {
+    aead: crypto::AeadContext,
+    version: u32,
+}
+
+impl TopicKey {
+    /// Decrypt an envelope sealed under this key
+    fn open(&self, envelope: &Envelope) -> Result<Vec<u8>> {
+        if envelope.key_version == 0 {
+            anyhow::bail!("Envelope is not encrypted");
+        }
+        if envelope.key_version != self.version {
+            anyhow::bail!(
+                "Envelope key version {} does not match configured version {}",
+                envelope.key_version,
+                self.version
+            );
+        }
+        self.aead
+            .open(&envelope.nonce, &envelope.aad, &envelope.payload)
+    }
 }
 
 impl Client {
     /// Create a new Client connected to the given endpoint
     pub async fn new(endpoint: impl AsRef<str>) -> Result<Self> {
-        let channel = Channel::from_shared(endpoint.as_ref().to_string())?
-            .connect()
-            .await
-            .context("connect to endpoint")?;
-
-        Ok(Self {
-            inner: FabricNodeClient::new(channel),
-            signing_key: None,
-            verifying_key: None,
-            bearer: None,
-            sequence: Arc::new(AtomicU64::new(1)),
-        })
+        Self::builder(endpoint.as_ref()).build().await
     }
 
     /// Create a Client with mTLS
@@ -51,241 +204,1007 @@ impl Client {
         key_pem: impl AsRef<[u8]>,
         ca_pem: impl AsRef<[u8]>,
     ) -> Result<Self> {
-        let identity = Identity::from_pem(cert_pem.as_ref(), key_pem.as_ref());
-        let ca_cert = Certificate::from_pem(ca_pem.as_ref());
+        let tls = TlsConfig::new()
+            .with_ca_pem(ca_pem)
+            .with_identity(cert_pem, key_pem);
+
+        Self::connect_tls(endpoint, tls).await
+    }
+
+    /// Create a Client with server-authenticated TLS and no client certificate
+    ///
+    /// The node's certificate is validated against `ca_pem`, or against the
+    /// platform's trust store when `ca_pem` is `None`, and must be issued for
+    /// `domain`. Identity is then typically provided with [`Client::with_bearer`].
+    pub async fn with_tls(
+        endpoint: impl AsRef<str>,
+        ca_pem: Option<&[u8]>,
+        domain: impl Into<String>,
+    ) -> Result<Self> {
+        let tls = match ca_pem {
+            Some(ca_pem) => TlsConfig::new().with_ca_pem(ca_pem),
+            None => TlsConfig::new().with_native_roots()?,
+        };
 
-        let tls = ClientTlsConfig::new()
-            .identity(identity)
-            .ca_certificate(ca_cert);
+        Self::connect_tls(endpoint, tls.with_domain(domain)).await
+    }
 
-        let channel = Channel::from_shared(endpoint.as_ref().to_string())?
-            .tls_config(tls)?
-            .connect()
+    /// Create a Client with an explicit [`TlsConfig`]
+    pub async fn connect_tls(endpoint: impl AsRef<str>, tls: TlsConfig) -> Result<Self> {
+        Self::builder(endpoint.as_ref())
+            .tls(tls)
+            .build()
             .await
-            .context("connect with TLS")?;
+            .context("connect with TLS")
+    }
 
-        Ok(Self {
-            inner: FabricNodeClient::new(channel),
+    pub(crate) fn from_channel(channel: Channel, transport: config::Transport) -> Self {
+        Self {
+            inner: FabricNodeClient::new(channel.clone()),
+            channel,
             signing_key: None,
             verifying_key: None,
+            signing_key_rotator: None,
             bearer: None,
             sequence: Arc::new(AtomicU64::new(1)),
-        })
+            default_recipients: HashMap::new(),
+            encryption: None,
+            stats: Default::default(),
+            active_subscriptions: Default::default(),
+            send_order: None,
+            send_queue: None,
+            resume_offsets: HashMap::new(),
+            instruments: Default::default(),
+            keyring: None,
+            ping_timeout: ping::DEFAULT_PING_TIMEOUT,
+            sign_order: Default::default(),
+            verify_mode: Default::default(),
+            strict_utf8_topics: false,
+            validation: Default::default(),
+            retry: Default::default(),
+            entropy: Default::default(),
+            recipient_key: None,
+            capabilities: Default::default(),
+            cosigners: Vec::new(),
+            subscribe_compression: Vec::new(),
+            negative_cache: None,
+            payload_digest: false,
+            max_aad_len: crypto::MAX_AAD_LEN,
+            pre_send: Vec::new(),
+            post_receive: Default::default(),
+            send_timeout: None,
+            circuit_breaker: None,
+            clock: Default::default(),
+            nonces: Default::default(),
+            peer_identity: None,
+            protocol_version: None,
+            msg_id_hasher: Arc::new(msg_id::Blake3),
+            transport: Arc::new(transport),
+            lifecycle: Default::default(),
+        }
     }
 
     /// Set signing key for message signatures
-    pub fn with_signing_key(mut self, key: SigningKey) -> Self {
+    ///
+    /// Accepts an Ed25519 `ed25519_dalek::SigningKey` or a secp256k1
+    /// `k256::ecdsa::SigningKey`; the scheme is recorded in each envelope. Wrap
+    /// an Ed25519 key in [`SigningKey::Ed25519ph`](crypto::scheme::SigningKey::Ed25519ph)
+    /// to sign its envelopes with Ed25519ph.
+    pub fn with_signing_key(mut self, key: impl Into<crypto::scheme::SigningKey>) -> Self {
+        let key = key.into();
         self.verifying_key = Some(key.verifying_key());
         self.signing_key = Some(key);
         self
     }
 
+    /// Enable end-to-end encryption of payloads with XChaCha20-Poly1305
+    ///
+    /// The envelope's 24-byte `nonce` doubles as the AEAD nonce and the AAD is bound
+    /// as associated data. `key_version` is stamped on every envelope.
+    ///
+    /// Fails if `key_version` is 0, which denotes plaintext, or
+    /// [`SEALED_KEY_VERSION`](crypto::SEALED_KEY_VERSION), which denotes
+    /// sealed envelopes.
+    pub fn with_encryption(mut self, key: [u8; crypto::KEY_LEN], key_version: u32) -> Result<Self> {
+        anyhow::ensure!(key_version != 0, "key_version 0 is reserved for plaintext");
+        anyhow::ensure!(
+            key_version != crypto::SEALED_KEY_VERSION,
+            "key_version {} is reserved for sealed envelopes",
+            crypto::SEALED_KEY_VERSION
+        );
+        self.encryption = Some(TopicKey {
+            aead: crypto::AeadContext::new(&key).with_max_aad_len(self.max_aad_len),
+            version: key_version,
+        });
+        Ok(self)
+    }
+
+    /// Limit the AAD of envelopes this client encrypts or decrypts to `max` bytes
+    ///
+    /// Defaults to [`MAX_AAD_LEN`](crypto::MAX_AAD_LEN). Applies to topic-key
+    /// encryption and sealed envelopes in both directions; larger AAD, such as
+    /// from many bound headers, fails with [`Error::AadTooLarge`] before any
+    /// cryptographic work.
+    pub fn with_max_aad_len(mut self, max: usize) -> Self {
+        self.max_aad_len = max;
+        if let Some(topic_key) = self.encryption.take() {
+            self.encryption = Some(TopicKey {
+                aead: topic_key.aead.with_max_aad_len(max),
+                version: topic_key.version,
+            });
+        }
+        self
+    }
+
+    /// Choose whether encrypted envelopes are signed over ciphertext or plaintext
+    ///
+    /// Defaults to [`SignOrder::EncryptThenSign`](crypto::SignOrder::EncryptThenSign),
+    /// which lets the node authenticate envelopes on ingress. See
+    /// [`crypto::SignOrder`] for the trade-offs. Has no effect without
+    /// [`Client::with_encryption`].
+    pub fn with_sign_order(mut self, order: crypto::SignOrder) -> Self {
+        self.sign_order = order;
+        self
+    }
+
+    /// Choose how strictly received signatures are checked
+    ///
+    /// Defaults to [`VerifyMode::Strict`](crypto::VerifyMode::Strict). Applies
+    /// to [`Client::verify`] and everything built on it, including validation,
+    /// handlers and decrypted subscriptions. Read the warning on
+    /// [`crypto::VerifyMode`] before choosing `Permissive`.
+    pub fn with_verify_mode(mut self, mode: crypto::VerifyMode) -> Self {
+        self.verify_mode = mode;
+        self
+    }
+
+    /// Require byte-string topics to be valid UTF-8
+    ///
+    /// Topics taken as `&[u8]`, by [`Client::subscribe`], [`Client::ack`] and the
+    /// other subscription methods, are then checked before any request is made
+    /// and rejected with [`Error::InvalidTopic`], and [`Subscription::topic_str`]
+    /// always succeeds. Send topics are `&str` and received `Envelope::topic` is
+    /// a `String` either way. Off by default, keeping arbitrary byte topics.
+    pub fn with_strict_utf8_topics(mut self, strict: bool) -> Self {
+        self.strict_utf8_topics = strict;
+        self
+    }
+
+    /// Serialize sends so their requests go out in call order
+    ///
+    /// By default, concurrent sends from clones of this client may put their requests
+    /// on the connection in a different order than their sequence numbers. With
+    /// ordered send enabled, each send takes a first-in, first-out turn shared by all
+    /// clones, assigns and signs its seq, and hands its request to the connection
+    /// before passing the turn on, so this sender's requests are opened in strictly
+    /// increasing seq. The turn is not held while waiting for the node's answer, so
+    /// several sends can be in flight at once and the node may finish handling them
+    /// in a different order; receivers that need the sender's order follow the
+    /// signed seq, as [`chain::SenderChainVerifier`] does. A send retried under
+    /// [`Client::with_send_retries`] goes out again behind the sends started since.
+    pub fn with_ordered_send(mut self, enabled: bool) -> Self {
+        self.send_order = enabled.then(|| Arc::new(tokio::sync::Mutex::new(())));
+        self
+    }
+
+    /// Bound the number of sends buffered by this client and its clones
+    ///
+    /// A send holds a queue slot from the moment it is called until the node
+    /// answers, so the bound covers sends waiting for their turn under
+    /// [`Client::with_ordered_send`] as well as sends on the wire. Once `capacity`
+    /// slots are taken, `policy` decides whether the next send waits, fails with
+    /// [`Error::QueueFull`], or evicts the oldest send not yet on the wire, which
+    /// then fails with [`Error::Dropped`]. Without a queue, sends are unbounded.
+    ///
+    /// Fails if `capacity` is 0, which would admit no send at all.
+    pub fn with_send_queue(mut self, capacity: usize, policy: OverflowPolicy) -> Result<Self> {
+        anyhow::ensure!(capacity > 0, "send queue capacity must be non-zero");
+        self.send_queue = Some(Arc::new(queue::SendQueue::new(capacity, policy)));
+        Ok(self)
+    }
+
+    /// Number of sends currently occupying the send queue
+    ///
+    /// Always 0 when no queue is configured.
+    pub fn queued_sends(&self) -> usize {
+        self.send_queue.as_ref().map_or(0, |q| q.len())
+    }
+
     /// Set bearer token for authentication
+    ///
+    /// A token that cannot be sent as a header value, such as one containing
+    /// a newline, fails every request.
     pub fn with_bearer(mut self, token: impl Into<String>) -> Self {
-        self.bearer = Some(token.into());
+        self.bearer = Some(auth::Bearer::Static(token.into()));
         self
     }
 
-    /// Build an envelope with signature
-    fn build_envelope(&self, topic: &str, payload: &[u8]) -> Result<Envelope> {
-        let signing_key = self
-            .signing_key
-            .as_ref()
-            .context("No signing key configured")?;
-        let verifying_key = self
-            .verifying_key
+    /// Wrap a message in a request carrying the configured bearer token and
+    /// pinned protocol version
+    ///
+    /// Fails with [`Error::Closed`] once the client is [closed](Client::close),
+    /// or if the bearer token is not a valid header value.
+    fn authorized<T>(&self, message: T) -> Result<Request<T>> {
+        self.lifecycle.check()?;
+        self.with_metadata(message)
+    }
+
+    /// [`Client::authorized`] for sends that started before the client closed
+    fn with_metadata<T>(&self, message: T) -> Result<Request<T>> {
+        let mut req = Request::new(message);
+
+        if let Some(bearer) = &self.bearer {
+            req.metadata_mut().insert("authorization", bearer.header()?);
+        }
+        if let Some(version) = self.protocol_version {
+            req.metadata_mut().insert(
+                "securefabric-protocol",
+                version.to_string().parse().unwrap(),
+            );
+        }
+
+        Ok(req)
+    }
+
+    /// Reject a non-UTF-8 topic when strict UTF-8 topics are enabled
+    fn check_topic(&self, topic: &[u8]) -> Result<()> {
+        if self.strict_utf8_topics {
+            if let Err(err) = std::str::from_utf8(topic) {
+                return Err(Error::InvalidTopic {
+                    valid_up_to: err.valid_up_to(),
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Build a signed envelope, assigning it the next seq and its msg_id
+    ///
+    /// The seq is bound into the signed AAD, so it is taken once the checks
+    /// that can fail early have passed, keeping failed sends from leaving gaps.
+    fn sign_envelope(
+        &self,
+        topic: &str,
+        outgoing: Outgoing<'_>,
+        payload: &[u8],
+    ) -> Result<Envelope> {
+        let Outgoing {
+            to,
+            headers,
+            key,
+            tombstone,
+            sealed_for,
+        } = outgoing;
+        recipient::Recipient::from_wire(to)?.check_sealing(sealed_for)?;
+        let context;
+        let (topic, headers, payload) = match self.pre_send.as_slice() {
+            [] => (topic, headers, payload),
+            hooks => {
+                context = hooks::run_pre_send(hooks, topic, headers, payload);
+                (
+                    context.topic.as_str(),
+                    &context.headers,
+                    context.payload.as_slice(),
+                )
+            }
+        };
+        let rotated = self
+            .signing_key_rotator
             .as_ref()
-            .context("No verifying key configured")?;
+            .map(|rotator| rotator.next());
+        let (signing_key, verifying_key) = match rotated {
+            Some(key) => (&key.signing_key, &key.verifying_key),
+            None => (
+                self.signing_key
+                    .as_ref()
+                    .context("No signing key configured")?,
+                self.verifying_key
+                    .as_ref()
+                    .context("No verifying key configured")?,
+            ),
+        };
 
-        let seq = self.sequence.fetch_add(1, Ordering::SeqCst);
         let nonce = self.generate_nonce();
-        let pubkey = verifying_key.to_bytes().to_vec();
+        let pubkey = verifying_key.to_bytes();
 
-        // Build AAD: {"topic":"...","key_version":0}
-        let aad = serde_json::json!({
+        let sealing = match sealed_for {
+            [] => None,
+            recipients => Some(sealed::Sealing::new(&self.entropy, &nonce, recipients)?),
+        };
+        let key_version = match &sealing {
+            Some(_) => crypto::SEALED_KEY_VERSION,
+            None => self.encryption.as_ref().map_or(0, |k| k.version),
+        };
+
+        let timestamp_ms = self.clock.timestamp_ms(self.entropy.now_ms())?;
+        // Rotated keys number their own sends so each key's chain has no gaps
+        let seq = rotated
+            .map_or(&*self.sequence, |key| &key.sequence)
+            .fetch_add(1, Ordering::SeqCst);
+
+        // Build AAD: {"topic":"...","key_version":N,"seq":N,"ts":...}, plus "to" for directed
+        // messages, "headers" when any are set, "key"/"tombstone" for compaction
+        // "peer" for connection-bound signing and "kid" for rotated keys
+        let mut aad = serde_json::json!({
             "topic": topic,
-            "key_version": 0u32,
+            "key_version": key_version,
+            "seq": seq,
+            "ts": timestamp_ms,
         });
+        if !to.is_empty() {
+            aad["to"] = hex::encode(to).into();
+        }
+        if !headers.is_empty() {
+            aad["headers"] = serde_json::to_value(headers)?;
+        }
+        if !key.is_empty() {
+            aad["key"] = hex::encode(key).into();
+        }
+        if tombstone {
+            aad["tombstone"] = true.into();
+        }
+        if let Some(peer) = &self.peer_identity {
+            aad["peer"] = peer.to_hex().into();
+        }
+        if let Some(key) = rotated {
+            aad["kid"] = key.fingerprint.clone().into();
+        }
+        if let Some(sealing) = &sealing {
+            sealing.bind(&mut aad);
+        }
+        let sign_plaintext = sealing.is_none()
+            && self.encryption.is_some()
+            && self.sign_order == crypto::SignOrder::SignThenEncrypt;
+        if sign_plaintext {
+            aad["signed"] = "plaintext".into();
+        }
         let aad_bytes = serde_json::to_vec(&aad)?;
+        if sealing.is_some() {
+            crypto::check_aad_len(&aad_bytes, self.max_aad_len)?;
+        }
 
-        // Sign: signature = Ed25519(aad || payload)
-        let mut message_to_sign = Vec::new();
-        message_to_sign.extend_from_slice(&aad_bytes);
-        message_to_sign.extend_from_slice(payload);
-        let signature = signing_key.sign(&message_to_sign);
+        let sealed = match (&sealing, &self.encryption) {
+            (Some(sealing), _) => Some(sealing.seal(&nonce, payload, &aad_bytes)?),
+            (None, Some(topic_key)) => Some(topic_key.aead.seal(&nonce, &aad_bytes, payload)?),
+            (None, None) => None,
+        };
+        let signed_payload = match &sealed {
+            Some(ciphertext) if !sign_plaintext => ciphertext.as_slice(),
+            _ => payload,
+        };
 
-        // Compute BLAKE3 message ID: hex(blake3(pubkey || seq || nonce))
-        let msg_id = self.compute_msg_id(&pubkey, seq, &nonce);
+        let signature = signing_key.sign_parts(&signed_parts(
+            &aad_bytes,
+            &nonce,
+            signed_payload,
+            key_version,
+        ));
+        let payload = sealed.unwrap_or_else(|| payload.to_vec());
 
-        Ok(Envelope {
+        let mut envelope = Envelope {
             pubkey,
-            sig: signature.to_bytes().to_vec(),
+            sig: signature,
             nonce: nonce.to_vec(),
             aad: aad_bytes,
-            payload: payload.to_vec(),
+            payload,
             seq,
-            msg_id,
-            key_version: 0,
+            msg_id: String::new(),
+            key_version,
             topic: topic.to_string(),
-        })
+            to: to.to_vec(),
+            timestamp_ms,
+            sig_scheme: signing_key.scheme().wire_value(),
+            key: key.to_vec(),
+            tombstone,
+            ephemeral_key: sealing
+                .as_ref()
+                .map(|sealing| sealing.ephemeral_key())
+                .unwrap_or_default(),
+            wrapped_keys: sealing
+                .map(|sealing| sealing.wrapped_keys)
+                .unwrap_or_default(),
+            cosignatures: Vec::new(),
+            payload_digest: Vec::new(),
+            msg_id_hash: self.msg_id_hasher.id(),
+        };
+        // hex(hash(pubkey || seq || nonce)) with the hash named by msg_id_hash
+        envelope.msg_id = msg_id::compute(&*self.msg_id_hasher, &envelope);
+        if self.payload_digest {
+            digest::attach_payload_digest(&mut envelope);
+        }
+        for cosigner in &self.cosigners {
+            cosign::cosign(&mut envelope, cosigner);
+        }
+        Ok(envelope)
     }
 
     /// Generate a random 24-byte nonce
     fn generate_nonce(&self) -> Vec<u8> {
-        use rand::RngCore;
         let mut nonce = vec![0u8; 24];
-        rand::rngs::OsRng.fill_bytes(&mut nonce);
+        self.entropy.fill(&mut nonce);
         nonce
     }
 
-    /// Compute BLAKE3 message ID
-    fn compute_msg_id(&self, pubkey: &[u8], seq: u64, nonce: &[u8]) -> String {
-        let mut hasher = blake3::Hasher::new();
-        hasher.update(pubkey);
-        hasher.update(&seq.to_le_bytes());
-        hasher.update(nonce);
-        hasher.finalize().to_hex().to_string()
+    /// Send a broadcast message
+    pub async fn send(&mut self, topic: &str, payload: &[u8]) -> Result<String> {
+        self.send_to(topic, &[], payload).await
     }
 
-    /// Send a message
-    pub async fn send(&mut self, topic: &str, payload: &[u8]) -> Result<String> {
-        let envelope = self.build_envelope(topic, payload)?;
-        let msg_id = envelope.msg_id.clone();
+    /// Send a message directed at the recipient public key `to`
+    ///
+    /// An empty `to` sends a broadcast, the same as [`Client::send`]. `to` may
+    /// also be any other [`Recipient`](recipient::Recipient) in its wire
+    /// encoding; other values fail with [`Error::InvalidRecipient`].
+    pub async fn send_to(&mut self, topic: &str, to: &[u8], payload: &[u8]) -> Result<String> {
+        self.send_envelope(
+            topic,
+            Outgoing {
+                to,
+                ..Default::default()
+            },
+            payload,
+        )
+        .await
+    }
 
-        let mut req = Request::new(SendReq {
-            envelope: Some(envelope),
-        });
+    /// Sign, sequence and dispatch one message through the send queue
+    async fn send_envelope(
+        &mut self,
+        topic: &str,
+        outgoing: Outgoing<'_>,
+        payload: &[u8],
+    ) -> Result<String> {
+        let in_flight = self.lifecycle.begin_send()?;
+        in_flight
+            .run(async {
+                let slot = self.admit().await?;
+                self.send_admitted(slot, topic, outgoing, payload).await
+            })
+            .await
+    }
 
-        if let Some(bearer) = &self.bearer {
-            req.metadata_mut().insert(
-                "authorization",
-                format!("Bearer {}", bearer).parse().unwrap(),
-            );
+    /// Take a send queue slot, if a queue is configured
+    async fn admit(&self) -> Result<Option<queue::QueueSlot>> {
+        match self.send_queue.clone() {
+            Some(queue) => Ok(Some(queue.admit().await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Sign, sequence and dispatch one message that already holds its queue slot
+    async fn send_admitted(
+        &mut self,
+        mut slot: Option<queue::QueueSlot>,
+        topic: &str,
+        outgoing: Outgoing<'_>,
+        payload: &[u8],
+    ) -> Result<String> {
+        // An open circuit fails the send before it is signed or sequenced
+        let permit = match &self.circuit_breaker {
+            Some(breaker) => Some(breaker.admit()?),
+            None => None,
+        };
+        // With ordered send, hold the turn from seq assignment until the request is queued
+        let turn = match (self.send_order.clone(), slot.as_mut()) {
+            (Some(order), Some(slot)) => tokio::select! {
+                turn = order.lock_owned() => Some(turn),
+                _ = slot.evicted() => return Err(Error::Dropped.into()),
+            },
+            (Some(order), None) => Some(order.lock_owned().await),
+            (None, _) => None,
+        };
+        if let Some(slot) = slot.as_mut() {
+            slot.start_dispatch()?;
+        }
+        let envelope = self.sign_envelope(topic, outgoing, payload)?;
+        let outcome = release_after_first_poll(self.dispatch(envelope), turn).await;
+        if let Some(permit) = permit {
+            permit.finish(&outcome);
+        }
+        outcome
+    }
+
+    /// Send a finished envelope, returning its message ID
+    ///
+    /// Retries according to [`Client::with_send_retries`] and the retry budget.
+    async fn dispatch(&mut self, envelope: Envelope) -> Result<String> {
+        let msg_id = envelope.msg_id.clone();
+        let envelope_len = self
+            .instruments
+            .size_histograms()
+            .then(|| envelope.encoded_len());
+
+        let mut attempt = 0;
+        loop {
+            // Unchecked, so a send under way may finish while the client closes
+            let req = self.with_metadata(SendReq {
+                envelope: Some(envelope.clone()),
+            })?;
+            let deadline = self.send_timeout();
+            let start = std::time::Instant::now();
+            let response = match deadline {
+                Some(deadline) => {
+                    match tokio::time::timeout(deadline, self.inner.send(req)).await {
+                        Ok(response) => response,
+                        Err(_) => {
+                            self.record_send_latency(deadline);
+                            return Err(Error::Timeout { after: deadline }.into());
+                        }
+                    }
+                }
+                None => self.inner.send(req).await,
+            };
+            let status = match response {
+                Ok(_) => {
+                    self.record_send_latency(start.elapsed());
+                    break;
+                }
+                Err(status) => status,
+            };
+            match self
+                .retry
+                .after_failure(attempt, status.code(), &self.entropy)
+            {
+                retry::Decision::RetryAfter(backoff) => tokio::time::sleep(backoff).await,
+                retry::Decision::GiveUp => return Err(status).context("send message"),
+                retry::Decision::BudgetExhausted => {
+                    return Err(status).context("send message (retry budget exhausted)")
+                }
+            }
+            attempt += 1;
         }
 
-        self.inner.send(req).await.context("send message")?;
+        self.retry.on_success();
+        self.instruments.on_sent(envelope_len);
         Ok(msg_id)
     }
 
+    /// Feed a send round trip into the adaptive timeout, if enabled
+    fn record_send_latency(&self, latency: std::time::Duration) {
+        if let Some(estimator) = &self.send_timeout {
+            estimator.lock().unwrap().record(latency);
+        }
+    }
+
+    /// Set the recipient used by [`Client::send_to_default`] for a topic
+    pub fn set_default_recipient(&mut self, topic: impl Into<String>, to: impl Into<Vec<u8>>) {
+        self.default_recipients.insert(topic.into(), to.into());
+    }
+
+    /// Remove the default recipient for a topic, returning it if one was set
+    pub fn clear_default_recipient(&mut self, topic: &str) -> Option<Vec<u8>> {
+        self.default_recipients.remove(topic)
+    }
+
+    /// Send a message to the topic's configured default recipient
+    ///
+    /// Fails if no default recipient has been set for `topic`.
+    pub async fn send_to_default(&mut self, topic: &str, payload: &[u8]) -> Result<String> {
+        let to = self
+            .default_recipients
+            .get(topic)
+            .cloned()
+            .with_context(|| format!("No default recipient configured for topic {topic}"))?;
+        self.send_to(topic, &to, payload).await
+    }
+
     /// Subscribe to messages matching a topic pattern
-    pub async fn subscribe(&mut self, topic: &[u8]) -> Result<Streaming<Envelope>> {
-        let mut req = Request::new(SubscribeReq {
+    pub async fn subscribe(&mut self, topic: &[u8]) -> Result<Subscription> {
+        self.open_subscription(SubscribeReq {
             topic: topic.to_vec(),
-        });
+            ..Default::default()
+        })
+        .await
+    }
 
-        if let Some(bearer) = &self.bearer {
-            req.metadata_mut().insert(
-                "authorization",
-                format!("Bearer {}", bearer).parse().unwrap(),
-            );
+    /// Raw gRPC client for the subscribe RPCs, accepting the configured compressions
+    fn subscribe_grpc(&self) -> tonic::client::Grpc<Channel> {
+        let mut grpc = tonic::client::Grpc::new(self.channel.clone());
+        for compression in &self.subscribe_compression {
+            grpc = grpc.accept_compressed(compression.encoding());
         }
+        grpc
+    }
+
+    /// Open a subscribe stream for a fully specified request
+    async fn open_subscription(&mut self, message: SubscribeReq) -> Result<Subscription> {
+        self.check_topic(&message.topic)?;
+        let topic = message.topic.clone();
+        let req = self.authorized(message)?;
 
-        let stream = self
-            .inner
-            .subscribe(req)
+        // Decode through the hardened codec rather than the generated client
+        let mut grpc = self.subscribe_grpc();
+        grpc.ready().await.context("subscribe to topic")?;
+        let path = PathAndQuery::from_static("/securefabric.FabricNode/Subscribe");
+        let stream = grpc
+            .server_streaming(req, path, codec::SubscribeCodec::<SubscribeReq>::default())
             .await
             .context("subscribe to topic")?
             .into_inner();
 
-        Ok(stream)
+        let resume = self.resume_offsets.get(&topic).cloned().unwrap_or_default();
+        Ok(Subscription::new(
+            stream,
+            topic,
+            self.stats.clone(),
+            self.instruments.clone(),
+            self.verifier(),
+        )
+        .skipping_through(resume)
+        .tracked(&self.active_subscriptions)
+        .with_post_receive(self.post_receive.clone()))
+    }
+
+    /// Subscribe for an adapter that verifies envelopes itself
+    ///
+    /// Post-receive hooks ordered after verification are left out of the
+    /// stream; the adapter runs them with `PostReceive::after_verification`.
+    async fn subscribe_verifying(&mut self, topic: &[u8]) -> Result<Subscription> {
+        let subscription = self.subscribe(topic).await?;
+        Ok(subscription.with_post_receive(self.post_receive.before_verification()))
+    }
+
+    /// Acknowledge messages received on a topic as processed
+    ///
+    /// Fails with [`Error::Unsupported`] on nodes without the `Ack` RPC.
+    pub async fn ack(&mut self, topic: &[u8], msg_ids: Vec<String>) -> Result<()> {
+        self.check_topic(topic)?;
+        self.require(capabilities::Feature::Ack).await?;
+        let req = self.authorized(AckReq {
+            topic: topic.to_vec(),
+            msg_ids,
+        })?;
+
+        self.inner
+            .ack(req)
+            .await
+            .map_err(capabilities::rpc_error(capabilities::Feature::Ack))
+            .context("ack messages")?;
+        Ok(())
+    }
+
+    /// Decrypt an envelope's payload with the configured end-to-end key
+    ///
+    /// Fails if no key is configured, the envelope is plaintext, or its key version
+    /// differs from the configured one. Verify the envelope before decrypting it.
+    /// With [`Client::with_nonce_tracking`], a repeated nonce may fail with
+    /// [`Error::NonceReused`].
+    pub fn decrypt(&self, envelope: &Envelope) -> Result<Vec<u8>> {
+        let plaintext = self
+            .encryption
+            .as_ref()
+            .context("No encryption key configured")?
+            .open(envelope)?;
+        self.nonces.observe(envelope)?;
+        Ok(plaintext)
     }
 
     /// Verify an envelope's signature
+    ///
+    /// Envelopes signed over their plaintext ([`crypto::SignOrder::SignThenEncrypt`])
+    /// are decrypted with the configured key first; verifying them fails without one.
     pub fn verify(&self, envelope: &Envelope) -> Result<bool> {
-        if envelope.sig.is_empty() || envelope.sig.len() != 64 {
-            return Ok(false);
+        verify_trusted(
+            self.keyring.as_ref(),
+            self.encryption.as_ref(),
+            self.verify_mode,
+            self.negative_cache.as_ref(),
+            self.peer_identity.as_ref(),
+            envelope,
+        )
+    }
+
+    /// An envelope's payload, only if its signature and msg_id check out
+    ///
+    /// Fails with [`Error::Unsigned`], [`Error::InvalidSignature`] or
+    /// [`Error::InvalidMsgId`], or with whatever [`Client::verify`] fails
+    /// with. The payload of an encrypted envelope is its ciphertext; pass the
+    /// envelope to [`Client::decrypt`] once this succeeds.
+    pub fn verified_payload<'a>(&self, envelope: &'a Envelope) -> Result<&'a [u8]> {
+        if envelope.sig.is_empty() {
+            return Err(Error::Unsigned {
+                msg_id: envelope.msg_id.clone(),
+            }
+            .into());
+        }
+        if !self.verify(envelope)? {
+            return Err(Error::InvalidSignature {
+                msg_id: envelope.msg_id.clone(),
+            }
+            .into());
         }
+        if !self.verify_msg_id(envelope) {
+            return Err(Error::InvalidMsgId {
+                msg_id: envelope.msg_id.clone(),
+            }
+            .into());
+        }
+        Ok(&envelope.payload)
+    }
+}
+
+/// A client's signature and msg_id checks, detached from the client
+///
+/// Lets streams and verifiers check envelopes as [`Client::verify`] and
+/// [`Client::verify_msg_id`] would, without holding on to the client.
+#[derive(Clone)]
+pub(crate) struct Verifier {
+    keyring: Option<keyring::Keyring>,
+    encryption: Option<TopicKey>,
+    mode: crypto::VerifyMode,
+    cache: Option<negative_cache::NegativeCache>,
+    peer: Option<peer::PeerIdentity>,
+    msg_id_hasher: Arc<dyn msg_id::MsgIdHasher>,
+}
 
-        if envelope.pubkey.is_empty() || envelope.pubkey.len() != 32 {
-            return Ok(false);
+impl Default for Verifier {
+    /// Checks against the envelope's own key, strictly, with BLAKE3 msg_ids
+    fn default() -> Self {
+        Self {
+            keyring: None,
+            encryption: None,
+            mode: crypto::VerifyMode::default(),
+            cache: None,
+            peer: None,
+            msg_id_hasher: Arc::new(msg_id::Blake3),
         }
+    }
+}
+
+impl Verifier {
+    /// The envelope's [signed seq](signed_seq), if its signature and msg_id check out
+    pub(crate) fn verified_seq(&self, envelope: &Envelope) -> Option<u64> {
+        let seq = signed_seq(envelope)?;
+        let verified = self.verify(envelope).unwrap_or(false) && self.verify_msg_id(envelope);
+        verified.then_some(seq)
+    }
 
-        let vk = VerifyingKey::from_bytes(
-            envelope
-                .pubkey
-                .as_slice()
-                .try_into()
-                .context("Invalid pubkey length")?,
+    /// [`Client::verify`]
+    pub(crate) fn verify(&self, envelope: &Envelope) -> Result<bool> {
+        verify_trusted(
+            self.keyring.as_ref(),
+            self.encryption.as_ref(),
+            self.mode,
+            self.cache.as_ref(),
+            self.peer.as_ref(),
+            envelope,
         )
-        .context("Invalid public key")?;
+    }
 
-        let sig = ed25519_dalek::Signature::from_slice(&envelope.sig).context("parse signature")?;
+    /// [`Client::verify_msg_id`]
+    pub(crate) fn verify_msg_id(&self, envelope: &Envelope) -> bool {
+        msg_id::matches(&*self.msg_id_hasher, envelope)
+    }
+}
 
-        // Verify: signature = Ed25519(aad || payload)
-        let mut message = Vec::new();
-        message.extend_from_slice(&envelope.aad);
-        message.extend_from_slice(&envelope.payload);
+impl Client {
+    /// This client's envelope checks, for use after the client has moved on
+    pub(crate) fn verifier(&self) -> Verifier {
+        Verifier {
+            keyring: self.keyring.clone(),
+            encryption: self.encryption.clone(),
+            mode: self.verify_mode,
+            cache: self.negative_cache.clone(),
+            peer: self.peer_identity,
+            msg_id_hasher: self.msg_id_hasher.clone(),
+        }
+    }
+}
 
-        Ok(vk.verify_strict(&message, &sig).is_ok())
+/// Check an envelope's signature, restricted to the keyring's senders if one is set
+///
+/// Plaintext-signed envelopes are decrypted with `encryption` and verified over
+/// the recovered plaintext.
+pub(crate) fn verify_trusted(
+    keyring: Option<&keyring::Keyring>,
+    encryption: Option<&TopicKey>,
+    mode: crypto::VerifyMode,
+    cache: Option<&negative_cache::NegativeCache>,
+    peer: Option<&peer::PeerIdentity>,
+    envelope: &Envelope,
+) -> Result<bool> {
+    if crypto::SignOrder::of(envelope) == crypto::SignOrder::SignThenEncrypt {
+        let plaintext = encryption
+            .context("Envelope is signed over its plaintext; an encryption key is required")?
+            .open(envelope)?;
+        return verify_trusted_over(keyring, mode, cache, peer, envelope, &plaintext);
     }
+    verify_trusted_over(keyring, mode, cache, peer, envelope, &envelope.payload)
+}
 
-    /// Verify message ID
-    pub fn verify_msg_id(&self, envelope: &Envelope) -> bool {
-        let computed = {
-            let mut hasher = blake3::Hasher::new();
-            hasher.update(&envelope.pubkey);
-            hasher.update(&envelope.seq.to_le_bytes());
-            hasher.update(&envelope.nonce);
-            hasher.finalize().to_hex().to_string()
-        };
-        computed == envelope.msg_id
+/// Check an envelope's signature over `signed_payload` with the trusted sender key
+///
+/// Failures are recorded in `cache`, and checks it has seen fail recently are
+/// rejected without verifying. Envelopes bound to a node other than `peer`
+/// are rejected too.
+pub(crate) fn verify_trusted_over(
+    keyring: Option<&keyring::Keyring>,
+    mode: crypto::VerifyMode,
+    cache: Option<&negative_cache::NegativeCache>,
+    peer: Option<&peer::PeerIdentity>,
+    envelope: &Envelope,
+    signed_payload: &[u8],
+) -> Result<bool> {
+    if peer.is_some_and(|peer| peer.rejects(envelope)) {
+        return Ok(false);
+    }
+    let vk = match keyring {
+        Some(keyring) => keyring.sender_key(envelope)?,
+        None => match sender_key(envelope)? {
+            Some(vk) => vk,
+            None => return Ok(false),
+        },
+    };
+    let Some(cache) = cache else {
+        return verify_signature_over(envelope, &vk, signed_payload, mode);
+    };
+    let key = negative_cache::NegativeCache::key(&vk, mode, envelope, signed_payload);
+    if cache.contains(&key) {
+        return Ok(false);
     }
+    let valid = verify_signature_over(envelope, &vk, signed_payload, mode)?;
+    if !valid {
+        cache.insert(key);
+    }
+    Ok(valid)
 }
 
-/// Crypto helpers
-pub mod crypto {
-    use super::*;
-    use rand::rngs::OsRng;
-
-    /// Ed25519 keypair
-    pub struct Keypair {
-        pub signing_key: SigningKey,
-        pub verifying_key: VerifyingKey,
-    }
-
-    impl Keypair {
-        /// Generate a new random keypair
-        pub fn generate() -> Self {
-            use rand::RngCore;
-            let mut seed = [0u8; 32];
-            OsRng.fill_bytes(&mut seed);
-            let signing_key = SigningKey::from_bytes(&seed);
-            let verifying_key = signing_key.verifying_key();
-            Self {
-                signing_key,
-                verifying_key,
-            }
-        }
+/// Check an envelope's signature over `aad || payload` with its embedded sender key
+pub(crate) fn verify_signature(envelope: &Envelope) -> Result<bool> {
+    match sender_key(envelope)? {
+        Some(vk) => verify_signature_with(envelope, &vk),
+        None => Ok(false),
+    }
+}
 
-        /// Load keypair from 32-byte seed
-        pub fn from_bytes(bytes: &[u8; 32]) -> Self {
-            let signing_key = SigningKey::from_bytes(bytes);
-            let verifying_key = signing_key.verifying_key();
-            Self {
-                signing_key,
-                verifying_key,
-            }
-        }
+/// Parse the sender key embedded in an envelope
+///
+/// Returns `None` if the scheme is unsupported or the key has the wrong length
+/// for it.
+fn sender_key(envelope: &Envelope) -> Result<Option<crypto::scheme::VerifyingKey>> {
+    let Some(scheme) = crypto::SignatureScheme::of(envelope) else {
+        return Ok(None);
+    };
+    if envelope.pubkey.len() != scheme.public_key_len() {
+        return Ok(None);
+    }
+    crypto::scheme::VerifyingKey::from_bytes(scheme, &envelope.pubkey).map(Some)
+}
 
-        /// Load keypair from hex string
-        pub fn from_hex(hex: &str) -> Result<Self> {
-            let bytes = hex::decode(hex).context("decode hex")?;
-            if bytes.len() != 32 {
-                anyhow::bail!("Expected 32 bytes, got {}", bytes.len());
-            }
-            let mut arr = [0u8; 32];
-            arr.copy_from_slice(&bytes);
-            Ok(Self::from_bytes(&arr))
-        }
+/// Check an envelope's signature strictly against an already parsed sender key
+pub(crate) fn verify_signature_with(
+    envelope: &Envelope,
+    vk: &crypto::scheme::VerifyingKey,
+) -> Result<bool> {
+    verify_signature_over(envelope, vk, &envelope.payload, crypto::VerifyMode::Strict)
+}
 
-        /// Export signing key as hex
-        pub fn to_hex(&self) -> String {
-            hex::encode(self.signing_key.to_bytes())
-        }
+/// Check an envelope's signature with `signed_payload` in place of its payload
+///
+/// Used for envelopes signed over their plaintext, after decryption. An
+/// envelope whose declared scheme differs from the key's is rejected.
+fn verify_signature_over(
+    envelope: &Envelope,
+    vk: &crypto::scheme::VerifyingKey,
+    signed_payload: &[u8],
+    mode: crypto::VerifyMode,
+) -> Result<bool> {
+    let Some(scheme) = crypto::SignatureScheme::of(envelope) else {
+        return Ok(false);
+    };
+    if !vk.supports(scheme) || envelope.sig.len() != scheme.signature_len() {
+        return Ok(false);
+    }
+    // A seq the sender signed must be the one the envelope carries
+    if declared_seq(&envelope.aad).is_some_and(|seq| seq != envelope.seq) {
+        return Ok(false);
+    }
 
-        /// Export verifying key as hex
-        pub fn verifying_key_hex(&self) -> String {
-            hex::encode(self.verifying_key.to_bytes())
-        }
+    let parts = signed_parts(
+        &envelope.aad,
+        &envelope.nonce,
+        signed_payload,
+        envelope.key_version,
+    );
+
+    Ok(vk.verify_scheme_parts(scheme, &parts, &envelope.sig, mode))
+}
+
+/// Drive a send until it first waits, then release its ordered-send turn
+///
+/// The first poll hands the request to the channel, which writes requests to
+/// the connection in the order it was given them; a send still waiting for
+/// channel capacity is queued fairly behind earlier ones.
+async fn release_after_first_poll<F: std::future::Future>(
+    future: F,
+    mut turn: Option<tokio::sync::OwnedMutexGuard<()>>,
+) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    std::future::poll_fn(|cx| {
+        let poll = future.as_mut().poll(cx);
+        turn = None;
+        poll
+    })
+    .await
+}
+
+/// The envelope's seq, if the sender bound it into the signed AAD
+///
+/// `None` for envelopes whose AAD has no `"seq"`, as from older senders, and
+/// for envelopes whose `seq` differs from it. Only a signed seq shows where an
+/// envelope sits in its sender's sequence; an unsigned one can be rewritten in
+/// transit along with the msg_id.
+pub(crate) fn signed_seq(envelope: &Envelope) -> Option<u64> {
+    declared_seq(&envelope.aad).filter(|&seq| seq == envelope.seq)
+}
+
+/// Digest of an envelope's sender key and the bytes it signed
+///
+/// Unlike the msg_id, no part of it can be changed without breaking the
+/// signature, so it tells copies of one signed message, however they were
+/// re-sequenced, apart from distinct messages.
+pub(crate) fn signed_digest(envelope: &Envelope) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&(envelope.pubkey.len() as u32).to_be_bytes());
+    hasher.update(&envelope.pubkey);
+    for part in signed_parts(
+        &envelope.aad,
+        &envelope.nonce,
+        &envelope.payload,
+        envelope.key_version,
+    ) {
+        hasher.update(part);
+    }
+    *hasher.finalize().as_bytes()
+}
+
+/// `"seq"` as declared in an envelope's AAD
+fn declared_seq(aad: &[u8]) -> Option<u64> {
+    #[derive(serde::Deserialize)]
+    struct Declared {
+        seq: Option<u64>,
     }
+    serde_json::from_slice::<Declared>(aad).ok()?.seq
+}
+
+/// Bytes covered by an envelope signature
+///
+/// Plaintext envelopes sign `aad || payload`. Encrypted envelopes (`key_version > 0`)
+/// sign `aad || nonce || payload` so the AEAD nonce cannot be swapped in transit.
+pub(crate) fn signing_preimage(
+    aad: &[u8],
+    nonce: &[u8],
+    payload: &[u8],
+    key_version: u32,
+) -> Vec<u8> {
+    signed_parts(aad, nonce, payload, key_version).concat()
+}
+
+/// The pieces of the [`signing_preimage`], in order, without joining them
+fn signed_parts<'a>(
+    aad: &'a [u8],
+    nonce: &'a [u8],
+    payload: &'a [u8],
+    key_version: u32,
+) -> [&'a [u8]; 3] {
+    let nonce = match key_version {
+        0 => &[],
+        _ => nonce,
+    };
+    [aad, nonce, payload]
+}
+
+/// Recompute the msg_id with the built-in hash the envelope names and compare it
+///
+/// Envelopes naming a custom hash never match; see [`Client::verify_msg_id`].
+pub(crate) fn msg_id_matches(envelope: &Envelope) -> bool {
+    expected_msg_id(envelope).is_some_and(|digest| hex::encode(digest) == envelope.msg_id)
+}
+
+/// The msg_id digest an envelope should carry, or `None` if it names no built-in hash
+pub(crate) fn expected_msg_id(envelope: &Envelope) -> Option<Vec<u8>> {
+    let hasher = msg_id::builtin(envelope.msg_id_hash)?;
+    let preimage = msg_id::preimage(&envelope.pubkey, envelope.seq, &envelope.nonce);
+    Some(hasher.hash(&preimage))
 }
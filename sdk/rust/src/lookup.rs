@@ -0,0 +1,52 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Fetching single messages
+//!
+//! Debugging and audit tools sometimes need one specific envelope rather than
+//! a subscription. [`Client::get_message`] asks the node for it by message ID.
+
+use crate::capabilities::{rpc_error, Feature};
+use crate::error::Error;
+use crate::pb::{Envelope, GetMessageReq};
+use crate::Client;
+use anyhow::{Context, Result};
+use tonic::Code;
+
+impl Client {
+    /// Fetch the envelope with `msg_id` on `topic`, or `None` if the node does not retain it
+    ///
+    /// The envelope's message ID is checked against its contents. With a
+    /// [keyring](Client::with_keyring) installed its signature is verified too,
+    /// failing with [`Error::InvalidSignature`] or [`Error::UnknownSender`].
+    /// Fails with [`Error::Unsupported`] on nodes without `GetMessage`.
+    pub async fn get_message(&mut self, topic: &[u8], msg_id: &str) -> Result<Option<Envelope>> {
+        self.check_topic(topic)?;
+        self.require(Feature::GetMessage).await?;
+        let req = self.authorized(GetMessageReq {
+            topic: topic.to_vec(),
+            msg_id: msg_id.to_string(),
+        })?;
+
+        let envelope = match self.inner.get_message(req).await {
+            Ok(response) => response.into_inner().envelope,
+            Err(status) if status.code() == Code::NotFound => None,
+            Err(status) => {
+                return Err(rpc_error(Feature::GetMessage)(status)).context("get message")
+            }
+        };
+        let Some(envelope) = envelope else {
+            return Ok(None);
+        };
+
+        if envelope.msg_id != msg_id || !self.verify_msg_id(&envelope) {
+            anyhow::bail!("node answered a lookup of {msg_id} with a different message");
+        }
+        if self.keyring.is_some() && !self.verify(&envelope)? {
+            return Err(Error::InvalidSignature {
+                msg_id: envelope.msg_id,
+            }
+            .into());
+        }
+        Ok(Some(envelope))
+    }
+}
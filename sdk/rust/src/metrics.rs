@@ -0,0 +1,138 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Client instrumentation
+//!
+//! A [`MetricsRecorder`] installed with [`Client::with_metrics`] receives
+//! counters for sent and received envelopes. Envelope size distributions are
+//! recorded only when enabled with [`Client::with_size_histograms`], both into
+//! the recorder and into [`SizeHistogram`]s readable from the client.
+//!
+//! With the `prometheus` feature, [`PrometheusRecorder`] keeps the metrics in
+//! memory and [`serve`] exposes them to Prometheus over HTTP.
+
+use crate::Client;
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "prometheus")]
+mod prometheus;
+#[cfg(feature = "prometheus")]
+pub use prometheus::{serve, MetricsServer, PrometheusRecorder};
+
+/// Envelopes accepted by the node
+pub const SENT_TOTAL: &str = "securefabric_sent_total";
+/// Envelopes delivered to consumers by subscriptions
+pub const RECEIVED_TOTAL: &str = "securefabric_received_total";
+/// Encoded size in bytes of each sent envelope
+pub const SENT_ENVELOPE_BYTES: &str = "securefabric_sent_envelope_bytes";
+/// Encoded size in bytes of each delivered envelope
+pub const RECEIVED_ENVELOPE_BYTES: &str = "securefabric_received_envelope_bytes";
+
+/// Upper bounds (inclusive, in bytes) of the [`SizeHistogram`] buckets
+///
+/// A final overflow bucket counts envelopes larger than the last bound.
+pub const SIZE_BUCKETS: [usize; 8] = [
+    64,
+    256,
+    1024,
+    4 * 1024,
+    16 * 1024,
+    64 * 1024,
+    256 * 1024,
+    1024 * 1024,
+];
+
+/// Sink for the SDK's metrics, typically backed by an application's metrics library
+pub trait MetricsRecorder: Send + Sync {
+    /// Add `value` to the counter `name`
+    fn increment_counter(&self, name: &'static str, value: u64);
+
+    /// Record one observation for the histogram `name`
+    fn record_histogram(&self, name: &'static str, value: f64);
+}
+
+/// Counts of envelope sizes per [`SIZE_BUCKETS`] range
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SizeHistogram {
+    counts: [u64; SIZE_BUCKETS.len() + 1],
+}
+
+impl SizeHistogram {
+    /// Index of the bucket a size falls into
+    pub fn bucket_for(size: usize) -> usize {
+        SIZE_BUCKETS.partition_point(|&bound| bound < size)
+    }
+
+    /// Count one envelope of `size` bytes
+    pub fn record(&mut self, size: usize) {
+        self.counts[Self::bucket_for(size)] += 1;
+    }
+
+    /// Per-bucket counts; the last entry is the overflow bucket
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    /// Total number of envelopes counted
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+}
+
+/// Metrics configuration shared by a client, its clones and its subscriptions
+#[derive(Clone, Default)]
+pub(crate) struct Instruments {
+    recorder: Option<Arc<dyn MetricsRecorder>>,
+    sent_sizes: Option<Arc<Mutex<SizeHistogram>>>,
+}
+
+impl Instruments {
+    pub(crate) fn size_histograms(&self) -> bool {
+        self.sent_sizes.is_some()
+    }
+
+    pub(crate) fn on_sent(&self, envelope_len: Option<usize>) {
+        if let Some(recorder) = &self.recorder {
+            recorder.increment_counter(SENT_TOTAL, 1);
+        }
+        if let (Some(sizes), Some(envelope_len)) = (&self.sent_sizes, envelope_len) {
+            sizes.lock().unwrap().record(envelope_len);
+            if let Some(recorder) = &self.recorder {
+                recorder.record_histogram(SENT_ENVELOPE_BYTES, envelope_len as f64);
+            }
+        }
+    }
+
+    pub(crate) fn on_received(&self, envelope_len: Option<usize>) {
+        if let Some(recorder) = &self.recorder {
+            recorder.increment_counter(RECEIVED_TOTAL, 1);
+            if let Some(envelope_len) = envelope_len {
+                recorder.record_histogram(RECEIVED_ENVELOPE_BYTES, envelope_len as f64);
+            }
+        }
+    }
+}
+
+impl Client {
+    /// Report metrics to `recorder`
+    pub fn with_metrics(mut self, recorder: Arc<dyn MetricsRecorder>) -> Self {
+        self.instruments.recorder = Some(recorder);
+        self
+    }
+
+    /// Record the distribution of encoded envelope sizes
+    ///
+    /// Off by default. When enabled, sent sizes are available from
+    /// [`Client::sent_envelope_sizes`], delivered sizes per topic from
+    /// [`SubscriptionStats::envelope_sizes`](crate::subscription::SubscriptionStats::envelope_sizes),
+    /// and both are forwarded to the [`MetricsRecorder`] if one is installed.
+    pub fn with_size_histograms(mut self, enabled: bool) -> Self {
+        self.instruments.sent_sizes = enabled.then(Default::default);
+        self
+    }
+
+    /// Size distribution of envelopes sent so far, if size histograms are enabled
+    pub fn sent_envelope_sizes(&self) -> Option<SizeHistogram> {
+        let sizes = self.instruments.sent_sizes.as_ref()?;
+        Some(sizes.lock().unwrap().clone())
+    }
+}
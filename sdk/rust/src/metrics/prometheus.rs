@@ -0,0 +1,191 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Prometheus exposition over a minimal embedded HTTP server
+//!
+//! [`PrometheusRecorder`] is a [`MetricsRecorder`] that keeps the SDK's
+//! metrics in memory and renders them in the Prometheus text format.
+//! [`serve`] answers `GET /metrics` with that rendering, for services that
+//! want a scrape target without wiring up a metrics library. The server reads
+//! one request per connection and closes it after responding.
+
+use super::{MetricsRecorder, SIZE_BUCKETS};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+/// Longest request head read before the connection is dropped
+const MAX_REQUEST_HEAD: usize = 8 * 1024;
+
+/// Counters and histograms recorded in memory for scraping
+///
+/// Histograms use [`SIZE_BUCKETS`] as their bucket bounds, since every
+/// histogram the SDK records is an envelope size in bytes.
+#[derive(Debug, Default)]
+pub struct PrometheusRecorder {
+    counters: Mutex<BTreeMap<&'static str, u64>>,
+    histograms: Mutex<BTreeMap<&'static str, Histogram>>,
+}
+
+#[derive(Debug, Default)]
+struct Histogram {
+    /// Observations per bucket, the last one being `+Inf`
+    buckets: [u64; SIZE_BUCKETS.len() + 1],
+    sum: f64,
+    count: u64,
+}
+
+impl PrometheusRecorder {
+    /// Empty recorder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Current metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for (name, value) in self.counters.lock().unwrap().iter() {
+            let _ = writeln!(out, "# TYPE {name} counter\n{name} {value}");
+        }
+        for (name, histogram) in self.histograms.lock().unwrap().iter() {
+            let _ = writeln!(out, "# TYPE {name} histogram");
+            let mut cumulative = 0;
+            for (bound, count) in SIZE_BUCKETS.iter().zip(&histogram.buckets) {
+                cumulative += count;
+                let _ = writeln!(out, "{name}_bucket{{le=\"{bound}\"}} {cumulative}");
+            }
+            let _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {}", histogram.count);
+            let _ = writeln!(out, "{name}_sum {}", histogram.sum);
+            let _ = writeln!(out, "{name}_count {}", histogram.count);
+        }
+        out
+    }
+}
+
+impl MetricsRecorder for PrometheusRecorder {
+    fn increment_counter(&self, name: &'static str, value: u64) {
+        *self.counters.lock().unwrap().entry(name).or_default() += value;
+    }
+
+    fn record_histogram(&self, name: &'static str, value: f64) {
+        let mut histograms = self.histograms.lock().unwrap();
+        let histogram = histograms.entry(name).or_default();
+        let bucket = SIZE_BUCKETS.partition_point(|&bound| (bound as f64) < value);
+        histogram.buckets[bucket] += 1;
+        histogram.sum += value;
+        histogram.count += 1;
+    }
+}
+
+/// Running metrics endpoint returned by [`serve`]
+///
+/// Dropping it stops the server without waiting; [`MetricsServer::shutdown`]
+/// stops accepting connections and waits for the accept loop to exit.
+#[derive(Debug)]
+pub struct MetricsServer {
+    local_addr: SocketAddr,
+    stop: Option<oneshot::Sender<()>>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl MetricsServer {
+    /// Address the server is listening on, useful when bound to port 0
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Stop accepting connections and wait for the server to exit
+    pub async fn shutdown(mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        if let Some(task) = &self.task {
+            task.abort();
+        }
+    }
+}
+
+/// Serve `recorder`'s metrics at `http://<addr>/metrics`
+///
+/// Binds before returning, so bind errors are reported here and the endpoint
+/// is ready to scrape once this resolves. Install the same recorder on
+/// clients with [`Client::with_metrics`](crate::Client::with_metrics).
+pub async fn serve(
+    addr: impl ToSocketAddrs,
+    recorder: Arc<PrometheusRecorder>,
+) -> io::Result<MetricsServer> {
+    let listener = TcpListener::bind(addr).await?;
+    let local_addr = listener.local_addr()?;
+    let (stop, mut stopped) = oneshot::channel();
+
+    let task = tokio::spawn(async move {
+        loop {
+            let stream = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(_) => continue,
+                },
+                _ = &mut stopped => return,
+            };
+            let recorder = recorder.clone();
+            tokio::spawn(async move {
+                let _ = respond(stream, &recorder).await;
+            });
+        }
+    });
+
+    Ok(MetricsServer {
+        local_addr,
+        stop: Some(stop),
+        task: Some(task),
+    })
+}
+
+/// Answer one HTTP/1.x request and close the connection
+async fn respond(mut stream: TcpStream, recorder: &PrometheusRecorder) -> io::Result<()> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        if head.len() >= MAX_REQUEST_HEAD {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "request head too large",
+            ));
+        }
+        let read = stream.read(&mut buf).await?;
+        if read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        head.extend_from_slice(&buf[..read]);
+    }
+
+    let request_line = head.split(|&b| b == b'\r').next().unwrap_or_default();
+    let mut parts = request_line.split(|&b| b == b' ');
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some(b"GET"), Some(b"/metrics")) => ("200 OK", recorder.render()),
+        (Some(b"GET"), _) => ("404 Not Found", "not found\n".to_string()),
+        _ => ("405 Method Not Allowed", "method not allowed\n".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: text/plain; version=0.0.4; charset=utf-8\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
@@ -0,0 +1,122 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Hash functions for message IDs
+//!
+//! An envelope's msg_id is the hex digest of `pubkey || seq || nonce`, with
+//! `seq` as 8 little-endian bytes. The hash is BLAKE3 unless the sender picks
+//! another with [`Client::with_msg_id_hasher`]; the envelope records which one
+//! in `msg_id_hash`, so verifiers recompute the msg_id with the same hash.
+//!
+//! | Hash      | `msg_id_hash` |
+//! |-----------|---------------|
+//! | BLAKE3    | 0             |
+//! | SHA-256   | 1             |
+//!
+//! Identifiers below [`FIRST_CUSTOM_ID`] are reserved for built-in hashes.
+//! Like the msg_id itself, `msg_id_hash` is not covered by the signature.
+
+use crate::pb::Envelope;
+use crate::Client;
+use sha2::Digest;
+use std::sync::Arc;
+
+/// `msg_id_hash` of BLAKE3 msg_ids, the default
+pub const BLAKE3: u32 = 0;
+/// `msg_id_hash` of SHA-256 msg_ids
+pub const SHA256: u32 = 1;
+/// Lowest identifier available to custom hashers
+pub const FIRST_CUSTOM_ID: u32 = 256;
+
+/// Hash function turning an envelope's pubkey, seq and nonce into its msg_id
+///
+/// Implement this for a hash outside the built-in [`Blake3`] and [`Sha256`],
+/// with an [`id`](MsgIdHasher::id) of at least [`FIRST_CUSTOM_ID`]. Only
+/// clients configured with the same hasher, and nodes that know it, can
+/// verify msg_ids it produced.
+pub trait MsgIdHasher: Send + Sync {
+    /// Identifier recorded in the envelope's `msg_id_hash`
+    fn id(&self) -> u32;
+
+    /// Digest of `preimage`; the msg_id is its lowercase hex encoding
+    fn hash(&self, preimage: &[u8]) -> Vec<u8>;
+}
+
+/// BLAKE3, identifier [`BLAKE3`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Blake3;
+
+impl MsgIdHasher for Blake3 {
+    fn id(&self) -> u32 {
+        BLAKE3
+    }
+
+    fn hash(&self, preimage: &[u8]) -> Vec<u8> {
+        blake3::hash(preimage).as_bytes().to_vec()
+    }
+}
+
+/// SHA-256, identifier [`SHA256`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sha256;
+
+impl MsgIdHasher for Sha256 {
+    fn id(&self) -> u32 {
+        SHA256
+    }
+
+    fn hash(&self, preimage: &[u8]) -> Vec<u8> {
+        sha2::Sha256::digest(preimage).to_vec()
+    }
+}
+
+/// The built-in hasher with identifier `id`
+pub fn builtin(id: u32) -> Option<&'static dyn MsgIdHasher> {
+    match id {
+        BLAKE3 => Some(&Blake3),
+        SHA256 => Some(&Sha256),
+        _ => None,
+    }
+}
+
+/// The bytes hashed into a msg_id: `pubkey || seq (u64 LE) || nonce`
+pub fn preimage(pubkey: &[u8], seq: u64, nonce: &[u8]) -> Vec<u8> {
+    [pubkey, &seq.to_le_bytes(), nonce].concat()
+}
+
+/// The msg_id `hasher` gives an envelope
+pub fn compute(hasher: &dyn MsgIdHasher, envelope: &Envelope) -> String {
+    hex::encode(hasher.hash(&preimage(&envelope.pubkey, envelope.seq, &envelope.nonce)))
+}
+
+impl Client {
+    /// Compute msg_ids of sent envelopes with `hasher` instead of BLAKE3
+    ///
+    /// The envelope records the hasher's identifier, and
+    /// [`Client::verify_msg_id`] accepts msg_ids from any built-in hasher
+    /// whatever this setting.
+    pub fn with_msg_id_hasher(mut self, hasher: impl MsgIdHasher + 'static) -> Self {
+        self.msg_id_hasher = Arc::new(hasher);
+        self
+    }
+
+    /// Check the envelope's msg_id against the hash its `msg_id_hash` names
+    ///
+    /// Uses this client's [hasher](Client::with_msg_id_hasher) when the
+    /// identifiers match and the built-in hash otherwise. An envelope naming
+    /// an unknown hash never verifies.
+    pub fn verify_msg_id(&self, envelope: &Envelope) -> bool {
+        matches(&*self.msg_id_hasher, envelope)
+    }
+}
+
+/// Check a msg_id with `own` if the envelope names it, a built-in hash otherwise
+pub(crate) fn matches(own: &dyn MsgIdHasher, envelope: &Envelope) -> bool {
+    let hasher = match envelope.msg_id_hash {
+        id if id == own.id() => own,
+        id => match builtin(id) {
+            Some(hasher) => hasher,
+            None => return false,
+        },
+    };
+    compute(hasher, envelope) == envelope.msg_id
+}
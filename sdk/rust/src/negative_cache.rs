@@ -0,0 +1,183 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Short-lived cache of failed signature checks
+//!
+//! A node replaying a forged or corrupted envelope, or a peer resending one,
+//! makes every subscriber pay for the same failing signature check again. A
+//! [`NegativeCache`] remembers recent failures for a bounded time, so repeats
+//! are rejected without re-running the verification.
+//!
+//! Only failures are ever recorded: a cache hit can reject an envelope, never
+//! accept one. Entries are keyed on a digest of everything the check depends
+//! on (the sender key it was checked against, the verify mode, the signature
+//! scheme, `aad`, `nonce`, `key_version`, the signed payload and `sig`), not
+//! just `msg_id` and `sig`. Both of those are chosen by the sender, so a
+//! forgery reusing a genuine envelope's message ID and signature keys
+//! differently and cannot get the genuine envelope rejected.
+
+use crate::crypto::scheme::VerifyingKey;
+use crate::crypto::VerifyMode;
+use crate::pb::Envelope;
+use crate::Client;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Bounded LRU of recently failed verifications
+///
+/// Clones share the same entries, so several clients with the same trust
+/// configuration can share one cache.
+#[derive(Clone, Debug)]
+pub struct NegativeCache {
+    inner: Arc<Mutex<Entries>>,
+    capacity: usize,
+    ttl: Duration,
+}
+
+/// Counters of a [`NegativeCache`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NegativeCacheStats {
+    /// Checks rejected from the cache without verifying
+    pub hits: u64,
+    /// Checks that were not cached and ran the full verification
+    pub misses: u64,
+    /// Failures recorded
+    pub inserts: u64,
+    /// Entries currently cached, including expired ones not yet evicted
+    pub len: usize,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    /// Entry key to its expiry and position in `order`
+    entries: HashMap<[u8; 32], (Instant, u64)>,
+    /// Recency order, least recently used first
+    order: BTreeMap<u64, [u8; 32]>,
+    next: u64,
+    stats: NegativeCacheStats,
+}
+
+impl NegativeCache {
+    /// Cache up to `capacity` failures, each for `ttl` after it was recorded
+    ///
+    /// A hit refreshes an entry's recency but not its expiry, so a failure is
+    /// re-verified at least once per `ttl`.
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        assert!(capacity > 0, "negative cache capacity must be non-zero");
+        Self {
+            inner: Default::default(),
+            capacity,
+            ttl,
+        }
+    }
+
+    /// Current counters
+    pub fn stats(&self) -> NegativeCacheStats {
+        let inner = self.inner.lock().unwrap();
+        NegativeCacheStats {
+            len: inner.entries.len(),
+            ..inner.stats
+        }
+    }
+
+    /// Drop every entry, keeping the counters
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.entries.clear();
+        inner.order.clear();
+    }
+
+    /// Digest identifying one signature check
+    pub(crate) fn key(
+        vk: &VerifyingKey,
+        mode: VerifyMode,
+        envelope: &Envelope,
+        signed_payload: &[u8],
+    ) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new_derive_key("securefabric/negative-cache/v1");
+        let mode: u8 = match mode {
+            VerifyMode::Strict => 0,
+            VerifyMode::Permissive => 1,
+        };
+        hasher.update(&[mode]);
+        hasher.update(&envelope.sig_scheme.to_be_bytes());
+        hasher.update(&envelope.key_version.to_be_bytes());
+        for field in [
+            &vk.to_bytes()[..],
+            &envelope.aad,
+            &envelope.nonce,
+            signed_payload,
+            &envelope.sig,
+        ] {
+            hasher.update(&(field.len() as u64).to_be_bytes());
+            hasher.update(field);
+        }
+        *hasher.finalize().as_bytes()
+    }
+
+    /// Whether the check identified by `key` failed within the TTL
+    pub(crate) fn contains(&self, key: &[u8; 32]) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        let now = Instant::now();
+        match inner.entries.get(key).copied() {
+            Some((expiry, stamp)) if expiry > now => {
+                let next = inner.bump();
+                inner.order.remove(&stamp);
+                inner.order.insert(next, *key);
+                inner.entries.insert(*key, (expiry, next));
+                inner.stats.hits += 1;
+                true
+            }
+            expired => {
+                if let Some((_, stamp)) = expired {
+                    inner.order.remove(&stamp);
+                    inner.entries.remove(key);
+                }
+                inner.stats.misses += 1;
+                false
+            }
+        }
+    }
+
+    /// Record that the check identified by `key` failed
+    pub(crate) fn insert(&self, key: [u8; 32]) {
+        let mut inner = self.inner.lock().unwrap();
+        let stamp = inner.bump();
+        if let Some((_, old)) = inner
+            .entries
+            .insert(key, (Instant::now() + self.ttl, stamp))
+        {
+            inner.order.remove(&old);
+        }
+        inner.order.insert(stamp, key);
+        inner.stats.inserts += 1;
+        while inner.entries.len() > self.capacity {
+            let Some((_, oldest)) = inner.order.pop_first() else {
+                break;
+            };
+            inner.entries.remove(&oldest);
+        }
+    }
+}
+
+impl Entries {
+    fn bump(&mut self) -> u64 {
+        self.next += 1;
+        self.next
+    }
+}
+
+impl Client {
+    /// Remember failed signature checks in `cache`
+    ///
+    /// Applies to [`Client::verify`] and everything built on it, including
+    /// windowed and decrypted subscriptions. A cached failure rejects a repeat
+    /// of the same envelope without verifying it again; valid envelopes are
+    /// always verified in full.
+    pub fn with_negative_cache(mut self, cache: NegativeCache) -> Self {
+        self.negative_cache = Some(cache);
+        self
+    }
+}
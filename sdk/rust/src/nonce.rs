@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Detecting nonce reuse on decrypt
+//!
+//! XChaCha20-Poly1305 loses confidentiality and integrity when a key seals two
+//! messages under one nonce, so two envelopes with the same topic key version
+//! and nonce mean a sender's RNG failed or an old ciphertext is being
+//! presented again. The replay window of [`Client::with_replay_window`]
+//! catches repeated msg_ids; [`Client::with_nonce_tracking`] catches repeated
+//! nonces, which a re-signed envelope with a fresh seq still carries.
+
+use crate::error::Error;
+use crate::pb::Envelope;
+use crate::validate::ReplayWindow;
+use crate::Client;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// What to do with an envelope whose nonce was already seen under its key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonceReuseAction {
+    /// Only call the [nonce reuse warning](Client::with_nonce_reuse_warning)
+    /// hooks, and decrypt as usual
+    Warn,
+    /// Fail decryption with [`Error::NonceReused`]
+    Reject,
+}
+
+type NonceReuseWarning = Arc<dyn Fn(&Envelope) + Send + Sync>;
+
+/// Nonce tracking settings of a client, and the nonces shared with its clones
+#[derive(Clone, Default)]
+pub(crate) struct NonceTracker {
+    action: Option<NonceReuseAction>,
+    warnings: Vec<NonceReuseWarning>,
+    window: usize,
+    seen: Arc<Mutex<HashMap<u32, ReplayWindow<Vec<u8>>>>>,
+}
+
+impl NonceTracker {
+    /// Record the nonce of a decrypted envelope, failing if it is a rejected repeat
+    pub(crate) fn observe(&self, envelope: &Envelope) -> Result<(), Error> {
+        let Some(action) = self.action else {
+            return Ok(());
+        };
+        let repeated = self
+            .seen
+            .lock()
+            .unwrap()
+            .entry(envelope.key_version)
+            .or_insert_with(|| ReplayWindow::new(self.window))
+            .observe(envelope.nonce.as_slice());
+        if !repeated {
+            return Ok(());
+        }
+        for warning in &self.warnings {
+            warning(envelope);
+        }
+        match action {
+            NonceReuseAction::Warn => Ok(()),
+            NonceReuseAction::Reject => Err(Error::NonceReused {
+                key_version: envelope.key_version,
+                nonce: hex::encode(&envelope.nonce),
+            }),
+        }
+    }
+}
+
+impl Client {
+    /// Track the last `window` nonces decrypted under each key version
+    ///
+    /// Applies to [`Client::decrypt`] and [`Client::subscribe_decrypted`].
+    /// Only envelopes that decrypt successfully are recorded, so a forgery
+    /// cannot make a genuine envelope look like a repeat. A node delivering
+    /// the same envelope twice is reported too. The tracked nonces are shared
+    /// by clones of the client.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is zero.
+    pub fn with_nonce_tracking(mut self, window: usize, action: NonceReuseAction) -> Self {
+        assert!(window > 0, "nonce window must be non-zero");
+        self.nonces.action = Some(action);
+        self.nonces.window = window;
+        self.nonces.seen = Default::default();
+        self
+    }
+
+    /// Call `hook` with every decrypted envelope whose nonce is a repeat
+    ///
+    /// Runs whatever the [`NonceReuseAction`], once tracking is enabled with
+    /// [`Client::with_nonce_tracking`]. Hooks added by repeated calls run in
+    /// the order they were added.
+    pub fn with_nonce_reuse_warning(
+        mut self,
+        hook: impl Fn(&Envelope) + Send + Sync + 'static,
+    ) -> Self {
+        self.nonces.warnings.push(Arc::new(hook));
+        self
+    }
+}
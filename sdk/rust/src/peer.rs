@@ -0,0 +1,137 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Binding signatures to the node's TLS identity
+//!
+//! A signed envelope is valid wherever it is presented, so one captured on a
+//! connection to one node can be replayed to another. With
+//! [`ClientBuilder::with_connection_bound_signing`] the client records the
+//! node's [`PeerIdentity`] when it connects and adds it to the AAD of every
+//! envelope it signs, as `"peer"`. Verifiers bound the same way reject
+//! envelopes bound to any other node.
+//!
+//! tonic does not expose the certificate of the connection it negotiates, so
+//! the identity is read from a separate handshake made with the same trust
+//! roots, server name, address and proxy.
+
+use crate::pb::Envelope;
+use crate::proxy::Proxy;
+use crate::tls::TlsConfig;
+use crate::{Client, ClientBuilder};
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpStream;
+use tonic::codegen::http::Uri;
+use x509_cert::der::{Decode, Encode};
+
+/// SHA-256 of the DER SubjectPublicKeyInfo of a node's leaf certificate
+///
+/// Stays the same when the certificate is renewed for the same key, like an
+/// HPKP pin.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PeerIdentity([u8; 32]);
+
+impl PeerIdentity {
+    /// Identity of the key certified by a DER certificate
+    pub fn from_certificate(der: &[u8]) -> Result<Self> {
+        let certificate = x509_cert::Certificate::from_der(der).context("parse certificate")?;
+        let spki = certificate
+            .tbs_certificate
+            .subject_public_key_info
+            .to_der()
+            .context("encode subject public key info")?;
+        Ok(Self(Sha256::digest(spki).into()))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Hex encoding, as carried in the AAD
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
+    }
+
+    /// Whether `envelope` is bound to a node other than this one
+    ///
+    /// Envelopes without a `"peer"` in their AAD are not bound to any node.
+    pub(crate) fn rejects(&self, envelope: &Envelope) -> bool {
+        let bound = serde_json::from_slice::<serde_json::Value>(&envelope.aad)
+            .ok()
+            .and_then(|aad| aad.get("peer")?.as_str().map(str::to_owned));
+        bound.is_some_and(|bound| bound != self.to_hex())
+    }
+}
+
+impl fmt::Display for PeerIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl fmt::Debug for PeerIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PeerIdentity({self})")
+    }
+}
+
+/// Read the identity of the node at `uri` from a TLS handshake
+///
+/// Dials `addr` if given, else through `proxy` if given, else the endpoint host.
+pub(crate) async fn probe(
+    uri: &Uri,
+    tls: &TlsConfig,
+    addr: Option<SocketAddr>,
+    proxy: Option<&Proxy>,
+    nodelay: bool,
+) -> Result<PeerIdentity> {
+    let host = uri.host().context("endpoint has no host")?;
+    let tcp = match (addr, proxy) {
+        (Some(addr), _) => TcpStream::connect(addr).await?,
+        (None, Some(proxy)) => proxy.tunnel(uri, nodelay).await?,
+        (None, None) => TcpStream::connect((host, uri.port_u16().unwrap_or(443))).await?,
+    };
+
+    let name = tls.domain().unwrap_or(host).to_string();
+    let server_name = rustls::pki_types::ServerName::try_from(name.clone())
+        .with_context(|| format!("invalid TLS server name {name}"))?;
+    let connector = tokio_rustls::TlsConnector::from(Arc::new(tls.rustls_config()?));
+    let stream = connector
+        .connect(server_name, tcp)
+        .await
+        .with_context(|| format!("handshake with {name}"))?;
+    let (_, session) = stream.get_ref();
+    let leaf = session
+        .peer_certificates()
+        .and_then(<[_]>::first)
+        .context("node presented no certificate")?;
+    PeerIdentity::from_certificate(leaf)
+}
+
+impl ClientBuilder {
+    /// Bind every signature to the identity of the node connected to
+    ///
+    /// `build` then makes a TLS handshake to read the node's
+    /// [`PeerIdentity`], even with [`connect_lazy`](ClientBuilder::connect_lazy),
+    /// and fails without a [`tls`](ClientBuilder::tls) configuration. The
+    /// client includes the identity in the AAD of everything it signs, and
+    /// [`Client::verify`] and the verifying subscriptions reject envelopes
+    /// bound to a different node. Receivers connected to other nodes of a
+    /// fabric, or verifying without this option, ignore the binding.
+    pub fn with_connection_bound_signing(mut self) -> Self {
+        self.connection_bound = true;
+        self
+    }
+}
+
+impl Client {
+    /// Identity of the node this client binds signatures to
+    ///
+    /// `None` unless built with
+    /// [`ClientBuilder::with_connection_bound_signing`].
+    pub fn peer_identity(&self) -> Option<PeerIdentity> {
+        self.peer_identity
+    }
+}
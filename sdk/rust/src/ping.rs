@@ -0,0 +1,109 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Round-trip latency probes
+//!
+//! [`Client::ping`] times one `Ping` RPC. [`Client::ping_n`] repeats it and
+//! summarises the samples for monitoring dashboards.
+
+use crate::capabilities::{rpc_error, Feature};
+use crate::error::Error;
+use crate::pb::PingReq;
+use crate::Client;
+use anyhow::{Context, Result};
+use std::time::{Duration, Instant};
+
+/// Default time to wait for a single ping before reporting [`Error::Timeout`]
+pub const DEFAULT_PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Summary of a series of pings from [`Client::ping_n`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PingStats {
+    /// Pings that completed
+    pub completed: u32,
+    /// Pings that exceeded the timeout
+    pub timed_out: u32,
+    pub min: Duration,
+    pub avg: Duration,
+    pub max: Duration,
+    /// 99th percentile (nearest rank) of completed pings
+    pub p99: Duration,
+}
+
+impl PingStats {
+    fn from_samples(mut samples: Vec<Duration>, timed_out: u32) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort();
+        let completed = samples.len() as u32;
+        let total: Duration = samples.iter().sum();
+        let rank = (samples.len() * 99).div_ceil(100);
+
+        Some(Self {
+            completed,
+            timed_out,
+            min: samples[0],
+            avg: total / completed,
+            max: samples[samples.len() - 1],
+            p99: samples[rank - 1],
+        })
+    }
+}
+
+impl Client {
+    /// Time allowed for each ping before it counts as timed out
+    pub fn with_ping_timeout(mut self, timeout: Duration) -> Self {
+        self.ping_timeout = timeout;
+        self
+    }
+
+    /// Measure the round-trip time of one `Ping` RPC
+    ///
+    /// Fails with [`Error::Timeout`] if the node does not answer within the ping
+    /// timeout, [`Error::Unsupported`] on nodes without `Ping`, and with the
+    /// underlying error if the RPC fails.
+    pub async fn ping(&mut self) -> Result<Duration> {
+        self.require(Feature::Ping).await?;
+        let timeout = self.ping_timeout;
+        let req = self.authorized(PingReq {})?;
+
+        let start = Instant::now();
+        match tokio::time::timeout(timeout, self.inner.ping(req)).await {
+            Ok(response) => {
+                response
+                    .map_err(rpc_error(Feature::Ping))
+                    .context("ping node")?;
+                Ok(start.elapsed())
+            }
+            Err(_) => Err(Error::Timeout { after: timeout }.into()),
+        }
+    }
+
+    /// Ping the node `count` times in sequence and summarise the round-trip times
+    ///
+    /// Timed-out pings are counted in [`PingStats::timed_out`] and excluded from
+    /// the latency figures. Any other error aborts the series. Fails with
+    /// [`Error::Timeout`] if every ping timed out.
+    pub async fn ping_n(&mut self, count: u32) -> Result<PingStats> {
+        anyhow::ensure!(count > 0, "Ping count must be non-zero");
+
+        let mut samples = Vec::with_capacity(count as usize);
+        let mut timed_out = 0;
+        for _ in 0..count {
+            match self.ping().await {
+                Ok(rtt) => samples.push(rtt),
+                Err(error) if matches!(error.downcast_ref(), Some(Error::Timeout { .. })) => {
+                    timed_out += 1;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+
+        PingStats::from_samples(samples, timed_out).ok_or_else(|| {
+            Error::Timeout {
+                after: self.ping_timeout,
+            }
+            .into()
+        })
+    }
+}
@@ -0,0 +1,304 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Connecting through an HTTP CONNECT proxy
+//!
+//! Networks that only allow outbound traffic through a proxy are handled by
+//! opening a `CONNECT` tunnel to the node and running the usual gRPC
+//! connection, TLS included, inside it. The proxy only sees the node's address,
+//! never the traffic. A proxy can be set with
+//! [`ClientBuilder::with_proxy`](crate::ClientBuilder::with_proxy); otherwise
+//! `HTTPS_PROXY` and `NO_PROXY` are honoured.
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tonic::codegen::http::Uri;
+
+/// Longest proxy response head accepted before the tunnel is considered broken
+const MAX_RESPONSE_HEAD: usize = 8 * 1024;
+
+/// Credentials presented to the proxy
+#[derive(Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ProxyAuth {
+    /// HTTP Basic credentials sent in `Proxy-Authorization`
+    Basic { username: String, password: String },
+}
+
+impl ProxyAuth {
+    /// HTTP Basic credentials
+    pub fn basic(username: impl Into<String>, password: impl Into<String>) -> Self {
+        Self::Basic {
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+
+    fn header_value(&self) -> String {
+        match self {
+            Self::Basic { username, password } => {
+                let credentials = format!("{username}:{password}");
+                format!(
+                    "Basic {}",
+                    base64::engine::general_purpose::STANDARD.encode(credentials)
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Debug for ProxyAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Basic { username, .. } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .field("password", &"<redacted>")
+                .finish(),
+        }
+    }
+}
+
+/// A parsed proxy address with its credentials
+#[derive(Clone, Debug)]
+pub(crate) struct Proxy {
+    authority: String,
+    auth: Option<ProxyAuth>,
+}
+
+impl Proxy {
+    /// Parse `http://[user:password@]host[:port]`; the scheme may be omitted
+    ///
+    /// Credentials embedded in the URI are used unless `auth` is given.
+    pub(crate) fn parse(uri: &str, auth: Option<ProxyAuth>) -> Result<Self> {
+        let parse = || -> Result<Self> {
+            let uri = if uri.contains("://") {
+                uri.to_string()
+            } else {
+                format!("http://{uri}")
+            };
+            let uri: Uri = uri.parse()?;
+            anyhow::ensure!(
+                uri.scheme_str() == Some("http"),
+                "only http:// proxies are supported"
+            );
+            let authority = uri.authority().context("missing proxy host")?;
+            let embedded = match authority.as_str().rsplit_once('@') {
+                Some((userinfo, _)) => Some(userinfo_auth(userinfo)?),
+                None => None,
+            };
+            Ok(Self {
+                authority: format!(
+                    "{}:{}",
+                    authority.host(),
+                    authority.port_u16().unwrap_or(80)
+                ),
+                auth: auth.or(embedded),
+            })
+        };
+        parse().with_context(|| format!("invalid proxy URI {:?}", redact(uri)))
+    }
+
+    /// Proxy for `target` from `HTTPS_PROXY`, unless `NO_PROXY` exempts it
+    pub(crate) fn from_env(target: &Uri) -> Result<Option<Self>> {
+        let Some(proxy) = env_var("HTTPS_PROXY") else {
+            return Ok(None);
+        };
+        let host = target.host().unwrap_or_default();
+        let port = target_port(target);
+        if env_var("NO_PROXY").is_some_and(|no_proxy| bypasses(&no_proxy, host, port)) {
+            return Ok(None);
+        }
+        Self::parse(&proxy, None)
+            .map(Some)
+            .context("read proxy from HTTPS_PROXY")
+    }
+
+    /// `host:port` of the proxy
+    pub(crate) fn authority(&self) -> &str {
+        &self.authority
+    }
+
+    /// Connector dialling through this proxy, with `TCP_NODELAY` set to `nodelay`
+    pub(crate) fn connector(self, nodelay: bool) -> ProxyConnector {
+        ProxyConnector {
+            proxy: Arc::new(self),
+            nodelay,
+        }
+    }
+
+    /// Open a tunnel to `target` through the proxy
+    pub(crate) async fn tunnel(&self, target: &Uri, nodelay: bool) -> io::Result<TcpStream> {
+        let host = target
+            .host()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "endpoint has no host"))?;
+        let target = format!("{host}:{}", target_port(target));
+
+        let mut stream = TcpStream::connect(&self.authority).await?;
+        stream.set_nodelay(nodelay)?;
+
+        let mut request = format!("CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n");
+        if let Some(auth) = &self.auth {
+            request.push_str(&format!("Proxy-Authorization: {}\r\n", auth.header_value()));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes()).await?;
+
+        let head = read_response_head(&mut stream).await?;
+        let status = head
+            .lines()
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|code| code.parse::<u16>().ok());
+        match status {
+            Some(200) => Ok(stream),
+            Some(407) => Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "proxy {} refused credentials for {target} (407)",
+                    self.authority
+                ),
+            )),
+            _ => Err(io::Error::other(format!(
+                "proxy {} refused tunnel to {target}: {}",
+                self.authority,
+                head.lines().next().unwrap_or_default()
+            ))),
+        }
+    }
+}
+
+/// Reads the proxy's reply one byte at a time, so no bytes of the tunnelled
+/// stream are consumed along with it
+async fn read_response_head(stream: &mut TcpStream) -> io::Result<String> {
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() == MAX_RESPONSE_HEAD {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "proxy response head too large",
+            ));
+        }
+        head.push(stream.read_u8().await?);
+    }
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+/// Connector for tonic that dials the node through a CONNECT tunnel
+///
+/// TLS, when configured on the endpoint, is layered on the tunnel by tonic.
+#[derive(Clone)]
+pub(crate) struct ProxyConnector {
+    proxy: Arc<Proxy>,
+    nodelay: bool,
+}
+
+impl tower_service::Service<Uri> for ProxyConnector {
+    type Response = hyper_util::rt::TokioIo<TcpStream>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<Self::Response>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, target: Uri) -> Self::Future {
+        let proxy = self.proxy.clone();
+        let nodelay = self.nodelay;
+        Box::pin(async move {
+            proxy
+                .tunnel(&target, nodelay)
+                .await
+                .map(hyper_util::rt::TokioIo::new)
+        })
+    }
+}
+
+fn target_port(target: &Uri) -> u16 {
+    target
+        .port_u16()
+        .unwrap_or(if target.scheme_str() == Some("https") {
+            443
+        } else {
+            80
+        })
+}
+
+/// Uppercase or lowercase environment variable, ignoring empty values
+fn env_var(name: &str) -> Option<String> {
+    [name.to_string(), name.to_lowercase()]
+        .iter()
+        .find_map(|name| std::env::var(name).ok())
+        .filter(|value| !value.trim().is_empty())
+}
+
+/// Whether a `NO_PROXY` list exempts `host:port`
+///
+/// Entries are hostnames or IP addresses, optionally with a port. A hostname
+/// also matches its subdomains, with or without a leading dot, and `*`
+/// matches everything.
+fn bypasses(no_proxy: &str, host: &str, port: u16) -> bool {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    no_proxy
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| {
+            if entry == "*" {
+                return true;
+            }
+            let (name, entry_port) = split_port(entry);
+            if entry_port.is_some_and(|entry_port| entry_port != port) {
+                return false;
+            }
+            let name = name.trim_start_matches('.');
+            host.eq_ignore_ascii_case(name)
+                || host
+                    .to_ascii_lowercase()
+                    .ends_with(&format!(".{}", name.to_ascii_lowercase()))
+        })
+}
+
+/// Split `host:port` or `[v6]:port`, leaving bare IPv6 addresses whole
+fn split_port(entry: &str) -> (&str, Option<u16>) {
+    if let Some(rest) = entry.strip_prefix('[') {
+        return match rest.split_once(']') {
+            Some((host, port)) => (host, port.strip_prefix(':').and_then(|p| p.parse().ok())),
+            None => (rest, None),
+        };
+    }
+    match entry.split_once(':') {
+        Some((host, port)) if !port.contains(':') => (host, port.parse().ok()),
+        _ => (entry, None),
+    }
+}
+
+/// Basic credentials from percent-encoded `user:password` userinfo
+fn userinfo_auth(userinfo: &str) -> Result<ProxyAuth> {
+    let decode = |part: &str| -> Result<String> {
+        Ok(percent_encoding::percent_decode_str(part)
+            .decode_utf8()
+            .context("credentials are not valid UTF-8")?
+            .into_owned())
+    };
+    let (username, password) = userinfo.split_once(':').unwrap_or((userinfo, ""));
+    Ok(ProxyAuth::basic(decode(username)?, decode(password)?))
+}
+
+/// The URI with any embedded credentials removed, for error messages
+pub(crate) fn redact(uri: &str) -> String {
+    match uri.rsplit_once('@') {
+        Some((before, host)) => match before.split_once("://") {
+            Some((scheme, _)) => format!("{scheme}://***@{host}"),
+            None => format!("***@{host}"),
+        },
+        None => uri.to_string(),
+    }
+}
@@ -0,0 +1,159 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Bounded send queue shared by all buffered send paths
+//!
+//! Every send occupies one slot from admission until the node answers. When
+//! all slots are taken the configured [`OverflowPolicy`] decides what happens
+//! to the next send.
+
+use crate::error::Error;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{oneshot, Notify};
+
+/// What to do with a send when the queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Wait until a slot frees up
+    Block,
+    /// Fail the new send with [`Error::QueueFull`]
+    RejectNew,
+    /// Evict the oldest send that has not reached the wire yet, failing it with
+    /// [`Error::Dropped`]; if every queued send is already on the wire, the new
+    /// send is rejected with [`Error::QueueFull`]
+    DropOldest,
+}
+
+pub(crate) struct SendQueue {
+    capacity: usize,
+    policy: OverflowPolicy,
+    state: Mutex<QueueState>,
+    freed: Notify,
+}
+
+#[derive(Default)]
+struct QueueState {
+    next_id: u64,
+    entries: VecDeque<Entry>,
+}
+
+struct Entry {
+    id: u64,
+    dispatching: bool,
+    evict: Option<oneshot::Sender<()>>,
+}
+
+impl SendQueue {
+    pub(crate) fn new(capacity: usize, policy: OverflowPolicy) -> Self {
+        assert!(capacity > 0, "send queue capacity must be non-zero");
+        Self {
+            capacity,
+            policy,
+            state: Mutex::new(QueueState::default()),
+            freed: Notify::new(),
+        }
+    }
+
+    /// Maximum number of sends that can hold a slot at once
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    /// Reserve a slot for one send, applying the overflow policy when full
+    pub(crate) async fn admit(self: &Arc<Self>) -> Result<QueueSlot, Error> {
+        loop {
+            let freed = self.freed.notified();
+            tokio::pin!(freed);
+            freed.as_mut().enable();
+
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.entries.len() < self.capacity {
+                    return Ok(self.push(&mut state));
+                }
+                match self.policy {
+                    OverflowPolicy::Block => {}
+                    OverflowPolicy::RejectNew => {
+                        return Err(Error::QueueFull {
+                            capacity: self.capacity,
+                        })
+                    }
+                    OverflowPolicy::DropOldest => {
+                        let oldest = state.entries.iter().position(|e| !e.dispatching);
+                        let Some(index) = oldest else {
+                            return Err(Error::QueueFull {
+                                capacity: self.capacity,
+                            });
+                        };
+                        if let Some(evict) = state.entries.remove(index).and_then(|e| e.evict) {
+                            let _ = evict.send(());
+                        }
+                        return Ok(self.push(&mut state));
+                    }
+                }
+            }
+
+            freed.await;
+        }
+    }
+
+    fn push(self: &Arc<Self>, state: &mut QueueState) -> QueueSlot {
+        let id = state.next_id;
+        state.next_id += 1;
+        let (evict, evicted) = oneshot::channel();
+        state.entries.push_back(Entry {
+            id,
+            dispatching: false,
+            evict: Some(evict),
+        });
+        QueueSlot {
+            queue: self.clone(),
+            id,
+            evicted,
+        }
+    }
+}
+
+/// A reserved place in the [`SendQueue`], released on drop
+pub(crate) struct QueueSlot {
+    queue: Arc<SendQueue>,
+    id: u64,
+    evicted: oneshot::Receiver<()>,
+}
+
+impl QueueSlot {
+    /// Resolves if the slot is evicted before it starts dispatching
+    pub(crate) async fn evicted(&mut self) {
+        // A closed channel means the slot was released, never evicted
+        if (&mut self.evicted).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+
+    /// Mark the send as on the wire so it can no longer be evicted
+    pub(crate) fn start_dispatch(&mut self) -> Result<(), Error> {
+        let mut state = self.queue.state.lock().unwrap();
+        match state.entries.iter_mut().find(|e| e.id == self.id) {
+            Some(entry) => {
+                entry.dispatching = true;
+                Ok(())
+            }
+            None => Err(Error::Dropped),
+        }
+    }
+}
+
+impl Drop for QueueSlot {
+    fn drop(&mut self) {
+        let mut state = self.queue.state.lock().unwrap();
+        if let Some(index) = state.entries.iter().position(|e| e.id == self.id) {
+            state.entries.remove(index);
+        }
+        drop(state);
+        self.queue.freed.notify_waiters();
+    }
+}
@@ -0,0 +1,137 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Who an envelope is addressed to
+//!
+//! The envelope's `to` field carries one of three kinds of [`Recipient`]:
+//!
+//! - empty: a broadcast to every subscriber of the topic
+//! - a 32-byte Ed25519 or 33-byte compressed secp256k1 public key: a message
+//!   directed at one party
+//! - `group:` followed by a non-empty UTF-8 group id: a message for the
+//!   members of an application-defined group
+//!
+//! Any other value is malformed. The prefix is checked first, so a direct
+//! key that happens to start with `group:` cannot be sent. Every send path
+//! checks its `to` against these rules and against how the payload is
+//! encrypted: a payload sealed per recipient with [`Client::send_sealed`]
+//! cannot be broadcast, and a direct message is sealed to exactly one key.
+
+use crate::error::Error;
+use crate::pb::Envelope;
+use crate::{sealed, Client, Outgoing};
+use anyhow::Result;
+
+/// Prefix of a group recipient in the `to` field
+pub const GROUP_PREFIX: &[u8] = b"group:";
+
+/// Addressee of an envelope
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Recipient {
+    /// Every subscriber of the topic
+    Broadcast,
+    /// The holder of this public key
+    Direct(Vec<u8>),
+    /// The members of this group
+    Group(String),
+}
+
+impl Recipient {
+    /// Encode as the envelope's `to` field
+    pub fn to_wire(&self) -> Result<Vec<u8>, Error> {
+        match self {
+            Self::Broadcast => Ok(Vec::new()),
+            Self::Direct(key) => {
+                check_key(key)?;
+                Ok(key.clone())
+            }
+            Self::Group(id) => {
+                if id.is_empty() {
+                    return Err(invalid("group id is empty"));
+                }
+                Ok([GROUP_PREFIX, id.as_bytes()].concat())
+            }
+        }
+    }
+
+    /// Parse the envelope's `to` field
+    pub fn from_wire(to: &[u8]) -> Result<Self, Error> {
+        if to.is_empty() {
+            return Ok(Self::Broadcast);
+        }
+        if let Some(id) = to.strip_prefix(GROUP_PREFIX) {
+            if id.is_empty() {
+                return Err(invalid("group id is empty"));
+            }
+            let id = std::str::from_utf8(id).map_err(|_| invalid("group id is not UTF-8"))?;
+            return Ok(Self::Group(id.to_string()));
+        }
+        check_key(to)?;
+        Ok(Self::Direct(to.to_vec()))
+    }
+
+    /// Check that a payload sealed to `sealed_for` can be addressed to this recipient
+    ///
+    /// An empty `sealed_for` means the payload is not sealed per recipient.
+    pub(crate) fn check_sealing(&self, sealed_for: &[sealed::PublicKey]) -> Result<(), Error> {
+        if sealed_for.is_empty() {
+            return Ok(());
+        }
+        match self {
+            Self::Broadcast => Err(invalid(
+                "a broadcast cannot be sealed to individual recipients",
+            )),
+            Self::Direct(_) if sealed_for.iter().any(|key| *key != sealed_for[0]) => Err(invalid(
+                "a direct message can only be sealed to one recipient",
+            )),
+            Self::Direct(_) | Self::Group(_) => Ok(()),
+        }
+    }
+}
+
+fn check_key(key: &[u8]) -> Result<(), Error> {
+    if key.starts_with(GROUP_PREFIX) {
+        return Err(invalid("direct recipient key starts with the group prefix"));
+    }
+    match key.len() {
+        32 | 33 => Ok(()),
+        len => Err(Error::InvalidRecipient {
+            reason: format!("expected a 32- or 33-byte public key, got {len} bytes"),
+        }),
+    }
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::InvalidRecipient {
+        reason: reason.to_string(),
+    }
+}
+
+impl Envelope {
+    /// Who the envelope is addressed to, parsed from its `to` field
+    ///
+    /// The recipient is only authentic once the envelope has been verified.
+    pub fn recipient(&self) -> Result<Recipient, Error> {
+        Recipient::from_wire(&self.to)
+    }
+}
+
+impl Client {
+    /// Send a message to `to`
+    ///
+    /// The recipient is encoded into the envelope's `to` field and bound into
+    /// the signed AAD. Fails with [`Error::InvalidRecipient`] for a key of the
+    /// wrong length or an empty group id.
+    pub async fn send_to_recipient(
+        &mut self,
+        topic: &str,
+        to: &Recipient,
+        payload: &[u8],
+    ) -> Result<String> {
+        let to = to.to_wire()?;
+        let outgoing = Outgoing {
+            to: &to,
+            ..Default::default()
+        };
+        self.send_envelope(topic, outgoing, payload).await
+    }
+}
@@ -0,0 +1,81 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Request-reply over topics
+//!
+//! [`Client::request`] sends a message with two signed headers and waits for
+//! the answer:
+//!
+//! - `correlation-id`: random id the reply must echo
+//! - `reply-to`: topic the requester is listening on for the reply
+//!
+//! A responder answers by sending on the `reply-to` topic with the request's
+//! `correlation-id` header copied over.
+
+use crate::error::Error;
+use crate::headers::HeaderFilter;
+use crate::pb::Envelope;
+use crate::{Client, Outgoing};
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Header tying a reply to its request
+pub const CORRELATION_ID: &str = "correlation-id";
+/// Header naming the topic a reply should be sent on
+pub const REPLY_TO: &str = "reply-to";
+
+impl Client {
+    /// Send `payload` on `topic` and wait up to `timeout` for the reply on `reply_topic`
+    ///
+    /// Subscribes to `reply_topic` before sending, so a fast responder cannot
+    /// answer before the requester is listening. Only verified replies whose
+    /// `correlation-id` matches are accepted; other envelopes on the reply
+    /// topic are skipped, so several requests can share one reply topic. The
+    /// reply is returned as received, still encrypted if it was sent
+    /// encrypted. The reply subscription is closed when this returns.
+    ///
+    /// Fails with [`Error::Timeout`] if no reply arrives in time, and when the
+    /// reply subscription ends first.
+    pub async fn request(
+        &mut self,
+        topic: &str,
+        payload: &[u8],
+        reply_topic: &str,
+        timeout: Duration,
+    ) -> Result<Envelope> {
+        let mut id = [0u8; 16];
+        self.entropy.fill(&mut id);
+        let correlation_id = hex::encode(id);
+
+        let exchange = async {
+            let filter = HeaderFilter::new().equals(CORRELATION_ID, &correlation_id);
+            let mut replies = self
+                .subscribe_matching(reply_topic.as_bytes(), filter)
+                .await?;
+
+            let mut headers = BTreeMap::new();
+            headers.insert(CORRELATION_ID.to_string(), correlation_id.clone());
+            headers.insert(REPLY_TO.to_string(), reply_topic.to_string());
+            let outgoing = Outgoing {
+                headers: &headers,
+                ..Default::default()
+            };
+            self.send_envelope(topic, outgoing, payload)
+                .await
+                .context("send request")?;
+
+            while let Some(item) = replies.next().await {
+                let envelope = item.context("subscription failed")?;
+                if self.verify(&envelope).unwrap_or(false) && self.verify_msg_id(&envelope) {
+                    return Ok(envelope);
+                }
+            }
+            anyhow::bail!("Reply subscription ended before a reply arrived")
+        };
+
+        tokio::time::timeout(timeout, exchange)
+            .await
+            .map_err(|_| Error::Timeout { after: timeout })?
+    }
+}
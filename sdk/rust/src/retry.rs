@@ -0,0 +1,177 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Send retries and the client-wide retry budget
+//!
+//! [`Client::with_send_retries`] retries sends the node answered with
+//! `UNAVAILABLE`. Per-call retries alone multiply load during an outage, so
+//! [`Client::with_retry_budget`] adds a token bucket in the style of gRPC
+//! retry throttling: every retry spends a token, every successful send earns
+//! a fraction of one, and a send that finds the bucket empty fails at once.
+//! [`Client::with_retry_jitter`] randomizes the waits so that clients failing
+//! together, as when a node restarts, do not all retry in lockstep.
+
+use crate::config::RetryView;
+use crate::entropy::Entropy;
+use crate::Client;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tonic::Code;
+
+/// Tokens are tracked in thousandths so fractional ratios add up exactly
+const MILLI: u64 = 1000;
+
+/// Retry settings shared by clones of a client
+#[derive(Clone, Default)]
+pub(crate) struct Retry {
+    policy: Option<RetryPolicy>,
+    budget: Option<Arc<RetryBudget>>,
+    /// Bound of the extra random wait before the first retry, if jittered
+    jitter: Option<Duration>,
+}
+
+#[derive(Clone, Copy)]
+struct RetryPolicy {
+    max_retries: u32,
+    backoff: Duration,
+}
+
+/// Token bucket limiting retries to a fraction of successful sends
+#[derive(Debug)]
+pub(crate) struct RetryBudget {
+    earn_per_success: u64,
+    capacity: u64,
+    balance: Mutex<u64>,
+}
+
+impl RetryBudget {
+    fn new(ratio: f64, min_tokens: u32) -> Self {
+        let capacity = u64::from(min_tokens) * MILLI;
+        Self {
+            earn_per_success: (ratio * MILLI as f64).round() as u64,
+            capacity,
+            balance: Mutex::new(capacity),
+        }
+    }
+
+    fn on_success(&self) {
+        let mut balance = self.balance.lock().unwrap();
+        *balance = (*balance + self.earn_per_success).min(self.capacity);
+    }
+
+    /// Spend one token, returning whether a retry is allowed
+    fn try_spend(&self) -> bool {
+        let mut balance = self.balance.lock().unwrap();
+        if *balance < MILLI {
+            return false;
+        }
+        *balance -= MILLI;
+        true
+    }
+}
+
+/// What to do after a failed send attempt
+pub(crate) enum Decision {
+    /// Wait this long, then try again
+    RetryAfter(Duration),
+    /// Fail with the error from this attempt
+    GiveUp,
+    /// The attempt was retryable but the retry budget is exhausted
+    BudgetExhausted,
+}
+
+impl Retry {
+    /// Decide whether the failed attempt number `attempt` (0-based) is retried
+    ///
+    /// Jittered waits are drawn from `entropy`.
+    pub(crate) fn after_failure(&self, attempt: u32, code: Code, entropy: &Entropy) -> Decision {
+        let Some(policy) = self.policy else {
+            return Decision::GiveUp;
+        };
+        if code != Code::Unavailable || attempt >= policy.max_retries {
+            return Decision::GiveUp;
+        }
+        match &self.budget {
+            Some(budget) if !budget.try_spend() => Decision::BudgetExhausted,
+            _ => Decision::RetryAfter(match self.jitter {
+                None => policy.backoff,
+                Some(initial) if attempt == 0 => {
+                    entropy.up_to(initial) + entropy.up_to(policy.backoff)
+                }
+                Some(_) => entropy.up_to(policy.backoff),
+            }),
+        }
+    }
+
+    /// Settings as reported by [`Client::config`]
+    pub(crate) fn view(&self) -> Option<RetryView> {
+        let policy = self.policy?;
+        Some(RetryView {
+            max_retries: policy.max_retries,
+            backoff: policy.backoff,
+            jitter: self.jitter,
+            budget: self.budget.as_ref().map(|budget| {
+                (
+                    budget.earn_per_success as f64 / MILLI as f64,
+                    (budget.capacity / MILLI) as u32,
+                )
+            }),
+        })
+    }
+
+    /// Record a successful send, earning retry tokens
+    pub(crate) fn on_success(&self) {
+        if let Some(budget) = &self.budget {
+            budget.on_success();
+        }
+    }
+}
+
+impl Client {
+    /// Retry sends the node rejects with `UNAVAILABLE`
+    ///
+    /// Each send is attempted up to `1 + max_retries` times, waiting `backoff`
+    /// between attempts. The retried envelope keeps its seq and msg_id, so the
+    /// node can discard a duplicate if an earlier attempt did arrive.
+    pub fn with_send_retries(mut self, max_retries: u32, backoff: Duration) -> Self {
+        self.retry.policy = Some(RetryPolicy {
+            max_retries,
+            backoff,
+        });
+        self
+    }
+
+    /// Randomize the waits between send retries
+    ///
+    /// Each wait becomes a uniformly random duration up to the backoff ("full
+    /// jitter"), and the first also waits up to `initial` more, so clients
+    /// that lost the same node at the same moment spread their retries out
+    /// instead of arriving together. Pass `Duration::ZERO` for full jitter
+    /// alone. Randomness comes from the client's nonce RNG, which the
+    /// `test-determinism` feature makes reproducible. Has no effect without
+    /// [`Client::with_send_retries`], in either order.
+    pub fn with_retry_jitter(mut self, initial: Duration) -> Self {
+        self.retry.jitter = Some(initial);
+        self
+    }
+
+    /// Cap send retries across the client to a fraction of successful sends
+    ///
+    /// The budget starts with `min_tokens` tokens and never holds more. Each
+    /// retry spends one token and each successful send earns `ratio` tokens, so
+    /// under sustained failure retries drop to `ratio` per success. When no
+    /// token is left, a failed send returns its error without retrying. The
+    /// budget is shared by clones of the client. Has no effect without
+    /// [`Client::with_send_retries`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ratio` is negative or not finite.
+    pub fn with_retry_budget(mut self, ratio: f64, min_tokens: u32) -> Self {
+        assert!(
+            ratio.is_finite() && ratio >= 0.0,
+            "retry budget ratio must be finite and non-negative"
+        );
+        self.retry.budget = Some(Arc::new(RetryBudget::new(ratio, min_tokens)));
+        self
+    }
+}
@@ -0,0 +1,127 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Spreading sends over several signing keys
+//!
+//! A fleet that signs everything with one identity loses all of it if that
+//! key leaks. With [`Client::with_signing_key_rotator`] each send is signed
+//! by the next key of a [`SigningKeyRotator`] instead, and carries that key's
+//! [fingerprint](crate::keyring::fingerprint) in its AAD as `"kid"`.
+//! Receivers trust the whole set by adding
+//! [`SigningKeyRotator::verifying_keys`] to a [`Keyring`](crate::keyring::Keyring).
+//!
+//! Keys are picked by smooth weighted round-robin: over any run of sends
+//! adding up to the total weight, each key signs exactly its weight's share,
+//! and a heavier key's turns are spread out rather than bunched together.
+//!
+//! Each key numbers its own sends from seq 1, so every key's envelopes form an
+//! unbroken chain a [`SenderChainVerifier`](crate::chain::SenderChainVerifier)
+//! for that key accepts. The client's own sequence, and with it
+//! [`SessionState::next_seq`](crate::session::SessionState::next_seq), only
+//! counts sends signed with [`Client::with_signing_key`].
+
+use crate::crypto::scheme::{SigningKey, VerifyingKey};
+use crate::keyring::fingerprint;
+use crate::Client;
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Mutex};
+
+/// Signing keys used in turn, from [`SigningKeyRotator::round_robin`] or [`SigningKeyRotator::weighted`]
+///
+/// Clones share the rotation and each key's seq counter, so clients built from
+/// one rotator take turns from the same sequence.
+#[derive(Clone)]
+pub struct SigningKeyRotator {
+    keys: Arc<[RotatedKey]>,
+    /// Current weight of each key in the smooth round-robin
+    current: Arc<Mutex<Vec<i64>>>,
+}
+
+/// One key of a rotation, with its parsed public key, fingerprint and seq counter
+pub(crate) struct RotatedKey {
+    pub(crate) signing_key: SigningKey,
+    pub(crate) verifying_key: VerifyingKey,
+    pub(crate) fingerprint: String,
+    /// Next seq to assign to an envelope signed with this key
+    pub(crate) sequence: AtomicU64,
+    weight: u32,
+}
+
+impl SigningKeyRotator {
+    /// Rotate over `keys` in order, each signing an equal share
+    ///
+    /// Panics if `keys` is empty.
+    pub fn round_robin<K: Into<SigningKey>>(keys: impl IntoIterator<Item = K>) -> Self {
+        Self::weighted(keys.into_iter().map(|key| (key, 1)))
+    }
+
+    /// Rotate over `keys`, each signing in proportion to its weight
+    ///
+    /// Panics if `keys` is empty or a weight is zero.
+    pub fn weighted<K: Into<SigningKey>>(keys: impl IntoIterator<Item = (K, u32)>) -> Self {
+        let keys: Arc<[RotatedKey]> = keys
+            .into_iter()
+            .map(|(key, weight)| {
+                assert!(weight > 0, "signing key weight must be non-zero");
+                let signing_key = key.into();
+                let verifying_key = signing_key.verifying_key();
+                RotatedKey {
+                    fingerprint: fingerprint(&verifying_key.to_bytes()),
+                    signing_key,
+                    verifying_key,
+                    sequence: AtomicU64::new(1),
+                    weight,
+                }
+            })
+            .collect();
+        assert!(
+            !keys.is_empty(),
+            "signing key rotator needs at least one key"
+        );
+        Self {
+            current: Arc::new(Mutex::new(vec![0; keys.len()])),
+            keys,
+        }
+    }
+
+    /// Public keys of the rotation, in the order given, for a receiver's keyring
+    pub fn verifying_keys(&self) -> Vec<VerifyingKey> {
+        self.keys.iter().map(|key| key.verifying_key).collect()
+    }
+
+    /// Fingerprints of the rotation's keys, in the order given
+    pub fn fingerprints(&self) -> Vec<String> {
+        self.keys
+            .iter()
+            .map(|key| key.fingerprint.clone())
+            .collect()
+    }
+
+    /// Key to sign the next send with
+    pub(crate) fn next(&self) -> &RotatedKey {
+        let mut current = self.current.lock().unwrap();
+        let total: i64 = self.keys.iter().map(|key| i64::from(key.weight)).sum();
+        for (current, key) in current.iter_mut().zip(self.keys.iter()) {
+            *current += i64::from(key.weight);
+        }
+        // First key with the highest current weight, so ties go in order
+        let (chosen, _) = current
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, weight)| **weight)
+            .expect("rotator has at least one key");
+        current[chosen] -= total;
+        &self.keys[chosen]
+    }
+}
+
+impl Client {
+    /// Sign each send with the next key of `rotator`
+    ///
+    /// See the [module docs](crate::rotator). Takes precedence over
+    /// [`Client::with_signing_key`]. Clones of this client share the rotation.
+    pub fn with_signing_key_rotator(mut self, rotator: SigningKeyRotator) -> Self {
+        self.signing_key_rotator = Some(rotator);
+        self
+    }
+}
@@ -0,0 +1,212 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Envelope encryption for fan-out to several recipients
+//!
+//! [`Client::send_sealed`] encrypts the payload once under a random content
+//! key and wraps that key for each recipient's X25519 public key, so a large
+//! payload is not encrypted and carried once per recipient. Each wrapping key
+//! is derived with BLAKE3 from an X25519 exchange between a per-envelope
+//! ephemeral key and the recipient's key. The ephemeral key and the recipient
+//! list are bound into the signed AAD as `"ephemeral"` and `"recipients"`, and
+//! the envelope's `key_version` is [`SEALED_KEY_VERSION`]. A recipient
+//! configured with [`Client::with_recipient_key`] recovers the payload with
+//! [`Client::open_sealed`].
+
+use crate::crypto::{self, KEY_LEN, SEALED_KEY_VERSION};
+use crate::entropy::Entropy;
+use crate::error::Error;
+use crate::pb::{Envelope, WrappedKey};
+use crate::recipient::Recipient;
+use crate::{Client, Outgoing};
+use anyhow::{Context, Result};
+
+use x25519_dalek::SharedSecret;
+pub use x25519_dalek::{PublicKey, StaticSecret};
+
+/// BLAKE3 key derivation context for wrapping keys
+const WRAP_CONTEXT: &str = "securefabric sealed envelope v1 key wrap";
+
+/// Content key and its per-recipient wrappings for one outgoing envelope
+pub(crate) struct Sealing {
+    content_key: [u8; KEY_LEN],
+    ephemeral: PublicKey,
+    recipients: Vec<PublicKey>,
+    pub(crate) wrapped_keys: Vec<WrappedKey>,
+}
+
+impl Sealing {
+    /// Draw a content key and ephemeral key and wrap the content key for each recipient
+    pub(crate) fn new(entropy: &Entropy, nonce: &[u8], recipients: &[PublicKey]) -> Result<Self> {
+        let mut content_key = [0u8; KEY_LEN];
+        entropy.fill(&mut content_key);
+        let mut ephemeral_secret = [0u8; 32];
+        entropy.fill(&mut ephemeral_secret);
+        let ephemeral_secret = StaticSecret::from(ephemeral_secret);
+        let ephemeral = PublicKey::from(&ephemeral_secret);
+
+        let mut unique: Vec<PublicKey> = Vec::with_capacity(recipients.len());
+        for recipient in recipients {
+            if !unique.contains(recipient) {
+                unique.push(*recipient);
+            }
+        }
+        let wrapped_keys = unique
+            .iter()
+            .map(|recipient| {
+                let shared = ephemeral_secret.diffie_hellman(recipient);
+                let wrapping_key = wrapping_key(shared, &ephemeral, recipient)?;
+                Ok(WrappedKey {
+                    recipient: recipient.as_bytes().to_vec(),
+                    wrapped: crypto::seal(&wrapping_key, nonce, &content_key, &[])?,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            content_key,
+            ephemeral,
+            recipients: unique,
+            wrapped_keys,
+        })
+    }
+
+    /// Record the ephemeral key and recipients in the envelope's AAD
+    pub(crate) fn bind(&self, aad: &mut serde_json::Value) {
+        aad["ephemeral"] = hex::encode(self.ephemeral.as_bytes()).into();
+        aad["recipients"] = self
+            .recipients
+            .iter()
+            .map(|recipient| hex::encode(recipient.as_bytes()))
+            .collect();
+    }
+
+    /// Encrypt the payload under the content key
+    pub(crate) fn seal(&self, nonce: &[u8], payload: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        crypto::seal(&self.content_key, nonce, payload, aad)
+    }
+
+    pub(crate) fn ephemeral_key(&self) -> Vec<u8> {
+        self.ephemeral.as_bytes().to_vec()
+    }
+}
+
+/// Key wrapping the content key for `recipient`, from their X25519 exchange
+/// with the envelope's ephemeral key
+fn wrapping_key(
+    shared: SharedSecret,
+    ephemeral: &PublicKey,
+    recipient: &PublicKey,
+) -> Result<[u8; KEY_LEN]> {
+    anyhow::ensure!(
+        shared.was_contributory(),
+        "X25519 exchange with a low-order key"
+    );
+    let mut hasher = blake3::Hasher::new_derive_key(WRAP_CONTEXT);
+    hasher.update(shared.as_bytes());
+    hasher.update(ephemeral.as_bytes());
+    hasher.update(recipient.as_bytes());
+    Ok(*hasher.finalize().as_bytes())
+}
+
+/// Public keys listed in a sealed envelope's AAD under `field`
+fn aad_keys(aad: &serde_json::Value, field: &str) -> Vec<Vec<u8>> {
+    let values = match aad.get(field) {
+        Some(serde_json::Value::Array(values)) => values.iter().collect(),
+        Some(value) => vec![value],
+        None => Vec::new(),
+    };
+    values
+        .into_iter()
+        .filter_map(|value| hex::decode(value.as_str()?).ok())
+        .collect()
+}
+
+impl Client {
+    /// Decrypt sealed envelopes addressed to this X25519 key
+    pub fn with_recipient_key(mut self, secret: StaticSecret) -> Self {
+        self.recipient_key = Some(secret);
+        self
+    }
+
+    /// Send `payload` to `to`, encrypted once and readable only by `recipients`
+    ///
+    /// `to` must be a [`Recipient::Group`] or, when `recipients` holds a
+    /// single key, a [`Recipient::Direct`]; a sealed payload cannot be
+    /// broadcast, and other combinations fail with
+    /// [`Error::InvalidRecipient`]. Duplicate recipients are sealed to once.
+    /// The topic key from
+    /// [`Client::with_encryption`] is not used, and the signature always
+    /// covers the ciphertext, so the node and any receiver can authenticate
+    /// the envelope without being a recipient.
+    pub async fn send_sealed(
+        &mut self,
+        topic: &str,
+        to: &Recipient,
+        recipients: &[PublicKey],
+        payload: &[u8],
+    ) -> Result<String> {
+        anyhow::ensure!(!recipients.is_empty(), "Sealed envelopes need a recipient");
+        let to = to.to_wire()?;
+        let outgoing = Outgoing {
+            to: &to,
+            sealed_for: recipients,
+            ..Default::default()
+        };
+        self.send_envelope(topic, outgoing, payload).await
+    }
+
+    /// Verify a sealed envelope and decrypt its payload with the recipient key
+    ///
+    /// Fails with [`Error::NotRecipient`] if the envelope was not sealed to
+    /// the key from [`Client::with_recipient_key`].
+    pub fn open_sealed(&self, envelope: &Envelope) -> Result<Vec<u8>> {
+        let secret = self
+            .recipient_key
+            .as_ref()
+            .context("No recipient key configured")?;
+        anyhow::ensure!(
+            envelope.key_version == SEALED_KEY_VERSION,
+            "Envelope is not sealed to recipients"
+        );
+        crypto::check_aad_len(&envelope.aad, self.max_aad_len)?;
+        anyhow::ensure!(
+            self.verify(envelope)?,
+            "Invalid signature on {}",
+            envelope.msg_id
+        );
+
+        // The ephemeral key and recipients are only trusted as signed in the AAD
+        let aad = serde_json::from_slice::<serde_json::Value>(&envelope.aad).unwrap_or_default();
+        anyhow::ensure!(
+            aad_keys(&aad, "ephemeral") == [envelope.ephemeral_key.clone()],
+            "Ephemeral key of {} does not match its AAD",
+            envelope.msg_id
+        );
+        let ephemeral: [u8; 32] = envelope
+            .ephemeral_key
+            .as_slice()
+            .try_into()
+            .context("Ephemeral key must be 32 bytes")?;
+        let ephemeral = PublicKey::from(ephemeral);
+
+        let public = PublicKey::from(secret);
+        let listed = aad_keys(&aad, "recipients").contains(&public.as_bytes().to_vec());
+        let slot = envelope
+            .wrapped_keys
+            .iter()
+            .find(|slot| slot.recipient == public.as_bytes());
+        let (true, Some(slot)) = (listed, slot) else {
+            return Err(Error::NotRecipient.into());
+        };
+
+        let wrapping_key = wrapping_key(secret.diffie_hellman(&ephemeral), &ephemeral, &public)?;
+        let content_key = crypto::open(&wrapping_key, &envelope.nonce, &slot.wrapped, &[])
+            .context("unwrap content key")?;
+        crypto::open(
+            &content_key,
+            &envelope.nonce,
+            &envelope.payload,
+            &envelope.aad,
+        )
+    }
+}
@@ -0,0 +1,79 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Portable client session state
+//!
+//! [`SessionState`] captures what a client needs to continue where another
+//! left off: the sender's next sequence number and, per topic, the highest seq
+//! consumed from each sender, counting only envelopes that verified. Moving it to a new process with
+//! [`Client::restore_state`] lets a consumer resume without re-delivering what
+//! was already consumed and without its own sends restarting at seq 1.
+
+use crate::Client;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::Ordering;
+
+/// Snapshot of a client's session, serializable with serde
+///
+/// Topics and sender public keys are hex-encoded so the state round-trips
+/// through JSON and other text formats.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionState {
+    /// Next seq the client will stamp on a sent envelope
+    pub next_seq: u64,
+    /// Highest consumed seq, keyed by hex topic and then hex sender public key
+    pub offsets: BTreeMap<String, BTreeMap<String, u64>>,
+}
+
+impl Client {
+    /// Snapshot the session so it can be restored into another client
+    ///
+    /// Includes offsets restored earlier but not yet resumed by a subscription.
+    pub fn export_state(&self) -> SessionState {
+        let mut offsets: BTreeMap<String, BTreeMap<String, u64>> = BTreeMap::new();
+        let mut merge = |topic: &[u8], senders: &HashMap<Vec<u8>, u64>| {
+            let entry = offsets.entry(hex::encode(topic)).or_default();
+            for (pubkey, &seq) in senders {
+                let last = entry.entry(hex::encode(pubkey)).or_default();
+                *last = (*last).max(seq);
+            }
+        };
+
+        for (topic, senders) in &self.resume_offsets {
+            merge(topic, senders);
+        }
+        for (topic, stats) in self.stats.lock().unwrap().iter() {
+            merge(topic, &stats.last_seq);
+        }
+
+        SessionState {
+            next_seq: self.sequence.load(Ordering::SeqCst),
+            offsets,
+        }
+    }
+
+    /// Continue a session exported from another client
+    ///
+    /// Later subscriptions to a saved topic skip envelopes at or below the saved
+    /// seq for each sender, so a node replaying retained messages does not
+    /// re-deliver them. The send sequence continues from the saved value.
+    /// Restore before subscribing or sending.
+    pub fn restore_state(&mut self, state: SessionState) -> Result<()> {
+        let mut resume = HashMap::new();
+        for (topic, senders) in state.offsets {
+            let topic = hex::decode(&topic).with_context(|| format!("Invalid topic {topic}"))?;
+            let mut offsets = HashMap::new();
+            for (pubkey, seq) in senders {
+                let pubkey = hex::decode(&pubkey)
+                    .with_context(|| format!("Invalid sender public key {pubkey}"))?;
+                offsets.insert(pubkey, seq);
+            }
+            resume.insert(topic, offsets);
+        }
+
+        self.resume_offsets = resume;
+        self.sequence.store(state.next_seq.max(1), Ordering::SeqCst);
+        Ok(())
+    }
+}
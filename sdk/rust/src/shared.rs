@@ -0,0 +1,190 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! One subscription, several consumers
+//!
+//! A [`Subscription`] can only be consumed once. [`Client::subscribe_shared`]
+//! wraps one in a [`SharedSubscription`], and every
+//! [`SharedSubscription::subscribe`] handle then receives its own copy of
+//! each envelope, over a broadcast channel fed by a background task.
+//!
+//! Each consumer has a buffer of the subscription's capacity. A consumer
+//! that falls further behind than that loses the oldest envelopes it has not
+//! read: its next item is [`Error::Lagged`] with the number skipped, and it
+//! continues with the oldest envelope still buffered. The other consumers
+//! and the node stream are not slowed down by it.
+
+use crate::error::Error;
+use crate::pb::Envelope;
+use crate::subscription::{StreamEnd, Subscription};
+use crate::Client;
+use anyhow::Result;
+use futures::stream::{self, BoxStream, StreamExt};
+use futures::Stream;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::sync::broadcast;
+use tokio::task::AbortHandle;
+
+/// Envelopes buffered per consumer by [`Client::subscribe_shared`]
+pub const DEFAULT_SHARED_CAPACITY: usize = 1024;
+
+/// Subscription fanned out to any number of consumers, from [`Client::subscribe_shared`]
+///
+/// The node stream is read from once a consumer is first polled, so
+/// consumers created before that all see the first envelope. Consumers
+/// created later receive the envelopes arriving after they subscribed.
+/// Envelopes arriving while there are no consumers are dropped. The
+/// background task stops when the node ends the stream, or once this handle
+/// and every consumer are dropped.
+pub struct SharedSubscription {
+    shared: Arc<Shared>,
+}
+
+/// State shared by a [`SharedSubscription`] and its consumers
+struct Shared {
+    topic: Vec<u8>,
+    /// Dropped when the node stream ends, closing the consumers' channels
+    sender: Mutex<Option<broadcast::Sender<Result<Envelope, Error>>>>,
+    /// The subscription until the background task takes it
+    pending: Mutex<Option<Subscription>>,
+    task: Mutex<Option<AbortHandle>>,
+    end: Mutex<Option<StreamEnd>>,
+}
+
+impl Shared {
+    /// Start forwarding the node stream, unless already started
+    fn start(self: &Arc<Self>) {
+        let Some(mut subscription) = self.pending.lock().unwrap().take() else {
+            return;
+        };
+        let shared = Arc::downgrade(self);
+        let task = tokio::spawn(async move {
+            while let Some(item) = subscription.next().await {
+                let Some(state) = shared.upgrade() else {
+                    return;
+                };
+                let sender = state.sender.lock().unwrap();
+                if let Some(sender) = &*sender {
+                    // Fails only while there are no consumers
+                    let _ = sender.send(item.map_err(Error::from));
+                }
+            }
+            if let Some(state) = shared.upgrade() {
+                *state.end.lock().unwrap() = subscription.stream_end().cloned();
+                state.sender.lock().unwrap().take();
+            }
+        });
+        *self.task.lock().unwrap() = Some(task.abort_handle());
+    }
+}
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.get_mut().unwrap().take() {
+            task.abort();
+        }
+    }
+}
+
+impl SharedSubscription {
+    /// New consumer receiving a copy of every envelope from now on
+    ///
+    /// Once the node stream has ended, the consumer yields nothing.
+    pub fn subscribe(&self) -> SharedConsumer {
+        let receiver = match &*self.shared.sender.lock().unwrap() {
+            Some(sender) => sender.subscribe(),
+            None => broadcast::channel(1).1,
+        };
+        let items = stream::unfold(
+            (self.shared.clone(), receiver),
+            |(shared, mut receiver)| async move {
+                shared.start();
+                let item = match receiver.recv().await {
+                    Ok(item) => item,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        Err(Error::Lagged { skipped })
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                };
+                Some((item, (shared, receiver)))
+            },
+        );
+        SharedConsumer {
+            items: items.boxed(),
+        }
+    }
+
+    /// Topic pattern the subscription was opened with
+    pub fn topic(&self) -> &[u8] {
+        &self.shared.topic
+    }
+
+    /// Consumers currently subscribed
+    pub fn consumer_count(&self) -> usize {
+        match &*self.shared.sender.lock().unwrap() {
+            Some(sender) => sender.receiver_count(),
+            None => 0,
+        }
+    }
+
+    /// Why the node ended the stream, as [`Subscription::stream_end`]
+    pub fn stream_end(&self) -> Option<StreamEnd> {
+        self.shared.end.lock().unwrap().clone()
+    }
+}
+
+/// One consumer of a [`SharedSubscription`]
+///
+/// Yields each envelope, the node's errors as [`Error::Rpc`], and
+/// [`Error::Lagged`] when it fell too far behind. Ends when the node stream
+/// ends and everything buffered for it has been read.
+pub struct SharedConsumer {
+    items: BoxStream<'static, Result<Envelope, Error>>,
+}
+
+impl Stream for SharedConsumer {
+    type Item = Result<Envelope, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.items.poll_next_unpin(cx)
+    }
+}
+
+impl Client {
+    /// Subscribe to a topic once for several consumers in this process
+    ///
+    /// See [`SharedSubscription`]. Each consumer buffers up to
+    /// [`DEFAULT_SHARED_CAPACITY`] envelopes.
+    ///
+    /// Must be called within a Tokio runtime.
+    pub async fn subscribe_shared(&mut self, topic: &[u8]) -> Result<SharedSubscription> {
+        self.subscribe_shared_with_capacity(topic, DEFAULT_SHARED_CAPACITY)
+            .await
+    }
+
+    /// [`Client::subscribe_shared`] buffering up to `capacity` envelopes per consumer
+    ///
+    /// Panics if `capacity` is zero.
+    pub async fn subscribe_shared_with_capacity(
+        &mut self,
+        topic: &[u8],
+        capacity: usize,
+    ) -> Result<SharedSubscription> {
+        assert!(
+            capacity > 0,
+            "shared subscription capacity must be non-zero"
+        );
+        let subscription = self.subscribe(topic).await?;
+        let (sender, _) = broadcast::channel(capacity);
+        Ok(SharedSubscription {
+            shared: Arc::new(Shared {
+                topic: topic.to_vec(),
+                sender: Mutex::new(Some(sender)),
+                pending: Mutex::new(Some(subscription)),
+                task: Mutex::new(None),
+                end: Mutex::new(None),
+            }),
+        })
+    }
+}
@@ -0,0 +1,1013 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Subscription streams and consumer-side statistics
+//!
+//! [`Client::subscribe`] returns a [`Subscription`], which behaves like the raw
+//! gRPC stream but records what was consumed so the client can report how far
+//! behind a consumer is ([`Client::lag`]) and how long messages took to arrive.
+//! Open subscriptions are listed by [`Client::active_subscriptions`] until
+//! they are dropped. [`Client::subscribe_batched`] receives envelopes in
+//! batches and flattens them into the same stream, or hands the batches over
+//! with [`Subscription::raw_batches`].
+//!
+//! A node ending a subscription on purpose, for example after evicting the
+//! consumer, closes the stream with an OK status and an
+//! [`END_REASON_METADATA`] trailer. The subscription then ends like any
+//! other stream and reports the reason as its [`Subscription::stream_end`];
+//! a stream ended by an error yields the error status instead.
+
+use crate::capabilities::{rpc_error, Feature};
+use crate::credits::Credits;
+use crate::crypto::scheme::VerifyingKey;
+use crate::crypto::SignOrder;
+use crate::error::Error;
+use crate::hooks::PostReceive;
+use crate::metrics::{Instruments, SizeHistogram};
+use crate::pb::{Envelope, EnvelopeBatch, HeadReq, SubscribeReq};
+use crate::throttle::Pacing;
+use crate::{codec, verify_trusted_over, Client, Verifier};
+use anyhow::{Context as _, Result};
+use futures::stream::{BoxStream, StreamExt};
+use futures::FutureExt;
+use futures::Stream;
+use prost::Message;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll, Waker};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::{Status, Streaming};
+
+/// Per-topic consumption statistics shared between a client and its subscriptions
+pub(crate) type StatsRegistry = Arc<Mutex<HashMap<Vec<u8>, SubscriptionStats>>>;
+
+/// What a client has consumed from one topic
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubscriptionStats {
+    /// Envelopes delivered to the consumer
+    pub received: u64,
+    /// Highest seq consumed per sender public key
+    ///
+    /// Only envelopes whose signature and msg_id check out, as
+    /// [`Client::verify`] and [`Client::verify_msg_id`] see them, and whose
+    /// seq is bound into the signed AAD move it. A forged or renumbered
+    /// envelope cannot push a sender's offset ahead and have later messages
+    /// skipped on resume.
+    pub last_seq: HashMap<Vec<u8>, u64>,
+    /// End-to-end latency of the most recent timestamped envelope
+    pub last_latency: Option<Duration>,
+    /// Highest end-to-end latency observed
+    pub max_latency: Option<Duration>,
+    /// Encoded sizes of delivered envelopes, if size histograms are enabled
+    pub envelope_sizes: Option<SizeHistogram>,
+}
+
+impl SubscriptionStats {
+    fn record(
+        &mut self,
+        envelope: &Envelope,
+        verified_seq: Option<u64>,
+        envelope_len: Option<usize>,
+        received_at: SystemTime,
+    ) {
+        self.received += 1;
+        if let Some(envelope_len) = envelope_len {
+            self.envelope_sizes
+                .get_or_insert_with(Default::default)
+                .record(envelope_len);
+        }
+
+        if let Some(seq) = verified_seq {
+            let last = self.last_seq.entry(envelope.pubkey.clone()).or_default();
+            *last = (*last).max(seq);
+        }
+
+        if let Some(latency) = end_to_end_latency(envelope, received_at) {
+            self.last_latency = Some(latency);
+            self.max_latency = Some(self.max_latency.map_or(latency, |max| max.max(latency)));
+        }
+    }
+}
+
+/// Open subscription, as listed by [`Client::active_subscriptions`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubscriptionInfo {
+    /// Identifier unique among the subscriptions of a client and its clones
+    pub id: u64,
+    /// Topic pattern the subscription was opened with
+    ///
+    /// The first of `topics` for a subscription from [`Client::subscribe_many`].
+    pub topic: Vec<u8>,
+    /// Every topic the subscription carries
+    pub topics: Vec<Vec<u8>>,
+    /// Highest seq per sender the subscription resumes after on `topic`
+    ///
+    /// Empty unless the client restored a session with offsets for the topic.
+    pub start_position: HashMap<Vec<u8>, u64>,
+    /// Envelopes delivered to the consumer so far
+    pub messages_received: u64,
+    /// When the subscription was opened
+    pub since: SystemTime,
+}
+
+/// Subscriptions open on a client and its clones
+#[derive(Default)]
+pub(crate) struct ActiveSubscriptions {
+    next_id: AtomicU64,
+    open: Mutex<BTreeMap<u64, SubscriptionInfo>>,
+    /// Set by [`Client::close`]; every subscription ends when next polled
+    closed: AtomicBool,
+    /// Wakers of the subscriptions waiting for envelopes, by id
+    wakers: Mutex<HashMap<u64, Waker>>,
+}
+
+impl ActiveSubscriptions {
+    /// End every subscription, returning how many were open
+    pub(crate) fn close_all(&self) -> usize {
+        self.closed.store(true, Ordering::SeqCst);
+        let wakers = std::mem::take(&mut *self.wakers.lock().unwrap());
+        for waker in wakers.into_values() {
+            waker.wake();
+        }
+        self.open.lock().unwrap().len()
+    }
+}
+
+/// Listing of a [`Subscription`] in [`ActiveSubscriptions`], removed on drop
+struct Registration {
+    active: Arc<ActiveSubscriptions>,
+    id: u64,
+}
+
+impl Registration {
+    fn new(
+        active: &Arc<ActiveSubscriptions>,
+        topics: &[Vec<u8>],
+        start: &HashMap<Vec<u8>, u64>,
+    ) -> Self {
+        let id = active.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let info = SubscriptionInfo {
+            id,
+            topic: topics[0].clone(),
+            topics: topics.to_vec(),
+            start_position: start.clone(),
+            messages_received: 0,
+            since: SystemTime::now(),
+        };
+        active.open.lock().unwrap().insert(id, info);
+        Self {
+            active: active.clone(),
+            id,
+        }
+    }
+
+    fn received(&self) {
+        if let Some(info) = self.active.open.lock().unwrap().get_mut(&self.id) {
+            info.messages_received += 1;
+        }
+    }
+
+    /// Whether the client was closed, arranging to be woken when it is
+    fn closed(&self, cx: &Context<'_>) -> bool {
+        // Checked under the lock, so close_all either sees this waker or is seen
+        let mut wakers = self.active.wakers.lock().unwrap();
+        if self.active.closed.load(Ordering::SeqCst) {
+            return true;
+        }
+        match wakers.get_mut(&self.id) {
+            Some(waker) => waker.clone_from(cx.waker()),
+            None => {
+                wakers.insert(self.id, cx.waker().clone());
+            }
+        }
+        false
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.active.open.lock().unwrap().remove(&self.id);
+        self.active.wakers.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Time between the sender's `timestamp_ms` and `received_at`
+///
+/// Returns `None` for envelopes without a timestamp. A timestamp ahead of
+/// `received_at` (clock skew) yields zero rather than a negative latency.
+pub fn end_to_end_latency(envelope: &Envelope, received_at: SystemTime) -> Option<Duration> {
+    if envelope.timestamp_ms == 0 {
+        return None;
+    }
+    let sent_at = UNIX_EPOCH + Duration::from_millis(envelope.timestamp_ms);
+    Some(received_at.duration_since(sent_at).unwrap_or_default())
+}
+
+/// Shard a sender's envelopes are delivered on when a topic is split `shard_count` ways
+///
+/// Computed as the first four bytes of `blake3(pubkey)`, little-endian, modulo
+/// `shard_count`, so every envelope from one sender lands on the same shard.
+pub fn shard_for(pubkey: &[u8], shard_count: u32) -> u32 {
+    assert!(shard_count > 0, "shard_count must be non-zero");
+    let hash = blake3::hash(pubkey);
+    u32::from_le_bytes(hash.as_bytes()[..4].try_into().unwrap()) % shard_count
+}
+
+/// Trailer naming why the node ended a subscription on purpose
+pub const END_REASON_METADATA: &str = "securefabric-end-reason";
+
+/// Why the node ended a subscription, from the [`END_REASON_METADATA`] trailer
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum EndReason {
+    /// The node evicted this consumer, `evicted`
+    Evicted,
+    /// The topic was deleted, `topic_deleted`
+    TopicDeleted,
+    /// The topic's consumers were rebalanced onto other streams, `rebalanced`
+    Rebalanced,
+    /// A reason this SDK does not know
+    Other(String),
+}
+
+impl EndReason {
+    /// Parse the value of the [`END_REASON_METADATA`] trailer
+    pub fn from_wire(reason: &str) -> Self {
+        match reason {
+            "evicted" => Self::Evicted,
+            "topic_deleted" => Self::TopicDeleted,
+            "rebalanced" => Self::Rebalanced,
+            other => Self::Other(other.to_string()),
+        }
+    }
+
+    /// Value of the [`END_REASON_METADATA`] trailer
+    pub fn wire_name(&self) -> &str {
+        match self {
+            Self::Evicted => "evicted",
+            Self::TopicDeleted => "topic_deleted",
+            Self::Rebalanced => "rebalanced",
+            Self::Other(reason) => reason,
+        }
+    }
+}
+
+/// Intentional end of a subscription by the node, from [`Subscription::stream_end`]
+///
+/// Resubscribing right away would only be ended again, so consumers should
+/// not reconnect after one unless the reason says otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamEnd {
+    pub reason: EndReason,
+}
+
+/// Per-envelope errors yielded by [`Client::subscribe_decrypted`]
+#[derive(Debug, thiserror::Error)]
+pub enum DecryptError {
+    /// The envelope signature does not verify, so it was not decrypted
+    #[error("invalid signature on {msg_id}")]
+    BadSignature { msg_id: String },
+
+    /// The envelope is authentic but could not be decrypted with the configured key
+    #[error("cannot decrypt {msg_id}: {reason}")]
+    Decrypt { msg_id: String, reason: String },
+
+    /// The envelope decrypted, but its nonce was already seen under its key
+    ///
+    /// Only yielded with [`NonceReuseAction::Reject`](crate::nonce::NonceReuseAction::Reject).
+    #[error("{msg_id} reuses a nonce")]
+    NonceReused { msg_id: String },
+
+    /// The underlying subscription failed
+    #[error("subscription error: {0}")]
+    Transport(Box<tonic::Status>),
+}
+
+impl From<tonic::Status> for DecryptError {
+    fn from(status: tonic::Status) -> Self {
+        Self::Transport(Box::new(status))
+    }
+}
+
+/// Per-envelope errors yielded by [`Client::subscribe_text`]
+#[derive(Debug, thiserror::Error)]
+pub enum TextError {
+    /// The payload is not valid UTF-8
+    #[error("payload of {msg_id} is not valid UTF-8 after {valid_up_to} bytes")]
+    NotUtf8 { msg_id: String, valid_up_to: usize },
+
+    /// The underlying subscription failed
+    #[error("subscription error: {0}")]
+    Transport(Box<tonic::Status>),
+}
+
+impl From<tonic::Status> for TextError {
+    fn from(status: tonic::Status) -> Self {
+        Self::Transport(Box::new(status))
+    }
+}
+
+/// Where a subscription's envelopes come from
+pub(crate) enum Source {
+    Envelopes(Streaming<Envelope>),
+    /// `SubscribeBatched`, with the undelivered rest of the current batch
+    Batches(Streaming<EnvelopeBatch>, VecDeque<Envelope>),
+    /// The stream was dropped when the client closed
+    Closed,
+}
+
+impl Source {
+    /// Next envelope, in the order the node sent them
+    fn poll_envelope(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Envelope, Status>>> {
+        match self {
+            Self::Envelopes(stream) => Pin::new(stream).poll_next(cx),
+            Self::Batches(stream, rest) => loop {
+                if let Some(envelope) = rest.pop_front() {
+                    return Poll::Ready(Some(Ok(envelope)));
+                }
+                match ready!(Pin::new(&mut *stream).poll_next(cx)) {
+                    Some(Ok(batch)) => rest.extend(batch.envelopes),
+                    Some(Err(status)) => return Poll::Ready(Some(Err(status))),
+                    None => return Poll::Ready(None),
+                }
+            },
+            Self::Closed => Poll::Ready(None),
+        }
+    }
+
+    /// Next batch as the node sent it; unbatched envelopes come one per batch
+    fn poll_batch(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Vec<Envelope>, Status>>> {
+        let batch = match self {
+            Self::Envelopes(stream) => match ready!(Pin::new(stream).poll_next(cx)) {
+                Some(Ok(envelope)) => vec![envelope],
+                Some(Err(status)) => return Poll::Ready(Some(Err(status))),
+                None => return Poll::Ready(None),
+            },
+            Self::Batches(_, rest) if !rest.is_empty() => rest.drain(..).collect(),
+            Self::Batches(stream, _) => match ready!(Pin::new(stream).poll_next(cx)) {
+                Some(Ok(batch)) => batch.envelopes,
+                Some(Err(status)) => return Poll::Ready(Some(Err(status))),
+                None => return Poll::Ready(None),
+            },
+            Self::Closed => return Poll::Ready(None),
+        };
+        Poll::Ready(Some(Ok(batch)))
+    }
+}
+
+impl Source {
+    /// Why the node ended the stream, once it has ended with an OK status
+    fn stream_end(&mut self) -> Option<StreamEnd> {
+        let trailers = match self {
+            Self::Envelopes(stream) => stream.trailers().now_or_never(),
+            Self::Batches(stream, _) => stream.trailers().now_or_never(),
+            Self::Closed => return None,
+        };
+        let trailers = trailers?.ok()??;
+        let reason = trailers.get(END_REASON_METADATA)?.to_str().ok()?;
+        Some(StreamEnd {
+            reason: EndReason::from_wire(reason),
+        })
+    }
+}
+
+impl From<Streaming<Envelope>> for Source {
+    fn from(stream: Streaming<Envelope>) -> Self {
+        Self::Envelopes(stream)
+    }
+}
+
+/// Stream of envelopes from [`Client::subscribe`]
+///
+/// Yields the same items as the underlying gRPC stream while recording
+/// consumption into the client's [`SubscriptionStats`] for the topic.
+/// Envelopes are yielded unverified, but each is checked before it moves
+/// its sender's [consumed seq](SubscriptionStats::last_seq).
+pub struct Subscription {
+    inner: Source,
+    topic: Vec<u8>,
+    topics: Vec<Vec<u8>>,
+    stats: StatsRegistry,
+    instruments: Instruments,
+    /// Decides which envelopes may move the consumed seq
+    verifier: Verifier,
+    /// Resume offsets per topic, then per sender
+    skip_through: HashMap<Vec<u8>, HashMap<Vec<u8>, u64>>,
+    credits: Option<Credits>,
+    pacing: Option<Pacing>,
+    post_receive: PostReceive,
+    registration: Option<Registration>,
+    /// Set once the stream has ended
+    end: Option<Option<StreamEnd>>,
+}
+
+impl Subscription {
+    pub(crate) fn new(
+        inner: impl Into<Source>,
+        topic: Vec<u8>,
+        stats: StatsRegistry,
+        instruments: Instruments,
+        verifier: Verifier,
+    ) -> Self {
+        Self {
+            inner: inner.into(),
+            topics: vec![topic.clone()],
+            topic,
+            stats,
+            instruments,
+            verifier,
+            skip_through: HashMap::new(),
+            credits: None,
+            pacing: None,
+            post_receive: PostReceive::default(),
+            registration: None,
+            end: None,
+        }
+    }
+
+    /// Grant credits back to the node as envelopes are consumed
+    pub(crate) fn with_credits(mut self, credits: Credits) -> Self {
+        self.credits = Some(credits);
+        self
+    }
+
+    /// Space delivered envelopes out on the client side
+    pub(crate) fn paced(mut self, pacing: Pacing) -> Self {
+        self.pacing = Some(pacing);
+        self
+    }
+
+    /// Wait until pacing, if any, lets the next envelope be read
+    fn poll_paced(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        match &mut self.pacing {
+            Some(pacing) => pacing.poll_ready(cx),
+            None => Poll::Ready(()),
+        }
+    }
+
+    /// Start the pacing interval after `count` envelopes were delivered
+    fn paced_out(&mut self, count: usize) {
+        if let Some(pacing) = &mut self.pacing {
+            pacing.delivered(count);
+        }
+    }
+
+    /// Carry envelopes of every topic in `topics`, the first being [`Subscription::topic`]
+    ///
+    /// Stats and resume offsets are then kept per envelope topic.
+    pub(crate) fn merging(mut self, topics: Vec<Vec<u8>>) -> Self {
+        self.topics = topics;
+        self
+    }
+
+    /// Drop envelopes at or below a per-sender seq, used when resuming a session
+    pub(crate) fn skipping_through(self, offsets: HashMap<Vec<u8>, u64>) -> Self {
+        let topic = self.topic.clone();
+        self.skipping_through_on(topic, offsets)
+    }
+
+    /// [`Subscription::skipping_through`] for one topic of a merged subscription
+    pub(crate) fn skipping_through_on(
+        mut self,
+        topic: Vec<u8>,
+        offsets: HashMap<Vec<u8>, u64>,
+    ) -> Self {
+        if !offsets.is_empty() {
+            self.skip_through.insert(topic, offsets);
+        }
+        self
+    }
+
+    /// List the subscription in `active` until it is dropped
+    ///
+    /// Call after [`Subscription::skipping_through`] so the resume offsets are
+    /// reported as its start position.
+    pub(crate) fn tracked(mut self, active: &Arc<ActiveSubscriptions>) -> Self {
+        let start = self.skip_through.get(&self.topic).cloned();
+        self.registration = Some(Registration::new(
+            active,
+            &self.topics,
+            &start.unwrap_or_default(),
+        ));
+        self
+    }
+
+    /// Run post-receive hooks on envelopes once they are recorded as consumed
+    pub(crate) fn with_post_receive(mut self, post_receive: PostReceive) -> Self {
+        self.post_receive = post_receive;
+        self
+    }
+
+    /// Topic pattern this subscription was opened with
+    ///
+    /// The first topic for a subscription from [`Client::subscribe_many`].
+    pub fn topic(&self) -> &[u8] {
+        &self.topic
+    }
+
+    /// Every topic this subscription carries
+    pub fn topics(&self) -> &[Vec<u8>] {
+        &self.topics
+    }
+
+    /// Topic pattern as a string, or `None` if it is not valid UTF-8
+    ///
+    /// Always `Some` for clients with
+    /// [`Client::with_strict_utf8_topics`] enabled.
+    pub fn topic_str(&self) -> Option<&str> {
+        std::str::from_utf8(&self.topic).ok()
+    }
+
+    /// Why the node ended the stream, if it ended it on purpose
+    ///
+    /// `None` while the stream is open, and after it ended with an error or
+    /// without a reason.
+    pub fn stream_end(&self) -> Option<&StreamEnd> {
+        self.end.as_ref()?.as_ref()
+    }
+
+    /// Drop the stream if the client was closed, returning whether it was
+    ///
+    /// Dropping the stream cancels the call on the node.
+    fn close_if_client_closed(&mut self, cx: &Context<'_>) -> bool {
+        if !self
+            .registration
+            .as_ref()
+            .is_some_and(|registration| registration.closed(cx))
+        {
+            return false;
+        }
+        self.inner = Source::Closed;
+        self.registration = None;
+        self.end = Some(None);
+        true
+    }
+
+    /// Record the end of the stream, reading the reason from its trailers
+    fn ended(&mut self) {
+        if self.end.is_none() {
+            self.end = Some(self.inner.stream_end());
+        }
+    }
+
+    /// Yield SDK errors instead of gRPC statuses
+    pub fn typed(self) -> TypedSubscription {
+        TypedSubscription { inner: self }
+    }
+
+    /// Yield envelopes in the batches the node pushed them in
+    ///
+    /// For subscriptions from [`Client::subscribe_batched`]; other
+    /// subscriptions yield batches of one. Envelopes are skipped, recorded and
+    /// hooked exactly as when yielded one by one, and batches left empty by
+    /// that are not yielded. Envelopes of a batch partly consumed before the
+    /// call come first, as a batch of their own. A throttled subscription
+    /// waits out the interval of every envelope of a batch before the next.
+    pub fn raw_batches(self) -> RawBatches {
+        RawBatches { inner: self }
+    }
+
+    /// Account for an envelope from the node, returning it if it is delivered
+    fn deliver(&mut self, envelope: Envelope) -> Option<Envelope> {
+        if let Some(credits) = &mut self.credits {
+            credits.consumed();
+        }
+        // A merged subscription accounts each envelope to the topic it came from
+        let topic = match self.topics.len() {
+            1 => self.topic.clone(),
+            _ => envelope.topic.clone().into_bytes(),
+        };
+        if self
+            .skip_through
+            .get(&topic)
+            .and_then(|offsets| offsets.get(&envelope.pubkey))
+            .is_some_and(|&through| envelope.seq <= through)
+        {
+            return None;
+        }
+        let envelope_len = self
+            .instruments
+            .size_histograms()
+            .then(|| envelope.encoded_len());
+        self.instruments.on_received(envelope_len);
+        let verified_seq = self.verifier.verified_seq(&envelope);
+        self.stats.lock().unwrap().entry(topic).or_default().record(
+            &envelope,
+            verified_seq,
+            envelope_len,
+            SystemTime::now(),
+        );
+        if let Some(registration) = &self.registration {
+            registration.received();
+        }
+        self.post_receive.apply(envelope)
+    }
+}
+
+impl Stream for Subscription {
+    type Item = Result<Envelope, Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.close_if_client_closed(cx) {
+            return Poll::Ready(None);
+        }
+        ready!(self.poll_paced(cx));
+        loop {
+            let envelope = match self.inner.poll_envelope(cx) {
+                Poll::Ready(Some(Ok(envelope))) => envelope,
+                Poll::Ready(None) => {
+                    self.ended();
+                    return Poll::Ready(None);
+                }
+                poll => return poll,
+            };
+            if let Some(envelope) = self.deliver(envelope) {
+                self.paced_out(1);
+                return Poll::Ready(Some(Ok(envelope)));
+            }
+        }
+    }
+}
+
+/// [`Subscription`] yielding whole batches, from [`Subscription::raw_batches`]
+pub struct RawBatches {
+    inner: Subscription,
+}
+
+impl RawBatches {
+    /// Topic pattern this subscription was opened with
+    pub fn topic(&self) -> &[u8] {
+        self.inner.topic()
+    }
+
+    /// Why the node ended the stream, as [`Subscription::stream_end`]
+    pub fn stream_end(&self) -> Option<&StreamEnd> {
+        self.inner.stream_end()
+    }
+}
+
+impl Stream for RawBatches {
+    type Item = Result<Vec<Envelope>, Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let subscription = &mut self.inner;
+        if subscription.close_if_client_closed(cx) {
+            return Poll::Ready(None);
+        }
+        ready!(subscription.poll_paced(cx));
+        loop {
+            let batch = match subscription.inner.poll_batch(cx) {
+                Poll::Ready(Some(Ok(batch))) => batch,
+                Poll::Ready(None) => {
+                    subscription.ended();
+                    return Poll::Ready(None);
+                }
+                poll => return poll,
+            };
+            let delivered: Vec<Envelope> = batch
+                .into_iter()
+                .filter_map(|envelope| subscription.deliver(envelope))
+                .collect();
+            if !delivered.is_empty() {
+                subscription.paced_out(delivered.len());
+                return Poll::Ready(Some(Ok(delivered)));
+            }
+        }
+    }
+}
+
+/// [`Subscription`] whose errors are [`Error`]s, from [`Client::subscribe_typed`]
+///
+/// A `TryStream` of envelopes, so it composes with `TryStreamExt` and `?`
+/// like the results of sends do. Statuses from the node become [`Error::Rpc`].
+pub struct TypedSubscription {
+    inner: Subscription,
+}
+
+impl TypedSubscription {
+    /// Topic pattern this subscription was opened with
+    pub fn topic(&self) -> &[u8] {
+        self.inner.topic()
+    }
+
+    /// Why the node ended the stream, as [`Subscription::stream_end`]
+    pub fn stream_end(&self) -> Option<&StreamEnd> {
+        self.inner.stream_end()
+    }
+
+    /// The underlying subscription, yielding raw statuses
+    pub fn into_inner(self) -> Subscription {
+        self.inner
+    }
+}
+
+impl Stream for TypedSubscription {
+    type Item = Result<Envelope, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.inner)
+            .poll_next(cx)
+            .map(|item| item.map(|item| item.map_err(Error::from)))
+    }
+}
+
+impl Client {
+    /// Subscriptions opened through this client or its clones and not yet dropped
+    ///
+    /// Ordered by when they were opened. Streams built on a subscription,
+    /// such as [`Client::subscribe_decrypted`], are listed under the
+    /// subscription they wrap.
+    pub fn active_subscriptions(&self) -> Vec<SubscriptionInfo> {
+        self.active_subscriptions
+            .open
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Consumption statistics for a topic, if anything has been received on it
+    pub fn subscription_stats(&self, topic: &[u8]) -> Option<SubscriptionStats> {
+        self.stats.lock().unwrap().get(topic).cloned()
+    }
+
+    /// How many messages from `sender` on `topic` have not been consumed yet
+    ///
+    /// Compares the node's latest seq for the sender (via the `Head` RPC) with
+    /// the highest verified seq this client has consumed from that sender. Fails with
+    /// [`Error::Unsupported`](crate::Error::Unsupported) on nodes without it.
+    /// An Ed25519 key converts with `.into()`.
+    pub async fn lag(&mut self, topic: &[u8], sender: &VerifyingKey) -> Result<u64> {
+        self.check_topic(topic)?;
+        self.require(Feature::Head).await?;
+        let pubkey = sender.to_bytes();
+        let req = self.authorized(HeadReq {
+            topic: topic.to_vec(),
+            pubkey: pubkey.clone(),
+        })?;
+        let latest = self
+            .inner
+            .head(req)
+            .await
+            .map_err(rpc_error(Feature::Head))
+            .context("query topic head")?
+            .into_inner()
+            .latest_seq;
+
+        let consumed = self
+            .subscription_stats(topic)
+            .and_then(|stats| stats.last_seq.get(&pubkey).copied())
+            .unwrap_or(0);
+        Ok(latest.saturating_sub(consumed))
+    }
+
+    /// Subscribe to a topic, yielding [`Error`] instead of gRPC statuses
+    ///
+    /// Like [`Client::subscribe`], except that a node refusing the
+    /// subscription also fails with [`Error::Rpc`].
+    pub async fn subscribe_typed(&mut self, topic: &[u8]) -> Result<TypedSubscription> {
+        match self.subscribe(topic).await {
+            Ok(subscription) => Ok(subscription.typed()),
+            Err(error) => {
+                match error.downcast_ref::<Status>() {
+                    Some(status) => Err(anyhow::Error::from(Error::from(status.clone()))
+                        .context("subscribe to topic")),
+                    None => Err(error),
+                }
+            }
+        }
+    }
+
+    /// Subscribe to a topic, yielding envelopes with their payloads decrypted
+    ///
+    /// Each envelope is verified and decrypted with the key from
+    /// [`Client::with_encryption`], in the inverse of the order it was signed in:
+    /// ciphertext-signed envelopes are verified before decryption, plaintext-signed
+    /// ones after. Envelopes that fail either step are yielded as errors and the
+    /// stream continues. Plaintext envelopes are rejected rather than passed
+    /// through. The yielded envelopes carry the plaintext in `payload`, so a
+    /// ciphertext signature over them no longer verifies. With
+    /// [`Client::with_nonce_tracking`], repeated nonces may be yielded as
+    /// [`DecryptError::NonceReused`].
+    ///
+    /// Fails up front if no encryption key is configured.
+    pub async fn subscribe_decrypted(
+        &mut self,
+        topic: &[u8],
+    ) -> Result<BoxStream<'static, Result<Envelope, DecryptError>>> {
+        let topic_key = self
+            .encryption
+            .clone()
+            .context("No encryption key configured")?;
+        let keyring = self.keyring.clone();
+        let mode = self.verify_mode;
+        let cache = self.negative_cache.clone();
+        let peer = self.peer_identity;
+        let post_receive = self.post_receive.clone();
+        let nonces = self.nonces.clone();
+        let inner = self.subscribe_verifying(topic).await?;
+
+        let decrypted = inner.map(move |item| {
+            let mut envelope = item?;
+            let verified = |signed_payload: &[u8]| {
+                verify_trusted_over(
+                    keyring.as_ref(),
+                    mode,
+                    cache.as_ref(),
+                    peer.as_ref(),
+                    &envelope,
+                    signed_payload,
+                )
+                .unwrap_or(false)
+            };
+            let opened = match SignOrder::of(&envelope) {
+                SignOrder::EncryptThenSign if !verified(&envelope.payload) => None,
+                SignOrder::EncryptThenSign => Some(topic_key.open(&envelope)),
+                // The signature covers the plaintext, so decrypt before verifying
+                SignOrder::SignThenEncrypt => match topic_key.open(&envelope) {
+                    Ok(plaintext) if !verified(&plaintext) => None,
+                    opened => Some(opened),
+                },
+            };
+            match opened {
+                None => Err(DecryptError::BadSignature {
+                    msg_id: envelope.msg_id,
+                }),
+                Some(Ok(_)) if nonces.observe(&envelope).is_err() => {
+                    Err(DecryptError::NonceReused {
+                        msg_id: envelope.msg_id,
+                    })
+                }
+                Some(Ok(plaintext)) => {
+                    envelope.payload = plaintext;
+                    Ok(envelope)
+                }
+                Some(Err(error)) => Err(DecryptError::Decrypt {
+                    msg_id: envelope.msg_id,
+                    reason: error.to_string(),
+                }),
+            }
+        });
+        let stream = decrypted.filter_map(move |item| {
+            futures::future::ready(match item {
+                Ok(envelope) => post_receive.after_verification(envelope).map(Ok),
+                Err(error) => Some(Err(error)),
+            })
+        });
+
+        Ok(stream.boxed())
+    }
+
+    /// Subscribe to a topic, yielding each envelope with its payload as text
+    ///
+    /// A payload that is not valid UTF-8 is yielded as [`TextError::NotUtf8`]
+    /// and the stream continues; nothing is replaced lossily. The envelope is
+    /// passed through unchanged, so it can still be verified. Like
+    /// [`Client::subscribe`], envelopes are not verified here.
+    pub async fn subscribe_text(
+        &mut self,
+        topic: &[u8],
+    ) -> Result<BoxStream<'static, Result<(Envelope, String), TextError>>> {
+        let inner = self.subscribe(topic).await?;
+
+        let stream = inner.map(|item| {
+            let envelope = item?;
+            match std::str::from_utf8(&envelope.payload) {
+                Ok(text) => {
+                    let text = text.to_owned();
+                    Ok((envelope, text))
+                }
+                Err(error) => Err(TextError::NotUtf8 {
+                    msg_id: envelope.msg_id,
+                    valid_up_to: error.valid_up_to(),
+                }),
+            }
+        });
+
+        Ok(stream.boxed())
+    }
+
+    /// Subscribe to a topic, receiving envelopes from the node in batches
+    ///
+    /// The node pushes arrays of envelopes to save per-message framing on
+    /// high-volume topics. The returned subscription flattens them into the
+    /// envelopes of [`Client::subscribe`], in the same order; call
+    /// [`Subscription::raw_batches`] on it to consume the batches instead.
+    /// Fails with [`Error::Unsupported`] on nodes without `SubscribeBatched`.
+    pub async fn subscribe_batched(&mut self, topic: &[u8]) -> Result<Subscription> {
+        self.check_topic(topic)?;
+        self.require(Feature::BatchedSubscribe).await?;
+        let req = self.authorized(SubscribeReq {
+            topic: topic.to_vec(),
+            ..Default::default()
+        })?;
+
+        let mut grpc = self.subscribe_grpc();
+        grpc.ready().await.context("subscribe to topic")?;
+        let path = PathAndQuery::from_static("/securefabric.FabricNode/SubscribeBatched");
+        let stream = grpc
+            .server_streaming(
+                req,
+                path,
+                codec::SubscribeCodec::<SubscribeReq, codec::BatchDecoder>::default(),
+            )
+            .await
+            .map_err(rpc_error(Feature::BatchedSubscribe))
+            .context("subscribe to topic")?
+            .into_inner();
+
+        let resume = self.resume_offsets.get(topic).cloned().unwrap_or_default();
+        Ok(Subscription::new(
+            Source::Batches(stream, VecDeque::new()),
+            topic.to_vec(),
+            self.stats.clone(),
+            self.instruments.clone(),
+            self.verifier(),
+        )
+        .skipping_through(resume)
+        .tracked(&self.active_subscriptions)
+        .with_post_receive(self.post_receive.clone()))
+    }
+
+    /// Subscribe to several topics over one stream
+    ///
+    /// Sends every topic in a single `SubscribeReq` and yields the envelopes
+    /// of all of them, each with the `topic` it was published on. Envelopes of
+    /// one topic keep their order; those of different topics are interleaved
+    /// as the node forwards them. Stats and resume offsets are kept per topic,
+    /// and [`Subscription::topic`] is the first topic. Fails with
+    /// [`Error::Unsupported`] on nodes without multi-topic subscriptions.
+    pub async fn subscribe_many(&mut self, topics: &[impl AsRef<[u8]>]) -> Result<Subscription> {
+        anyhow::ensure!(!topics.is_empty(), "Subscription needs at least one topic");
+        let topics: Vec<Vec<u8>> = topics.iter().map(|t| t.as_ref().to_vec()).collect();
+        for topic in &topics {
+            self.check_topic(topic)?;
+        }
+        self.require(Feature::MultiTopic).await?;
+        let req = self.authorized(SubscribeReq {
+            topics: topics.clone(),
+            ..Default::default()
+        })?;
+
+        let mut grpc = self.subscribe_grpc();
+        grpc.ready().await.context("subscribe to topics")?;
+        let path = PathAndQuery::from_static("/securefabric.FabricNode/Subscribe");
+        let stream = grpc
+            .server_streaming(req, path, codec::SubscribeCodec::<SubscribeReq>::default())
+            .await
+            .map_err(rpc_error(Feature::MultiTopic))
+            .context("subscribe to topics")?
+            .into_inner();
+
+        let mut subscription = Subscription::new(
+            stream,
+            topics[0].clone(),
+            self.stats.clone(),
+            self.instruments.clone(),
+            self.verifier(),
+        )
+        .merging(topics.clone());
+        for topic in topics {
+            let resume = self.resume_offsets.get(&topic).cloned().unwrap_or_default();
+            subscription = subscription.skipping_through_on(topic, resume);
+        }
+        Ok(subscription
+            .tracked(&self.active_subscriptions)
+            .with_post_receive(self.post_receive.clone()))
+    }
+
+    /// Subscribe to a topic split across `shards` parallel streams
+    ///
+    /// Opens one stream per shard over the shared connection and merges them.
+    /// Each item carries the index of the shard it arrived on. Envelopes within a
+    /// shard, and therefore from any one sender (see [`shard_for`]), keep their
+    /// order; items from different shards are interleaved as they arrive. The
+    /// merged stream ends once every shard stream has ended. Fails with
+    /// [`Error::Unsupported`] on nodes without sharded subscriptions.
+    pub async fn subscribe_sharded(
+        &mut self,
+        topic: &[u8],
+        shards: u32,
+    ) -> Result<BoxStream<'static, (u32, Result<Envelope, Status>)>> {
+        anyhow::ensure!(shards > 0, "Shard count must be non-zero");
+        self.require(Feature::Shards).await?;
+
+        let mut streams = Vec::with_capacity(shards as usize);
+        for shard in 0..shards {
+            let subscription = self
+                .open_subscription(SubscribeReq {
+                    topic: topic.to_vec(),
+                    shard,
+                    shard_count: shards,
+                    ..Default::default()
+                })
+                .await
+                .with_context(|| format!("subscribe to shard {shard}"))?;
+            streams.push(subscription.map(move |item| (shard, item)).boxed());
+        }
+
+        Ok(futures::stream::select_all(streams).boxed())
+    }
+}
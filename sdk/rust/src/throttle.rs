@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Rate-limited subscriptions
+//!
+//! A subscriber that processes envelopes slowly would rather have the node
+//! hold them back than buffer or drop them itself. [`Client::subscribe_throttled`]
+//! asks the node to pace the stream through `SubscribeReq.max_rate`, and paces
+//! delivery client side as well, so the rate holds on nodes that cannot pace.
+
+use crate::capabilities::Feature;
+use crate::pb::SubscribeReq;
+use crate::subscription::Subscription;
+use crate::Client;
+use anyhow::Result;
+use futures::FutureExt;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+use tokio::time::{Instant, Sleep};
+use tonic::{Code, Status};
+
+impl Client {
+    /// Subscribe to a topic, receiving at most `messages_per_sec` envelopes per second
+    ///
+    /// The rate is sent to the node so it holds envelopes back, unless its
+    /// [capabilities](Client::capabilities) lack rate limiting. If the node
+    /// answers `UNIMPLEMENTED`, the subscription is reopened without it. Either
+    /// way the returned subscription spaces envelopes at least
+    /// `1 / messages_per_sec` apart, so a node that ignores the rate cannot
+    /// overrun the consumer. Fails if `messages_per_sec` is zero.
+    pub async fn subscribe_throttled(
+        &mut self,
+        topic: &[u8],
+        messages_per_sec: u32,
+    ) -> Result<Subscription> {
+        anyhow::ensure!(
+            messages_per_sec > 0,
+            "rate must be at least 1 message per second"
+        );
+        let pacing = self.capabilities().await.map_or(true, |caps| {
+            caps.supports(Feature::RateLimit) != Some(false)
+        });
+        let req = SubscribeReq {
+            topic: topic.to_vec(),
+            max_rate: messages_per_sec,
+            ..Default::default()
+        };
+        let opened = if pacing {
+            self.open_subscription(req).await
+        } else {
+            self.subscribe(topic).await
+        };
+        let subscription = match opened {
+            Ok(subscription) => subscription,
+            Err(error)
+                if error
+                    .downcast_ref::<Status>()
+                    .is_some_and(|status| status.code() == Code::Unimplemented) =>
+            {
+                self.subscribe(topic).await?
+            }
+            Err(error) => return Err(error),
+        };
+        Ok(subscription.paced(Pacing::new(messages_per_sec)))
+    }
+}
+
+/// Client-side spacing of a subscription's envelopes
+///
+/// The first envelope passes straight through. After that the subscription
+/// does not read from the node until the interval since the previous delivery
+/// has elapsed, so envelopes it is not ready for stay with the node.
+pub(crate) struct Pacing {
+    interval: Duration,
+    sleep: Pin<Box<Sleep>>,
+    waiting: bool,
+}
+
+impl Pacing {
+    /// Space envelopes `1 / max_rate` seconds apart
+    pub(crate) fn new(max_rate: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / max_rate,
+            sleep: Box::pin(tokio::time::sleep(Duration::ZERO)),
+            waiting: false,
+        }
+    }
+
+    /// Wait until the next envelope may be read
+    pub(crate) fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.waiting {
+            ready!(self.sleep.poll_unpin(cx));
+            self.waiting = false;
+        }
+        Poll::Ready(())
+    }
+
+    /// Hold the next read back after `count` envelopes were delivered at once
+    pub(crate) fn delivered(&mut self, count: usize) {
+        let count = u32::try_from(count).unwrap_or(u32::MAX);
+        let wait = self.interval.saturating_mul(count);
+        self.sleep.as_mut().reset(Instant::now() + wait);
+        self.waiting = true;
+    }
+}
@@ -0,0 +1,123 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Adaptive send timeouts
+//!
+//! A fixed send timeout is either too tight on a slow network or too loose on
+//! a fast one. [`Client::with_adaptive_timeout`] instead tracks the round-trip
+//! time of sends with exponential moving averages of the mean and the mean
+//! deviation, as TCP does for its retransmission timeout (RFC 6298), and gives
+//! each send a deadline that is a multiple of `mean + 4 * deviation`, an
+//! estimate of a high quantile of recent latencies.
+
+use crate::Client;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Settings for [`Client::with_adaptive_timeout`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveConfig {
+    /// Deadline used until the first send completes (default: 5s)
+    pub initial: Duration,
+    /// Shortest deadline ever given (default: 50ms)
+    pub min: Duration,
+    /// Longest deadline ever given (default: 30s)
+    pub max: Duration,
+    /// Deadline as a multiple of the latency estimate (default: 1.5)
+    pub multiplier: f64,
+    /// Weight of each new sample in the moving averages, in `(0, 1]` (default: 0.125)
+    pub smoothing: f64,
+}
+
+impl Default for AdaptiveConfig {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(5),
+            min: Duration::from_millis(50),
+            max: Duration::from_secs(30),
+            multiplier: 1.5,
+            smoothing: 0.125,
+        }
+    }
+}
+
+/// Moving-average latency estimate behind an adaptive timeout
+#[derive(Debug, Clone)]
+pub struct LatencyEstimator {
+    config: AdaptiveConfig,
+    /// Smoothed mean and mean deviation in seconds, once a sample was recorded
+    moments: Option<(f64, f64)>,
+}
+
+impl LatencyEstimator {
+    /// Estimator with no samples yet
+    ///
+    /// Panics if `min` exceeds `max`, or `multiplier` or `smoothing` is out
+    /// of range.
+    pub fn new(config: AdaptiveConfig) -> Self {
+        assert!(config.min <= config.max, "min timeout exceeds max timeout");
+        assert!(config.multiplier > 0.0, "multiplier must be positive");
+        assert!(
+            config.smoothing > 0.0 && config.smoothing <= 1.0,
+            "smoothing must be in (0, 1]"
+        );
+        Self {
+            config,
+            moments: None,
+        }
+    }
+
+    /// Fold one observed latency into the estimate
+    pub fn record(&mut self, latency: Duration) {
+        let sample = latency.as_secs_f64();
+        let alpha = self.config.smoothing;
+        self.moments = Some(match self.moments {
+            None => (sample, sample / 2.0),
+            Some((mean, deviation)) => (
+                mean + alpha * (sample - mean),
+                deviation + alpha * ((sample - mean).abs() - deviation),
+            ),
+        });
+    }
+
+    /// Smoothed mean latency, if any sample was recorded
+    pub fn mean(&self) -> Option<Duration> {
+        self.moments.map(|(mean, _)| Duration::from_secs_f64(mean))
+    }
+
+    /// Deadline for the next call, within `min..=max`
+    pub fn timeout(&self) -> Duration {
+        let Some((mean, deviation)) = self.moments else {
+            return self.config.initial.clamp(self.config.min, self.config.max);
+        };
+        let estimate = mean + 4.0 * deviation;
+        Duration::try_from_secs_f64(self.config.multiplier * estimate)
+            .unwrap_or(self.config.max)
+            .clamp(self.config.min, self.config.max)
+    }
+}
+
+/// Latency estimate shared by a client and its clones
+pub(crate) type SharedEstimator = Arc<Mutex<LatencyEstimator>>;
+
+impl Client {
+    /// Give each send attempt a deadline derived from recent send latencies
+    ///
+    /// The round-trip time of every successful send attempt updates the
+    /// estimate shared by this client and its clones. An attempt that misses
+    /// its deadline fails the send with [`Error::Timeout`](crate::Error::Timeout)
+    /// without retrying, and counts as a sample of the deadline, so repeated
+    /// timeouts widen it. A batch from [`Client::send_batch`] is timed as one
+    /// send attempt.
+    /// Without this, sends wait as long as the transport allows.
+    pub fn with_adaptive_timeout(mut self, config: AdaptiveConfig) -> Self {
+        self.send_timeout = Some(Arc::new(Mutex::new(LatencyEstimator::new(config))));
+        self
+    }
+
+    /// Deadline the next send attempt will get, if adaptive timeouts are enabled
+    pub fn send_timeout(&self) -> Option<Duration> {
+        self.send_timeout
+            .as_ref()
+            .map(|estimator| estimator.lock().unwrap().timeout())
+    }
+}
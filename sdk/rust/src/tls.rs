@@ -0,0 +1,299 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! TLS settings for connecting to a node
+//!
+//! [`TlsConfig`] collects the trust roots used to validate the node's
+//! certificate and, for mutual TLS, the client identity presented to it.
+//! [`validate_identity`] checks that identity offline, so misconfigured
+//! certificates fail at startup instead of in the handshake. With the
+//! `dev-pki` feature, [`testing::generate_dev_pki`] issues throwaway
+//! certificates for local mutual TLS.
+
+#[cfg(feature = "dev-pki")]
+pub mod testing;
+
+use anyhow::{Context, Result};
+use rustls::pki_types::{CertificateDer, TrustAnchor, UnixTime};
+use std::fmt;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tonic::transport::{Certificate, ClientTlsConfig, Identity};
+use x509_cert::der::Decode;
+
+/// Trust roots and optional client identity for [`Client::connect_tls`](crate::Client::connect_tls)
+#[derive(Clone, Debug, Default)]
+pub struct TlsConfig {
+    ca_certificates: Vec<Certificate>,
+    native_roots: Vec<TrustAnchor<'static>>,
+    identity: Option<ClientIdentity>,
+    domain: Option<String>,
+}
+
+/// PEM-encoded client certificate chain and private key
+#[derive(Clone)]
+struct ClientIdentity {
+    cert_pem: Vec<u8>,
+    key_pem: Vec<u8>,
+}
+
+impl fmt::Debug for ClientIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientIdentity")
+            .field("cert_pem", &String::from_utf8_lossy(&self.cert_pem))
+            .field("key_pem", &"<redacted>")
+            .finish()
+    }
+}
+
+impl TlsConfig {
+    /// Empty configuration; add at least one trust root before connecting
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust the CA certificates in a PEM bundle
+    pub fn with_ca_pem(mut self, ca_pem: impl AsRef<[u8]>) -> Self {
+        self.ca_certificates
+            .push(Certificate::from_pem(ca_pem.as_ref()));
+        self
+    }
+
+    /// Trust the platform's native certificate store
+    ///
+    /// Honours `SSL_CERT_FILE` and `SSL_CERT_DIR` when set. Individual
+    /// certificates that fail to parse are skipped; fails only if no usable root
+    /// certificate is found.
+    pub fn with_native_roots(mut self) -> Result<Self> {
+        let loaded = rustls_native_certs::load_native_certs();
+        let anchors: Vec<_> = loaded
+            .certs
+            .iter()
+            .filter_map(|der| webpki::anchor_from_trusted_cert(der).ok())
+            .map(|anchor| anchor.to_owned())
+            .collect();
+
+        if anchors.is_empty() {
+            match loaded.errors.first() {
+                Some(error) => anyhow::bail!("No native root certificates found: {error}"),
+                None => anyhow::bail!("No native root certificates found"),
+            }
+        }
+
+        self.native_roots.extend(anchors);
+        Ok(self)
+    }
+
+    /// Present a client certificate for mutual TLS
+    pub fn with_identity(mut self, cert_pem: impl AsRef<[u8]>, key_pem: impl AsRef<[u8]>) -> Self {
+        self.identity = Some(ClientIdentity {
+            cert_pem: cert_pem.as_ref().to_vec(),
+            key_pem: key_pem.as_ref().to_vec(),
+        });
+        self
+    }
+
+    /// Validate the node's certificate against `domain` instead of the endpoint host
+    pub fn with_domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
+
+    /// Number of trust anchors loaded from the native certificate store
+    pub fn native_root_count(&self) -> usize {
+        self.native_roots.len()
+    }
+
+    pub(crate) fn into_tonic(self) -> ClientTlsConfig {
+        let mut tls = ClientTlsConfig::new()
+            .ca_certificates(self.ca_certificates)
+            .trust_anchors(self.native_roots);
+        if let Some(identity) = self.identity {
+            tls = tls.identity(Identity::from_pem(identity.cert_pem, identity.key_pem));
+        }
+        if let Some(domain) = self.domain {
+            tls = tls.domain_name(domain);
+        }
+        tls
+    }
+
+    /// Whether a client certificate is presented
+    pub(crate) fn has_identity(&self) -> bool {
+        self.identity.is_some()
+    }
+
+    /// Name the node's certificate is validated against, if overridden
+    pub(crate) fn domain(&self) -> Option<&str> {
+        self.domain.as_deref()
+    }
+
+    /// Equivalent rustls configuration, offering only `h2` via ALPN as tonic does
+    pub(crate) fn rustls_config(&self) -> Result<rustls::ClientConfig> {
+        let mut roots = rustls::RootCertStore::empty();
+        for ca in &self.ca_certificates {
+            for cert in rustls_pemfile::certs(&mut ca.get_ref()) {
+                roots
+                    .add(cert.context("parse CA certificate")?)
+                    .context("add CA certificate")?;
+            }
+        }
+        roots.extend(self.native_roots.iter().cloned());
+
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = rustls::ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots);
+        let mut config = match &self.identity {
+            Some(identity) => {
+                let certs = rustls_pemfile::certs(&mut identity.cert_pem.as_slice())
+                    .collect::<Result<Vec<_>, _>>()
+                    .context("parse client certificate")?;
+                let key = rustls_pemfile::private_key(&mut identity.key_pem.as_slice())
+                    .context("parse client key")?
+                    .context("no private key in client key PEM")?;
+                builder.with_client_auth_cert(certs, key)?
+            }
+            None => builder.with_no_client_auth(),
+        };
+        config.alpn_protocols = vec![b"h2".to_vec()];
+        Ok(config)
+    }
+}
+
+/// Client certificate details reported by [`validate_identity`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertInfo {
+    /// Subject of the leaf certificate, in RFC 4514 form
+    pub subject: String,
+    /// Issuer of the leaf certificate, in RFC 4514 form
+    pub issuer: String,
+    /// Start of the leaf certificate's validity period
+    pub not_before: SystemTime,
+    /// End of the leaf certificate's validity period
+    pub not_after: SystemTime,
+    /// Number of certificates in the presented chain, leaf first
+    pub chain_len: usize,
+}
+
+/// Why a client identity failed [`validate_identity`]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum IdentityError {
+    /// A PEM input holds no block of the expected kind
+    #[error("no {what} found in PEM")]
+    Missing { what: &'static str },
+
+    /// A certificate or key could not be parsed
+    #[error("invalid {what}: {reason}")]
+    Invalid { what: &'static str, reason: String },
+
+    /// The private key is not the one the certificate was issued for
+    #[error("private key does not match the certificate")]
+    KeyMismatch,
+
+    /// The leaf certificate's validity period has ended
+    #[error("certificate expired at {not_after:?}")]
+    Expired { not_after: SystemTime },
+
+    /// The leaf certificate's validity period has not started
+    #[error("certificate not valid before {not_before:?}")]
+    NotYetValid { not_before: SystemTime },
+
+    /// The certificate does not chain to the CA
+    #[error("certificate does not chain to the CA: {reason}")]
+    Untrusted { reason: String },
+}
+
+/// Check a PEM client identity against a CA bundle without connecting
+///
+/// Parses the certificate chain, private key and CA certificates, checks the
+/// key belongs to the leaf certificate and that the leaf is currently valid
+/// and chains to one of the CAs for client authentication, through any
+/// intermediates in `cert_pem`.
+pub fn validate_identity(
+    cert_pem: impl AsRef<[u8]>,
+    key_pem: impl AsRef<[u8]>,
+    ca_pem: impl AsRef<[u8]>,
+) -> Result<CertInfo, IdentityError> {
+    let chain = pem_certs(cert_pem.as_ref(), "certificate")?;
+    let cas = pem_certs(ca_pem.as_ref(), "CA certificate")?;
+    let key = rustls_pemfile::private_key(&mut key_pem.as_ref())
+        .map_err(|e| invalid("private key", e))?
+        .ok_or(IdentityError::Missing {
+            what: "private key",
+        })?;
+
+    let leaf =
+        x509_cert::Certificate::from_der(&chain[0]).map_err(|e| invalid("certificate", e))?;
+    let validity = &leaf.tbs_certificate.validity;
+    let info = CertInfo {
+        subject: leaf.tbs_certificate.subject.to_string(),
+        issuer: leaf.tbs_certificate.issuer.to_string(),
+        not_before: validity.not_before.to_system_time(),
+        not_after: validity.not_after.to_system_time(),
+        chain_len: chain.len(),
+    };
+    let now = SystemTime::now();
+    if now < info.not_before {
+        return Err(IdentityError::NotYetValid {
+            not_before: info.not_before,
+        });
+    }
+    if now > info.not_after {
+        return Err(IdentityError::Expired {
+            not_after: info.not_after,
+        });
+    }
+
+    let provider = rustls::crypto::ring::default_provider();
+    let signing_key = provider
+        .key_provider
+        .load_private_key(key)
+        .map_err(|e| invalid("private key", e))?;
+    let certified = rustls::sign::CertifiedKey::new(chain.clone(), signing_key);
+    if let Err(rustls::Error::InconsistentKeys(rustls::InconsistentKeys::KeyMismatch)) =
+        certified.keys_match()
+    {
+        return Err(IdentityError::KeyMismatch);
+    }
+
+    let anchors = cas
+        .iter()
+        .map(|ca| webpki::anchor_from_trusted_cert(ca).map_err(|e| invalid("CA certificate", e)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let end_entity =
+        webpki::EndEntityCert::try_from(&chain[0]).map_err(|e| invalid("certificate", e))?;
+    end_entity
+        .verify_for_usage(
+            provider.signature_verification_algorithms.all,
+            &anchors,
+            &chain[1..],
+            UnixTime::now(),
+            webpki::KeyUsage::client_auth(),
+            None,
+            None,
+        )
+        .map_err(|e| IdentityError::Untrusted {
+            reason: e.to_string(),
+        })?;
+    Ok(info)
+}
+
+fn pem_certs(
+    pem: &[u8],
+    what: &'static str,
+) -> Result<Vec<CertificateDer<'static>>, IdentityError> {
+    let certs = rustls_pemfile::certs(&mut &*pem)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| invalid(what, e))?;
+    if certs.is_empty() {
+        return Err(IdentityError::Missing { what });
+    }
+    Ok(certs)
+}
+
+fn invalid(what: &'static str, reason: impl fmt::Display) -> IdentityError {
+    IdentityError::Invalid {
+        what,
+        reason: reason.to_string(),
+    }
+}
@@ -0,0 +1,71 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Throwaway PKI for local mutual TLS
+//!
+//! [`generate_dev_pki`] issues a fresh CA with a server and a client
+//! certificate under it, so tests and development setups can run mutual TLS
+//! without reaching for openssl. Keys are generated on every call and never
+//! written anywhere; nothing issued here belongs in production.
+
+use anyhow::{Context, Result};
+use rcgen::{BasicConstraints, CertificateParams, DistinguishedName, DnType, IsCa, KeyPair};
+
+/// Name the server certificate is issued for, along with `127.0.0.1` and `::1`
+pub const SERVER_NAME: &str = "localhost";
+
+/// CA, server and client certificates and keys, PEM-encoded
+///
+/// The client half goes to [`Client::with_mtls`](crate::Client::with_mtls)
+/// as `client_cert_pem`, `client_key_pem` and `ca_pem`; the server half is
+/// the node's identity, with `ca_pem` as its client CA root.
+#[derive(Clone)]
+pub struct DevPki {
+    pub ca_pem: String,
+    pub server_cert_pem: String,
+    pub server_key_pem: String,
+    pub client_cert_pem: String,
+    pub client_key_pem: String,
+}
+
+/// Generate a CA with a server certificate for [`SERVER_NAME`] and a client certificate
+pub fn generate_dev_pki() -> Result<DevPki> {
+    let ca_key = KeyPair::generate().context("generate CA key")?;
+    let mut ca_params = CertificateParams::new(Vec::new())?;
+    ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    ca_params.distinguished_name = common_name("securefabric dev CA");
+    let ca = ca_params
+        .self_signed(&ca_key)
+        .context("self-sign CA certificate")?;
+
+    let server_key = KeyPair::generate().context("generate server key")?;
+    let mut server_params = CertificateParams::new(vec![
+        SERVER_NAME.to_string(),
+        "127.0.0.1".to_string(),
+        "::1".to_string(),
+    ])?;
+    server_params.distinguished_name = common_name(SERVER_NAME);
+    let server = server_params
+        .signed_by(&server_key, &ca, &ca_key)
+        .context("issue server certificate")?;
+
+    let client_key = KeyPair::generate().context("generate client key")?;
+    let mut client_params = CertificateParams::new(vec!["client".to_string()])?;
+    client_params.distinguished_name = common_name("securefabric dev client");
+    let client = client_params
+        .signed_by(&client_key, &ca, &ca_key)
+        .context("issue client certificate")?;
+
+    Ok(DevPki {
+        ca_pem: ca.pem(),
+        server_cert_pem: server.pem(),
+        server_key_pem: server_key.serialize_pem(),
+        client_cert_pem: client.pem(),
+        client_key_pem: client_key.serialize_pem(),
+    })
+}
+
+fn common_name(name: &str) -> DistinguishedName {
+    let mut dn = DistinguishedName::new();
+    dn.push(DnType::CommonName, name);
+    dn
+}
@@ -0,0 +1,196 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Topic templates rendered at send time
+//!
+//! A [`TopicTemplate`] such as `sensors.{id}.temp` names its variable parts,
+//! and [`TopicTemplate::render`] fills them in to produce a concrete
+//! [`Topic`]. Values may not contain the [`SEPARATOR`], so a value can never
+//! add or change levels of the topic hierarchy.
+
+use crate::Client;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Separator between levels of the topic hierarchy
+pub const SEPARATOR: char = '.';
+
+/// Why a template could not be parsed or rendered
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum TemplateError {
+    /// The template has an unclosed, unopened or unnamed placeholder
+    #[error("malformed topic template at byte {offset}: {reason}")]
+    Malformed { offset: usize, reason: &'static str },
+
+    /// No value was given for a placeholder
+    #[error("no value for placeholder {{{name}}}")]
+    Missing { name: String },
+
+    /// A value was given for a name the template does not contain
+    #[error("template has no placeholder {{{name}}}")]
+    Unknown { name: String },
+
+    /// A value is empty or contains the separator or a brace
+    #[error("invalid value {value:?} for placeholder {{{name}}}")]
+    InvalidValue { name: String, value: String },
+}
+
+/// A concrete topic rendered from a [`TopicTemplate`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Topic(String);
+
+impl Topic {
+    /// The rendered topic
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The rendered topic as an owned string
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl AsRef<str> for Topic {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Topic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Placeholder(String),
+}
+
+/// A topic with named `{placeholders}`, such as `sensors.{id}.temp`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopicTemplate {
+    template: String,
+    parts: Vec<Part>,
+}
+
+impl TopicTemplate {
+    /// Parse a template; placeholder names must be non-empty and braces balanced
+    pub fn parse(template: &str) -> Result<Self, TemplateError> {
+        let malformed = |offset, reason| TemplateError::Malformed { offset, reason };
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find(['{', '}']) {
+            let offset = template.len() - rest.len() + start;
+            if rest[start..].starts_with('}') {
+                return Err(malformed(offset, "unopened placeholder"));
+            }
+            literal.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            let end = after
+                .find('}')
+                .ok_or(malformed(offset, "unclosed placeholder"))?;
+            let name = &after[..end];
+            if name.is_empty() {
+                return Err(malformed(offset, "unnamed placeholder"));
+            }
+            if name.contains('{') {
+                return Err(malformed(offset, "nested placeholder"));
+            }
+            if !literal.is_empty() {
+                parts.push(Part::Literal(std::mem::take(&mut literal)));
+            }
+            parts.push(Part::Placeholder(name.to_string()));
+            rest = &after[end + 1..];
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Self {
+            template: template.to_string(),
+            parts,
+        })
+    }
+
+    /// Names of the placeholders, in order of first appearance
+    pub fn placeholders(&self) -> impl Iterator<Item = &str> {
+        let mut seen = Vec::new();
+        self.parts.iter().filter_map(move |part| match part {
+            Part::Placeholder(name) if !seen.contains(&name) => {
+                seen.push(name);
+                Some(name.as_str())
+            }
+            _ => None,
+        })
+    }
+
+    /// Fill every placeholder with its value from `values`
+    ///
+    /// Fails if a placeholder has no value, a value names no placeholder, or a
+    /// value is empty or contains the [`SEPARATOR`] or a brace.
+    pub fn render(&self, values: &[(&str, &str)]) -> Result<Topic, TemplateError> {
+        let mut lookup = BTreeMap::new();
+        for &(name, value) in values {
+            if !self.placeholders().any(|placeholder| placeholder == name) {
+                return Err(TemplateError::Unknown {
+                    name: name.to_string(),
+                });
+            }
+            if value.is_empty() || value.contains([SEPARATOR, '{', '}']) {
+                return Err(TemplateError::InvalidValue {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                });
+            }
+            lookup.insert(name, value);
+        }
+
+        let mut topic = String::with_capacity(self.template.len());
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => topic.push_str(literal),
+                Part::Placeholder(name) => match lookup.get(name.as_str()) {
+                    Some(value) => topic.push_str(value),
+                    None => return Err(TemplateError::Missing { name: name.clone() }),
+                },
+            }
+        }
+        Ok(Topic(topic))
+    }
+}
+
+impl FromStr for TopicTemplate {
+    type Err = TemplateError;
+
+    fn from_str(template: &str) -> Result<Self, TemplateError> {
+        Self::parse(template)
+    }
+}
+
+impl fmt::Display for TopicTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.template)
+    }
+}
+
+impl Client {
+    /// Render `template` with `values` and send `payload` to the resulting topic
+    ///
+    /// Fails with a [`TemplateError`] before anything is sent if the template
+    /// cannot be rendered.
+    pub async fn send_templated(
+        &mut self,
+        template: &TopicTemplate,
+        values: &[(&str, &str)],
+        payload: &[u8],
+    ) -> Result<String> {
+        let topic = template.render(values)?;
+        self.send(topic.as_str(), payload).await
+    }
+}
@@ -0,0 +1,151 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Topic hierarchy for display
+//!
+//! Management UIs render topics as a tree rather than a flat list. A
+//! [`TopicTree`] groups topics by their [`SEPARATOR`]-delimited segments, so
+//! `sensors.kitchen.temp` and `sensors.hall.temp` share the `sensors` node,
+//! and keeps per-subtree topic and message counts. It is built client side
+//! from whatever list of topics the caller has.
+
+use crate::topic::SEPARATOR;
+use std::collections::BTreeMap;
+
+/// Topics grouped by segment, with aggregate counts
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TopicTree {
+    root: TopicNode,
+}
+
+/// One segment of a [`TopicTree`]
+///
+/// A node is a topic itself when a topic with its path was added, and may
+/// have children either way: `sensors` can be a topic and the parent of
+/// `sensors.kitchen`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TopicNode {
+    path: String,
+    is_topic: bool,
+    messages: u64,
+    total_messages: u64,
+    topic_count: usize,
+    children: BTreeMap<String, TopicNode>,
+}
+
+impl TopicTree {
+    /// Build a tree from `(topic, message count)` pairs
+    ///
+    /// Topics are split on [`SEPARATOR`] as they are, without normalization,
+    /// so `a..b` has an empty middle segment. Counts of repeated topics are
+    /// added up.
+    pub fn from_topics<S: AsRef<str>>(topics: impl IntoIterator<Item = (S, u64)>) -> Self {
+        let mut tree = Self::default();
+        for (topic, messages) in topics {
+            tree.insert(topic.as_ref(), messages);
+        }
+        tree
+    }
+
+    /// Add `messages` for `topic`, creating its node and any missing parents
+    pub fn insert(&mut self, topic: &str, messages: u64) {
+        let new_topic = !self.get(topic).is_some_and(|node| node.is_topic);
+        let mut node = &mut self.root;
+        let mut path = None;
+        for segment in topic.split(SEPARATOR) {
+            node.total_messages += messages;
+            node.topic_count += usize::from(new_topic);
+            let child_path = match path {
+                None => segment.to_string(),
+                Some(parent) => format!("{parent}{SEPARATOR}{segment}"),
+            };
+            node = node
+                .children
+                .entry(segment.to_string())
+                .or_insert_with(|| TopicNode {
+                    path: child_path.clone(),
+                    ..Default::default()
+                });
+            path = Some(child_path);
+        }
+        node.is_topic = true;
+        node.messages += messages;
+        node.total_messages += messages;
+        node.topic_count += usize::from(new_topic);
+    }
+
+    /// Root of the tree, whose children are the first segments
+    ///
+    /// The root itself is never a topic and has an empty path.
+    pub fn root(&self) -> &TopicNode {
+        &self.root
+    }
+
+    /// Node at `path`, such as `sensors.kitchen`
+    pub fn get(&self, path: &str) -> Option<&TopicNode> {
+        path.split(SEPARATOR)
+            .try_fold(&self.root, |node, segment| node.child(segment))
+    }
+
+    /// Every node below the root, depth first, children in segment order
+    pub fn iter(&self) -> impl Iterator<Item = &TopicNode> {
+        let mut stack: Vec<&TopicNode> = self.root.children.values().rev().collect();
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(node.children.values().rev());
+            Some(node)
+        })
+    }
+
+    /// Topics in the tree, for iterating only those
+    pub fn topics(&self) -> impl Iterator<Item = &TopicNode> {
+        self.iter().filter(|node| node.is_topic)
+    }
+}
+
+impl<S: AsRef<str>> FromIterator<(S, u64)> for TopicTree {
+    fn from_iter<I: IntoIterator<Item = (S, u64)>>(topics: I) -> Self {
+        Self::from_topics(topics)
+    }
+}
+
+impl TopicNode {
+    /// Full topic path of this node
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Last segment of the path
+    pub fn segment(&self) -> &str {
+        self.path.rsplit(SEPARATOR).next().unwrap_or_default()
+    }
+
+    /// Whether a topic with exactly this path was added
+    pub fn is_topic(&self) -> bool {
+        self.is_topic
+    }
+
+    /// Messages on this topic itself
+    pub fn messages(&self) -> u64 {
+        self.messages
+    }
+
+    /// Messages on this topic and every topic below it
+    pub fn total_messages(&self) -> u64 {
+        self.total_messages
+    }
+
+    /// Topics in this subtree, including this node if it is one
+    pub fn topic_count(&self) -> usize {
+        self.topic_count
+    }
+
+    /// Child with the given segment
+    pub fn child(&self, segment: &str) -> Option<&TopicNode> {
+        self.children.get(segment)
+    }
+
+    /// Children in segment order
+    pub fn children(&self) -> impl Iterator<Item = &TopicNode> {
+        self.children.values()
+    }
+}
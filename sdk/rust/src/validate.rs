@@ -0,0 +1,195 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Combined envelope validation
+//!
+//! [`Client::validate`] runs every configured check on a received envelope and
+//! reports each outcome, and [`Client::validate_strict`] turns any failure into
+//! an error. The signature and msg_id checks always run; the age and replay
+//! checks run once enabled with [`Client::with_max_envelope_age`] and
+//! [`Client::with_replay_window`].
+
+use crate::error::Error;
+use crate::pb::Envelope;
+use crate::{signed_digest, Client};
+use anyhow::Result;
+use std::borrow::Borrow;
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Outcome of each check run by [`Client::validate`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// The signature verifies against the sender's key
+    pub signature: bool,
+    /// The msg_id matches the envelope's pubkey, seq and nonce
+    pub msg_id: bool,
+    /// The envelope is older than the maximum age, or carries no timestamp
+    ///
+    /// Always `false` without [`Client::with_max_envelope_age`].
+    pub expired: bool,
+    /// An envelope with the same sender and signed bytes was already validated
+    ///
+    /// Always `false` without [`Client::with_replay_window`].
+    pub replayed: bool,
+}
+
+impl ValidationReport {
+    /// Whether every check passed
+    pub fn is_valid(&self) -> bool {
+        self.signature && self.msg_id && !self.expired && !self.replayed
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let failed: Vec<&str> = [
+            (!self.signature, "bad signature"),
+            (!self.msg_id, "bad msg_id"),
+            (self.expired, "expired"),
+            (self.replayed, "replayed"),
+        ]
+        .into_iter()
+        .filter_map(|(failed, name)| failed.then_some(name))
+        .collect();
+
+        if failed.is_empty() {
+            f.write_str("valid")
+        } else {
+            f.write_str(&failed.join(", "))
+        }
+    }
+}
+
+/// Optional checks enabled on a client
+#[derive(Clone, Default)]
+pub(crate) struct Checks {
+    max_age: Option<Duration>,
+    /// Signed digests of recently validated envelopes
+    replay: Option<Arc<Mutex<ReplayWindow<[u8; 32]>>>>,
+}
+
+/// The most recent `capacity` distinct values observed
+pub(crate) struct ReplayWindow<T> {
+    capacity: usize,
+    order: VecDeque<T>,
+    seen: HashSet<T>,
+}
+
+impl<T: Hash + Eq + Clone> ReplayWindow<T> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            order: VecDeque::with_capacity(capacity),
+            seen: HashSet::with_capacity(capacity),
+        }
+    }
+
+    /// Record `value`, returning whether it was already in the window
+    pub(crate) fn observe<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = T> + ?Sized,
+    {
+        if self.seen.contains(value) {
+            return true;
+        }
+        if self.order.len() == self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove::<T>(&oldest);
+            }
+        }
+        self.order.push_back(value.to_owned());
+        self.seen.insert(value.to_owned());
+        false
+    }
+}
+
+impl Client {
+    /// Treat envelopes older than `max_age` as expired in [`Client::validate`]
+    ///
+    /// Age is measured from the sender's `timestamp_ms`, so it is subject to
+    /// clock skew between sender and receiver. Envelopes without a timestamp
+    /// count as expired.
+    pub fn with_max_envelope_age(mut self, max_age: Duration) -> Self {
+        self.validation.max_age = Some(max_age);
+        self
+    }
+
+    /// Flag envelopes that repeat one of the last `window` validated ones
+    ///
+    /// Envelopes are told apart by their sender and the bytes it signed, not
+    /// by msg_id: the msg_id hashes `seq` and `nonce`, which a relay can
+    /// change, so a replayed copy could otherwise pass as new under a fresh
+    /// msg_id. Only envelopes with a valid signature and msg_id enter the
+    /// window, so forgeries cannot make a genuine envelope look replayed. The
+    /// window is shared by clones of the client.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is zero.
+    pub fn with_replay_window(mut self, window: usize) -> Self {
+        assert!(window > 0, "replay window must be non-zero");
+        self.validation.replay = Some(Arc::new(Mutex::new(ReplayWindow::new(window))));
+        self
+    }
+
+    /// Run every configured check on an envelope
+    ///
+    /// A signature that cannot be checked at all, for example because the
+    /// sender is not in the keyring, is reported as `signature: false`; use
+    /// [`Client::validate_strict`] to get the underlying error.
+    pub fn validate(&self, envelope: &Envelope) -> ValidationReport {
+        self.validate_with(envelope, self.verify(envelope))
+    }
+
+    /// Validate an envelope, failing unless every check passes
+    ///
+    /// Errors from the signature check itself are returned as they are;
+    /// otherwise a failed check yields [`Error::Invalid`] with the report.
+    pub fn validate_strict(&self, envelope: &Envelope) -> Result<()> {
+        let signature = self.verify(envelope)?;
+        let report = self.validate_with(envelope, Ok(signature));
+        if report.is_valid() {
+            Ok(())
+        } else {
+            Err(Error::Invalid { report }.into())
+        }
+    }
+
+    pub(crate) fn validate_with(
+        &self,
+        envelope: &Envelope,
+        signature: Result<bool>,
+    ) -> ValidationReport {
+        let signature = signature.unwrap_or(false);
+        let msg_id = self.verify_msg_id(envelope);
+        let expired = self
+            .validation
+            .max_age
+            .is_some_and(|max_age| is_expired(envelope, max_age, SystemTime::now()));
+        let replayed = match &self.validation.replay {
+            Some(window) if signature && msg_id => {
+                window.lock().unwrap().observe(&signed_digest(envelope))
+            }
+            _ => false,
+        };
+
+        ValidationReport {
+            signature,
+            msg_id,
+            expired,
+            replayed,
+        }
+    }
+}
+
+fn is_expired(envelope: &Envelope, max_age: Duration, now: SystemTime) -> bool {
+    if envelope.timestamp_ms == 0 {
+        return true;
+    }
+    let sent_at = UNIX_EPOCH + Duration::from_millis(envelope.timestamp_ms);
+    now.duration_since(sent_at).is_ok_and(|age| age > max_age)
+}
@@ -0,0 +1,152 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Windowed subscriptions for batch consumers
+//!
+//! [`Client::subscribe_windowed`] groups a subscription into batches that are
+//! flushed once they hold `max_count` envelopes or `max_delay` has passed since
+//! their first envelope arrived, whichever comes first. Envelopes are verified
+//! before they join a batch, so every batch holds only authentic envelopes.
+
+use crate::pb::Envelope;
+use crate::{verify_trusted, Client};
+use anyhow::Result;
+use futures::stream::{BoxStream, StreamExt};
+use futures::Stream;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::time::Sleep;
+
+/// Per-envelope errors yielded by [`Client::subscribe_windowed`]
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum WindowError {
+    /// The envelope does not verify, so it was left out of its batch
+    #[error("invalid signature on {msg_id}")]
+    BadSignature { msg_id: String },
+
+    /// The underlying subscription failed
+    #[error("subscription error: {0}")]
+    Transport(Box<tonic::Status>),
+}
+
+impl From<tonic::Status> for WindowError {
+    fn from(status: tonic::Status) -> Self {
+        Self::Transport(Box::new(status))
+    }
+}
+
+impl Client {
+    /// Subscribe to a topic, yielding verified envelopes in batches
+    ///
+    /// A batch is flushed when it reaches `max_count` envelopes or `max_delay`
+    /// after its first envelope arrived, so no batch is ever empty. Envelopes
+    /// that fail verification, like [`Client::verify`], are yielded as
+    /// [`WindowError::BadSignature`] without flushing the batch in progress.
+    /// When the subscription ends, the partial batch is flushed before the
+    /// stream ends.
+    ///
+    /// Fails up front if `max_count` is zero.
+    pub async fn subscribe_windowed(
+        &mut self,
+        topic: &[u8],
+        max_count: usize,
+        max_delay: Duration,
+    ) -> Result<BoxStream<'static, Result<Vec<Envelope>, WindowError>>> {
+        anyhow::ensure!(max_count > 0, "Window size must be non-zero");
+        let keyring = self.keyring.clone();
+        let encryption = self.encryption.clone();
+        let mode = self.verify_mode;
+        let cache = self.negative_cache.clone();
+        let peer = self.peer_identity;
+        let post_receive = self.post_receive.clone();
+        let inner = self.subscribe_verifying(topic).await?;
+
+        let verified = inner.filter_map(move |item| {
+            let item = match item {
+                Err(status) => Some(Err(status.into())),
+                Ok(envelope)
+                    if verify_trusted(
+                        keyring.as_ref(),
+                        encryption.as_ref(),
+                        mode,
+                        cache.as_ref(),
+                        peer.as_ref(),
+                        &envelope,
+                    )
+                    .unwrap_or(false) =>
+                {
+                    post_receive.after_verification(envelope).map(Ok)
+                }
+                Ok(envelope) => Some(Err(WindowError::BadSignature {
+                    msg_id: envelope.msg_id,
+                })),
+            };
+            futures::future::ready(item)
+        });
+
+        Ok(Windows {
+            inner: verified.boxed(),
+            max_count,
+            max_delay,
+            batch: Vec::new(),
+            deadline: None,
+            ended: false,
+        }
+        .boxed())
+    }
+}
+
+/// Batches a stream of envelopes by count and time
+struct Windows {
+    inner: BoxStream<'static, Result<Envelope, WindowError>>,
+    max_count: usize,
+    max_delay: Duration,
+    batch: Vec<Envelope>,
+    /// When the batch in progress is due; set by its first envelope
+    deadline: Option<Pin<Box<Sleep>>>,
+    ended: bool,
+}
+
+impl Windows {
+    fn flush(&mut self) -> Poll<Option<Result<Vec<Envelope>, WindowError>>> {
+        self.deadline = None;
+        Poll::Ready(Some(Ok(std::mem::take(&mut self.batch))))
+    }
+}
+
+impl Stream for Windows {
+    type Item = Result<Vec<Envelope>, WindowError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(deadline) = self.deadline.as_mut() {
+                if deadline.as_mut().poll(cx).is_ready() {
+                    return self.flush();
+                }
+            }
+            if self.ended {
+                if self.batch.is_empty() {
+                    return Poll::Ready(None);
+                }
+                return self.flush();
+            }
+
+            match self.inner.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(envelope))) => {
+                    if self.batch.is_empty() {
+                        self.deadline = Some(Box::pin(tokio::time::sleep(self.max_delay)));
+                    }
+                    self.batch.push(envelope);
+                    if self.batch.len() >= self.max_count {
+                        return self.flush();
+                    }
+                }
+                Poll::Ready(Some(Err(error))) => return Poll::Ready(Some(Err(error))),
+                Poll::Ready(None) => self.ended = true,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
@@ -0,0 +1,268 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Topics as line-oriented byte sinks
+//!
+//! [`TopicWriter`] implements [`std::io::Write`] and [`AsyncTopicWriter`]
+//! implements Tokio's [`AsyncWrite`], so a topic can be handed to logging
+//! frameworks and other code that writes to a sink. Both split the bytes
+//! written on `\n` and publish each line, without its line ending, as one
+//! broadcast message on the writer's topic. A trailing partial line is
+//! published by `flush` and when the writer is dropped.
+
+use crate::Client;
+use anyhow::{Context as _, Result};
+use futures::future::BoxFuture;
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+use tokio::io::AsyncWrite;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Bytes written since the last complete line
+#[derive(Default)]
+struct LineBuffer {
+    partial: Vec<u8>,
+}
+
+impl LineBuffer {
+    /// Append `bytes`, returning the lines they complete
+    fn push(&mut self, bytes: &[u8]) -> Vec<Vec<u8>> {
+        let mut lines = Vec::new();
+        let mut rest = bytes;
+        while let Some(end) = rest.iter().position(|&b| b == b'\n') {
+            self.partial.extend_from_slice(&rest[..end]);
+            lines.push(strip_cr(std::mem::take(&mut self.partial)));
+            rest = &rest[end + 1..];
+        }
+        self.partial.extend_from_slice(rest);
+        lines
+    }
+
+    /// The partial line, if any bytes are buffered
+    fn take(&mut self) -> Option<Vec<u8>> {
+        (!self.partial.is_empty()).then(|| strip_cr(std::mem::take(&mut self.partial)))
+    }
+}
+
+fn strip_cr(mut line: Vec<u8>) -> Vec<u8> {
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    line
+}
+
+/// Non-blocking [`io::Write`] sink publishing each line to a topic
+///
+/// Created by [`Client::topic_writer`]. Writes never wait on the network:
+/// lines are queued to a background task that publishes them in order, so
+/// the writer can be used from synchronous code and from inside async tasks
+/// alike. `flush` queues the partial line but does not wait for delivery;
+/// [`TopicWriter::finish`] does. Once a publish fails, later lines are
+/// discarded and writes fail with that error.
+pub struct TopicWriter {
+    lines: LineBuffer,
+    queue: Option<mpsc::UnboundedSender<Vec<u8>>>,
+    failed: Arc<Mutex<Option<String>>>,
+    task: Option<JoinHandle<Result<u64>>>,
+}
+
+impl TopicWriter {
+    /// Publish the partial line and wait until every queued line is published
+    ///
+    /// Returns the number of lines published by this writer, or the error
+    /// the first failed publish ended with.
+    pub async fn finish(mut self) -> Result<u64> {
+        self.queue_partial();
+        self.queue = None;
+        let task = self.task.take().expect("task is only taken by finish");
+        task.await.context("topic writer task failed")?
+    }
+
+    fn queue_line(&mut self, line: Vec<u8>) {
+        if let Some(queue) = &self.queue {
+            // A closed queue means the task stopped on an error, reported below
+            let _ = queue.send(line);
+        }
+    }
+
+    fn queue_partial(&mut self) {
+        if let Some(line) = self.lines.take() {
+            self.queue_line(line);
+        }
+    }
+
+    fn check(&self) -> io::Result<()> {
+        match &*self.failed.lock().unwrap() {
+            Some(error) => Err(io::Error::other(error.clone())),
+            None => Ok(()),
+        }
+    }
+}
+
+impl io::Write for TopicWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.check()?;
+        for line in self.lines.push(buf) {
+            self.queue_line(line);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.check()?;
+        self.queue_partial();
+        Ok(())
+    }
+}
+
+impl Drop for TopicWriter {
+    /// Queue the partial line; the background task publishes what is queued
+    fn drop(&mut self) {
+        self.queue_partial();
+    }
+}
+
+/// [`AsyncWrite`] sink publishing each line to a topic
+///
+/// Created by [`Client::async_topic_writer`]. Lines are published in order,
+/// one at a time: `poll_write` waits for lines already completed to be
+/// published before accepting more bytes, and `poll_flush` publishes the
+/// partial line and waits for every line to be published. Dropping the
+/// writer publishes the lines not yet flushed from a background task, if a
+/// Tokio runtime is available.
+pub struct AsyncTopicWriter {
+    client: Client,
+    topic: String,
+    lines: LineBuffer,
+    pending: VecDeque<Vec<u8>>,
+    sending: Option<BoxFuture<'static, Result<String>>>,
+}
+
+impl AsyncTopicWriter {
+    /// Publish pending lines until none are left or one is in flight
+    fn poll_publish(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            if let Some(sending) = &mut self.sending {
+                let sent = ready!(sending.as_mut().poll(cx));
+                self.sending = None;
+                sent.map_err(|error| io::Error::other(format!("{error:#}")))?;
+            }
+            let Some(line) = self.pending.pop_front() else {
+                return Poll::Ready(Ok(()));
+            };
+            let mut client = self.client.clone();
+            let topic = self.topic.clone();
+            self.sending = Some(Box::pin(async move { client.send(&topic, &line).await }));
+        }
+    }
+}
+
+impl AsyncWrite for AsyncTopicWriter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_publish(cx))?;
+        let lines = this.lines.push(buf);
+        this.pending.extend(lines);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if let Some(line) = this.lines.take() {
+            this.pending.push_back(line);
+        }
+        this.poll_publish(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+impl Drop for AsyncTopicWriter {
+    fn drop(&mut self) {
+        if let Some(line) = self.lines.take() {
+            self.pending.push_back(line);
+        }
+        if self.sending.is_none() && self.pending.is_empty() {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let sending = self.sending.take();
+        let pending = std::mem::take(&mut self.pending);
+        let mut client = self.client.clone();
+        let topic = std::mem::take(&mut self.topic);
+        runtime.spawn(async move {
+            if let Some(sending) = sending {
+                if sending.await.is_err() {
+                    return;
+                }
+            }
+            for line in pending {
+                if client.send(&topic, &line).await.is_err() {
+                    return;
+                }
+            }
+        });
+    }
+}
+
+impl Client {
+    /// Writer publishing each line written to it as a message on `topic`
+    ///
+    /// See [`TopicWriter`]. The writer publishes through a clone of this
+    /// client, with the same signing, encryption and retries as
+    /// [`Client::send`].
+    ///
+    /// Must be called within a Tokio runtime.
+    pub fn topic_writer(&self, topic: &str) -> TopicWriter {
+        let (queue, mut lines) = mpsc::unbounded_channel::<Vec<u8>>();
+        let failed = Arc::new(Mutex::new(None));
+        let mut client = self.clone();
+        let topic = topic.to_string();
+        let task = tokio::spawn({
+            let failed = failed.clone();
+            async move {
+                let mut published = 0;
+                while let Some(line) = lines.recv().await {
+                    if let Err(error) = client.send(&topic, &line).await {
+                        *failed.lock().unwrap() = Some(format!("{error:#}"));
+                        return Err(error);
+                    }
+                    published += 1;
+                }
+                Ok(published)
+            }
+        });
+        TopicWriter {
+            lines: LineBuffer::default(),
+            queue: Some(queue),
+            failed,
+            task: Some(task),
+        }
+    }
+
+    /// Async writer publishing each line written to it as a message on `topic`
+    ///
+    /// See [`AsyncTopicWriter`]. The writer publishes through a clone of this
+    /// client, with the same signing, encryption and retries as
+    /// [`Client::send`].
+    pub fn async_topic_writer(&self, topic: &str) -> AsyncTopicWriter {
+        AsyncTopicWriter {
+            client: self.clone(),
+            topic: topic.to_string(),
+            lines: LineBuffer::default(),
+            pending: VecDeque::new(),
+            sending: None,
+        }
+    }
+}
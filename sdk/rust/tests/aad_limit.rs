@@ -0,0 +1,115 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode};
+use securefabric_sdk::crypto::{self, AeadContext, MAX_AAD_LEN};
+use securefabric_sdk::{Client, Error};
+use std::collections::BTreeMap;
+
+const KEY: [u8; crypto::KEY_LEN] = [7u8; crypto::KEY_LEN];
+const NONCE: [u8; crypto::NONCE_LEN] = [1u8; crypto::NONCE_LEN];
+
+fn typed_error(result: anyhow::Result<impl std::fmt::Debug>) -> Option<Error> {
+    result.unwrap_err().downcast_ref::<Error>().cloned()
+}
+
+#[test]
+fn default_limit_accepts_boundary_and_rejects_one_more() {
+    let at_limit = vec![b'a'; MAX_AAD_LEN];
+    let over = vec![b'a'; MAX_AAD_LEN + 1];
+    let expected = Some(Error::AadTooLarge {
+        len: MAX_AAD_LEN + 1,
+        max: MAX_AAD_LEN,
+    });
+
+    let (ciphertext, tag) = crypto::encrypt(&KEY, &NONCE, b"payload", &at_limit).unwrap();
+    assert_eq!(
+        crypto::decrypt(&KEY, &NONCE, &ciphertext, &at_limit, &tag).unwrap(),
+        b"payload"
+    );
+    assert_eq!(
+        typed_error(crypto::encrypt(&KEY, &NONCE, b"payload", &over)),
+        expected
+    );
+    assert_eq!(
+        typed_error(crypto::decrypt(&KEY, &NONCE, &ciphertext, &over, &tag)),
+        expected
+    );
+
+    let context = AeadContext::new(&KEY);
+    let sealed = context.seal(&NONCE, &at_limit, b"payload").unwrap();
+    assert_eq!(
+        context.open(&NONCE, &at_limit, &sealed).unwrap(),
+        b"payload"
+    );
+    assert_eq!(
+        typed_error(context.seal(&NONCE, &over, b"payload")),
+        expected
+    );
+}
+
+#[test]
+fn context_limit_is_configurable_on_both_paths() {
+    let roomy = AeadContext::new(&KEY).with_max_aad_len(MAX_AAD_LEN * 2);
+    let aad = vec![b'a'; MAX_AAD_LEN + 1];
+    let sealed = roomy.seal(&NONCE, &aad, b"payload").unwrap();
+    assert_eq!(roomy.open(&NONCE, &aad, &sealed).unwrap(), b"payload");
+
+    let tight = AeadContext::new(&KEY).with_max_aad_len(16);
+    assert!(tight.open(&NONCE, &[0; 16], &sealed).is_err());
+    assert_eq!(
+        typed_error(tight.open(&NONCE, &aad, &sealed)),
+        Some(Error::AadTooLarge {
+            len: aad.len(),
+            max: 16
+        })
+    );
+}
+
+#[tokio::test]
+async fn client_limit_applies_to_encrypted_envelopes() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let headers = BTreeMap::from([("trace".to_string(), "x".repeat(4096))]);
+    let mut sender = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_encryption(KEY, 1)
+        .unwrap();
+    sender
+        .send_with_headers("orders", &headers, b"payload")
+        .await
+        .unwrap();
+    let envelope = node.sent().remove(0);
+    let aad_len = envelope.aad.len();
+
+    // The limit can be set before or after the key
+    let receiver = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_max_aad_len(aad_len)
+        .with_encryption(KEY, 1)
+        .unwrap();
+    assert_eq!(receiver.decrypt(&envelope).unwrap(), b"payload");
+    let receiver = receiver.with_max_aad_len(aad_len - 1);
+    assert_eq!(
+        typed_error(receiver.decrypt(&envelope)),
+        Some(Error::AadTooLarge {
+            len: aad_len,
+            max: aad_len - 1
+        })
+    );
+
+    let mut sender = sender.with_max_aad_len(1024);
+    assert!(matches!(
+        typed_error(
+            sender
+                .send_with_headers("orders", &headers, b"payload")
+                .await
+        ),
+        Some(Error::AadTooLarge { max: 1024, .. })
+    ));
+    assert_eq!(node.sent().len(), 1);
+}
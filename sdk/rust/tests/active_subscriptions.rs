@@ -0,0 +1,67 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use futures::StreamExt;
+use securefabric_sdk::Client;
+
+#[tokio::test]
+async fn lists_subscriptions_until_they_are_dropped() {
+    let key = signing_key(1);
+    let node = MockNode::with_feed(vec![
+        signed_envelope(&key, "events", 1, b"one"),
+        signed_envelope(&key, "events", 2, b"two"),
+    ]);
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+    assert!(client.active_subscriptions().is_empty());
+
+    let mut events = client.subscribe(b"events").await.unwrap();
+    let alerts = client.clone().subscribe(b"alerts").await.unwrap();
+    let active = client.active_subscriptions();
+    let topics: Vec<&[u8]> = active.iter().map(|info| info.topic.as_slice()).collect();
+    assert_eq!(topics, [&b"events"[..], b"alerts"]);
+    assert!(active.iter().all(|info| info.start_position.is_empty()));
+    assert!(active.iter().all(|info| info.messages_received == 0));
+    assert!(active[0].since <= active[1].since);
+    assert_ne!(active[0].id, active[1].id);
+
+    events.next().await.unwrap().unwrap();
+    events.next().await.unwrap().unwrap();
+    let active = client.active_subscriptions();
+    assert_eq!(active[0].messages_received, 2);
+
+    drop(events);
+    let active = client.active_subscriptions();
+    assert_eq!(active.len(), 1);
+    assert_eq!(active[0].topic, b"alerts");
+
+    drop(alerts);
+    assert!(client.active_subscriptions().is_empty());
+}
+
+#[tokio::test]
+async fn wrapped_streams_are_removed_when_dropped() {
+    let node = MockNode::default();
+    let _live = node.live_feed();
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_encryption([7; 32], 1)
+        .unwrap();
+
+    let decrypted = client.subscribe_decrypted(b"events").await.unwrap();
+    assert_eq!(client.active_subscriptions().len(), 1);
+    drop(decrypted);
+    assert!(client.active_subscriptions().is_empty());
+
+    let credited = client
+        .subscribe_with_credits(b"events", 8, 2)
+        .await
+        .unwrap();
+    assert_eq!(client.active_subscriptions().len(), 1);
+    drop(credited);
+    assert!(client.active_subscriptions().is_empty());
+}
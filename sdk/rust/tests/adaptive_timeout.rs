@@ -0,0 +1,126 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode};
+use securefabric_sdk::timeout::{AdaptiveConfig, LatencyEstimator};
+use securefabric_sdk::{Client, Error};
+use std::time::Duration;
+
+fn ms(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+#[test]
+fn timeout_tracks_latency_distribution() {
+    let mut estimator = LatencyEstimator::new(AdaptiveConfig::default());
+    assert_eq!(estimator.timeout(), Duration::from_secs(5));
+
+    // Steady latencies: the deviation decays and the deadline settles near
+    // multiplier * mean
+    for _ in 0..100 {
+        estimator.record(ms(100));
+    }
+    assert!((ms(149)..=ms(151)).contains(&estimator.timeout()));
+
+    // Jittery latencies around 200ms: the deadline covers the slow samples
+    for i in 0..200 {
+        estimator.record(if i % 2 == 0 { ms(100) } else { ms(300) });
+    }
+    let mean = estimator.mean().unwrap();
+    assert!((ms(180)..=ms(220)).contains(&mean), "{mean:?}");
+    let jittery = estimator.timeout();
+    assert!((ms(450)..=ms(1000)).contains(&jittery), "{jittery:?}");
+
+    // Back to a fast, steady network: the deadline tightens again
+    for _ in 0..200 {
+        estimator.record(ms(20));
+    }
+    assert!(estimator.timeout() < jittery);
+    assert!((ms(50)..=ms(60)).contains(&estimator.timeout()));
+}
+
+#[test]
+fn timeout_is_bounded() {
+    let config = AdaptiveConfig {
+        min: ms(100),
+        max: ms(500),
+        ..Default::default()
+    };
+    let mut estimator = LatencyEstimator::new(config);
+    assert_eq!(estimator.timeout(), ms(500));
+
+    for _ in 0..50 {
+        estimator.record(ms(1));
+    }
+    assert_eq!(estimator.timeout(), ms(100));
+
+    for _ in 0..50 {
+        estimator.record(Duration::from_secs(10));
+    }
+    assert_eq!(estimator.timeout(), ms(500));
+}
+
+#[tokio::test]
+async fn send_times_out_at_adaptive_deadline() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let config = AdaptiveConfig {
+        initial: ms(200),
+        min: ms(200),
+        max: ms(400),
+        smoothing: 0.5,
+        ..Default::default()
+    };
+    let mut client = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_adaptive_timeout(config);
+    assert_eq!(client.send_timeout(), Some(ms(200)));
+
+    client.send("rtt", b"fast").await.unwrap();
+
+    let gate = node.hold_sends();
+    let before = client.send_timeout().unwrap();
+    let error = client.send("rtt", b"stalled").await.unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<Error>(),
+        Some(&Error::Timeout { after: before })
+    );
+    // The missed deadline widens the next one
+    assert!(client.send_timeout().unwrap() > before);
+
+    gate.add_permits(10);
+    client.send("rtt", b"released").await.unwrap();
+    assert_eq!(Client::new(&endpoint).await.unwrap().send_timeout(), None);
+}
+
+#[tokio::test]
+async fn batch_times_out_at_adaptive_deadline() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut client = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_adaptive_timeout(AdaptiveConfig {
+            initial: ms(200),
+            min: ms(200),
+            max: ms(400),
+            ..Default::default()
+        });
+
+    let gate = node.hold_sends();
+    let before = client.send_timeout().unwrap();
+    let error = client.send_batch("rtt", &[b"a", b"b"]).await.unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<Error>(),
+        Some(&Error::Timeout { after: before })
+    );
+    assert!(client.send_timeout().unwrap() > before);
+
+    gate.add_permits(10);
+    let results = client.send_batch("rtt", &[b"released"]).await.unwrap();
+    assert!(results[0].is_ok());
+}
@@ -0,0 +1,47 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use securefabric_sdk::crypto::{self, AeadContext};
+
+const KEY: [u8; crypto::KEY_LEN] = [7u8; crypto::KEY_LEN];
+
+#[test]
+fn matches_one_shot_functions() {
+    let context = AeadContext::new(&KEY);
+    for (i, len) in [0usize, 1, 63, 64, 1000, 64 * 1024].into_iter().enumerate() {
+        let nonce = [i as u8; crypto::NONCE_LEN];
+        let aad = format!(r#"{{"message":{i}}}"#);
+        let plaintext: Vec<u8> = (0..len).map(|b| (b % 251) as u8).collect();
+
+        let (ciphertext, tag) = crypto::encrypt(&KEY, &nonce, &plaintext, aad.as_bytes()).unwrap();
+        let sealed = context.seal(&nonce, aad.as_bytes(), &plaintext).unwrap();
+        assert_eq!(sealed, [ciphertext.as_slice(), &tag].concat());
+
+        assert_eq!(
+            context.open(&nonce, aad.as_bytes(), &sealed).unwrap(),
+            plaintext
+        );
+        assert_eq!(
+            crypto::decrypt(&KEY, &nonce, &ciphertext, aad.as_bytes(), &tag).unwrap(),
+            plaintext
+        );
+    }
+}
+
+#[test]
+fn rejects_what_one_shot_decrypt_rejects() {
+    let context = AeadContext::new(&KEY);
+    let nonce = [1u8; crypto::NONCE_LEN];
+    let mut sealed = context.seal(&nonce, b"aad", b"payload").unwrap();
+
+    assert!(context.open(&nonce, b"other aad", &sealed).is_err());
+    assert!(context.open(&[1u8; 12], b"aad", &sealed).is_err());
+    assert!(context
+        .open(&nonce, b"aad", &sealed[..crypto::TAG_LEN - 1])
+        .is_err());
+    assert!(AeadContext::new(&[8u8; crypto::KEY_LEN])
+        .open(&nonce, b"aad", &sealed)
+        .is_err());
+
+    sealed[0] ^= 1;
+    assert!(context.open(&nonce, b"aad", &sealed).is_err());
+}
@@ -0,0 +1,85 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope_at, signing_key, MockNode};
+use securefabric_sdk::keyring::{fingerprint, Keyring};
+use securefabric_sdk::Client;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+async fn client() -> Client {
+    let endpoint = common::spawn(MockNode::default()).await;
+    Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_max_envelope_age(Duration::from_secs(60))
+        .with_replay_window(16)
+}
+
+#[tokio::test]
+async fn valid_envelope_is_recorded_with_its_sender() {
+    let client = client().await;
+    let key = signing_key(1);
+    let sent_at = now_ms();
+    let envelope = signed_envelope_at(&key, "audit", 7, sent_at, b"payload");
+
+    let record = client.audit(&envelope);
+    assert!(record.is_valid(), "{record}");
+    assert_eq!(record.msg_id, envelope.msg_id);
+    assert_eq!(record.topic, "audit");
+    assert_eq!(record.sender, fingerprint(key.verifying_key().as_bytes()));
+    assert_eq!(record.seq, 7);
+    assert_eq!(record.timestamp_ms, Some(sent_at));
+    assert!(record.signature && record.msg_id_valid);
+    assert!(!record.expired && !record.replayed);
+    assert_eq!(record.signature_error, None);
+
+    // The same envelope again is a replay
+    assert!(client.audit(&envelope).replayed);
+}
+
+#[tokio::test]
+async fn invalid_envelope_records_each_failure() {
+    let client = client().await;
+    let mut tampered = signed_envelope_at(&signing_key(1), "audit", 3, 1_000, b"payload");
+    tampered.payload = b"tampered".to_vec();
+    tampered.msg_id = "00".repeat(32);
+
+    let record = client.audit(&tampered);
+    assert!(!record.is_valid());
+    assert_eq!(record.seq, 3);
+    assert_eq!(record.timestamp_ms, Some(1_000));
+    assert!(!record.signature);
+    assert!(!record.msg_id_valid);
+    assert!(record.expired);
+    assert!(!record.replayed);
+    assert_eq!(record.signature_error, None);
+    assert!(record
+        .to_string()
+        .ends_with(": bad signature, bad msg_id, expired"));
+}
+
+#[tokio::test]
+async fn unknown_sender_records_why_the_signature_was_not_checked() {
+    let keyring = Keyring::new();
+    keyring.insert(signing_key(2).verifying_key());
+    let client = client().await.with_keyring(keyring);
+    let envelope = signed_envelope_at(&signing_key(1), "audit", 1, now_ms(), b"payload");
+
+    let record = client.audit(&envelope);
+    assert!(!record.signature);
+    assert!(record.msg_id_valid);
+    let error = record.signature_error.unwrap();
+    assert!(error.contains("unknown sender"), "{error}");
+    assert_eq!(
+        serde_json::to_value(client.audit(&envelope)).unwrap()["seq"],
+        1
+    );
+}
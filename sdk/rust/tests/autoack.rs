@@ -0,0 +1,151 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use securefabric_sdk::handler::{async_trait, AckPolicy, MessageHandler, RunSummary};
+use securefabric_sdk::pb::Envelope;
+use securefabric_sdk::Client;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Fails every envelope whose payload is `boom`
+struct Handler;
+
+#[async_trait]
+impl MessageHandler for Handler {
+    async fn handle(&self, envelope: Envelope) -> anyhow::Result<()> {
+        if envelope.payload == b"boom" {
+            anyhow::bail!("handler failed");
+        }
+        Ok(())
+    }
+}
+
+fn ids(envelopes: &[&Envelope]) -> Vec<String> {
+    envelopes.iter().map(|e| e.msg_id.clone()).collect()
+}
+
+#[tokio::test]
+async fn acks_in_batches_of_every_n() {
+    let key = signing_key(1);
+    let feed: Vec<_> = (1..=5)
+        .map(|seq| signed_envelope(&key, "jobs", seq, b"work"))
+        .collect();
+    let node = MockNode::with_feed(feed.clone());
+    let mut client = Client::new(common::spawn(node.clone()).await)
+        .await
+        .unwrap();
+
+    let policy = AckPolicy {
+        every_n: 2,
+        every_duration: Duration::from_secs(60),
+    };
+    let summary = client
+        .subscribe_autoack(b"jobs", Handler, policy)
+        .await
+        .unwrap();
+
+    assert_eq!(summary.acked, 5);
+    // The last partial batch is acknowledged when the stream ends
+    assert_eq!(
+        *node.state.ack_calls.lock().unwrap(),
+        vec![
+            ids(&[&feed[0], &feed[1]]),
+            ids(&[&feed[2], &feed[3]]),
+            ids(&[&feed[4]]),
+        ]
+    );
+}
+
+async fn ack_calls_reach(node: &MockNode, count: usize) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while node.state.ack_calls.lock().unwrap().len() < count {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .expect("acks were not flushed in time");
+}
+
+#[tokio::test]
+async fn acks_after_every_duration_while_stream_is_idle() {
+    let key = signing_key(1);
+    let first = signed_envelope(&key, "jobs", 1, b"work");
+    let second = signed_envelope(&key, "jobs", 2, b"work");
+    let node = MockNode::with_feed(vec![first.clone()]);
+    let (live, rx) = mpsc::unbounded_channel();
+    *node.state.live.lock().unwrap() = Some(rx);
+    let mut client = Client::new(common::spawn(node.clone()).await)
+        .await
+        .unwrap();
+
+    let policy = AckPolicy {
+        every_n: 100,
+        every_duration: Duration::from_millis(50),
+    };
+    let run = tokio::spawn(async move { client.subscribe_autoack(b"jobs", Handler, policy).await });
+
+    // The stream stays open, so only the timer can flush the batch
+    ack_calls_reach(&node, 1).await;
+    live.send(second.clone()).unwrap();
+    ack_calls_reach(&node, 2).await;
+    drop(live);
+
+    let summary = run.await.unwrap().unwrap();
+    assert_eq!(summary.acked, 2);
+    assert_eq!(
+        *node.state.ack_calls.lock().unwrap(),
+        vec![ids(&[&first]), ids(&[&second])]
+    );
+}
+
+#[tokio::test]
+async fn failure_stops_acks_for_that_sender() {
+    let alice = signing_key(1);
+    let bob = signing_key(2);
+    let feed = vec![
+        signed_envelope(&alice, "jobs", 1, b"work"),
+        signed_envelope(&alice, "jobs", 2, b"boom"),
+        signed_envelope(&bob, "jobs", 1, b"work"),
+        signed_envelope(&alice, "jobs", 3, b"work"),
+        signed_envelope(&bob, "jobs", 2, b"work"),
+    ];
+    let node = MockNode::with_feed(feed.clone());
+    let mut client = Client::new(common::spawn(node.clone()).await)
+        .await
+        .unwrap();
+
+    let summary = client
+        .subscribe_autoack(b"jobs", Handler, AckPolicy::default())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        summary,
+        RunSummary {
+            handled: 4,
+            failed: 1,
+            rejected: 0,
+            acked: 3,
+            ack_failed: 0,
+            end: None,
+        }
+    );
+    // Alice's seq 3 was processed but stays unacknowledged behind the failed seq 2
+    assert_eq!(
+        *node.state.acked.lock().unwrap(),
+        ids(&[&feed[0], &feed[2], &feed[4]])
+    );
+}
+
+#[tokio::test]
+async fn unsupported_without_ack() {
+    let node = MockNode::default().without("ack");
+    let mut client = Client::new(common::spawn(node).await).await.unwrap();
+    let err = client
+        .subscribe_autoack(b"jobs", Handler, AckPolicy::default())
+        .await
+        .unwrap_err();
+    assert!(err.downcast_ref::<securefabric_sdk::Error>().is_some());
+}
@@ -0,0 +1,72 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode};
+use securefabric_sdk::batch::{failures, SendError};
+use securefabric_sdk::Client;
+use std::sync::atomic::Ordering;
+use tonic::Code;
+
+#[tokio::test]
+async fn partial_failures_are_reported_per_message_and_retried() {
+    let node = MockNode::default();
+    node.state.reject_alternate.store(true, Ordering::SeqCst);
+    let endpoint = common::spawn(node.clone()).await;
+    let mut client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+
+    let payloads: Vec<Vec<u8>> = (0..6).map(|i| format!("m{i}").into_bytes()).collect();
+    let results = client.send_batch("batch", &payloads).await.unwrap();
+
+    assert_eq!(results.len(), payloads.len());
+    let sent = node.sent();
+    assert_eq!(sent.len(), 3);
+    for (i, result) in results.iter().enumerate() {
+        if i % 2 == 0 {
+            let msg_id = result.as_ref().unwrap();
+            assert_eq!(sent[i / 2].msg_id, *msg_id);
+            assert_eq!(sent[i / 2].payload, payloads[i]);
+        } else {
+            assert!(matches!(
+                result,
+                Err(SendError::Rejected {
+                    code: Code::InvalidArgument,
+                    ..
+                })
+            ));
+        }
+    }
+
+    // Retrying only the failures sends m1, m3, m5; the mock rejects the second again
+    let retry = failures(&payloads, &results);
+    assert_eq!(retry, [&payloads[1], &payloads[3], &payloads[5]]);
+    let results = client.send_batch("batch", &retry).await.unwrap();
+    assert!(results[0].is_ok() && results[1].is_err() && results[2].is_ok());
+
+    let payloads: Vec<Vec<u8>> = node.sent().into_iter().map(|e| e.payload).collect();
+    assert_eq!(payloads, [b"m0", b"m2", b"m4", b"m1", b"m5"]);
+}
+
+#[tokio::test]
+async fn batch_is_sequenced_in_input_order() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+
+    let results = client
+        .send_batch("batch", &[b"a".as_slice(), b"b", b"c"])
+        .await
+        .unwrap();
+    assert!(results.iter().all(Result::is_ok));
+    client.send("batch", b"d").await.unwrap();
+
+    let seqs: Vec<u64> = node.sent().iter().map(|e| e.seq).collect();
+    assert_eq!(seqs, [1, 2, 3, 4]);
+    assert!(node.sent().iter().all(|e| client.verify(e).unwrap()));
+}
@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use futures::StreamExt;
+use securefabric_sdk::capabilities::Feature;
+use securefabric_sdk::fabric::InMemoryFabric;
+use securefabric_sdk::pb::Envelope;
+use securefabric_sdk::{Client, Error};
+
+fn batches(sizes: &[usize]) -> Vec<Vec<Envelope>> {
+    let key = signing_key(1);
+    let mut seq = 0;
+    sizes
+        .iter()
+        .map(|&size| {
+            (0..size)
+                .map(|_| {
+                    seq += 1;
+                    signed_envelope(&key, "events", seq, &[seq as u8])
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn flattens_batches_in_order() {
+    let node = MockNode::default();
+    *node.state.batches.lock().unwrap() = batches(&[3, 0, 1, 2]);
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let subscription = client.subscribe_batched(b"events").await.unwrap();
+    let seqs: Vec<u64> = subscription.map(|item| item.unwrap().seq).collect().await;
+    assert_eq!(seqs, [1, 2, 3, 4, 5, 6]);
+    assert_eq!(client.subscription_stats(b"events").unwrap().received, 6);
+}
+
+#[tokio::test]
+async fn raw_batches_keep_the_node_batching() {
+    let node = MockNode::default();
+    *node.state.batches.lock().unwrap() = batches(&[3, 0, 1, 2]);
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let mut subscription = client.subscribe_batched(b"events").await.unwrap();
+    assert_eq!(subscription.next().await.unwrap().unwrap().seq, 1);
+
+    // The rest of the partly consumed batch comes first; empty batches are skipped
+    let raw: Vec<Vec<u64>> = subscription
+        .raw_batches()
+        .map(|batch| batch.unwrap().iter().map(|e| e.seq).collect())
+        .collect()
+        .await;
+    assert_eq!(raw, [vec![2, 3], vec![4], vec![5, 6]]);
+}
+
+#[tokio::test]
+async fn unsupported_without_batched_subscribe() {
+    let node = MockNode::default().without("batched_subscribe");
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let error = client.subscribe_batched(b"events").await.err().unwrap();
+    assert_eq!(
+        error.downcast_ref::<Error>(),
+        Some(&Error::Unsupported {
+            feature: Feature::BatchedSubscribe
+        })
+    );
+}
+
+#[tokio::test]
+async fn in_memory_fabric_pushes_the_backlog_in_batches() {
+    let fabric = InMemoryFabric::new();
+    let mut sender = fabric
+        .client()
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+    for i in 0..5u8 {
+        sender.send("events", &[i]).await.unwrap();
+    }
+
+    let mut receiver = fabric.client().await.unwrap();
+    let mut raw = receiver
+        .subscribe_batched(b"events")
+        .await
+        .unwrap()
+        .raw_batches();
+    let batch = raw.next().await.unwrap().unwrap();
+    let payloads: Vec<Vec<u8>> = batch.into_iter().map(|e| e.payload).collect();
+    assert_eq!(payloads, [[0], [1], [2], [3], [4]]);
+}
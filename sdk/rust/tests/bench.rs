@@ -0,0 +1,60 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use securefabric_sdk::bench::{self, BenchConfig, HEADER_LEN};
+use std::time::Duration;
+
+#[tokio::test]
+async fn self_test_reports_sane_numbers() {
+    let config = BenchConfig {
+        producers: 2,
+        subscribers: 3,
+        messages: 50,
+        message_size: 64,
+        timeout: Duration::from_secs(20),
+        ..Default::default()
+    };
+    let report = bench::self_test(&config).await.unwrap();
+
+    assert_eq!(report.sent, 100);
+    assert_eq!(report.received, 300);
+    assert!(report.is_complete());
+    assert!(report.elapsed > Duration::ZERO && report.elapsed < config.timeout);
+    assert!(report.messages_per_sec().is_finite() && report.messages_per_sec() > 0.0);
+    assert_eq!(report.bytes_per_sec(), report.messages_per_sec() * 64.0);
+    let deliveries = report.messages_per_sec() * 3.0;
+    assert!((report.deliveries_per_sec() - deliveries).abs() < deliveries * 1e-9);
+
+    let latency = report.latency.unwrap();
+    assert!(latency.p50 <= latency.p90 && latency.p90 <= latency.p99);
+    assert!(latency.p99 <= latency.max && latency.max <= report.elapsed);
+    assert!(report.to_string().contains("received 300 of 300"));
+}
+
+#[tokio::test]
+async fn publish_only_run_has_no_latency() {
+    let config = BenchConfig {
+        subscribers: 0,
+        messages: 10,
+        ..Default::default()
+    };
+    let report = bench::self_test(&config).await.unwrap();
+    assert_eq!((report.sent, report.received), (10, 0));
+    assert!(report.is_complete());
+    assert_eq!(report.latency, None);
+}
+
+#[tokio::test]
+async fn invalid_load_rejected() {
+    for config in [
+        BenchConfig {
+            message_size: HEADER_LEN - 1,
+            ..Default::default()
+        },
+        BenchConfig {
+            producers: 0,
+            ..Default::default()
+        },
+    ] {
+        assert!(bench::self_test(&config).await.is_err());
+    }
+}
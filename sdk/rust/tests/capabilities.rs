@@ -0,0 +1,94 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode};
+use securefabric_sdk::capabilities::Feature;
+use securefabric_sdk::{Client, Error};
+use std::sync::atomic::Ordering;
+
+#[tokio::test]
+async fn advertised_features_are_cached_per_connection() {
+    let node = MockNode::default().without("send_batch");
+    let state = node.state.clone();
+    let mut client = Client::new(common::spawn(node).await).await.unwrap();
+
+    let capabilities = client.capabilities().await.unwrap();
+    assert!(capabilities.is_known());
+    assert_eq!(capabilities.supports(Feature::Ack), Some(true));
+    assert_eq!(capabilities.supports(Feature::SendBatch), Some(false));
+    assert!(!capabilities.features().any(|name| name == "send_batch"));
+
+    let mut clone = client.clone();
+    clone.ping().await.unwrap();
+    client.ack(b"demo", vec!["ab".repeat(32)]).await.unwrap();
+    assert_eq!(clone.capabilities().await.unwrap(), capabilities);
+    assert_eq!(state.capability_requests.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn unadvertised_feature_fails_without_calling_the_node() {
+    let node = MockNode::default().without("ack").without("send_batch");
+    let state = node.state.clone();
+    let mut client = Client::new(common::spawn(node).await)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+
+    let err = client
+        .ack(b"demo", vec!["ab".repeat(32)])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<Error>(),
+        Some(&Error::Unsupported {
+            feature: Feature::Ack
+        })
+    );
+    assert_eq!(err.to_string(), "node does not support ack");
+
+    let Err(err) = client.send_batch("demo", &[b"one"]).await else {
+        panic!("batch sent to a node without SendBatch");
+    };
+    assert_eq!(
+        err.downcast_ref::<Error>(),
+        Some(&Error::Unsupported {
+            feature: Feature::SendBatch
+        })
+    );
+    assert!(state.acked.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn older_node_reports_unimplemented_as_unsupported() {
+    let node = MockNode::default().without("head").without("ping");
+    node.state
+        .capabilities_unimplemented
+        .store(true, Ordering::SeqCst);
+    let mut client = Client::new(common::spawn(node).await).await.unwrap();
+
+    let capabilities = client.capabilities().await.unwrap();
+    assert!(!capabilities.is_known());
+    assert_eq!(capabilities.supports(Feature::Head), None);
+
+    let err = client
+        .lag(b"demo", &signing_key(2).verifying_key().into())
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<Error>(),
+        Some(&Error::Unsupported {
+            feature: Feature::Head
+        })
+    );
+    let err = client.ping().await.unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<Error>(),
+        Some(&Error::Unsupported {
+            feature: Feature::Ping
+        })
+    );
+
+    // Warming up still succeeds without Ping
+    client.warmup().await.unwrap();
+}
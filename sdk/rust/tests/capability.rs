@@ -0,0 +1,107 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::signing_key;
+use securefabric_sdk::crypto::capability::{
+    mint, verify_capability, CapabilityError, Ops, SIGNING_CONTEXT,
+};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+fn expiry() -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(1_800_000_000_000)
+}
+
+fn before_expiry() -> SystemTime {
+    expiry() - Duration::from_secs(60)
+}
+
+#[test]
+fn minted_token_verifies_within_scope() {
+    let key = signing_key(1);
+    let token = mint(&key, "orders.eu", Ops::PUBLISH | Ops::SUBSCRIBE, expiry()).unwrap();
+
+    let capability = verify_capability(&token, "orders.eu", Ops::PUBLISH, before_expiry()).unwrap();
+    assert_eq!(capability.issuer, key.verifying_key());
+    assert_eq!(capability.topic, "orders.eu");
+    assert!(capability.ops.contains(Ops::SUBSCRIBE));
+    assert_eq!(capability.expiry, expiry());
+}
+
+#[test]
+fn token_layout_matches_spec() {
+    let key = signing_key(1);
+    let token = mint(&key, "t", Ops::SUBSCRIBE, expiry()).unwrap();
+
+    assert_eq!(token.len(), 44 + 1 + 64);
+    assert_eq!(token[0], 1);
+    assert_eq!(&token[1..33], key.verifying_key().as_bytes());
+    assert_eq!(token[33], 0x02);
+    assert_eq!(&token[34..42], &1_800_000_000_000u64.to_le_bytes());
+    assert_eq!(&token[42..44], &[1, 0]);
+    assert_eq!(token[44], b't');
+
+    let mut signed = SIGNING_CONTEXT.to_vec();
+    signed.extend_from_slice(&token[..45]);
+    let sig = ed25519_dalek::Signature::from_bytes(token[45..].try_into().unwrap());
+    key.verifying_key().verify_strict(&signed, &sig).unwrap();
+}
+
+#[test]
+fn expired_token_rejected() {
+    let token = mint(&signing_key(1), "orders", Ops::PUBLISH, expiry()).unwrap();
+    assert_eq!(
+        verify_capability(&token, "orders", Ops::PUBLISH, expiry()),
+        Err(CapabilityError::Expired)
+    );
+}
+
+#[test]
+fn topic_and_operation_scope_enforced() {
+    let token = mint(&signing_key(1), "orders", Ops::SUBSCRIBE, expiry()).unwrap();
+
+    assert_eq!(
+        verify_capability(&token, "orders.eu", Ops::SUBSCRIBE, before_expiry()),
+        Err(CapabilityError::TopicMismatch {
+            granted: "orders".to_string(),
+            requested: "orders.eu".to_string(),
+        })
+    );
+    assert_eq!(
+        verify_capability(&token, "orders", Ops::PUBLISH, before_expiry()),
+        Err(CapabilityError::NotPermitted)
+    );
+}
+
+#[test]
+fn tampered_or_malformed_tokens_rejected() {
+    let token = mint(&signing_key(1), "orders", Ops::SUBSCRIBE, expiry()).unwrap();
+
+    // Widening the granted operations invalidates the signature
+    let mut widened = token.clone();
+    widened[33] = 0x03;
+    assert_eq!(
+        verify_capability(&widened, "orders", Ops::PUBLISH, before_expiry()),
+        Err(CapabilityError::BadSignature)
+    );
+
+    assert_eq!(
+        verify_capability(
+            &token[..token.len() - 1],
+            "orders",
+            Ops::SUBSCRIBE,
+            before_expiry()
+        ),
+        Err(CapabilityError::Malformed)
+    );
+    let mut future = token.clone();
+    future[0] = 2;
+    assert_eq!(
+        verify_capability(&future, "orders", Ops::SUBSCRIBE, before_expiry()),
+        Err(CapabilityError::UnsupportedVersion(2))
+    );
+    assert_eq!(
+        mint(&signing_key(1), &"x".repeat(256), Ops::PUBLISH, expiry()),
+        Err(CapabilityError::TopicTooLong(256))
+    );
+}
@@ -0,0 +1,214 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use ed25519_dalek::Signer;
+use futures::StreamExt;
+use securefabric_sdk::chain::{ChainError, ChainViolation, SenderChainVerifier};
+use securefabric_sdk::crypto::SignOrder;
+use securefabric_sdk::{msg_id, Client};
+
+#[test]
+fn clean_chain_accepted() {
+    let key = signing_key(1);
+    let mut verifier = SenderChainVerifier::new(key.verifying_key());
+
+    for seq in 5..10 {
+        let envelope = signed_envelope(&key, "demo", seq, b"payload");
+        assert_eq!(verifier.check(&envelope), Ok(()));
+    }
+    assert_eq!(verifier.next_seq(), Some(10));
+}
+
+#[test]
+fn gap_flagged_and_chain_resynchronised() {
+    let key = signing_key(1);
+    let mut verifier = SenderChainVerifier::starting_at(key.verifying_key(), 1);
+
+    assert_eq!(
+        verifier.check(&signed_envelope(&key, "demo", 1, b"a")),
+        Ok(())
+    );
+    assert_eq!(
+        verifier.check(&signed_envelope(&key, "demo", 4, b"b")),
+        Err(ChainViolation::Gap {
+            expected: 2,
+            got: 4
+        })
+    );
+    assert_eq!(
+        verifier.check(&signed_envelope(&key, "demo", 5, b"c")),
+        Ok(())
+    );
+}
+
+#[test]
+fn out_of_order_injection_rejected() {
+    let key = signing_key(1);
+    let mut verifier = SenderChainVerifier::new(key.verifying_key());
+
+    for seq in 1..=3 {
+        verifier
+            .check(&signed_envelope(&key, "demo", seq, b"x"))
+            .unwrap();
+    }
+
+    // Replaying an earlier message
+    assert_eq!(
+        verifier.check(&signed_envelope(&key, "demo", 2, b"x")),
+        Err(ChainViolation::OutOfOrder { head: 3, got: 2 })
+    );
+    // The head is unaffected by the rejected envelope
+    assert_eq!(
+        verifier.check(&signed_envelope(&key, "demo", 4, b"x")),
+        Ok(())
+    );
+}
+
+#[test]
+fn fork_detected_for_conflicting_seq() {
+    let key = signing_key(1);
+    let mut verifier = SenderChainVerifier::new(key.verifying_key());
+    let original = signed_envelope(&key, "demo", 1, b"x");
+    verifier.check(&original).unwrap();
+
+    let mut conflicting = signed_envelope(&key, "demo", 1, b"y");
+    conflicting.nonce[23] = 0xff;
+    conflicting.msg_id = {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&conflicting.pubkey);
+        hasher.update(&conflicting.seq.to_le_bytes());
+        hasher.update(&conflicting.nonce);
+        hasher.finalize().to_hex().to_string()
+    };
+
+    assert!(matches!(
+        verifier.check(&conflicting),
+        Err(ChainViolation::Fork { seq: 1, .. })
+    ));
+}
+
+#[test]
+fn foreign_or_tampered_envelopes_rejected() {
+    let key = signing_key(1);
+    let mut verifier = SenderChainVerifier::new(key.verifying_key());
+
+    let foreign = signed_envelope(&signing_key(2), "demo", 1, b"x");
+    assert_eq!(
+        verifier.check(&foreign),
+        Err(ChainViolation::BadSignature { seq: 1 })
+    );
+
+    let mut tampered = signed_envelope(&key, "demo", 1, b"x");
+    tampered.payload = b"y".to_vec();
+    assert_eq!(
+        verifier.check(&tampered),
+        Err(ChainViolation::BadSignature { seq: 1 })
+    );
+    assert_eq!(verifier.next_seq(), None);
+}
+
+#[tokio::test]
+async fn subscribe_chain_verified_reports_gap_and_skips_other_senders() {
+    let key = signing_key(1);
+    let other = signing_key(2);
+    let node = MockNode::with_feed(vec![
+        signed_envelope(&key, "demo", 1, b"a"),
+        signed_envelope(&other, "demo", 1, b"noise"),
+        signed_envelope(&key, "demo", 2, b"b"),
+        signed_envelope(&key, "demo", 4, b"d"),
+    ]);
+    let endpoint = common::spawn(node).await;
+
+    let mut client = Client::new(endpoint).await.unwrap();
+    let items: Vec<_> = client
+        .subscribe_chain_verified(b"demo", key.verifying_key())
+        .await
+        .unwrap()
+        .collect()
+        .await;
+
+    let seqs: Vec<u64> = items
+        .iter()
+        .filter_map(|item| item.as_ref().ok().map(|e| e.seq))
+        .collect();
+    assert_eq!(seqs, vec![1, 2, 4]);
+    assert!(matches!(
+        items[2],
+        Err(ChainError::Violation(ChainViolation::Gap {
+            expected: 3,
+            got: 4
+        }))
+    ));
+}
+
+#[test]
+fn renumbered_envelope_rejected() {
+    let key = signing_key(1);
+    let mut verifier = SenderChainVerifier::starting_at(key.verifying_key(), 1);
+    verifier
+        .check(&signed_envelope(&key, "demo", 1, b"a"))
+        .unwrap();
+
+    // A relay renumbers seq 3 to 2 to hide that 2 was dropped
+    let mut renumbered = signed_envelope(&key, "demo", 3, b"c");
+    renumbered.seq = 2;
+    renumbered.msg_id = msg_id::compute(&msg_id::Blake3, &renumbered);
+    assert_eq!(
+        verifier.check(&renumbered),
+        Err(ChainViolation::BadSignature { seq: 2 })
+    );
+    assert_eq!(verifier.next_seq(), Some(2));
+}
+
+#[test]
+fn unsigned_seq_rejected() {
+    let key = signing_key(1);
+    let mut verifier = SenderChainVerifier::new(key.verifying_key());
+
+    let aad = br#"{"key_version":0,"topic":"demo"}"#.to_vec();
+    let mut envelope = signed_envelope(&key, "demo", 1, b"x");
+    envelope.sig = key
+        .sign(&[aad.as_slice(), b"x"].concat())
+        .to_bytes()
+        .to_vec();
+    envelope.aad = aad;
+    assert_eq!(
+        verifier.check(&envelope),
+        Err(ChainViolation::UnsignedSeq { seq: 1 })
+    );
+}
+
+#[tokio::test]
+async fn plaintext_signed_encrypted_chain_verifies() {
+    let topic_key = [7u8; 32];
+    let key = signing_key(1);
+    let sender_node = MockNode::default();
+    let mut sender = Client::new(common::spawn(sender_node.clone()).await)
+        .await
+        .unwrap()
+        .with_signing_key(key.clone())
+        .with_encryption(topic_key, 1)
+        .unwrap()
+        .with_sign_order(SignOrder::SignThenEncrypt);
+    for payload in [b"a", b"b", b"c"] {
+        sender.send("demo", payload).await.unwrap();
+    }
+
+    let node = MockNode::with_feed(sender_node.sent());
+    let mut client = Client::new(common::spawn(node).await)
+        .await
+        .unwrap()
+        .with_encryption(topic_key, 1)
+        .unwrap();
+    let items: Vec<_> = client
+        .subscribe_chain_verified(b"demo", key.verifying_key())
+        .await
+        .unwrap()
+        .collect()
+        .await;
+
+    let seqs: Vec<u64> = items.into_iter().map(|item| item.unwrap().seq).collect();
+    assert_eq!(seqs, vec![1, 2, 3]);
+}
@@ -0,0 +1,149 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode};
+use securefabric_sdk::breaker::{CbConfig, CircuitState};
+use securefabric_sdk::{Client, Error};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+const COOLDOWN: Duration = Duration::from_millis(200);
+
+async fn client_with_breaker(node: &MockNode) -> Client {
+    let endpoint = common::spawn(node.clone()).await;
+    Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_circuit_breaker(CbConfig {
+            failure_threshold: 3,
+            cooldown: COOLDOWN,
+        })
+}
+
+fn attempts(node: &MockNode) -> usize {
+    node.state.send_attempts.load(Ordering::SeqCst)
+}
+
+#[tokio::test]
+async fn cycles_closed_open_half_open_closed() {
+    let node = MockNode::default();
+    let mut client = client_with_breaker(&node).await;
+    assert_eq!(client.circuit_state(), Some(CircuitState::Closed));
+
+    node.state.sends_unavailable.store(true, Ordering::SeqCst);
+    for _ in 0..2 {
+        assert!(client.send("flaky", b"x").await.is_err());
+        assert_eq!(client.circuit_state(), Some(CircuitState::Closed));
+    }
+    assert!(client.send("flaky", b"x").await.is_err());
+    assert_eq!(client.circuit_state(), Some(CircuitState::Open));
+    assert_eq!(attempts(&node), 3);
+
+    // Open: sends fail without reaching the node
+    let err = client.send("flaky", b"x").await.unwrap_err();
+    match err.downcast_ref::<Error>() {
+        Some(Error::CircuitOpen { retry_after }) => assert!(*retry_after <= COOLDOWN),
+        other => panic!("expected CircuitOpen, got {other:?}"),
+    }
+    assert_eq!(attempts(&node), 3);
+
+    tokio::time::sleep(COOLDOWN).await;
+    assert_eq!(client.circuit_state(), Some(CircuitState::HalfOpen));
+
+    // The trial send reaches the recovered node and closes the circuit
+    node.state.sends_unavailable.store(false, Ordering::SeqCst);
+    client.send("flaky", b"trial").await.unwrap();
+    assert_eq!(client.circuit_state(), Some(CircuitState::Closed));
+    assert_eq!(attempts(&node), 4);
+    client.send("flaky", b"after").await.unwrap();
+    assert_eq!(node.state.sent.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn failed_trial_reopens_the_circuit() {
+    let node = MockNode::default();
+    let mut client = client_with_breaker(&node).await;
+    node.state.sends_unavailable.store(true, Ordering::SeqCst);
+    for _ in 0..3 {
+        assert!(client.send("flaky", b"x").await.is_err());
+    }
+    tokio::time::sleep(COOLDOWN).await;
+
+    let err = client.send("flaky", b"trial").await.unwrap_err();
+    assert!(err.downcast_ref::<Error>().is_none());
+    assert_eq!(attempts(&node), 4);
+    assert_eq!(client.circuit_state(), Some(CircuitState::Open));
+    assert!(matches!(
+        client
+            .send("flaky", b"x")
+            .await
+            .unwrap_err()
+            .downcast_ref::<Error>(),
+        Some(Error::CircuitOpen { .. })
+    ));
+}
+
+#[tokio::test]
+async fn success_resets_the_failure_count() {
+    let node = MockNode::default();
+    let mut client = client_with_breaker(&node).await;
+    for _ in 0..3 {
+        node.state.sends_unavailable.store(true, Ordering::SeqCst);
+        for _ in 0..2 {
+            assert!(client.send("flaky", b"x").await.is_err());
+        }
+        node.state.sends_unavailable.store(false, Ordering::SeqCst);
+        client.send("flaky", b"ok").await.unwrap();
+    }
+    assert_eq!(client.circuit_state(), Some(CircuitState::Closed));
+}
+
+#[tokio::test]
+async fn clones_share_the_breaker() {
+    let node = MockNode::default();
+    let mut client = client_with_breaker(&node).await;
+    let mut clone = client.clone();
+    node.state.sends_unavailable.store(true, Ordering::SeqCst);
+    for _ in 0..3 {
+        assert!(clone.send("flaky", b"x").await.is_err());
+    }
+    assert_eq!(client.circuit_state(), Some(CircuitState::Open));
+    assert!(client.send("flaky", b"x").await.is_err());
+    assert_eq!(attempts(&node), 3);
+}
+
+#[tokio::test]
+async fn no_breaker_by_default() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node).await;
+    let client = Client::new(endpoint).await.unwrap();
+    assert_eq!(client.circuit_state(), None);
+}
+
+#[tokio::test]
+async fn batches_count_as_one_send() {
+    let node = MockNode::default();
+    let mut client = client_with_breaker(&node).await;
+    node.state.sends_unavailable.store(true, Ordering::SeqCst);
+    for _ in 0..3 {
+        assert!(client.send_batch("flaky", &[b"a", b"b"]).await.is_err());
+    }
+    assert_eq!(client.circuit_state(), Some(CircuitState::Open));
+    assert_eq!(attempts(&node), 3);
+
+    // Open: batches fail before they are sequenced or reach the node
+    let err = client.send_batch("flaky", &[b"a"]).await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::CircuitOpen { .. })
+    ));
+    assert_eq!(attempts(&node), 3);
+
+    tokio::time::sleep(COOLDOWN).await;
+    node.state.sends_unavailable.store(false, Ordering::SeqCst);
+    let results = client.send_batch("flaky", &[b"trial"]).await.unwrap();
+    assert!(results[0].is_ok());
+    assert_eq!(client.circuit_state(), Some(CircuitState::Closed));
+}
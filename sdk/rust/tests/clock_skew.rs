@@ -0,0 +1,114 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode};
+use securefabric_sdk::capabilities::Feature;
+use securefabric_sdk::clock::{ClockSkew, SkewAction};
+use securefabric_sdk::{Client, Error};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const AHEAD_MS: i64 = 90_000;
+const TOLERANCE_MS: i64 = 1_000;
+
+async fn skewed_node() -> (MockNode, Client) {
+    let node = MockNode::default();
+    node.state.clock_offset_ms.store(AHEAD_MS, Ordering::SeqCst);
+    let endpoint = common::spawn(node.clone()).await;
+    let client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+    (node, client)
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64
+}
+
+#[tokio::test]
+async fn detects_skew_of_the_node_clock() {
+    let (_node, mut client) = skewed_node().await;
+
+    let server_time = client.server_time().await.unwrap();
+    let ahead = server_time.duration_since(SystemTime::now()).unwrap();
+    assert!(ahead.as_millis().abs_diff(AHEAD_MS as u128) < TOLERANCE_MS as u128);
+
+    assert!(client.last_clock_skew().is_none());
+    let skew = client.clock_skew().await.unwrap();
+    assert!((skew.offset_ms - AHEAD_MS).abs() < TOLERANCE_MS, "{skew:?}");
+    assert!(skew.magnitude() > Duration::from_secs(60));
+    assert_eq!(client.clone().last_clock_skew(), Some(skew));
+}
+
+#[tokio::test]
+async fn skew_beyond_the_bound_warns_and_rejects_sends() {
+    let (node, client) = skewed_node().await;
+    let warnings = Arc::new(Mutex::new(Vec::<ClockSkew>::new()));
+    let seen = warnings.clone();
+    let mut client = client
+        .with_max_clock_skew(Duration::from_secs(5), SkewAction::Reject)
+        .with_skew_warning(move |skew| seen.lock().unwrap().push(*skew));
+
+    // Nothing is rejected before a measurement
+    client.send("ui", b"unmeasured").await.unwrap();
+
+    let skew = client.clock_skew().await.unwrap();
+    assert_eq!(*warnings.lock().unwrap(), vec![skew]);
+    let error = client.send("ui", b"skewed").await.unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<Error>(),
+        Some(&Error::ClockSkew {
+            offset_ms: skew.offset_ms,
+            max: Duration::from_secs(5)
+        })
+    );
+    assert_eq!(node.sent().len(), 1);
+
+    // Back within bounds, sends resume and nothing is reported
+    node.state.clock_offset_ms.store(0, Ordering::SeqCst);
+    client.clock_skew().await.unwrap();
+    assert_eq!(warnings.lock().unwrap().len(), 1);
+    client.send("ui", b"in sync").await.unwrap();
+    assert_eq!(node.sent().len(), 2);
+}
+
+#[tokio::test]
+async fn adjust_shifts_sent_timestamps_to_the_node_clock() {
+    let (node, client) = skewed_node().await;
+    let mut client = client.with_max_clock_skew(Duration::from_secs(5), SkewAction::Adjust);
+    client.clock_skew().await.unwrap();
+
+    client.send("ui", b"adjusted").await.unwrap();
+    let sent = &node.sent()[0];
+    let ahead = sent.timestamp_ms as i64 - now_ms();
+    assert!((ahead - AHEAD_MS).abs() < TOLERANCE_MS, "{ahead}");
+    let aad: serde_json::Value = serde_json::from_slice(&sent.aad).unwrap();
+    assert_eq!(aad["ts"], sent.timestamp_ms);
+    assert!(client.verify(sent).unwrap());
+
+    // Warn leaves timestamps alone
+    let mut client = client.with_max_clock_skew(Duration::from_secs(5), SkewAction::Warn);
+    client.send("ui", b"local").await.unwrap();
+    assert!((node.sent()[1].timestamp_ms as i64 - now_ms()).abs() < TOLERANCE_MS);
+}
+
+#[tokio::test]
+async fn nodes_without_server_time_are_unsupported() {
+    let node = MockNode::default().without("server_time");
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let error = client.clock_skew().await.unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<Error>(),
+        Some(&Error::Unsupported {
+            feature: Feature::ServerTime
+        })
+    );
+}
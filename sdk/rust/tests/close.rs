@@ -0,0 +1,97 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode};
+use futures::StreamExt;
+use securefabric_sdk::close::CloseSummary;
+use securefabric_sdk::{Client, Error};
+use std::time::Duration;
+
+async fn client(node: &MockNode) -> Client {
+    Client::new(common::spawn(node.clone()).await)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+}
+
+fn is_closed(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<Error>() == Some(&Error::Closed)
+}
+
+#[tokio::test]
+async fn ends_subscriptions_and_flushes_sends() {
+    let node = MockNode::default();
+    let mut client = client(&node).await;
+
+    let _live = node.live_feed();
+    let waiting = client.subscribe(b"events").await.unwrap();
+    let _live_too = node.live_feed();
+    let idle = client.subscribe(b"audit").await.unwrap();
+    assert_eq!(client.active_subscriptions().len(), 2);
+    // One subscription is parked waiting for envelopes when the client closes
+    let waiting = tokio::spawn(waiting.count());
+
+    let gate = node.hold_sends();
+    let send = client.send_async("events", &[], b"last").await.unwrap();
+
+    let (summary, ()) = tokio::join!(client.close_with_timeout(Duration::from_secs(5)), async {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        gate.add_permits(1);
+    });
+    assert_eq!(
+        summary.unwrap(),
+        CloseSummary {
+            subscriptions_cancelled: 2,
+            sends_flushed: 1,
+            sends_cancelled: 0,
+        }
+    );
+    send.await.unwrap();
+    assert_eq!(node.state.sent.lock().unwrap().len(), 1);
+
+    assert_eq!(waiting.await.unwrap(), 0);
+    assert_eq!(idle.count().await, 0);
+    assert!(client.active_subscriptions().is_empty());
+}
+
+#[tokio::test]
+async fn cancels_sends_still_running_at_the_timeout() {
+    let node = MockNode::default();
+    let mut client = client(&node).await;
+    let _gate = node.hold_sends();
+    let send = client.send_async("events", &[], b"stuck").await.unwrap();
+
+    let summary = client
+        .close_with_timeout(Duration::from_millis(100))
+        .await
+        .unwrap();
+    assert_eq!(
+        summary,
+        CloseSummary {
+            subscriptions_cancelled: 0,
+            sends_flushed: 0,
+            sends_cancelled: 1,
+        }
+    );
+    assert!(is_closed(&send.await.unwrap_err()));
+}
+
+#[tokio::test]
+async fn every_call_fails_after_close() {
+    let node = MockNode::default();
+    let mut client = client(&node).await;
+    let mut clone = client.clone();
+    client.close().await.unwrap();
+
+    assert!(is_closed(&client.send("events", b"x").await.unwrap_err()));
+    assert!(is_closed(&clone.send("events", b"x").await.unwrap_err()));
+    assert!(is_closed(
+        &clone.send_async("events", &[], b"x").await.unwrap_err()
+    ));
+    assert!(is_closed(&client.subscribe(b"events").await.err().unwrap()));
+    assert!(is_closed(&client.ping().await.unwrap_err()));
+    assert!(is_closed(&client.capabilities().await.unwrap_err()));
+    assert!(is_closed(&client.close().await.unwrap_err()));
+    assert!(node.state.sent.lock().unwrap().is_empty());
+}
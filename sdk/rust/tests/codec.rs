@@ -0,0 +1,175 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Regression tests for the hardened envelope decoder.
+
+use prost::Message;
+use securefabric_sdk::codec::{CodecError, MAX_ENVELOPE_LEN};
+use securefabric_sdk::pb::{Envelope, WrappedKey};
+
+fn sample_envelope() -> Envelope {
+    Envelope {
+        pubkey: vec![1u8; 32],
+        sig: vec![2u8; 64],
+        nonce: vec![3u8; 24],
+        aad: br#"{"key_version":0,"topic":"demo"}"#.to_vec(),
+        payload: b"hello".to_vec(),
+        seq: 7,
+        msg_id: "ab".repeat(32),
+        key_version: 0,
+        topic: "demo".to_string(),
+        to: Vec::new(),
+        timestamp_ms: 0,
+        sig_scheme: 0,
+        key: Vec::new(),
+        tombstone: false,
+        ephemeral_key: Vec::new(),
+        wrapped_keys: Vec::new(),
+        cosignatures: Vec::new(),
+        payload_digest: Vec::new(),
+        msg_id_hash: 0,
+    }
+}
+
+#[test]
+fn valid_envelope_round_trips() {
+    let envelope = sample_envelope();
+    let decoded = Envelope::try_from_bytes(&envelope.encode_to_vec()).unwrap();
+    assert_eq!(decoded, envelope);
+}
+
+#[test]
+fn empty_input_decodes_to_default() {
+    assert_eq!(Envelope::try_from_bytes(&[]).unwrap(), Envelope::default());
+}
+
+#[test]
+fn oversized_frame_rejected_before_decoding() {
+    let frame = vec![0u8; MAX_ENVELOPE_LEN + 1];
+    assert_eq!(
+        Envelope::try_from_bytes(&frame),
+        Err(CodecError::TooLarge {
+            len: MAX_ENVELOPE_LEN + 1,
+            max: MAX_ENVELOPE_LEN,
+        })
+    );
+}
+
+#[test]
+fn huge_length_prefix_does_not_allocate() {
+    // Field 1 (pubkey) claiming a ~4 GiB body with no bytes behind it
+    let frame = [0x0a, 0xff, 0xff, 0xff, 0xff, 0x0f];
+    assert!(matches!(
+        Envelope::try_from_bytes(&frame),
+        Err(CodecError::Malformed(_))
+    ));
+}
+
+#[test]
+fn truncated_varint_rejected() {
+    // Field 6 (seq) with a continuation bit and nothing after it
+    let frame = [0x30, 0x80];
+    assert!(matches!(
+        Envelope::try_from_bytes(&frame),
+        Err(CodecError::Malformed(_))
+    ));
+}
+
+#[test]
+fn overlong_varint_rejected() {
+    let mut frame = vec![0x30];
+    frame.extend_from_slice(&[0xff; 10]);
+    frame.push(0x01);
+    assert!(matches!(
+        Envelope::try_from_bytes(&frame),
+        Err(CodecError::Malformed(_))
+    ));
+}
+
+#[test]
+fn invalid_wire_type_and_field_zero_rejected() {
+    for frame in [&[0x0f][..], &[0x00][..]] {
+        assert!(matches!(
+            Envelope::try_from_bytes(frame),
+            Err(CodecError::Malformed(_))
+        ));
+    }
+}
+
+#[test]
+fn non_utf8_topic_rejected() {
+    // Field 9 (topic) holding a lone 0xff byte
+    let frame = [0x4a, 0x01, 0xff];
+    assert!(matches!(
+        Envelope::try_from_bytes(&frame),
+        Err(CodecError::Malformed(_))
+    ));
+}
+
+#[test]
+fn wrong_fixed_field_lengths_rejected() {
+    let mut envelope = sample_envelope();
+    envelope.pubkey.pop();
+    assert_eq!(
+        Envelope::try_from_bytes(&envelope.encode_to_vec()),
+        Err(CodecError::InvalidLength {
+            field: "pubkey",
+            len: 31,
+        })
+    );
+
+    let mut envelope = sample_envelope();
+    envelope.sig.push(0);
+    assert_eq!(
+        Envelope::try_from_bytes(&envelope.encode_to_vec()),
+        Err(CodecError::InvalidLength {
+            field: "sig",
+            len: 65,
+        })
+    );
+
+    let mut envelope = sample_envelope();
+    envelope.nonce.truncate(12);
+    assert_eq!(
+        Envelope::try_from_bytes(&envelope.encode_to_vec()),
+        Err(CodecError::InvalidLength {
+            field: "nonce",
+            len: 12,
+        })
+    );
+}
+
+#[test]
+fn wrong_sealed_field_lengths_rejected() {
+    let slot = WrappedKey {
+        recipient: vec![4u8; 32],
+        wrapped: vec![5u8; 48],
+    };
+    let mut envelope = sample_envelope();
+    envelope.ephemeral_key = vec![6u8; 32];
+    envelope.wrapped_keys = vec![slot.clone()];
+    let decoded = Envelope::try_from_bytes(&envelope.encode_to_vec()).unwrap();
+    assert_eq!(decoded, envelope);
+
+    let mut short = envelope.clone();
+    short.ephemeral_key.pop();
+    assert_eq!(
+        Envelope::try_from_bytes(&short.encode_to_vec()),
+        Err(CodecError::InvalidLength {
+            field: "ephemeral_key",
+            len: 31,
+        })
+    );
+
+    let mut long = envelope;
+    long.wrapped_keys.push(WrappedKey {
+        wrapped: vec![5u8; 49],
+        ..slot
+    });
+    assert_eq!(
+        Envelope::try_from_bytes(&long.encode_to_vec()),
+        Err(CodecError::InvalidLength {
+            field: "wrapped_keys.wrapped",
+            len: 49,
+        })
+    );
+}
@@ -0,0 +1,787 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Shared helpers for integration tests: a mock node and envelope fixtures.
+
+#![allow(dead_code)]
+
+use ed25519_dalek::{Signer, SigningKey};
+use futures::stream::{self, BoxStream, StreamExt};
+use securefabric_sdk::headers::HeaderFilter;
+use securefabric_sdk::pb::fabric_node_server::{FabricNode, FabricNodeServer};
+use securefabric_sdk::pb::{
+    AckReq, AckResp, CapabilitiesReq, CapabilitiesResp, Envelope, EnvelopeBatch, GetMessageReq,
+    GetMessageResp, HeadReq, HeadResp, JoinResp, NodeId, NodeInfo, PingReq, PingResp,
+    SendBatchResp, SendReq, SendResp, SendResult, StatsReq, StatsResp, SubscribeControl,
+    SubscribeReq,
+};
+use securefabric_sdk::subscription::{shard_for, END_REASON_METADATA};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, Semaphore};
+use tokio_stream::wrappers::TcpListenerStream;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tonic::codec::CompressionEncoding;
+use tonic::metadata::{MetadataMap, MetadataValue};
+use tonic::transport::{Certificate, Identity, ServerTlsConfig};
+use tonic::{Code, Request, Response, Status, Streaming};
+
+/// State shared between a [`MockNode`] and the test driving it
+#[derive(Default)]
+pub struct MockState {
+    /// Envelopes received via `Send`, in arrival order
+    pub sent: Mutex<Vec<Envelope>>,
+    /// Envelopes replayed to every `Subscribe` call
+    pub feed: Mutex<Vec<Envelope>>,
+    /// Message IDs received via `Ack`, in arrival order
+    pub acked: Mutex<Vec<String>>,
+    /// Message IDs of each `Ack` call, in arrival order
+    pub ack_calls: Mutex<Vec<Vec<String>>>,
+    /// When set, each `Send` and `SendBatch` waits for a permit before it is answered
+    pub send_gate: Mutex<Option<Arc<Semaphore>>>,
+    /// `Subscribe` requests received, in arrival order
+    pub subscribe_requests: Mutex<Vec<SubscribeReq>>,
+    /// Answer filtered subscriptions with `UNIMPLEMENTED`, like a node without filter support
+    pub filters_unsupported: AtomicBool,
+    /// Delay before each `Ping` is answered
+    pub ping_delay: Mutex<Duration>,
+    /// Answer `Ping` with `UNAVAILABLE`
+    pub ping_unavailable: AtomicBool,
+    /// Number of `Ping` calls received
+    pub pings: AtomicUsize,
+    /// Offset of the node clock reported by `Ping` from the local one, in milliseconds
+    pub clock_offset_ms: AtomicI64,
+    /// Number of TCP connections accepted by [`spawn`]
+    pub connections: AtomicUsize,
+    /// Reject every second envelope of each `SendBatch` stream
+    pub reject_alternate: AtomicBool,
+    /// `authorization` metadata of each `Send`, in arrival order
+    pub send_authorization: Mutex<Vec<Option<String>>>,
+    /// Answer every `Send` and `SendBatch` with `UNAVAILABLE`
+    pub sends_unavailable: AtomicBool,
+    /// Number of `Send` and `SendBatch` calls received, including failed ones
+    pub send_attempts: AtomicUsize,
+    /// Refuse every `Subscribe` with this status
+    pub subscribe_error: Mutex<Option<Status>>,
+    /// Status ending each `Subscribe` stream after the feed
+    pub feed_error: Mutex<Option<Status>>,
+    /// End each `Subscribe` stream after the feed on purpose, giving this reason
+    pub end_reason: Mutex<Option<&'static str>>,
+    /// Envelopes streamed to the next `Subscribe` after the feed, until the sender drops
+    pub live: Mutex<Option<mpsc::UnboundedReceiver<Envelope>>>,
+    /// Features neither advertised nor served; their RPCs answer `UNIMPLEMENTED`
+    pub unsupported: Mutex<Vec<&'static str>>,
+    /// Answer `Capabilities` with `UNIMPLEMENTED`, like a node that predates it
+    pub capabilities_unimplemented: AtomicBool,
+    /// Number of `Capabilities` calls received
+    pub capability_requests: AtomicUsize,
+    /// Protocol version reported in `Capabilities`, empty to report none
+    pub protocol_version: Mutex<String>,
+    /// `securefabric-protocol` metadata of each `Capabilities` call, in arrival order
+    pub pinned_protocol_versions: Mutex<Vec<Option<String>>>,
+    /// Compress `Subscribe` streams with this encoding if the client accepts it; read by [`spawn`]
+    pub stream_compression: Mutex<Option<CompressionEncoding>>,
+    /// `grpc-accept-encoding` metadata of each `Subscribe`, in arrival order
+    pub subscribe_accept_encoding: Mutex<Vec<Option<String>>>,
+    /// Credits granted on `SubscribeCredits` streams, in arrival order
+    pub credit_grants: Mutex<Vec<u32>>,
+    /// Number of envelopes sent on `SubscribeCredits` streams
+    pub credited_sends: AtomicUsize,
+    /// Batches streamed to each `SubscribeBatched`, followed by `feed_error` if set
+    pub batches: Mutex<Vec<Vec<Envelope>>>,
+}
+
+/// Features the mock serves unless listed in [`MockState::unsupported`]
+pub const FEATURES: &[&str] = &[
+    "ack",
+    "head",
+    "ping",
+    "server_time",
+    "send_batch",
+    "header_filters",
+    "get_message",
+    "credits",
+    "rate_limit",
+    "batched_subscribe",
+    "multi_topic",
+    "shards",
+];
+
+/// In-process FabricNode used as a test double
+#[derive(Clone, Default)]
+pub struct MockNode {
+    pub state: Arc<MockState>,
+}
+
+impl MockNode {
+    pub fn with_feed(feed: Vec<Envelope>) -> Self {
+        let node = Self::default();
+        *node.state.feed.lock().unwrap() = feed;
+        node
+    }
+
+    pub fn sent(&self) -> Vec<Envelope> {
+        self.state.sent.lock().unwrap().clone()
+    }
+
+    pub fn acked(&self) -> Vec<String> {
+        self.state.acked.lock().unwrap().clone()
+    }
+
+    /// Stall every `Send` and `SendBatch` until the returned semaphore is given permits
+    pub fn hold_sends(&self) -> Arc<Semaphore> {
+        let gate = Arc::new(Semaphore::new(0));
+        *self.state.send_gate.lock().unwrap() = Some(gate.clone());
+        gate
+    }
+
+    /// Keep the next subscription open after the feed, streaming whatever is sent
+    pub fn live_feed(&self) -> mpsc::UnboundedSender<Envelope> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        *self.state.live.lock().unwrap() = Some(rx);
+        tx
+    }
+
+    /// Stop serving `feature` and leave it out of `Capabilities`
+    pub fn without(self, feature: &'static str) -> Self {
+        self.state.unsupported.lock().unwrap().push(feature);
+        self
+    }
+
+    fn serves(&self, feature: &str) -> bool {
+        !self.state.unsupported.lock().unwrap().contains(&feature)
+    }
+}
+
+#[tonic::async_trait]
+impl FabricNode for MockNode {
+    async fn send(&self, request: Request<SendReq>) -> Result<Response<SendResp>, Status> {
+        let authorization = request
+            .metadata()
+            .get("authorization")
+            .map(|value| value.to_str().unwrap().to_string());
+        self.state
+            .send_authorization
+            .lock()
+            .unwrap()
+            .push(authorization);
+        self.state.send_attempts.fetch_add(1, Ordering::SeqCst);
+        if self.state.sends_unavailable.load(Ordering::SeqCst) {
+            return Err(Status::unavailable("node overloaded"));
+        }
+        let envelope = request
+            .into_inner()
+            .envelope
+            .ok_or_else(|| Status::invalid_argument("missing envelope"))?;
+        let gate = self.state.send_gate.lock().unwrap().clone();
+        if let Some(gate) = gate {
+            gate.acquire().await.unwrap().forget();
+        }
+        let msg_id = envelope.msg_id.clone();
+        self.state.sent.lock().unwrap().push(envelope);
+        Ok(Response::new(SendResp { ok: true, msg_id }))
+    }
+
+    async fn send_batch(
+        &self,
+        request: Request<Streaming<SendReq>>,
+    ) -> Result<Response<SendBatchResp>, Status> {
+        if !self.serves("send_batch") {
+            return Err(Status::unimplemented("send_batch not implemented"));
+        }
+        self.state.send_attempts.fetch_add(1, Ordering::SeqCst);
+        if self.state.sends_unavailable.load(Ordering::SeqCst) {
+            return Err(Status::unavailable("node overloaded"));
+        }
+        let gate = self.state.send_gate.lock().unwrap().clone();
+        if let Some(gate) = gate {
+            gate.acquire().await.unwrap().forget();
+        }
+        let mut stream = request.into_inner();
+        let mut results = Vec::new();
+        while let Some(req) = stream.message().await? {
+            let rejected =
+                self.state.reject_alternate.load(Ordering::SeqCst) && results.len() % 2 == 1;
+            let result = match req.envelope {
+                Some(_) if rejected => SendResult {
+                    ok: false,
+                    code: Code::InvalidArgument as i32,
+                    error: "rejected by mock".to_string(),
+                    ..Default::default()
+                },
+                Some(envelope) => {
+                    let msg_id = envelope.msg_id.clone();
+                    self.state.sent.lock().unwrap().push(envelope);
+                    SendResult {
+                        ok: true,
+                        msg_id,
+                        ..Default::default()
+                    }
+                }
+                None => SendResult {
+                    ok: false,
+                    code: Code::InvalidArgument as i32,
+                    error: "missing envelope".to_string(),
+                    ..Default::default()
+                },
+            };
+            results.push(result);
+        }
+        Ok(Response::new(SendBatchResp { results }))
+    }
+
+    type SubscribeStream = BoxStream<'static, Result<Envelope, Status>>;
+
+    async fn subscribe(
+        &self,
+        request: Request<SubscribeReq>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let accept_encoding = request
+            .metadata()
+            .get("grpc-accept-encoding")
+            .map(|value| value.to_str().unwrap().to_string());
+        self.state
+            .subscribe_accept_encoding
+            .lock()
+            .unwrap()
+            .push(accept_encoding);
+        let req = request.into_inner();
+        self.state
+            .subscribe_requests
+            .lock()
+            .unwrap()
+            .push(req.clone());
+        if let Some(status) = self.state.subscribe_error.lock().unwrap().clone() {
+            return Err(status);
+        }
+        if req.shard_count > 0 && req.shard >= req.shard_count {
+            return Err(Status::invalid_argument("shard out of range"));
+        }
+        if req.shard_count > 0 && !self.serves("shards") {
+            return Err(Status::unimplemented("shards not implemented"));
+        }
+        if !req.filter.is_empty() {
+            if self.state.filters_unsupported.load(Ordering::SeqCst) {
+                return Err(Status::unimplemented("header filters not supported"));
+            }
+            if !self.serves("header_filters") {
+                return Err(Status::unimplemented("header_filters not implemented"));
+            }
+        }
+        if req.max_rate > 0 && !self.serves("rate_limit") {
+            return Err(Status::unimplemented("rate_limit not implemented"));
+        }
+        if !req.topics.is_empty() && !self.serves("multi_topic") {
+            return Err(Status::unimplemented("multi_topic not implemented"));
+        }
+        let mut feed = self.state.feed.lock().unwrap().clone();
+        if !req.topics.is_empty() {
+            feed.retain(|e| req.topics.iter().any(|topic| e.topic.as_bytes() == topic));
+        }
+        if req.shard_count > 0 {
+            feed.retain(|e| shard_for(&e.pubkey, req.shard_count) == req.shard);
+        }
+        let filter = HeaderFilter::from(req.filter);
+        feed.retain(|e| filter.matches(e));
+        let feed_error = self.state.feed_error.lock().unwrap().clone();
+        let end = self.state.end_reason.lock().unwrap().map(end_of_stream);
+        let feed =
+            stream::iter(feed.into_iter().map(Ok)).chain(stream::iter(feed_error.or(end).map(Err)));
+        let stream = match self.state.live.lock().unwrap().take() {
+            Some(live) => feed
+                .chain(UnboundedReceiverStream::new(live).map(Ok))
+                .boxed(),
+            None => feed.boxed(),
+        };
+        if req.max_rate > 0 {
+            let interval = Duration::from_secs(1) / req.max_rate;
+            let paced = stream.then(move |item| async move {
+                tokio::time::sleep(interval).await;
+                item
+            });
+            return Ok(Response::new(paced.boxed()));
+        }
+        Ok(Response::new(stream))
+    }
+
+    async fn stats(&self, _request: Request<StatsReq>) -> Result<Response<StatsResp>, Status> {
+        Ok(Response::new(StatsResp::default()))
+    }
+
+    async fn join(&self, _request: Request<NodeInfo>) -> Result<Response<JoinResp>, Status> {
+        Ok(Response::new(JoinResp { ok: true }))
+    }
+
+    async fn unjoin(&self, _request: Request<NodeId>) -> Result<Response<JoinResp>, Status> {
+        Ok(Response::new(JoinResp { ok: true }))
+    }
+
+    async fn ack(&self, request: Request<AckReq>) -> Result<Response<AckResp>, Status> {
+        if !self.serves("ack") {
+            return Err(Status::unimplemented("ack not implemented"));
+        }
+        let msg_ids = request.into_inner().msg_ids;
+        self.state.ack_calls.lock().unwrap().push(msg_ids.clone());
+        self.state.acked.lock().unwrap().extend(msg_ids);
+        Ok(Response::new(AckResp { ok: true }))
+    }
+
+    async fn ping(&self, _request: Request<PingReq>) -> Result<Response<PingResp>, Status> {
+        if !self.serves("ping") {
+            return Err(Status::unimplemented("ping not implemented"));
+        }
+        self.state.pings.fetch_add(1, Ordering::SeqCst);
+        let delay = *self.state.ping_delay.lock().unwrap();
+        tokio::time::sleep(delay).await;
+        if self.state.ping_unavailable.load(Ordering::SeqCst) {
+            return Err(Status::unavailable("node draining"));
+        }
+        let server_time_ms = if self.serves("server_time") {
+            let now_ms = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64;
+            now_ms.saturating_add_signed(self.state.clock_offset_ms.load(Ordering::SeqCst))
+        } else {
+            0
+        };
+        Ok(Response::new(PingResp { server_time_ms }))
+    }
+
+    async fn head(&self, request: Request<HeadReq>) -> Result<Response<HeadResp>, Status> {
+        if !self.serves("head") {
+            return Err(Status::unimplemented("head not implemented"));
+        }
+        let req = request.into_inner();
+        let latest_seq = self
+            .state
+            .feed
+            .lock()
+            .unwrap()
+            .iter()
+            .chain(self.state.sent.lock().unwrap().iter())
+            .filter(|e| e.pubkey == req.pubkey && e.topic.as_bytes() == req.topic)
+            .map(|e| e.seq)
+            .max()
+            .unwrap_or(0);
+        Ok(Response::new(HeadResp { latest_seq }))
+    }
+
+    type SubscribeBatchedStream = BoxStream<'static, Result<EnvelopeBatch, Status>>;
+
+    async fn subscribe_batched(
+        &self,
+        _request: Request<SubscribeReq>,
+    ) -> Result<Response<Self::SubscribeBatchedStream>, Status> {
+        if !self.serves("batched_subscribe") {
+            return Err(Status::unimplemented("batched_subscribe not implemented"));
+        }
+        let batches = self.state.batches.lock().unwrap().clone();
+        let feed_error = self.state.feed_error.lock().unwrap().clone();
+        let batches = batches
+            .into_iter()
+            .map(|envelopes| EnvelopeBatch { envelopes });
+        let stream = stream::iter(batches.map(Ok)).chain(stream::iter(feed_error.map(Err)));
+        Ok(Response::new(stream.boxed()))
+    }
+
+    type SubscribeCreditsStream = BoxStream<'static, Result<Envelope, Status>>;
+
+    async fn subscribe_credits(
+        &self,
+        request: Request<Streaming<SubscribeControl>>,
+    ) -> Result<Response<Self::SubscribeCreditsStream>, Status> {
+        if !self.serves("credits") {
+            return Err(Status::unimplemented("credits not implemented"));
+        }
+        let mut control = request.into_inner();
+        let first = control
+            .message()
+            .await?
+            .ok_or_else(|| Status::invalid_argument("empty control stream"))?;
+        if first.subscribe.is_none() {
+            return Err(Status::invalid_argument("first message has no subscribe"));
+        }
+        self.state.credit_grants.lock().unwrap().push(first.credits);
+
+        // Send feed envelopes while credits last, then wait for the next grant
+        let feed = self.state.feed.lock().unwrap().clone();
+        let state = self.state.clone();
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut credits = first.credits;
+            let mut feed = feed.into_iter();
+            loop {
+                while credits > 0 {
+                    let Some(envelope) = feed.next() else {
+                        return;
+                    };
+                    if tx.send(Ok(envelope)).is_err() {
+                        return;
+                    }
+                    state.credited_sends.fetch_add(1, Ordering::SeqCst);
+                    credits -= 1;
+                }
+                match control.message().await {
+                    Ok(Some(grant)) => {
+                        state.credit_grants.lock().unwrap().push(grant.credits);
+                        credits += grant.credits;
+                    }
+                    _ => return,
+                }
+            }
+        });
+        Ok(Response::new(UnboundedReceiverStream::new(rx).boxed()))
+    }
+
+    async fn get_message(
+        &self,
+        request: Request<GetMessageReq>,
+    ) -> Result<Response<GetMessageResp>, Status> {
+        if !self.serves("get_message") {
+            return Err(Status::unimplemented("get_message not implemented"));
+        }
+        let req = request.into_inner();
+        let envelope = self
+            .state
+            .feed
+            .lock()
+            .unwrap()
+            .iter()
+            .chain(self.state.sent.lock().unwrap().iter())
+            .find(|e| e.msg_id == req.msg_id && e.topic.as_bytes() == req.topic)
+            .cloned();
+        Ok(Response::new(GetMessageResp { envelope }))
+    }
+
+    async fn capabilities(
+        &self,
+        request: Request<CapabilitiesReq>,
+    ) -> Result<Response<CapabilitiesResp>, Status> {
+        self.state
+            .capability_requests
+            .fetch_add(1, Ordering::SeqCst);
+        let pinned = request
+            .metadata()
+            .get("securefabric-protocol")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        self.state
+            .pinned_protocol_versions
+            .lock()
+            .unwrap()
+            .push(pinned);
+        if self.state.capabilities_unimplemented.load(Ordering::SeqCst) {
+            return Err(Status::unimplemented("capabilities not implemented"));
+        }
+        let unsupported = self.state.unsupported.lock().unwrap();
+        let features = FEATURES
+            .iter()
+            .filter(|feature| !unsupported.contains(feature))
+            .map(|feature| feature.to_string())
+            .collect();
+        let protocol_version = self.state.protocol_version.lock().unwrap().clone();
+        Ok(Response::new(CapabilitiesResp {
+            features,
+            protocol_version,
+        }))
+    }
+}
+
+/// OK status ending a stream on purpose with `reason` in its trailers
+fn end_of_stream(reason: &'static str) -> Status {
+    let mut metadata = MetadataMap::new();
+    metadata.insert(END_REASON_METADATA, MetadataValue::from_static(reason));
+    Status::with_metadata(Code::Ok, "", metadata)
+}
+
+/// Serve `node` on an ephemeral localhost port and return its endpoint URI
+pub async fn spawn(node: MockNode) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let state = node.state.clone();
+    let incoming = TcpListenerStream::new(listener).inspect(move |_| {
+        state.connections.fetch_add(1, Ordering::SeqCst);
+    });
+    let compression = *node.state.stream_compression.lock().unwrap();
+    let mut service = FabricNodeServer::new(node);
+    if let Some(encoding) = compression {
+        service = service.send_compressed(encoding);
+    }
+    tokio::spawn(
+        tonic::transport::Server::builder()
+            .add_service(service)
+            .serve_with_incoming(incoming),
+    );
+    format!("http://{addr}")
+}
+
+/// Throwaway CA with a server certificate for `localhost` and a client certificate
+pub struct TestPki {
+    pub ca_pem: String,
+    pub server_cert_pem: String,
+    pub server_key_pem: String,
+    pub client_cert_pem: String,
+    pub client_key_pem: String,
+}
+
+impl TestPki {
+    pub fn generate() -> Self {
+        let ca_key = rcgen::KeyPair::generate().unwrap();
+        let mut ca_params = rcgen::CertificateParams::new(Vec::new()).unwrap();
+        ca_params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+
+        let server_key = rcgen::KeyPair::generate().unwrap();
+        let server = rcgen::CertificateParams::new(vec!["localhost".to_string()])
+            .unwrap()
+            .signed_by(&server_key, &ca, &ca_key)
+            .unwrap();
+
+        let client_key = rcgen::KeyPair::generate().unwrap();
+        let client = rcgen::CertificateParams::new(vec!["client".to_string()])
+            .unwrap()
+            .signed_by(&client_key, &ca, &ca_key)
+            .unwrap();
+
+        Self {
+            ca_pem: ca.pem(),
+            server_cert_pem: server.pem(),
+            server_key_pem: server_key.serialize_pem(),
+            client_cert_pem: client.pem(),
+            client_key_pem: client_key.serialize_pem(),
+        }
+    }
+}
+
+/// Serve `node` over server-authenticated TLS and return its endpoint URI
+pub async fn spawn_tls(node: MockNode, pki: &TestPki) -> String {
+    let identity = Identity::from_pem(&pki.server_cert_pem, &pki.server_key_pem);
+    serve_tls(node, ServerTlsConfig::new().identity(identity)).await
+}
+
+/// Serve `node` over TLS requiring a client certificate issued by the test CA
+pub async fn spawn_mtls(node: MockNode, pki: &TestPki) -> String {
+    let identity = Identity::from_pem(&pki.server_cert_pem, &pki.server_key_pem);
+    let tls = ServerTlsConfig::new()
+        .identity(identity)
+        .client_ca_root(Certificate::from_pem(&pki.ca_pem));
+    serve_tls(node, tls).await
+}
+
+async fn serve_tls(node: MockNode, tls: ServerTlsConfig) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(
+        tonic::transport::Server::builder()
+            .tls_config(tls)
+            .unwrap()
+            .add_service(FabricNodeServer::new(node))
+            .serve_with_incoming(TcpListenerStream::new(listener)),
+    );
+    format!("https://{addr}")
+}
+
+/// Deterministic signing key for fixtures
+pub fn signing_key(seed: u8) -> SigningKey {
+    SigningKey::from_bytes(&[seed; 32])
+}
+
+/// Build an envelope signed the same way `Client::send` does
+pub fn signed_envelope(key: &SigningKey, topic: &str, seq: u64, payload: &[u8]) -> Envelope {
+    signed_envelope_with_headers(key, topic, seq, &[], payload)
+}
+
+/// Like [`signed_envelope`], with application headers bound into the AAD
+pub fn signed_envelope_with_headers(
+    key: &SigningKey,
+    topic: &str,
+    seq: u64,
+    headers: &[(&str, &str)],
+    payload: &[u8],
+) -> Envelope {
+    let mut aad = serde_json::json!({ "topic": topic, "key_version": 0u32, "seq": seq });
+    if !headers.is_empty() {
+        aad["headers"] = headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), serde_json::Value::from(*value)))
+            .collect::<serde_json::Map<_, _>>()
+            .into();
+    }
+    envelope_with_aad(key, topic, seq, seq_nonce(seq), aad, payload)
+}
+
+/// Like [`signed_envelope`], with a signed send time in the AAD and `timestamp_ms`
+pub fn signed_envelope_at(
+    key: &SigningKey,
+    topic: &str,
+    seq: u64,
+    timestamp_ms: u64,
+    payload: &[u8],
+) -> Envelope {
+    let aad =
+        serde_json::json!({ "topic": topic, "key_version": 0u32, "seq": seq, "ts": timestamp_ms });
+    Envelope {
+        timestamp_ms,
+        ..envelope_with_aad(key, topic, seq, seq_nonce(seq), aad, payload)
+    }
+}
+
+/// Envelope encrypted under `topic_key` with an explicit `nonce`, signed over the ciphertext
+pub fn encrypted_envelope(
+    key: &SigningKey,
+    topic: &str,
+    seq: u64,
+    nonce: [u8; 24],
+    topic_key: &[u8; 32],
+    key_version: u32,
+    plaintext: &[u8],
+) -> Envelope {
+    let aad = serde_json::json!({ "topic": topic, "key_version": key_version, "seq": seq });
+    let aad_bytes = serde_json::to_vec(&aad).unwrap();
+    let ciphertext = securefabric_sdk::crypto::AeadContext::new(topic_key)
+        .seal(&nonce, &aad_bytes, plaintext)
+        .unwrap();
+    Envelope {
+        key_version,
+        ..envelope_with_aad(key, topic, seq, nonce.to_vec(), aad, &ciphertext)
+    }
+}
+
+fn seq_nonce(seq: u64) -> Vec<u8> {
+    let mut nonce = vec![0u8; 24];
+    nonce[..8].copy_from_slice(&seq.to_le_bytes());
+    nonce
+}
+
+fn envelope_with_aad(
+    key: &SigningKey,
+    topic: &str,
+    seq: u64,
+    nonce: Vec<u8>,
+    aad: serde_json::Value,
+    payload: &[u8],
+) -> Envelope {
+    let pubkey = key.verifying_key().to_bytes().to_vec();
+    let encrypted = aad["key_version"].as_u64().unwrap_or(0) != 0;
+    let aad = serde_json::to_vec(&aad).unwrap();
+
+    let mut preimage = aad.clone();
+    if encrypted {
+        preimage.extend_from_slice(&nonce);
+    }
+    preimage.extend_from_slice(payload);
+    let sig = key.sign(&preimage).to_bytes().to_vec();
+
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&pubkey);
+    hasher.update(&seq.to_le_bytes());
+    hasher.update(&nonce);
+    let msg_id = hasher.finalize().to_hex().to_string();
+
+    Envelope {
+        pubkey,
+        sig,
+        nonce,
+        aad,
+        payload: payload.to_vec(),
+        seq,
+        msg_id,
+        key_version: 0,
+        topic: topic.to_string(),
+        to: Vec::new(),
+        timestamp_ms: 0,
+        sig_scheme: 0,
+        key: Vec::new(),
+        tombstone: false,
+        ephemeral_key: Vec::new(),
+        wrapped_keys: Vec::new(),
+        cosignatures: Vec::new(),
+        payload_digest: Vec::new(),
+        msg_id_hash: 0,
+    }
+}
+
+/// HTTP CONNECT proxy that records the tunnels it opens
+#[derive(Clone, Default)]
+pub struct MockProxy {
+    required_auth: Option<String>,
+    connects: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockProxy {
+    /// Proxy that only opens tunnels for requests carrying `authorization`
+    pub fn requiring(authorization: &str) -> Self {
+        Self {
+            required_auth: Some(authorization.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Targets of the tunnels opened so far
+    pub fn connects(&self) -> Vec<String> {
+        self.connects.lock().unwrap().clone()
+    }
+}
+
+/// Serve `proxy` on an ephemeral localhost port and return its URI
+pub async fn spawn_proxy(proxy: MockProxy) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            tokio::spawn(proxy.clone().tunnel(stream));
+        }
+    });
+    format!("http://{addr}")
+}
+
+impl MockProxy {
+    async fn tunnel(self, stream: tokio::net::TcpStream) {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let mut stream = BufReader::new(stream);
+        let mut request_line = String::new();
+        stream.read_line(&mut request_line).await.unwrap();
+        let target = request_line
+            .strip_prefix("CONNECT ")
+            .and_then(|rest| rest.split_whitespace().next())
+            .unwrap()
+            .to_string();
+
+        let mut authorization = None;
+        loop {
+            let mut line = String::new();
+            stream.read_line(&mut line).await.unwrap();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("proxy-authorization") {
+                    authorization = Some(value.trim().to_string());
+                }
+            }
+        }
+        let mut stream = stream.into_inner();
+
+        if self.required_auth.is_some() && authorization != self.required_auth {
+            let _ = stream
+                .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n")
+                .await;
+            return;
+        }
+        let Ok(mut upstream) = tokio::net::TcpStream::connect(&target).await else {
+            let _ = stream.write_all(b"HTTP/1.1 502 Bad Gateway\r\n\r\n").await;
+            return;
+        };
+        self.connects.lock().unwrap().push(target);
+        stream
+            .write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n")
+            .await
+            .unwrap();
+        let _ = tokio::io::copy_bidirectional(&mut stream, &mut upstream).await;
+    }
+}
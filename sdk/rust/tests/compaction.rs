@@ -0,0 +1,84 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode};
+use securefabric_sdk::compaction::KeyedView;
+use securefabric_sdk::Client;
+
+#[tokio::test]
+async fn materialized_view_keeps_latest_value_and_honours_tombstones() {
+    let producer_node = MockNode::default();
+    let endpoint = common::spawn(producer_node.clone()).await;
+    let mut producer = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+
+    producer
+        .send_keyed("prices", b"apple", b"1.00")
+        .await
+        .unwrap();
+    producer
+        .send_keyed("prices", b"pear", b"2.00")
+        .await
+        .unwrap();
+    producer
+        .send_keyed("prices", b"apple", b"1.10")
+        .await
+        .unwrap();
+    producer.send("prices", b"unkeyed").await.unwrap();
+    producer.send_tombstone("prices", b"pear").await.unwrap();
+    producer
+        .send_keyed("prices", b"apple", b"1.25")
+        .await
+        .unwrap();
+    producer
+        .send_keyed("prices", b"plum", b"3.00")
+        .await
+        .unwrap();
+
+    let sent = producer_node.sent();
+    assert_eq!(sent[4].key, b"pear");
+    assert!(sent[4].tombstone);
+    assert!(sent[4].payload.is_empty());
+
+    let endpoint = common::spawn(MockNode::with_feed(sent)).await;
+    let mut consumer = Client::new(&endpoint).await.unwrap();
+    let view = consumer.materialize(b"prices").await.unwrap();
+
+    assert_eq!(view.len(), 2);
+    assert_eq!(view.get(b"apple").unwrap().payload, b"1.25");
+    assert_eq!(view.get(b"plum").unwrap().payload, b"3.00");
+    assert!(view.get(b"pear").is_none());
+}
+
+#[tokio::test]
+async fn rewritten_compaction_key_is_rejected() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut producer = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+    producer
+        .send_keyed("prices", b"apple", b"1.00")
+        .await
+        .unwrap();
+    producer.send_tombstone("prices", b"pear").await.unwrap();
+    assert!(producer.send_keyed("prices", b"", b"x").await.is_err());
+
+    let mut sent = node.sent();
+    let mut view = KeyedView::new();
+
+    // The key is outside the signed payload, so only the AAD check catches this
+    let mut rewritten = sent[0].clone();
+    rewritten.key = b"pear".to_vec();
+    assert!(producer.verify(&rewritten).unwrap());
+    assert!(view.apply(rewritten).is_err());
+
+    let mut untombstoned = sent.remove(1);
+    untombstoned.tombstone = false;
+    assert!(view.apply(untombstoned).is_err());
+    assert!(view.is_empty());
+}
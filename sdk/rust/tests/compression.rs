@@ -0,0 +1,79 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use futures::StreamExt;
+use securefabric_sdk::compression::Compression;
+use securefabric_sdk::{Client, Envelope};
+use tonic::codec::CompressionEncoding;
+
+fn feed() -> Vec<Envelope> {
+    let key = signing_key(1);
+    (1..=3)
+        .map(|seq| signed_envelope(&key, "telemetry", seq, &vec![b'x'; 16 * 1024]))
+        .collect()
+}
+
+async fn received(client: &mut Client) -> Vec<Envelope> {
+    client
+        .subscribe(b"telemetry")
+        .await
+        .unwrap()
+        .map(|item| item.unwrap())
+        .collect()
+        .await
+}
+
+#[tokio::test]
+async fn compressing_node_streams_to_accepting_subscriber() {
+    for (accepted, encoding, advertised) in [
+        (Compression::Gzip, CompressionEncoding::Gzip, "gzip"),
+        (Compression::Zstd, CompressionEncoding::Zstd, "zstd"),
+    ] {
+        let node = MockNode::with_feed(feed());
+        *node.state.stream_compression.lock().unwrap() = Some(encoding);
+        let mut client = Client::new(common::spawn(node.clone()).await)
+            .await
+            .unwrap()
+            .with_subscribe_compression(&[accepted]);
+
+        let envelopes = received(&mut client).await;
+        assert_eq!(envelopes, feed());
+        assert!(envelopes.iter().all(|e| client.verify(e).unwrap()));
+        let header = node.state.subscribe_accept_encoding.lock().unwrap()[0].clone();
+        assert!(header.unwrap().contains(advertised));
+    }
+}
+
+#[tokio::test]
+async fn falls_back_to_uncompressed_streams() {
+    // The node does not compress, although the subscriber would accept it
+    let node = MockNode::with_feed(feed());
+    let mut client = Client::new(common::spawn(node).await)
+        .await
+        .unwrap()
+        .with_subscribe_compression(&[Compression::Gzip, Compression::Zstd]);
+    assert_eq!(received(&mut client).await, feed());
+
+    // The node compresses, but the subscriber asks for a raw stream
+    let node = MockNode::with_feed(feed());
+    *node.state.stream_compression.lock().unwrap() = Some(CompressionEncoding::Gzip);
+    let mut client = Client::new(common::spawn(node.clone()).await)
+        .await
+        .unwrap();
+    assert_eq!(received(&mut client).await, feed());
+    assert_eq!(
+        node.state.subscribe_accept_encoding.lock().unwrap()[0],
+        None
+    );
+
+    // No shared encoding
+    let node = MockNode::with_feed(feed());
+    *node.state.stream_compression.lock().unwrap() = Some(CompressionEncoding::Zstd);
+    let mut client = Client::new(common::spawn(node).await)
+        .await
+        .unwrap()
+        .with_subscribe_compression(&[Compression::Gzip]);
+    assert_eq!(received(&mut client).await, feed());
+}
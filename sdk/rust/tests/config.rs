@@ -0,0 +1,123 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode, TestPki};
+use securefabric_sdk::config::{AuthMode, RetryView, TlsMode};
+use securefabric_sdk::crypto::{SignOrder, VerifyMode};
+use securefabric_sdk::keyring::{fingerprint, Keyring};
+use securefabric_sdk::timeout::AdaptiveConfig;
+use securefabric_sdk::{Client, OverflowPolicy, TlsConfig};
+use std::time::Duration;
+
+const TOKEN: &str = "sk-live-0123456789abcdef";
+
+#[tokio::test]
+async fn view_reflects_builder_settings_and_masks_the_bearer() {
+    let pki = TestPki::generate();
+    let endpoint = common::spawn_mtls(MockNode::default(), &pki).await;
+    let keyring = Keyring::new();
+    keyring.insert(signing_key(2).verifying_key());
+
+    let client = Client::builder(&endpoint)
+        .tls(
+            TlsConfig::new()
+                .with_ca_pem(&pki.ca_pem)
+                .with_identity(&pki.client_cert_pem, &pki.client_key_pem)
+                .with_domain("localhost"),
+        )
+        .build()
+        .await
+        .unwrap()
+        .with_bearer(TOKEN)
+        .with_signing_key(signing_key(1))
+        .with_encryption([7u8; 32], 3)
+        .unwrap()
+        .with_sign_order(SignOrder::SignThenEncrypt)
+        .with_keyring(keyring)
+        .with_verify_mode(VerifyMode::Permissive)
+        .with_ping_timeout(Duration::from_millis(750))
+        .with_adaptive_timeout(AdaptiveConfig {
+            initial: Duration::from_secs(2),
+            ..Default::default()
+        })
+        .with_send_retries(3, Duration::from_millis(100))
+        .with_retry_budget(0.25, 10)
+        .with_send_queue(64, OverflowPolicy::Block)
+        .unwrap()
+        .with_ordered_send(true);
+
+    let config = client.config();
+    assert_eq!(config.endpoint, endpoint);
+    assert_eq!(config.tls, TlsMode::Mutual);
+    assert_eq!(config.tls_domain.as_deref(), Some("localhost"));
+    assert_eq!(config.proxy, None);
+    assert_eq!(
+        config.auth,
+        AuthMode::Bearer {
+            masked: "****cdef".into()
+        }
+    );
+    assert_eq!(
+        config.signing_key,
+        Some(fingerprint(signing_key(1).verifying_key().as_bytes()))
+    );
+    assert_eq!(config.sign_order, SignOrder::SignThenEncrypt);
+    assert_eq!(config.encryption_key_version, Some(3));
+    assert_eq!(config.keyring_len, Some(1));
+    assert_eq!(config.verify_mode, VerifyMode::Permissive);
+    assert_eq!(config.ping_timeout, Duration::from_millis(750));
+    assert_eq!(config.send_timeout, Some(Duration::from_secs(2)));
+    assert_eq!(
+        config.retry,
+        Some(RetryView {
+            max_retries: 3,
+            backoff: Duration::from_millis(100),
+            jitter: None,
+            budget: Some((0.25, 10)),
+        })
+    );
+    assert_eq!(config.send_queue, Some(64));
+    assert!(config.ordered_send);
+
+    let printed = format!("{config:?}");
+    assert!(!printed.contains(TOKEN), "{printed}");
+    assert!(!printed.contains(
+        &signing_key(1)
+            .to_bytes()
+            .map(|b| format!("{b:02x}"))
+            .concat()
+    ));
+}
+
+#[tokio::test]
+async fn defaults_and_credentials_in_the_endpoint() {
+    let endpoint = common::spawn(MockNode::default()).await;
+    let config = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_bearer("short")
+        .config();
+    assert_eq!(config.tls, TlsMode::None);
+    assert_eq!(
+        config.auth,
+        AuthMode::Bearer {
+            masked: "****".into()
+        }
+    );
+    assert_eq!(config.signing_key, None);
+    assert_eq!(config.keyring_len, None);
+    assert_eq!(config.retry, None);
+    assert_eq!(config.send_timeout, None);
+    assert!(!config.ordered_send);
+
+    let with_userinfo = endpoint.replace("http://", "http://admin:hunter2@");
+    let config = Client::builder(&with_userinfo)
+        .connect_lazy(true)
+        .build()
+        .await
+        .unwrap()
+        .config();
+    assert!(!config.endpoint.contains("hunter2"), "{}", config.endpoint);
+    assert_eq!(config.auth, AuthMode::None);
+}
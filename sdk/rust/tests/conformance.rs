@@ -0,0 +1,141 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use securefabric_sdk::conformance::{cross, reference, report, run, run_embedded, VECTORS};
+use std::process::Command;
+
+const RUST_CONFORMANCE: &str = env!("CARGO_BIN_EXE_securefabric-conformance");
+
+const VECTORS_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../tests/test_vectors.json");
+
+fn vectors() -> String {
+    VECTORS.to_string()
+}
+
+fn shell(script: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(script);
+    command
+}
+
+#[test]
+fn reference_reproduces_the_vectors() {
+    let vectors: serde_json::Value = serde_json::from_str(&vectors()).unwrap();
+    let results = reference(&vectors).unwrap();
+
+    let mut checked = 0;
+    for (name, tests) in vectors["signature_schemes"].as_object().unwrap() {
+        let Some(tests) = tests.as_array() else {
+            continue;
+        };
+        let results = results["signature_schemes"][name].as_array().unwrap();
+        assert_eq!(results.len(), tests.len());
+        for (vector, result) in tests.iter().zip(results) {
+            assert_eq!(result["public_key"], vector["public_key"], "{name}");
+            assert_eq!(result["signature"], vector["signature"], "{name}");
+            assert_eq!(result["verified"], true, "{name}");
+            checked += 1;
+        }
+    }
+    assert_eq!(checked, 5);
+}
+
+#[test]
+fn rust_binary_passes_as_external_sdk() {
+    cross(Command::new(RUST_CONFORMANCE), &vectors()).unwrap();
+}
+
+#[test]
+fn mismatch_lists_each_differing_field() {
+    let script = format!("{RUST_CONFORMANCE} | sed 's/\"verified\": true/\"verified\": false/'");
+    let error = cross(shell(&script), &vectors()).unwrap_err().to_string();
+    assert!(error.contains("on 5 field(s)"), "{error}");
+    assert!(
+        error.contains("signature_schemes.ed25519[0].verified: expected true, got false"),
+        "{error}"
+    );
+    assert!(
+        error.contains("signature_schemes.ed25519ph[1].verified: expected true, got false"),
+        "{error}"
+    );
+}
+
+#[test]
+fn missing_results_are_reported() {
+    let script = format!(
+        "{RUST_CONFORMANCE} >/dev/null; echo '{{\"signature_schemes\": {{\"ed25519\": []}}}}'"
+    );
+    let error = cross(shell(&script), &vectors()).unwrap_err().to_string();
+    assert!(
+        error.contains("signature_schemes.ed25519: expected 1 entries, got 0"),
+        "{error}"
+    );
+    assert!(
+        error.contains("signature_schemes.secp256k1: missing"),
+        "{error}"
+    );
+}
+
+#[test]
+fn failing_command_is_reported() {
+    let error = cross(shell("cat >/dev/null; echo broken >&2; exit 3"), &vectors())
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("broken"), "{error}");
+
+    let error = cross(shell("cat >/dev/null; echo not json"), &vectors())
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("did not print JSON"), "{error}");
+}
+
+#[test]
+fn run_passes_every_vector() {
+    let report = run(VECTORS_FILE).unwrap();
+    assert!(report.all_passed(), "{report}");
+    assert_eq!(report.encryption.passed(), 3);
+    assert_eq!(report.signatures.passed(), 3);
+    assert_eq!(report.msg_id.passed(), 2);
+    assert_eq!(report.replay.passed(), 4);
+    assert_eq!(report.tamper.passed(), 2);
+}
+
+#[test]
+fn report_flags_exactly_the_broken_vector() {
+    let mut vectors: serde_json::Value = serde_json::from_str(&vectors()).unwrap();
+    vectors["signatures"]["ed25519"][1]["signature"] = "00".repeat(64).into();
+    vectors["replay_protection"]["tests"][1]["expected"][3] = true.into();
+
+    let report = report(&vectors);
+    assert_eq!((report.passed(), report.failed()), (12, 2));
+    let failures: Vec<_> = report
+        .sections()
+        .into_iter()
+        .flat_map(|(name, section)| section.failures().map(move |case| (name, case)))
+        .collect();
+    assert_eq!(failures.len(), 2);
+    assert_eq!(failures[0].0, "signatures");
+    assert_eq!(failures[0].1.description, "Empty message");
+    assert!(failures[0]
+        .1
+        .failure
+        .as_ref()
+        .unwrap()
+        .starts_with("signature mismatch"));
+    assert_eq!(failures[1].0, "replay_protection");
+    assert_eq!(
+        failures[1].1.failure.as_deref(),
+        Some("counter 2 at index 3 was rejected")
+    );
+
+    assert!(run("../tests/missing.json").is_err());
+}
+
+#[test]
+fn embedded_vectors_pass_from_any_directory() {
+    // Every test in this file reads the vectors cwd-independently
+    std::env::set_current_dir(std::env::temp_dir()).unwrap();
+    let report = run_embedded().unwrap();
+    assert!(report.all_passed(), "{report}");
+    assert_eq!(report.passed(), 14);
+    assert_eq!(VECTORS, std::fs::read_to_string(VECTORS_FILE).unwrap());
+}
@@ -0,0 +1,76 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode, TestPki};
+use securefabric_sdk::peer::PeerIdentity;
+use securefabric_sdk::{Client, TlsConfig};
+
+async fn bound_client(endpoint: &str, pki: &TestPki) -> Client {
+    Client::builder(endpoint)
+        .tls(
+            TlsConfig::new()
+                .with_ca_pem(&pki.ca_pem)
+                .with_domain("localhost"),
+        )
+        .with_connection_bound_signing()
+        .build()
+        .await
+        .unwrap()
+}
+
+fn server_identity(pki: &TestPki) -> PeerIdentity {
+    let der = rustls_pemfile::certs(&mut pki.server_cert_pem.as_bytes())
+        .next()
+        .unwrap()
+        .unwrap();
+    PeerIdentity::from_certificate(&der).unwrap()
+}
+
+#[tokio::test]
+async fn signatures_are_bound_to_the_node_connected_to() {
+    let pki = TestPki::generate();
+    let node = MockNode::default();
+    let endpoint = common::spawn_tls(node.clone(), &pki).await;
+    let mut sender = bound_client(&endpoint, &pki)
+        .await
+        .with_signing_key(signing_key(1));
+    assert_eq!(sender.peer_identity(), Some(server_identity(&pki)));
+
+    sender.send("orders", b"once").await.unwrap();
+    let captured = node.sent().remove(0);
+    let aad: serde_json::Value = serde_json::from_slice(&captured.aad).unwrap();
+    assert_eq!(aad["peer"], server_identity(&pki).to_hex());
+
+    // Verifiers on the same node accept it
+    assert!(bound_client(&endpoint, &pki)
+        .await
+        .verify(&captured)
+        .unwrap());
+
+    // Replayed to a node with a different key, it no longer verifies
+    let other = TestPki::generate();
+    let other_endpoint = common::spawn_tls(MockNode::default(), &other).await;
+    let elsewhere = bound_client(&other_endpoint, &other).await;
+    assert_ne!(elsewhere.peer_identity(), sender.peer_identity());
+    assert!(!elsewhere.verify(&captured).unwrap());
+
+    // Verifiers that do not bind still check the signature alone
+    let unbound = Client::with_tls(&other_endpoint, Some(other.ca_pem.as_bytes()), "localhost")
+        .await
+        .unwrap();
+    assert!(unbound.peer_identity().is_none());
+    assert!(unbound.verify(&captured).unwrap());
+}
+
+#[tokio::test]
+async fn connection_bound_signing_requires_tls() {
+    let endpoint = common::spawn(MockNode::default()).await;
+    let error = Client::builder(endpoint)
+        .with_connection_bound_signing()
+        .build()
+        .await
+        .map(|_| ())
+        .unwrap_err();
+    assert!(error.to_string().contains("requires TLS"), "{error}");
+}
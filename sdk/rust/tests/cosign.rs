@@ -0,0 +1,119 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use securefabric_sdk::cosign::{cosign, CosignError, CosignPolicy};
+use securefabric_sdk::crypto::scheme::{SigningKey, VerifyingKey};
+use securefabric_sdk::keyring::fingerprint;
+use securefabric_sdk::Client;
+
+fn key(seed: u8) -> SigningKey {
+    signing_key(seed).into()
+}
+
+fn public(seed: u8) -> VerifyingKey {
+    key(seed).verifying_key()
+}
+
+#[tokio::test]
+async fn two_of_two_sent_envelope_verifies() {
+    let node = MockNode::default();
+    let mut client = Client::new(common::spawn(node.clone()).await)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_cosigner(signing_key(2));
+
+    client.send("payments", b"transfer 100").await.unwrap();
+    let sent = node.sent().remove(0);
+    assert_eq!(sent.cosignatures.len(), 1);
+    assert_eq!(
+        sent.cosignatures[0].signer,
+        fingerprint(&public(2).to_bytes())
+    );
+    assert!(client.verify(&sent).unwrap());
+
+    CosignPolicy::all([public(1), public(2)])
+        .check(&sent)
+        .unwrap();
+}
+
+#[test]
+fn missing_signer_is_rejected() {
+    let mut envelope = signed_envelope(&signing_key(1), "payments", 1, b"transfer 100");
+    let policy = CosignPolicy::all([public(2), public(3)]);
+    assert_eq!(
+        policy.check(&envelope),
+        Err(CosignError::BelowThreshold {
+            valid: 0,
+            required: 2,
+            missing: vec![
+                fingerprint(&public(2).to_bytes()),
+                fingerprint(&public(3).to_bytes())
+            ],
+        })
+    );
+
+    cosign(&mut envelope, &key(3));
+    assert_eq!(
+        policy.check(&envelope),
+        Err(CosignError::BelowThreshold {
+            valid: 1,
+            required: 2,
+            missing: vec![fingerprint(&public(2).to_bytes())],
+        })
+    );
+
+    // Co-signatures are order-independent and re-signing replaces the old one
+    cosign(&mut envelope, &key(2));
+    cosign(&mut envelope, &key(3));
+    assert_eq!(envelope.cosignatures.len(), 2);
+    policy.check(&envelope).unwrap();
+}
+
+#[test]
+fn threshold_counts_distinct_policy_keys() {
+    let mut envelope = signed_envelope(&signing_key(1), "payments", 1, b"transfer 100");
+    let policy = CosignPolicy::threshold(2, [public(1), public(2), public(3), public(2)]);
+    assert!(matches!(
+        policy.check(&envelope),
+        Err(CosignError::BelowThreshold { valid: 1, .. })
+    ));
+
+    // A signer outside the policy does not count
+    cosign(&mut envelope, &key(4));
+    assert!(policy.check(&envelope).is_err());
+
+    cosign(&mut envelope, &key(3));
+    policy.check(&envelope).unwrap();
+}
+
+#[test]
+fn tampering_invalidates_cosignatures() {
+    let mut envelope = signed_envelope(&signing_key(1), "payments", 1, b"transfer 100");
+    cosign(&mut envelope, &key(2));
+    envelope.payload = b"transfer 900".to_vec();
+
+    // Neither the sender's signature nor the co-signature holds any more
+    assert_eq!(
+        CosignPolicy::all([public(1), public(2)]).check(&envelope),
+        Err(CosignError::BadSignature {
+            signer: fingerprint(&public(2).to_bytes())
+        })
+    );
+
+    // The co-signature also binds the sender
+    let mut moved = signed_envelope(&signing_key(5), "payments", 1, b"transfer 100");
+    moved.cosignatures = signed_envelope_cosigned_by(2).cosignatures;
+    assert!(matches!(
+        CosignPolicy::all([public(2)]).check(&moved),
+        Err(CosignError::BadSignature { .. })
+    ));
+}
+
+fn signed_envelope_cosigned_by(seed: u8) -> securefabric_sdk::Envelope {
+    let mut envelope = signed_envelope(&signing_key(1), "payments", 1, b"transfer 100");
+    cosign(&mut envelope, &key(seed));
+    envelope
+}
@@ -0,0 +1,70 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use futures::StreamExt;
+use securefabric_sdk::capabilities::Feature;
+use securefabric_sdk::{Client, Error};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+async fn settle() {
+    tokio::time::sleep(Duration::from_millis(100)).await;
+}
+
+#[tokio::test]
+async fn node_stops_when_slow_consumer_runs_out_of_credits() {
+    let key = signing_key(1);
+    let feed: Vec<_> = (1..=10)
+        .map(|seq| signed_envelope(&key, "jobs", seq, b"work"))
+        .collect();
+    let node = MockNode::with_feed(feed.clone());
+    let state = node.state.clone();
+    let mut client = Client::new(common::spawn(node).await).await.unwrap();
+
+    let mut subscription = client.subscribe_with_credits(b"jobs", 4, 1).await.unwrap();
+    settle().await;
+    assert_eq!(state.credited_sends.load(Ordering::SeqCst), 4);
+
+    // Above the low watermark nothing is granted back
+    let mut received = Vec::new();
+    for _ in 0..2 {
+        received.push(subscription.next().await.unwrap().unwrap());
+    }
+    settle().await;
+    assert_eq!(state.credited_sends.load(Ordering::SeqCst), 4);
+    assert_eq!(*state.credit_grants.lock().unwrap(), [4]);
+
+    // Reaching it tops the credits back up to the initial amount
+    received.push(subscription.next().await.unwrap().unwrap());
+    settle().await;
+    assert_eq!(state.credited_sends.load(Ordering::SeqCst), 7);
+    assert_eq!(*state.credit_grants.lock().unwrap(), [4, 3]);
+
+    while let Some(envelope) = subscription.next().await {
+        received.push(envelope.unwrap());
+    }
+    assert_eq!(received, feed);
+}
+
+#[tokio::test]
+async fn rejects_bad_credits_and_unsupported_nodes() {
+    let mut client = Client::new(common::spawn(MockNode::default()).await)
+        .await
+        .unwrap();
+    assert!(client.subscribe_with_credits(b"jobs", 0, 0).await.is_err());
+    assert!(client.subscribe_with_credits(b"jobs", 4, 4).await.is_err());
+
+    let node = MockNode::default().without("credits");
+    let mut client = Client::new(common::spawn(node).await).await.unwrap();
+    let Err(err) = client.subscribe_with_credits(b"jobs", 4, 1).await else {
+        panic!("node without SubscribeCredits accepted the subscription");
+    };
+    assert_eq!(
+        err.downcast_ref::<Error>(),
+        Some(&Error::Unsupported {
+            feature: Feature::Credits
+        })
+    );
+}
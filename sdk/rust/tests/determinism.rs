@@ -0,0 +1,61 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Run with `cargo test --features test-determinism`.
+
+mod common;
+
+use common::{signing_key, MockNode};
+use securefabric_sdk::{Client, Envelope};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+fn fixed_clock() -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(1_700_000_000_000)
+}
+
+async fn send_all(payloads: &[&[u8]]) -> Vec<Envelope> {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_deterministic_entropy(fixed_clock, 42);
+    for payload in payloads {
+        client.send("golden", payload).await.unwrap();
+    }
+    node.sent()
+}
+
+#[tokio::test]
+async fn identical_sends_produce_identical_envelopes() {
+    let payloads: [&[u8]; 3] = [b"one", b"two", b"three"];
+    let first = send_all(&payloads).await;
+    let second = send_all(&payloads).await;
+
+    assert_eq!(first, second);
+    assert!(first.iter().all(|e| e.timestamp_ms == 1_700_000_000_000));
+    assert_ne!(first[0].nonce, first[1].nonce);
+}
+
+#[tokio::test]
+async fn envelopes_match_golden_values() {
+    let envelope = send_all(&[b"hello"]).await.remove(0);
+
+    assert_eq!(
+        envelope.aad,
+        br#"{"key_version":0,"seq":1,"topic":"golden","ts":1700000000000}"#
+    );
+    assert_eq!(
+        hex::encode(&envelope.nonce),
+        "7848b5d711bc9883996317a3f9c90269d56771005d540a19"
+    );
+    assert_eq!(
+        envelope.msg_id,
+        "5df8798a74f7c11597db8d3fe5c0127c8cf82f987a4ba3e2ccd82d79953b413e"
+    );
+    assert_eq!(
+        hex::encode(&envelope.sig),
+        "767a69b8e5d968b107a68cc8967017deee08dfe351492521956d67356168d2a2\
+         9cc076f0b4bbb749af234f5f233e05e9bda99c429c7dc4eccc914a58b381df00"
+    );
+}
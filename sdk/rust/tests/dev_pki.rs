@@ -0,0 +1,49 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode, TestPki};
+use securefabric_sdk::tls::testing::generate_dev_pki;
+use securefabric_sdk::tls::validate_identity;
+use securefabric_sdk::Client;
+
+#[tokio::test]
+async fn dev_pki_establishes_mtls() {
+    let pki = generate_dev_pki().unwrap();
+    let node = MockNode::default();
+    let endpoint = common::spawn_mtls(
+        node.clone(),
+        &TestPki {
+            ca_pem: pki.ca_pem.clone(),
+            server_cert_pem: pki.server_cert_pem.clone(),
+            server_key_pem: pki.server_key_pem.clone(),
+            client_cert_pem: pki.client_cert_pem.clone(),
+            client_key_pem: pki.client_key_pem.clone(),
+        },
+    )
+    .await;
+
+    let mut client = Client::with_mtls(
+        &endpoint,
+        &pki.client_cert_pem,
+        &pki.client_key_pem,
+        &pki.ca_pem,
+    )
+    .await
+    .unwrap()
+    .with_signing_key(signing_key(1));
+    let msg_id = client.send("mtls", b"hello").await.unwrap();
+    assert_eq!(node.sent()[0].msg_id, msg_id);
+}
+
+#[test]
+fn dev_pki_is_fresh_and_valid() {
+    let pki = generate_dev_pki().unwrap();
+    let info = validate_identity(&pki.client_cert_pem, &pki.client_key_pem, &pki.ca_pem).unwrap();
+    assert_eq!(info.subject, "CN=securefabric dev client");
+    validate_identity(&pki.server_cert_pem, &pki.server_key_pem, &pki.ca_pem).unwrap();
+
+    let other = generate_dev_pki().unwrap();
+    assert_ne!(pki.ca_pem, other.ca_pem);
+    assert!(validate_identity(&pki.client_cert_pem, &pki.client_key_pem, &other.ca_pem).is_err());
+}
@@ -0,0 +1,78 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{MockNode, TestPki};
+use securefabric_sdk::diagnose::Stage;
+use securefabric_sdk::{Client, TlsConfig};
+
+fn stages(diagnosis: &securefabric_sdk::diagnose::Diagnosis) -> Vec<Stage> {
+    diagnosis.stages.iter().map(|report| report.stage).collect()
+}
+
+#[tokio::test]
+async fn healthy_plaintext_endpoint_passes_every_stage() {
+    let endpoint = common::spawn(MockNode::default()).await;
+    let diagnosis = Client::diagnose(&endpoint).await;
+
+    assert!(diagnosis.is_healthy(), "{diagnosis}");
+    assert_eq!(
+        stages(&diagnosis),
+        [Stage::Resolve, Stage::Connect, Stage::Http2, Stage::Rpc]
+    );
+}
+
+#[tokio::test]
+async fn unresolvable_host_fails_at_resolve() {
+    let diagnosis = Client::diagnose("http://securefabric.invalid:50051").await;
+
+    assert_eq!(diagnosis.failed_stage(), Some(Stage::Resolve));
+    assert_eq!(diagnosis.stages.len(), 1);
+    let error = diagnosis.stages[0].result.as_ref().unwrap_err();
+    assert!(error.contains("securefabric.invalid"), "{error}");
+}
+
+#[tokio::test]
+async fn closed_port_fails_at_connect() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    let diagnosis = Client::diagnose(format!("http://{addr}")).await;
+    assert_eq!(diagnosis.failed_stage(), Some(Stage::Connect));
+}
+
+#[tokio::test]
+async fn tls_stage_checks_certificate_and_alpn() {
+    let pki = TestPki::generate();
+    let endpoint = common::spawn_tls(MockNode::default(), &pki).await;
+
+    let trusted = TlsConfig::new()
+        .with_ca_pem(&pki.ca_pem)
+        .with_domain("localhost");
+    let diagnosis = Client::builder(&endpoint).tls(trusted).diagnose().await;
+    assert!(diagnosis.is_healthy(), "{diagnosis}");
+    assert_eq!(
+        stages(&diagnosis),
+        [
+            Stage::Resolve,
+            Stage::Connect,
+            Stage::Tls,
+            Stage::Http2,
+            Stage::Rpc
+        ]
+    );
+    assert!(diagnosis.stages[2]
+        .result
+        .as_ref()
+        .unwrap()
+        .contains("ALPN h2"));
+
+    let other = TestPki::generate();
+    let untrusted = TlsConfig::new()
+        .with_ca_pem(&other.ca_pem)
+        .with_domain("localhost");
+    let diagnosis = Client::builder(&endpoint).tls(untrusted).diagnose().await;
+    assert_eq!(diagnosis.failed_stage(), Some(Stage::Tls), "{diagnosis}");
+    assert_eq!(diagnosis.stages.len(), 3);
+}
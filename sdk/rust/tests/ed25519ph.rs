@@ -0,0 +1,152 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode};
+use prost::Message;
+use securefabric_sdk::crypto::scheme::{
+    sign_prehashed, verify_prehashed, Digest, Sha512, SigningKey, VerifyingKey, ED25519PH_CONTEXT,
+};
+use securefabric_sdk::crypto::{SignatureScheme, VerifyMode};
+use securefabric_sdk::keyring::Keyring;
+use securefabric_sdk::{conformance, Client, Envelope};
+
+#[test]
+fn conformance_vectors() {
+    let vectors: serde_json::Value = serde_json::from_str(conformance::VECTORS).unwrap();
+
+    let mut checked = 0;
+    for vector in vectors["signature_schemes"]["ed25519ph"]
+        .as_array()
+        .unwrap()
+    {
+        let field = |key: &str| hex::decode(vector[key].as_str().unwrap()).unwrap();
+        let description = vector["description"].as_str().unwrap();
+        let secret =
+            ed25519_dalek::SigningKey::from_bytes(&field("secret_key").try_into().unwrap());
+        let public = secret.verifying_key();
+        let context = field("context");
+        let message = field("message");
+        let signature = field("signature");
+
+        assert_eq!(vector["wire"], SignatureScheme::Ed25519ph.wire_value());
+        assert_eq!(
+            public.to_bytes().to_vec(),
+            field("public_key"),
+            "{description}"
+        );
+        let prehash = || Sha512::new_with_prefix(&message);
+        assert_eq!(
+            sign_prehashed(&secret, prehash(), &context),
+            signature,
+            "{description}"
+        );
+        assert!(
+            verify_prehashed(&public, prehash(), &context, &signature, VerifyMode::Strict),
+            "{description}"
+        );
+        assert!(
+            !verify_prehashed(&public, prehash(), b"other", &signature, VerifyMode::Strict),
+            "{description}"
+        );
+
+        if context == ED25519PH_CONTEXT {
+            let key = SigningKey::Ed25519ph(secret);
+            assert_eq!(key.sign(&message), signature, "{description}");
+            let public = key.verifying_key();
+            assert!(public.verify_scheme(
+                SignatureScheme::Ed25519ph,
+                &message,
+                &signature,
+                VerifyMode::Strict
+            ));
+            assert!(!public.verify(&message, &signature), "{description}");
+        }
+        checked += 1;
+    }
+    assert_eq!(checked, 2);
+}
+
+#[test]
+fn parts_sign_like_the_joined_message() {
+    let parts: [&[u8]; 3] = [b"{\"topic\":\"demo\"}", &[7; 24], b"payload"];
+    let joined = parts.concat();
+
+    for key in [
+        SigningKey::Ed25519ph(signing_key(1)),
+        SigningKey::Ed25519(signing_key(1)),
+    ] {
+        let signature = key.sign_parts(&parts);
+        assert_eq!(signature, key.sign(&joined));
+        let public = key.verifying_key();
+        assert!(public.verify_scheme_parts(key.scheme(), &parts, &signature, VerifyMode::Strict));
+        assert!(!public.verify_scheme_parts(
+            key.scheme(),
+            &parts[..2],
+            &signature,
+            VerifyMode::Strict
+        ));
+    }
+}
+
+#[tokio::test]
+async fn large_payload_round_trips_through_the_client() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut sender = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(SigningKey::Ed25519ph(signing_key(3)));
+
+    let payload: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+    sender.send("bulk", &payload).await.unwrap();
+    let sent = node.sent().remove(0);
+    assert_eq!(SignatureScheme::of(&sent), Some(SignatureScheme::Ed25519ph));
+    assert_eq!(sent.pubkey, signing_key(3).verifying_key().to_bytes());
+    assert_eq!(sent.sig.len(), 64);
+
+    let decoded = Envelope::try_from_bytes(&sent.encode_to_vec()).unwrap();
+    let receiver = Client::new(&endpoint).await.unwrap();
+    assert!(receiver.verify(&decoded).unwrap());
+
+    // The sender's plain Ed25519 key vouches for its Ed25519ph envelopes
+    let keyring = Keyring::new();
+    keyring.insert(signing_key(3).verifying_key());
+    let receiver = receiver.with_keyring(keyring);
+    assert!(receiver.verify(&decoded).unwrap());
+
+    let mut tampered = decoded;
+    tampered.payload[512 * 1024] ^= 1;
+    assert!(!receiver.verify(&tampered).unwrap());
+}
+
+#[tokio::test]
+async fn pure_and_prehashed_signatures_are_not_interchangeable() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut pure = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(4));
+    let mut prehashed = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(SigningKey::Ed25519ph(signing_key(4)));
+    pure.send("demo", b"hello").await.unwrap();
+    prehashed.send("demo", b"hello").await.unwrap();
+    let sent = node.sent();
+    let receiver = Client::new(&endpoint).await.unwrap();
+    assert!(receiver.verify(&sent[0]).unwrap());
+    assert!(receiver.verify(&sent[1]).unwrap());
+
+    let mut relabelled = sent[0].clone();
+    relabelled.sig_scheme = SignatureScheme::Ed25519ph.wire_value();
+    assert!(!receiver.verify(&relabelled).unwrap());
+    let mut relabelled = sent[1].clone();
+    relabelled.sig_scheme = SignatureScheme::Ed25519.wire_value();
+    assert!(!receiver.verify(&relabelled).unwrap());
+
+    let key = VerifyingKey::from_bytes(SignatureScheme::Ed25519ph, &sent[1].pubkey).unwrap();
+    assert!(key.supports(SignatureScheme::Ed25519ph));
+    assert!(!key.supports(SignatureScheme::Secp256k1));
+}
@@ -0,0 +1,231 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode};
+use futures::StreamExt;
+use securefabric_sdk::crypto::SignOrder;
+use securefabric_sdk::subscription::DecryptError;
+use securefabric_sdk::{crypto, Client};
+
+const TOPIC_KEY: [u8; 32] = [9u8; 32];
+
+#[test]
+fn xchacha_matches_reference_vector() {
+    let key =
+        hex::decode("808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f").unwrap();
+    let nonce = hex::decode("404142434445464748494a4b4c4d4e4f5051525354555657").unwrap();
+    let aad = hex::decode("50515253c0c1c2c3c4c5c6c7").unwrap();
+    let plaintext = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+
+    let (ciphertext, tag) = crypto::encrypt(&key, &nonce, plaintext, &aad).unwrap();
+    assert_eq!(hex::encode(&tag), "c0875924c1c7987947deafd8780acf49");
+    assert_eq!(
+        crypto::decrypt(&key, &nonce, &ciphertext, &aad, &tag).unwrap(),
+        plaintext
+    );
+}
+
+#[tokio::test]
+async fn encrypt_send_verify_decrypt_round_trip() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_encryption(TOPIC_KEY, 3)
+        .unwrap();
+
+    client.send("secrets", b"attack at dawn").await.unwrap();
+    let envelope = node.sent().remove(0);
+
+    assert_eq!(envelope.key_version, 3);
+    assert_eq!(envelope.nonce.len(), crypto::NONCE_LEN);
+    assert_ne!(envelope.payload, b"attack at dawn");
+    assert!(client.verify(&envelope).unwrap());
+    assert_eq!(client.decrypt(&envelope).unwrap(), b"attack at dawn");
+}
+
+#[tokio::test]
+async fn swapped_nonce_rejected() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_encryption(TOPIC_KEY, 1)
+        .unwrap();
+
+    client.send("secrets", b"first").await.unwrap();
+    client.send("secrets", b"second").await.unwrap();
+    let sent = node.sent();
+
+    let mut swapped = sent[0].clone();
+    swapped.nonce = sent[1].nonce.clone();
+
+    assert!(!client.verify(&swapped).unwrap());
+    assert!(client.decrypt(&swapped).is_err());
+}
+
+#[tokio::test]
+async fn decrypt_rejects_plaintext_and_wrong_version() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut plain = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+    plain.send("secrets", b"clear").await.unwrap();
+
+    let mut encrypted = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_encryption(TOPIC_KEY, 2)
+        .unwrap();
+    encrypted.send("secrets", b"hidden").await.unwrap();
+
+    let sent = node.sent();
+    let reader = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_encryption(TOPIC_KEY, 1)
+        .unwrap();
+    assert!(reader.decrypt(&sent[0]).is_err());
+    assert!(reader.decrypt(&sent[1]).is_err());
+}
+
+#[tokio::test]
+async fn subscribe_decrypted_round_trip_surfaces_bad_items() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut sender = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_encryption(TOPIC_KEY, 1)
+        .unwrap();
+    sender.send("secrets", b"one").await.unwrap();
+    sender.send("secrets", b"two").await.unwrap();
+
+    let mut sent = node.sent();
+    let mut tampered = sent[1].clone();
+    tampered.payload[0] ^= 0xff;
+    let plaintext = common::signed_envelope(&signing_key(1), "secrets", 9, b"clear");
+    sent.insert(1, tampered);
+    sent.push(plaintext);
+    *node.state.feed.lock().unwrap() = sent;
+
+    let mut reader = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_encryption(TOPIC_KEY, 1)
+        .unwrap();
+    let items: Vec<_> = reader
+        .subscribe_decrypted(b"secrets")
+        .await
+        .unwrap()
+        .collect()
+        .await;
+
+    assert_eq!(items.len(), 4);
+    assert_eq!(items[0].as_ref().unwrap().payload, b"one");
+    assert!(matches!(items[1], Err(DecryptError::BadSignature { .. })));
+    assert_eq!(items[2].as_ref().unwrap().payload, b"two");
+    assert!(matches!(items[3], Err(DecryptError::Decrypt { .. })));
+
+    let mut keyless = Client::new(&endpoint).await.unwrap();
+    assert!(keyless.subscribe_decrypted(b"secrets").await.is_err());
+}
+
+#[tokio::test]
+async fn encrypt_then_sign_rejects_tampered_ciphertext_without_key() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut sender = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_encryption(TOPIC_KEY, 1)
+        .unwrap()
+        .with_sign_order(SignOrder::EncryptThenSign);
+    sender.send("secrets", b"pay 10").await.unwrap();
+
+    let envelope = node.sent().remove(0);
+    assert_eq!(SignOrder::of(&envelope), SignOrder::EncryptThenSign);
+
+    // A party without the topic key, such as the node, can authenticate it
+    let keyless = Client::new(&endpoint).await.unwrap();
+    assert!(keyless.verify(&envelope).unwrap());
+
+    let mut tampered = envelope.clone();
+    tampered.payload[0] ^= 0x01;
+    assert!(!keyless.verify(&tampered).unwrap());
+    assert!(!sender.verify(&tampered).unwrap());
+    assert!(sender.decrypt(&tampered).is_err());
+}
+
+#[tokio::test]
+async fn sign_then_encrypt_verifies_after_decryption() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut sender = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_encryption(TOPIC_KEY, 1)
+        .unwrap()
+        .with_sign_order(SignOrder::SignThenEncrypt);
+    sender.send("secrets", b"pay 10").await.unwrap();
+    sender.send("secrets", b"pay 20").await.unwrap();
+
+    let sent = node.sent();
+    assert_eq!(SignOrder::of(&sent[0]), SignOrder::SignThenEncrypt);
+    let reader = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_encryption(TOPIC_KEY, 1)
+        .unwrap();
+    assert!(reader.verify(&sent[0]).unwrap());
+    assert_eq!(reader.decrypt(&sent[0]).unwrap(), b"pay 10");
+
+    // Without the topic key the signature cannot be checked
+    let keyless = Client::new(&endpoint).await.unwrap();
+    assert!(keyless.verify(&sent[0]).is_err());
+
+    // Ciphertext tampering fails decryption, and with it verification
+    let mut tampered = sent[0].clone();
+    tampered.payload[0] ^= 0x01;
+    assert!(reader.verify(&tampered).is_err());
+
+    // The subscription adapter decrypts first, then checks the plaintext signature
+    let mut forged = sent[1].clone();
+    forged.sig = sent[0].sig.clone();
+    *node.state.feed.lock().unwrap() = vec![sent[0].clone(), forged, tampered];
+    let mut reader = reader;
+    let items: Vec<_> = reader
+        .subscribe_decrypted(b"secrets")
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    assert_eq!(items[0].as_ref().unwrap().payload, b"pay 10");
+    assert!(matches!(items[1], Err(DecryptError::BadSignature { .. })));
+    assert!(matches!(items[2], Err(DecryptError::Decrypt { .. })));
+}
+
+#[tokio::test]
+async fn rejects_reserved_key_versions() {
+    let endpoint = common::spawn(MockNode::default()).await;
+    let client = Client::new(endpoint).await.unwrap();
+    for version in [0, crypto::SEALED_KEY_VERSION] {
+        let err = client
+            .clone()
+            .with_encryption(TOPIC_KEY, version)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("reserved"), "{err}");
+    }
+}
@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode};
+use securefabric_sdk::fabric::InMemoryFabric;
+use securefabric_sdk::{Client, Error};
+use std::path::PathBuf;
+use std::time::Duration;
+
+fn scratch(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("sf-file-{name}-{}", std::process::id()))
+}
+
+fn contents(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 31 % 251) as u8).collect()
+}
+
+#[tokio::test]
+async fn multi_chunk_file_round_trips() {
+    let source = scratch("round-trip-in");
+    let target = scratch("round-trip-out");
+    std::fs::write(&source, contents(10_000)).unwrap();
+
+    let fabric = InMemoryFabric::new();
+    let mut sender = fabric
+        .client()
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+    let mut receiver = fabric.client().await.unwrap();
+
+    sender.send("logs", b"unrelated").await.unwrap();
+    let file_id = sender.send_file("logs", &[], &source, 1024).await.unwrap();
+    assert_eq!(fabric.retained(b"logs").len(), 1 + 10);
+
+    let received = receiver.receive_file(b"logs", &target).await.unwrap();
+    assert_eq!(received, file_id);
+    assert_eq!(std::fs::read(&target).unwrap(), contents(10_000));
+
+    std::fs::remove_file(source).unwrap();
+    std::fs::remove_file(target).unwrap();
+}
+
+#[tokio::test]
+async fn missing_chunk_fails_without_output() {
+    let source = scratch("gap-in");
+    let target = scratch("gap-out");
+    std::fs::write(&source, contents(4_000)).unwrap();
+
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut sender = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+    sender.send_file("logs", &[], &source, 1000).await.unwrap();
+
+    let mut feed = node.sent();
+    feed.remove(2);
+    *node.state.feed.lock().unwrap() = feed;
+    let mut receiver = Client::new(&endpoint).await.unwrap();
+    let error = receiver.receive_file(b"logs", &target).await.unwrap_err();
+    assert!(
+        error.to_string().contains("chunk 2 of 4 is missing"),
+        "{error}"
+    );
+    assert!(!target.exists());
+
+    std::fs::remove_file(source).unwrap();
+}
+
+#[tokio::test]
+async fn stalled_transfer_times_out() {
+    let source = scratch("stall-in");
+    let target = scratch("stall-out");
+    std::fs::write(&source, contents(3_000)).unwrap();
+
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut sender = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+    sender.send_file("logs", &[], &source, 1000).await.unwrap();
+
+    let mut feed = node.sent();
+    feed.truncate(2);
+    *node.state.feed.lock().unwrap() = feed;
+    let _live = node.live_feed();
+    let mut receiver = Client::new(&endpoint).await.unwrap();
+    let error = receiver
+        .receive_file_within(b"logs", &target, Duration::from_millis(100))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<Error>(),
+        Some(&Error::Timeout {
+            after: Duration::from_millis(100)
+        })
+    );
+    assert!(!target.exists());
+
+    std::fs::remove_file(source).unwrap();
+}
@@ -0,0 +1,33 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use ed25519_dalek::Signer;
+use securefabric_sdk::crypto::generate_keypairs;
+use std::collections::HashSet;
+
+#[test]
+fn generates_distinct_keypairs() {
+    let keypairs = generate_keypairs(5_000);
+    assert_eq!(keypairs.len(), 5_000);
+
+    let public: HashSet<_> = keypairs.iter().map(|k| k.verifying_key_hex()).collect();
+    let secret: HashSet<_> = keypairs.iter().map(|k| k.to_hex()).collect();
+    assert_eq!(public.len(), keypairs.len());
+    assert_eq!(secret.len(), keypairs.len());
+
+    for keypair in keypairs.iter().step_by(500) {
+        assert_eq!(keypair.signing_key.verifying_key(), keypair.verifying_key);
+        let signature = keypair.signing_key.sign(b"provisioned");
+        assert!(keypair
+            .verifying_key
+            .verify_strict(b"provisioned", &signature)
+            .is_ok());
+    }
+
+    // Separate calls share nothing either
+    let again: HashSet<_> = generate_keypairs(100)
+        .iter()
+        .map(|k| k.verifying_key_hex())
+        .collect();
+    assert!(again.is_disjoint(&public));
+    assert!(generate_keypairs(0).is_empty());
+}
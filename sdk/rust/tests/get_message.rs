@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use securefabric_sdk::capabilities::Feature;
+use securefabric_sdk::keyring::{fingerprint, Keyring};
+use securefabric_sdk::{Client, Error};
+
+#[tokio::test]
+async fn returns_stored_envelope_or_none() {
+    let stored = signed_envelope(&signing_key(1), "audit", 3, b"entry");
+    let other = signed_envelope(&signing_key(1), "audit", 4, b"other");
+    let node = MockNode::with_feed(vec![stored.clone(), other]);
+    let mut client = Client::new(common::spawn(node).await).await.unwrap();
+
+    let found = client.get_message(b"audit", &stored.msg_id).await.unwrap();
+    assert_eq!(found, Some(stored.clone()));
+
+    assert_eq!(
+        client
+            .get_message(b"audit", &"00".repeat(32))
+            .await
+            .unwrap(),
+        None
+    );
+    // Message IDs are scoped to their topic
+    assert_eq!(
+        client.get_message(b"other", &stored.msg_id).await.unwrap(),
+        None
+    );
+}
+
+#[tokio::test]
+async fn verifies_against_the_keyring() {
+    let alice = signing_key(1);
+    let mallory = signing_key(2);
+    let genuine = signed_envelope(&alice, "audit", 1, b"entry");
+    let mut tampered = signed_envelope(&alice, "audit", 2, b"entry");
+    tampered.payload = b"forged".to_vec();
+    let unknown = signed_envelope(&mallory, "audit", 1, b"entry");
+    let node = MockNode::with_feed(vec![genuine.clone(), tampered.clone(), unknown.clone()]);
+
+    let keyring = Keyring::new();
+    keyring.insert(alice.verifying_key());
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(&endpoint).await.unwrap().with_keyring(keyring);
+
+    assert!(client
+        .get_message(b"audit", &genuine.msg_id)
+        .await
+        .unwrap()
+        .is_some());
+
+    let err = client
+        .get_message(b"audit", &tampered.msg_id)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<Error>(),
+        Some(&Error::InvalidSignature {
+            msg_id: tampered.msg_id.clone()
+        })
+    );
+
+    let err = client
+        .get_message(b"audit", &unknown.msg_id)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<Error>(),
+        Some(&Error::UnknownSender {
+            fingerprint: fingerprint(mallory.verifying_key().as_bytes())
+        })
+    );
+
+    // Without a keyring the stored envelope is returned as is
+    let mut client = Client::new(&endpoint).await.unwrap();
+    let found = client
+        .get_message(b"audit", &tampered.msg_id)
+        .await
+        .unwrap();
+    assert_eq!(found, Some(tampered));
+}
+
+#[tokio::test]
+async fn unsupported_without_get_message() {
+    let node = MockNode::default().without("get_message");
+    let mut client = Client::new(common::spawn(node).await).await.unwrap();
+
+    let err = client
+        .get_message(b"audit", &"00".repeat(32))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<Error>(),
+        Some(&Error::Unsupported {
+            feature: Feature::GetMessage
+        })
+    );
+}
@@ -0,0 +1,118 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use securefabric_sdk::handler::{async_trait, MessageHandler, RunOptions, RunSummary};
+use securefabric_sdk::pb::Envelope;
+use securefabric_sdk::Client;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+#[derive(Default)]
+struct CountingHandler {
+    seen: Arc<AtomicU64>,
+    errors: Arc<AtomicU64>,
+}
+
+#[async_trait]
+impl MessageHandler for CountingHandler {
+    async fn handle(&self, envelope: Envelope) -> anyhow::Result<()> {
+        self.seen.fetch_add(1, Ordering::SeqCst);
+        if envelope.payload == b"boom" {
+            anyhow::bail!("handler failed");
+        }
+        Ok(())
+    }
+
+    async fn on_error(&self, _msg_id: &str, _error: anyhow::Error) {
+        self.errors.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+fn feed() -> Vec<Envelope> {
+    let key = signing_key(1);
+    let mut forged = signed_envelope(&key, "jobs", 3, b"forged");
+    forged.payload = b"tampered".to_vec();
+    vec![
+        signed_envelope(&key, "jobs", 1, b"one"),
+        signed_envelope(&key, "jobs", 2, b"boom"),
+        forged,
+        signed_envelope(&key, "jobs", 4, b"four"),
+        signed_envelope(&key, "jobs", 5, b"five"),
+    ]
+}
+
+#[tokio::test]
+async fn run_dispatches_and_isolates_handler_errors() {
+    let endpoint = common::spawn(MockNode::with_feed(feed())).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let handler = CountingHandler::default();
+    let seen = handler.seen.clone();
+    let errors = handler.errors.clone();
+
+    let summary = client.run(b"jobs", handler).await.unwrap();
+
+    assert_eq!(
+        summary,
+        RunSummary {
+            handled: 3,
+            failed: 1,
+            rejected: 1,
+            acked: 0,
+            ack_failed: 0,
+            end: None,
+        }
+    );
+    assert_eq!(seen.load(Ordering::SeqCst), 4);
+    assert_eq!(errors.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn ack_on_success_acks_only_handled_messages() {
+    let feed = feed();
+    let expected: Vec<String> = [0, 3, 4].iter().map(|&i| feed[i].msg_id.clone()).collect();
+    let node = MockNode::with_feed(feed);
+    let endpoint = common::spawn(node.clone()).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let options = RunOptions {
+        ack_on_success: true,
+        ..RunOptions::default()
+    };
+    client
+        .run_with(b"jobs", CountingHandler::default(), options)
+        .await
+        .unwrap();
+
+    assert_eq!(node.acked(), expected);
+}
+
+#[tokio::test]
+async fn failed_acks_are_counted_and_the_loop_continues() {
+    let node = MockNode::with_feed(feed()).without("ack");
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let handler = CountingHandler::default();
+    let seen = handler.seen.clone();
+    let options = RunOptions {
+        ack_on_success: true,
+        ..RunOptions::default()
+    };
+    let summary = client.run_with(b"jobs", handler, options).await.unwrap();
+
+    assert_eq!(
+        summary,
+        RunSummary {
+            handled: 3,
+            failed: 1,
+            rejected: 1,
+            acked: 0,
+            ack_failed: 3,
+            end: None,
+        }
+    );
+    assert_eq!(seen.load(Ordering::SeqCst), 4);
+}
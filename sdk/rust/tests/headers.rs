@@ -0,0 +1,127 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope_with_headers, signing_key, MockNode};
+use futures::StreamExt;
+use securefabric_sdk::headers::HeaderFilter;
+use securefabric_sdk::Client;
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
+
+fn feed() -> Vec<securefabric_sdk::pb::Envelope> {
+    let key = signing_key(1);
+    vec![
+        signed_envelope_with_headers(&key, "orders", 1, &[("region", "eu-west")], b"1"),
+        signed_envelope_with_headers(&key, "orders", 2, &[("region", "us-east")], b"2"),
+        signed_envelope_with_headers(&key, "orders", 3, &[], b"3"),
+        signed_envelope_with_headers(
+            &key,
+            "orders",
+            4,
+            &[("region", "eu-north"), ("tier", "gold")],
+            b"4",
+        ),
+    ]
+}
+
+async fn matching_payloads(client: &mut Client, filter: HeaderFilter) -> Vec<Vec<u8>> {
+    client
+        .subscribe_matching(b"orders", filter)
+        .await
+        .unwrap()
+        .map(|item| item.unwrap().payload)
+        .collect()
+        .await
+}
+
+#[test]
+fn filter_predicates_match_headers() {
+    let envelopes = feed();
+    let matching = |filter: HeaderFilter| -> Vec<u64> {
+        envelopes
+            .iter()
+            .filter(|e| filter.matches(e))
+            .map(|e| e.seq)
+            .collect()
+    };
+
+    assert_eq!(matching(HeaderFilter::new()), vec![1, 2, 3, 4]);
+    assert_eq!(
+        matching(HeaderFilter::new().equals("region", "us-east")),
+        vec![2]
+    );
+    assert_eq!(
+        matching(HeaderFilter::new().one_of("region", ["us-east", "eu-north"])),
+        vec![2, 4]
+    );
+    assert_eq!(
+        matching(HeaderFilter::new().prefix("region", "eu-")),
+        vec![1, 4]
+    );
+    assert_eq!(
+        matching(
+            HeaderFilter::new()
+                .prefix("region", "eu-")
+                .equals("tier", "gold")
+        ),
+        vec![4]
+    );
+}
+
+#[tokio::test]
+async fn filter_is_pushed_down_to_the_node() {
+    let node = MockNode::with_feed(feed());
+    let endpoint = common::spawn(node.clone()).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let filter = HeaderFilter::new().prefix("region", "eu-");
+    let payloads = matching_payloads(&mut client, filter.clone()).await;
+
+    assert_eq!(payloads, vec![b"1".to_vec(), b"4".to_vec()]);
+    let requests = node.state.subscribe_requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].filter, filter.predicates());
+}
+
+#[tokio::test]
+async fn falls_back_to_client_side_filtering() {
+    let node = MockNode::with_feed(feed());
+    node.state.filters_unsupported.store(true, Ordering::SeqCst);
+    let endpoint = common::spawn(node.clone()).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let payloads =
+        matching_payloads(&mut client, HeaderFilter::new().prefix("region", "eu-")).await;
+
+    assert_eq!(payloads, vec![b"1".to_vec(), b"4".to_vec()]);
+    let requests = node.state.subscribe_requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert!(requests[1].filter.is_empty());
+}
+
+#[tokio::test]
+async fn sent_headers_are_signed() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+
+    let headers = BTreeMap::from([("region".to_string(), "eu-west".to_string())]);
+    client
+        .send_with_headers("orders", &headers, b"x")
+        .await
+        .unwrap();
+
+    let mut envelope = node.sent().remove(0);
+    assert_eq!(envelope.headers(), headers);
+    assert!(client.verify(&envelope).unwrap());
+
+    envelope.aad = String::from_utf8(envelope.aad)
+        .unwrap()
+        .replace("eu-west", "us-east")
+        .into_bytes();
+    assert!(!client.verify(&envelope).unwrap());
+}
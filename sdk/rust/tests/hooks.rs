@@ -0,0 +1,167 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use futures::StreamExt;
+use securefabric_sdk::hooks::{ReceiveOrder, SendContext};
+use securefabric_sdk::subscription::DecryptError;
+use securefabric_sdk::{Client, Envelope};
+use std::collections::BTreeMap;
+
+#[tokio::test]
+async fn pre_send_header_is_sent_and_signed() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut client = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_pre_send(|message: &mut SendContext| {
+            message
+                .headers
+                .insert("correlation-id".into(), "c-42".into());
+        });
+
+    client.send("orders", b"plain").await.unwrap();
+    let headers = BTreeMap::from([("region".to_string(), "eu-west".to_string())]);
+    client
+        .send_with_headers("orders", &headers, b"tagged")
+        .await
+        .unwrap();
+
+    let sent = node.sent();
+    assert_eq!(sent[0].headers()["correlation-id"], "c-42");
+    assert_eq!(sent[1].headers()["correlation-id"], "c-42");
+    assert_eq!(sent[1].headers()["region"], "eu-west");
+    for envelope in &sent {
+        assert!(client.verify(envelope).unwrap());
+    }
+
+    // The header is covered by the signature
+    let mut forged = sent[0].clone();
+    forged.aad = String::from_utf8(forged.aad)
+        .unwrap()
+        .replace("c-42", "c-43")
+        .into_bytes();
+    assert!(!client.verify(&forged).unwrap());
+}
+
+#[tokio::test]
+async fn pre_send_hooks_run_in_order_before_encryption() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut client = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_encryption([7u8; 32], 1)
+        .unwrap()
+        .with_pre_send(|message: &mut SendContext| {
+            message.topic = format!("v2.{}", message.topic);
+            message.payload.extend_from_slice(b" [redacted]");
+        })
+        .with_pre_send(|message: &mut SendContext| {
+            let version = message.topic.split('.').next().unwrap().to_string();
+            message.headers.insert("schema".into(), version);
+        });
+
+    client.send("orders", b"card=4111").await.unwrap();
+
+    let sent = &node.sent()[0];
+    assert_eq!(sent.topic, "v2.orders");
+    assert_eq!(sent.headers()["schema"], "v2");
+    assert!(client.verify(sent).unwrap());
+    assert_eq!(client.decrypt(sent).unwrap(), b"card=4111 [redacted]");
+}
+
+#[tokio::test]
+async fn post_receive_transforms_and_drops() {
+    let key = signing_key(1);
+    let node = MockNode::with_feed(vec![
+        signed_envelope(&key, "events", 1, b"keep"),
+        signed_envelope(&key, "events", 2, b"noise"),
+        signed_envelope(&key, "events", 3, b"also keep"),
+    ]);
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_post_receive(|envelope: Envelope| (envelope.payload != b"noise").then_some(envelope))
+        .with_post_receive(|mut envelope: Envelope| {
+            envelope.payload.make_ascii_uppercase();
+            Some(envelope)
+        });
+
+    let payloads: Vec<_> = client
+        .subscribe(b"events")
+        .await
+        .unwrap()
+        .map(|item| item.unwrap().payload)
+        .collect()
+        .await;
+    assert_eq!(payloads, vec![b"KEEP".to_vec(), b"ALSO KEEP".to_vec()]);
+    // Dropped envelopes were still consumed
+    assert_eq!(client.subscription_stats(b"events").unwrap().received, 3);
+}
+
+#[tokio::test]
+async fn post_receive_runs_before_or_after_decryption() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut sender = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_encryption([7u8; 32], 1)
+        .unwrap();
+    sender.send("secrets", b"one").await.unwrap();
+    sender.send("secrets", b"two").await.unwrap();
+    *node.state.feed.lock().unwrap() = node.sent();
+
+    let reader = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_encryption([7u8; 32], 1)
+        .unwrap();
+    let after = reader.clone().with_post_receive(|mut envelope: Envelope| {
+        (envelope.payload != b"two").then(|| {
+            envelope.payload.extend_from_slice(b"!");
+            envelope
+        })
+    });
+    let items: Vec<_> = after
+        .clone()
+        .subscribe_decrypted(b"secrets")
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].as_ref().unwrap().payload, b"one!");
+
+    // Before verification the hook sees ciphertext, so rewriting it breaks the
+    // signature and a plaintext match never drops anything
+    let items: Vec<_> = after
+        .with_receive_order(ReceiveOrder::BeforeVerification)
+        .subscribe_decrypted(b"secrets")
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    assert_eq!(items.len(), 2);
+    assert!(items
+        .iter()
+        .all(|item| matches!(item, Err(DecryptError::BadSignature { .. }))));
+
+    let dropped: Vec<_> = reader
+        .with_post_receive(|envelope: Envelope| (envelope.seq != 1).then_some(envelope))
+        .with_receive_order(ReceiveOrder::BeforeVerification)
+        .subscribe_decrypted(b"secrets")
+        .await
+        .unwrap()
+        .map(|item| item.unwrap().payload)
+        .collect()
+        .await;
+    assert_eq!(dropped, vec![b"two".to_vec()]);
+}
@@ -0,0 +1,165 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::signing_key;
+use futures::StreamExt;
+use securefabric_sdk::fabric::InMemoryFabric;
+use securefabric_sdk::Client;
+use std::time::Duration;
+use tonic::{Code, Status};
+
+async fn publisher(fabric: &InMemoryFabric, seed: u8) -> Client {
+    fabric
+        .client()
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(seed))
+}
+
+#[tokio::test]
+async fn publishes_from_one_client_to_another() {
+    let fabric = InMemoryFabric::new();
+    let mut sender = publisher(&fabric, 1).await;
+    let mut receiver = fabric.client().await.unwrap();
+
+    let mut subscription = receiver.subscribe(b"chat").await.unwrap();
+    sender.send("other", b"elsewhere").await.unwrap();
+    for payload in [&b"one"[..], b"two", b"three"] {
+        sender.send("chat", payload).await.unwrap();
+    }
+
+    let mut received = Vec::new();
+    for _ in 0..3 {
+        let envelope = subscription.next().await.unwrap().unwrap();
+        assert!(receiver.verify(&envelope).unwrap());
+        received.push((envelope.seq, envelope.payload));
+    }
+    assert_eq!(
+        received,
+        [
+            (2, b"one".to_vec()),
+            (3, b"two".to_vec()),
+            (4, b"three".to_vec())
+        ]
+    );
+    assert_eq!(fabric.retained(b"other").len(), 1);
+}
+
+#[tokio::test]
+async fn fans_out_concurrent_publishers_to_every_subscriber() {
+    let fabric = InMemoryFabric::new();
+    let mut first = fabric.client().await.unwrap();
+    let mut second = fabric.client().await.unwrap();
+    let subscriptions = [
+        first.subscribe(b"events").await.unwrap(),
+        second.subscribe(b"events").await.unwrap(),
+    ];
+
+    let mut tasks = Vec::new();
+    for seed in 1..=3 {
+        let mut client = publisher(&fabric, seed).await;
+        tasks.push(tokio::spawn(async move {
+            for i in 0..20u8 {
+                client.send("events", &[seed, i]).await.unwrap();
+            }
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    for subscription in subscriptions {
+        let envelopes: Vec<_> = tokio::time::timeout(
+            Duration::from_secs(5),
+            subscription
+                .take(60)
+                .map(Result::unwrap)
+                .collect::<Vec<_>>(),
+        )
+        .await
+        .unwrap();
+        for seed in 1..=3 {
+            let from_sender: Vec<u8> = envelopes
+                .iter()
+                .filter(|e| e.payload[0] == seed)
+                .map(|e| e.payload[1])
+                .collect();
+            // Each sender's envelopes arrive complete and in order
+            assert_eq!(from_sender, (0..20).collect::<Vec<_>>());
+        }
+    }
+}
+
+#[tokio::test]
+async fn acked_envelopes_are_not_redelivered() {
+    let fabric = InMemoryFabric::new();
+    let mut sender = publisher(&fabric, 1).await;
+    for payload in [&b"a"[..], b"b", b"c"] {
+        sender.send("jobs", payload).await.unwrap();
+    }
+
+    let mut worker = fabric.client().await.unwrap();
+    let delivered: Vec<_> = worker
+        .subscribe(b"jobs")
+        .await
+        .unwrap()
+        .take(2)
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    let msg_ids = delivered.into_iter().map(|e| e.msg_id).collect();
+    worker.ack(b"jobs", msg_ids).await.unwrap();
+
+    let mut next = fabric.client().await.unwrap();
+    let mut redelivered = next.subscribe(b"jobs").await.unwrap();
+    assert_eq!(redelivered.next().await.unwrap().unwrap().payload, b"c");
+    assert!(
+        tokio::time::timeout(Duration::from_millis(100), redelivered.next())
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn restored_sessions_resume_where_they_left_off() {
+    let fabric = InMemoryFabric::new();
+    let mut producer = publisher(&fabric, 1).await;
+    for i in 0..5u8 {
+        producer.send("events", &[i]).await.unwrap();
+    }
+
+    let mut first = publisher(&fabric, 2).await;
+    let mut stream = first.subscribe(b"events").await.unwrap();
+    for _ in 0..3 {
+        stream.next().await.unwrap().unwrap();
+    }
+    drop(stream);
+    first.send("replies", b"a").await.unwrap();
+
+    let mut second = publisher(&fabric, 2).await;
+    second.restore_state(first.export_state()).unwrap();
+    let mut resumed = second.subscribe(b"events").await.unwrap();
+    let payloads = [
+        resumed.next().await.unwrap().unwrap().payload,
+        resumed.next().await.unwrap().unwrap().payload,
+    ];
+    assert_eq!(payloads, [[3], [4]]);
+    // The restored sequence continues past the first client's sends
+    second.send("replies", b"b").await.unwrap();
+    let seqs: Vec<u64> = fabric.retained(b"replies").iter().map(|e| e.seq).collect();
+    assert_eq!(seqs, [1, 2]);
+}
+
+#[tokio::test]
+async fn rejects_reused_sequence_numbers() {
+    let fabric = InMemoryFabric::new();
+    let mut first = publisher(&fabric, 1).await;
+    let mut second = publisher(&fabric, 1).await;
+
+    first.send("events", b"first").await.unwrap();
+    let error = second.send("events", b"second").await.unwrap_err();
+    let status = error.downcast_ref::<Status>().unwrap();
+    assert_eq!(status.code(), Code::AlreadyExists);
+    assert_eq!(fabric.retained(b"events").len(), 1);
+}
@@ -0,0 +1,110 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode};
+use securefabric_sdk::chain::SenderChainVerifier;
+use securefabric_sdk::keyring::{fingerprint, Keyring};
+use securefabric_sdk::pb::Envelope;
+use securefabric_sdk::rotator::SigningKeyRotator;
+use securefabric_sdk::Client;
+
+fn kid(envelope: &Envelope) -> String {
+    let aad: serde_json::Value = serde_json::from_slice(&envelope.aad).unwrap();
+    aad["kid"].as_str().unwrap().to_string()
+}
+
+async fn send_batch(rotator: SigningKeyRotator, count: usize) -> Vec<Envelope> {
+    let node = MockNode::default();
+    let state = node.state.clone();
+    let mut client = Client::new(common::spawn(node).await)
+        .await
+        .unwrap()
+        .with_signing_key_rotator(rotator);
+    for i in 0..count {
+        client.send("fleet", &[i as u8]).await.unwrap();
+    }
+    let sent = state.sent.lock().unwrap().clone();
+    sent
+}
+
+#[tokio::test]
+async fn weighted_keys_sign_in_proportion() {
+    let rotator = SigningKeyRotator::weighted([
+        (signing_key(1), 3),
+        (signing_key(2), 1),
+        (signing_key(3), 2),
+    ]);
+    let fingerprints = rotator.fingerprints();
+    let sent = send_batch(rotator, 60).await;
+
+    let kids: Vec<String> = sent.iter().map(kid).collect();
+    let share = |fp: &String| kids.iter().filter(|kid| *kid == fp).count();
+    assert_eq!(share(&fingerprints[0]), 30);
+    assert_eq!(share(&fingerprints[1]), 10);
+    assert_eq!(share(&fingerprints[2]), 20);
+
+    // Each full round of six sends holds every key its weight's share of times
+    for round in kids.chunks(6) {
+        assert_eq!(
+            round.iter().filter(|kid| **kid == fingerprints[0]).count(),
+            3
+        );
+    }
+
+    // The stamped fingerprint names the key that signed
+    for envelope in &sent {
+        assert_eq!(kid(envelope), fingerprint(&envelope.pubkey));
+    }
+}
+
+#[tokio::test]
+async fn round_robin_takes_keys_in_order() {
+    let rotator = SigningKeyRotator::round_robin([signing_key(1), signing_key(2)]);
+    let fingerprints = rotator.fingerprints();
+    let sent = send_batch(rotator, 4).await;
+    let kids: Vec<String> = sent.iter().map(kid).collect();
+    assert_eq!(kids, [0, 1, 0, 1].map(|i| fingerprints[i].clone()));
+}
+
+#[tokio::test]
+async fn keyring_of_the_rotation_verifies_every_key() {
+    let rotator = SigningKeyRotator::round_robin([signing_key(1), signing_key(2), signing_key(3)]);
+    let keyring = Keyring::new();
+    keyring.reload(rotator.verifying_keys());
+    let sent = send_batch(rotator, 6).await;
+
+    let verifier = Client::new(common::spawn(MockNode::default()).await)
+        .await
+        .unwrap()
+        .with_keyring(keyring);
+    for envelope in &sent {
+        assert_eq!(
+            verifier.verified_payload(envelope).unwrap(),
+            envelope.payload
+        );
+    }
+}
+
+#[tokio::test]
+async fn each_rotated_key_signs_an_unbroken_chain() {
+    let keys = [signing_key(1), signing_key(2), signing_key(3)];
+    let rotator = SigningKeyRotator::weighted([
+        (keys[0].clone(), 3),
+        (keys[1].clone(), 1),
+        (keys[2].clone(), 2),
+    ]);
+    let sent = send_batch(rotator, 30).await;
+
+    for key in &keys {
+        let mut chain = SenderChainVerifier::starting_at(key.verifying_key(), 1);
+        let own: Vec<&Envelope> = sent
+            .iter()
+            .filter(|envelope| chain.is_from_sender(envelope))
+            .collect();
+        assert!(!own.is_empty());
+        for envelope in own {
+            chain.check(envelope).unwrap();
+        }
+    }
+}
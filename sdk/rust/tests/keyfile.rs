@@ -0,0 +1,136 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use securefabric_sdk::crypto::keyfile::{KeyFileError, MAGIC, VERSION};
+use securefabric_sdk::crypto::Keypair;
+use zeroize::Zeroize;
+
+const SEED: [u8; 32] = [0x42; 32];
+
+#[test]
+fn round_trips_with_and_without_public_key() {
+    let keypair = Keypair::from_bytes(&SEED);
+    for embed in [true, false] {
+        let bytes = keypair.to_file_bytes(embed);
+        assert_eq!(&bytes[..4], MAGIC);
+        assert_eq!(bytes[4], VERSION);
+        assert_eq!(bytes.len(), if embed { 102 } else { 70 });
+        let loaded = Keypair::from_file_bytes(&bytes).unwrap();
+        assert_eq!(loaded.to_hex(), keypair.to_hex());
+    }
+}
+
+#[test]
+fn save_and_load_file() {
+    let dir = std::env::temp_dir().join(format!("sf-keyfile-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("signing.key");
+
+    let keypair = Keypair::generate();
+    keypair.save_to_file(&path).unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+    let loaded = Keypair::load_from_file(&path).unwrap();
+    assert_eq!(loaded.verifying_key_hex(), keypair.verifying_key_hex());
+
+    // A bare seed is not a key file
+    std::fs::write(&path, SEED).unwrap();
+    let err = Keypair::load_from_file(&path).err().unwrap();
+    assert_eq!(
+        err.downcast_ref::<KeyFileError>(),
+        Some(&KeyFileError::BadMagic)
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn any_flipped_byte_is_detected() {
+    let bytes = Keypair::from_bytes(&SEED).to_file_bytes(true);
+    for i in 0..bytes.len() {
+        let mut corrupted = bytes.clone();
+        corrupted[i] ^= 0x01;
+        assert!(
+            Keypair::from_file_bytes(&corrupted).is_err(),
+            "flipped byte {i} not detected"
+        );
+    }
+
+    let mut seed_flipped = bytes.clone();
+    seed_flipped[6] ^= 0x80;
+    assert_eq!(
+        Keypair::from_file_bytes(&seed_flipped).err(),
+        Some(KeyFileError::BadChecksum)
+    );
+}
+
+#[test]
+fn mismatched_public_key_and_malformed_headers_are_rejected() {
+    // A checksum recomputed over a foreign public key still fails the cross-check
+    let mut bytes = Keypair::from_bytes(&SEED).to_file_bytes(true);
+    bytes.truncate(70);
+    bytes[38..70].copy_from_slice(&Keypair::from_bytes(&[7; 32]).verifying_key.to_bytes());
+    let checksum = blake3::hash(&bytes);
+    bytes.extend_from_slice(checksum.as_bytes());
+    assert_eq!(
+        Keypair::from_file_bytes(&bytes).err(),
+        Some(KeyFileError::PublicKeyMismatch)
+    );
+
+    let valid = Keypair::from_bytes(&SEED).to_file_bytes(false);
+    let mut version = valid.clone();
+    version[4] = 2;
+    assert_eq!(
+        Keypair::from_file_bytes(&version).err(),
+        Some(KeyFileError::UnsupportedVersion { version: 2 })
+    );
+    let mut flags = valid.clone();
+    flags[5] = 0x80;
+    assert_eq!(
+        Keypair::from_file_bytes(&flags).err(),
+        Some(KeyFileError::UnknownFlags { flags: 0x80 })
+    );
+    assert_eq!(
+        Keypair::from_file_bytes(&valid[..69]).err(),
+        Some(KeyFileError::InvalidLength {
+            len: 69,
+            expected: 70
+        })
+    );
+}
+
+/// Key file buffer borrowed from the test, so it can be inspected after loading
+struct Borrowed<'a>(&'a mut [u8]);
+
+impl AsRef<[u8]> for Borrowed<'_> {
+    fn as_ref(&self) -> &[u8] {
+        self.0
+    }
+}
+
+impl Zeroize for Borrowed<'_> {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+#[test]
+fn file_buffer_is_wiped_after_loading() {
+    let keypair = Keypair::from_bytes(&SEED);
+    let mut buffer = keypair.to_file_bytes(true);
+    let loaded = Keypair::from_file_buffer(Borrowed(&mut buffer)).unwrap();
+    assert_eq!(loaded.to_hex(), keypair.to_hex());
+    assert_eq!(buffer.len(), 102);
+    assert!(buffer.iter().all(|&byte| byte == 0));
+
+    // Rejected files are wiped too
+    let mut corrupt = keypair.to_file_bytes(false);
+    corrupt[10] ^= 1;
+    assert_eq!(
+        Keypair::from_file_buffer(Borrowed(&mut corrupt)).err(),
+        Some(KeyFileError::BadChecksum)
+    );
+    assert!(corrupt.iter().all(|&byte| byte == 0));
+}
@@ -0,0 +1,140 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signed_envelope_at, signing_key, MockNode};
+use securefabric_sdk::keyring::{fingerprint, KeyHistory, Keyring, Validity};
+use securefabric_sdk::{Client, Error};
+use std::time::{Duration, UNIX_EPOCH};
+
+#[tokio::test]
+async fn keyring_verifies_known_senders_and_rejects_unknown() {
+    let alice = signing_key(1);
+    let bob = signing_key(2);
+    let mallory = signing_key(3);
+
+    let keyring = Keyring::new();
+    let alice_fp = keyring.insert(alice.verifying_key());
+    keyring.insert(bob.verifying_key());
+    assert_eq!(alice_fp, fingerprint(alice.verifying_key().as_bytes()));
+
+    let endpoint = common::spawn(MockNode::default()).await;
+    let client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_keyring(keyring.clone());
+
+    assert!(client
+        .verify(&signed_envelope(&alice, "chat", 1, b"hi"))
+        .unwrap());
+    assert!(client
+        .verify(&signed_envelope(&bob, "chat", 1, b"hey"))
+        .unwrap());
+
+    let mut forged = signed_envelope(&alice, "chat", 2, b"hi");
+    forged.payload = b"bye".to_vec();
+    assert!(!client.verify(&forged).unwrap());
+
+    let unknown = signed_envelope(&mallory, "chat", 1, b"let me in");
+    let err = client.verify(&unknown).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<Error>(),
+        Some(&Error::UnknownSender {
+            fingerprint: fingerprint(mallory.verifying_key().as_bytes())
+        })
+    );
+
+    // Runtime changes through another handle apply to the client's keyring
+    assert!(keyring.remove(&alice_fp).is_some());
+    assert!(client
+        .verify(&signed_envelope(&alice, "chat", 3, b"hi"))
+        .is_err());
+    keyring.reload([mallory.verifying_key()]);
+    assert_eq!(keyring.len(), 1);
+    assert!(client.verify(&unknown).unwrap());
+    assert!(client
+        .verify(&signed_envelope(&bob, "chat", 2, b"hey"))
+        .is_err());
+}
+
+#[test]
+fn key_history_verifies_old_messages_under_the_key_valid_then() {
+    let old = signing_key(1);
+    let new = signing_key(2);
+    let issued = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let rotated = issued + Duration::from_secs(3600);
+    let ms = |at: std::time::SystemTime| at.duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+
+    let history = KeyHistory::new();
+    let old_fp = history.insert(old.verifying_key(), issued, None);
+    let new_fp = history.rotate(new.verifying_key(), rotated);
+    assert_eq!(
+        history.get(&old_fp).unwrap().1,
+        Validity {
+            from: issued,
+            until: Some(rotated)
+        }
+    );
+    assert!(history.key_at(&old_fp, rotated).is_none());
+    assert!(history.key_at(&new_fp, rotated).is_some());
+
+    // Signed under the old key while it was current
+    let before_rotation = ms(rotated) - 1;
+    let envelope = signed_envelope_at(&old, "audit", 1, before_rotation, b"old but valid");
+    assert!(history.verify(&envelope).unwrap());
+    let envelope = signed_envelope_at(&new, "audit", 2, ms(rotated), b"current");
+    assert!(history.verify(&envelope).unwrap());
+
+    // The old key after its retirement, and the new key before its issue
+    for (key, fp, at) in [
+        (&old, &old_fp, ms(rotated)),
+        (&new, &new_fp, before_rotation),
+        (&old, &old_fp, ms(issued) - 1),
+    ] {
+        let envelope = signed_envelope_at(key, "audit", 3, at, b"out of window");
+        let err = history.verify(&envelope).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Error>(),
+            Some(&Error::KeyNotValid {
+                fingerprint: fp.clone(),
+                timestamp_ms: at
+            })
+        );
+    }
+}
+
+#[test]
+fn key_history_uses_the_signed_timestamp() {
+    let old = signing_key(1);
+    let issued = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let retired = issued + Duration::from_secs(3600);
+    let retired_ms = retired.duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+
+    let history = KeyHistory::new();
+    history.insert(old.verifying_key(), issued, Some(retired));
+
+    // Backdating the unsigned field does not revive a retired key
+    let mut backdated = signed_envelope_at(&old, "audit", 1, retired_ms + 1, b"late");
+    backdated.timestamp_ms = retired_ms - 1;
+    assert!(history.verify(&backdated).is_err());
+
+    // Rewriting the signed one breaks the signature
+    let mut forged = signed_envelope_at(&old, "audit", 1, retired_ms + 1, b"late");
+    forged.aad = String::from_utf8(forged.aad)
+        .unwrap()
+        .replace(&(retired_ms + 1).to_string(), &(retired_ms - 1).to_string())
+        .into_bytes();
+    assert!(!history.verify(&forged).unwrap());
+
+    // No signed timestamp at all predates every window here
+    assert!(history
+        .verify(&signed_envelope(&old, "audit", 2, b"undated"))
+        .is_err());
+    let err = history
+        .verify(&signed_envelope(&signing_key(3), "audit", 1, b"stranger"))
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::UnknownSender { .. })
+    ));
+}
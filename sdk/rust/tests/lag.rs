@@ -0,0 +1,81 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use securefabric_sdk::subscription::end_to_end_latency;
+use securefabric_sdk::Client;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio_stream::StreamExt;
+
+#[test]
+fn latency_computed_from_injected_timestamp() {
+    let mut envelope = signed_envelope(&signing_key(1), "metrics", 1, b"x");
+    envelope.timestamp_ms = 1_700_000_000_000;
+    let received_at = UNIX_EPOCH + Duration::from_millis(1_700_000_000_250);
+
+    assert_eq!(
+        end_to_end_latency(&envelope, received_at),
+        Some(Duration::from_millis(250))
+    );
+
+    // Sender clock ahead of ours clamps to zero
+    let early = UNIX_EPOCH + Duration::from_millis(1_699_999_999_000);
+    assert_eq!(end_to_end_latency(&envelope, early), Some(Duration::ZERO));
+
+    envelope.timestamp_ms = 0;
+    assert_eq!(end_to_end_latency(&envelope, received_at), None);
+}
+
+#[tokio::test]
+async fn lag_and_stats_reflect_consumption() {
+    let key = signing_key(1);
+    let sent_at = SystemTime::now() - Duration::from_millis(500);
+    let sent_ms = sent_at.duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+    let feed = (1..=5)
+        .map(|seq| {
+            let mut envelope = signed_envelope(&key, "metrics", seq, b"x");
+            envelope.timestamp_ms = sent_ms;
+            envelope
+        })
+        .collect();
+    let endpoint = common::spawn(MockNode::with_feed(feed)).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let mut stream = client.subscribe(b"metrics").await.unwrap();
+    stream.next().await.unwrap().unwrap();
+    stream.next().await.unwrap().unwrap();
+
+    let stats = client.subscription_stats(b"metrics").unwrap();
+    assert_eq!(stats.received, 2);
+    assert_eq!(stats.last_seq[key.verifying_key().as_bytes().as_slice()], 2);
+    let latency = stats.last_latency.unwrap();
+    assert!(latency >= Duration::from_millis(500), "latency {latency:?}");
+    assert!(latency < Duration::from_secs(10), "latency {latency:?}");
+
+    assert_eq!(
+        client
+            .lag(b"metrics", &key.verifying_key().into())
+            .await
+            .unwrap(),
+        3
+    );
+    assert!(client.subscription_stats(b"other").is_none());
+}
+
+#[tokio::test]
+async fn lag_tracks_secp256k1_senders() {
+    let key = k256::ecdsa::SigningKey::from_slice(&[3; 32]).unwrap();
+    let endpoint = common::spawn(MockNode::default()).await;
+    let mut sender = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(key.clone());
+    for _ in 0..3 {
+        sender.send("metrics", b"x").await.unwrap();
+    }
+
+    let mut client = Client::new(&endpoint).await.unwrap();
+    let public = (*key.verifying_key()).into();
+    assert_eq!(client.lag(b"metrics", &public).await.unwrap(), 3);
+}
@@ -0,0 +1,112 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use futures::StreamExt;
+use securefabric_sdk::metrics::{self, MetricsRecorder, SizeHistogram, SIZE_BUCKETS};
+use securefabric_sdk::Client;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct TestRecorder {
+    counters: Mutex<HashMap<&'static str, u64>>,
+    histograms: Mutex<HashMap<&'static str, Vec<f64>>>,
+}
+
+impl MetricsRecorder for TestRecorder {
+    fn increment_counter(&self, name: &'static str, value: u64) {
+        *self.counters.lock().unwrap().entry(name).or_default() += value;
+    }
+
+    fn record_histogram(&self, name: &'static str, value: f64) {
+        self.histograms
+            .lock()
+            .unwrap()
+            .entry(name)
+            .or_default()
+            .push(value);
+    }
+}
+
+// Envelope overhead is a few hundred bytes, so these land in distinct buckets
+const PAYLOAD_SIZES: [usize; 5] = [500, 2_000, 2_000, 50_000, 2_000_000];
+const EXPECTED_COUNTS: [u64; SIZE_BUCKETS.len() + 1] = [0, 0, 1, 2, 0, 1, 0, 0, 1];
+
+#[test]
+fn bucket_bounds_are_inclusive() {
+    assert_eq!(SizeHistogram::bucket_for(0), 0);
+    assert_eq!(SizeHistogram::bucket_for(64), 0);
+    assert_eq!(SizeHistogram::bucket_for(65), 1);
+    assert_eq!(
+        SizeHistogram::bucket_for(1024 * 1024),
+        SIZE_BUCKETS.len() - 1
+    );
+    assert_eq!(
+        SizeHistogram::bucket_for(1024 * 1024 + 1),
+        SIZE_BUCKETS.len()
+    );
+}
+
+#[tokio::test]
+async fn size_histograms_count_sent_and_delivered_envelopes() {
+    let key = signing_key(1);
+    let feed = PAYLOAD_SIZES
+        .iter()
+        .enumerate()
+        .map(|(i, &size)| signed_envelope(&key, "sizes", i as u64 + 1, &vec![7u8; size]))
+        .collect();
+    let node = MockNode::with_feed(feed);
+    let endpoint = common::spawn(node.clone()).await;
+    let recorder = Arc::new(TestRecorder::default());
+    let mut client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(2))
+        .with_metrics(recorder.clone())
+        .with_size_histograms(true);
+
+    for size in PAYLOAD_SIZES {
+        client.send("sizes", &vec![7u8; size]).await.unwrap();
+    }
+    let delivered = client.subscribe(b"sizes").await.unwrap().count().await;
+    assert_eq!(delivered, PAYLOAD_SIZES.len());
+
+    let sent = client.sent_envelope_sizes().unwrap();
+    assert_eq!(sent.counts(), EXPECTED_COUNTS);
+    let received = client.subscription_stats(b"sizes").unwrap().envelope_sizes;
+    assert_eq!(received.unwrap().counts(), EXPECTED_COUNTS);
+
+    let counters = recorder.counters.lock().unwrap();
+    assert_eq!(counters[metrics::SENT_TOTAL], 5);
+    assert_eq!(counters[metrics::RECEIVED_TOTAL], 5);
+    let histograms = recorder.histograms.lock().unwrap();
+    assert_eq!(histograms[metrics::SENT_ENVELOPE_BYTES].len(), 5);
+    assert_eq!(histograms[metrics::RECEIVED_ENVELOPE_BYTES].len(), 5);
+}
+
+#[tokio::test]
+async fn size_histograms_off_by_default() {
+    let key = signing_key(1);
+    let node = MockNode::with_feed(vec![signed_envelope(&key, "sizes", 1, b"x")]);
+    let endpoint = common::spawn(node).await;
+    let recorder = Arc::new(TestRecorder::default());
+    let mut client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(2))
+        .with_metrics(recorder.clone());
+
+    client.send("sizes", b"x").await.unwrap();
+    client.subscribe(b"sizes").await.unwrap().count().await;
+
+    assert!(client.sent_envelope_sizes().is_none());
+    assert!(client
+        .subscription_stats(b"sizes")
+        .unwrap()
+        .envelope_sizes
+        .is_none());
+    assert!(recorder.histograms.lock().unwrap().is_empty());
+    assert_eq!(recorder.counters.lock().unwrap()[metrics::SENT_TOTAL], 1);
+}
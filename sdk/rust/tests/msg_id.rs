@@ -0,0 +1,97 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode};
+use securefabric_sdk::fabric::InMemoryFabric;
+use securefabric_sdk::msg_id::{self, MsgIdHasher, Sha256, FIRST_CUSTOM_ID};
+use securefabric_sdk::Client;
+
+/// Truncated BLAKE3 under a private identifier
+struct Short;
+
+impl MsgIdHasher for Short {
+    fn id(&self) -> u32 {
+        FIRST_CUSTOM_ID
+    }
+
+    fn hash(&self, preimage: &[u8]) -> Vec<u8> {
+        blake3::hash(preimage).as_bytes()[..8].to_vec()
+    }
+}
+
+#[tokio::test]
+async fn sha256_msg_ids_recorded_and_verified() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut sender = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_msg_id_hasher(Sha256);
+
+    let msg_id = sender.send("orders", b"x").await.unwrap();
+    let envelope = node.sent().remove(0);
+    assert_eq!(envelope.msg_id_hash, msg_id::SHA256);
+    assert_eq!(envelope.msg_id, msg_id);
+    let preimage = msg_id::preimage(&envelope.pubkey, envelope.seq, &envelope.nonce);
+    assert_eq!(msg_id, hex::encode(Sha256.hash(&preimage)));
+
+    // A verifier on the default hash follows the recorded one
+    let verifier = Client::new(&endpoint).await.unwrap();
+    assert!(verifier.verify_msg_id(&envelope));
+    assert!(verifier.validate(&envelope).is_valid());
+}
+
+#[tokio::test]
+async fn mismatched_algorithm_rejected() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut sender = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_msg_id_hasher(Sha256);
+    sender.send("orders", b"x").await.unwrap();
+    let envelope = node.sent().remove(0);
+    let verifier = Client::new(&endpoint).await.unwrap();
+
+    // A SHA-256 msg_id labelled as BLAKE3
+    let mut relabelled = envelope.clone();
+    relabelled.msg_id_hash = msg_id::BLAKE3;
+    assert!(!verifier.verify_msg_id(&relabelled));
+    assert!(!verifier.validate(&relabelled).msg_id);
+
+    let mut unknown = envelope;
+    unknown.msg_id_hash = 7;
+    assert!(!verifier.verify_msg_id(&unknown));
+}
+
+#[tokio::test]
+async fn custom_hasher_needs_a_matching_verifier() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut sender = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_msg_id_hasher(Short);
+    let msg_id = sender.send("orders", b"x").await.unwrap();
+    assert_eq!(msg_id.len(), 16);
+    let envelope = node.sent().remove(0);
+    assert_eq!(envelope.msg_id_hash, FIRST_CUSTOM_ID);
+
+    assert!(sender.verify_msg_id(&envelope));
+    let verifier = Client::new(&endpoint).await.unwrap();
+    assert!(!verifier.verify_msg_id(&envelope));
+
+    // Nodes only accept msg_ids from built-in hashes
+    let fabric = InMemoryFabric::new();
+    let mut publisher = fabric
+        .client()
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(2))
+        .with_msg_id_hasher(Short);
+    assert!(publisher.send("orders", b"x").await.is_err());
+}
@@ -0,0 +1,61 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Native trust store loading. Kept in its own test binary because it points
+//! `SSL_CERT_FILE` at a throwaway CA for the whole process.
+
+mod common;
+
+use common::{signing_key, MockNode, TestPki};
+use securefabric_sdk::{Client, TlsConfig};
+
+#[tokio::test]
+async fn native_roots_validate_node_and_compose_with_client_identity() {
+    let pki = TestPki::generate();
+    let bundle = std::env::temp_dir().join(format!("sf-native-roots-{}.pem", std::process::id()));
+    std::fs::write(&bundle, &pki.ca_pem).unwrap();
+    std::env::set_var("SSL_CERT_FILE", &bundle);
+    std::env::remove_var("SSL_CERT_DIR");
+
+    let tls = TlsConfig::new().with_native_roots().unwrap();
+    assert_eq!(tls.native_root_count(), 1);
+
+    // The node requires a client certificate, so this also exercises mTLS
+    let node = MockNode::default();
+    let endpoint = common::spawn_mtls(node.clone(), &pki).await;
+    let tls = tls
+        .with_identity(&pki.client_cert_pem, &pki.client_key_pem)
+        .with_domain("localhost");
+    let mut client = Client::connect_tls(&endpoint, tls)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+    client.send("native", b"hello").await.unwrap();
+    assert_eq!(node.sent().len(), 1);
+
+    // Without the identity the node refuses the connection
+    let anonymous = TlsConfig::new()
+        .with_native_roots()
+        .unwrap()
+        .with_domain("localhost");
+    let result = match Client::connect_tls(&endpoint, anonymous).await {
+        Ok(client) => {
+            client
+                .with_signing_key(signing_key(1))
+                .send("native", b"anonymous")
+                .await
+        }
+        Err(error) => Err(error),
+    };
+    assert!(result.is_err());
+
+    // `with_tls` without a CA PEM falls back to the same store
+    let endpoint = common::spawn_tls(node.clone(), &pki).await;
+    let mut client = Client::with_tls(&endpoint, None, "localhost")
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+    client.send("native", b"server-auth").await.unwrap();
+    assert_eq!(node.sent().len(), 2);
+
+    std::fs::remove_file(&bundle).unwrap();
+}
@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use securefabric_sdk::crypto::VerifyMode;
+use securefabric_sdk::negative_cache::{NegativeCache, NegativeCacheStats};
+use securefabric_sdk::Client;
+use std::time::Duration;
+
+async fn client(cache: &NegativeCache) -> Client {
+    Client::new(common::spawn(MockNode::default()).await)
+        .await
+        .unwrap()
+        .with_negative_cache(cache.clone())
+}
+
+#[tokio::test]
+async fn repeated_bad_envelope_skips_verification() {
+    let cache = NegativeCache::new(16, Duration::from_secs(60));
+    let client = client(&cache).await;
+    let mut bad = signed_envelope(&signing_key(1), "audit", 1, b"entry");
+    bad.payload = b"forged".to_vec();
+
+    assert!(!client.verify(&bad).unwrap());
+    assert!(!client.verify(&bad).unwrap());
+    assert!(!client.verify(&bad).unwrap());
+    assert_eq!(
+        cache.stats(),
+        NegativeCacheStats {
+            hits: 2,
+            misses: 1,
+            inserts: 1,
+            len: 1,
+        }
+    );
+
+    // Valid envelopes are verified every time and never cached
+    let good = signed_envelope(&signing_key(1), "audit", 2, b"entry");
+    assert!(client.verify(&good).unwrap());
+    assert!(client.verify(&good).unwrap());
+    let stats = cache.stats();
+    assert_eq!((stats.misses, stats.inserts, stats.len), (3, 1, 1));
+}
+
+#[tokio::test]
+async fn forgery_sharing_msg_id_and_sig_cannot_poison_genuine() {
+    let cache = NegativeCache::new(16, Duration::from_secs(60));
+    let client = client(&cache).await;
+    let genuine = signed_envelope(&signing_key(1), "audit", 1, b"entry");
+    let mut forged = genuine.clone();
+    forged.payload = b"forged".to_vec();
+    assert_eq!(
+        (&forged.msg_id, &forged.sig),
+        (&genuine.msg_id, &genuine.sig)
+    );
+
+    assert!(!client.verify(&forged).unwrap());
+    assert!(client.verify(&genuine).unwrap());
+    assert_eq!(cache.stats().hits, 0);
+
+    // The verify mode is part of the key too
+    let permissive = client.with_verify_mode(VerifyMode::Permissive);
+    assert!(!permissive.verify(&forged).unwrap());
+    assert_eq!(cache.stats().hits, 0);
+}
+
+#[tokio::test]
+async fn entries_expire_and_are_evicted() {
+    let cache = NegativeCache::new(2, Duration::from_millis(50));
+    let client = client(&cache).await;
+    let bad: Vec<_> = (1..=3)
+        .map(|seq| {
+            let mut envelope = signed_envelope(&signing_key(1), "audit", seq, b"entry");
+            envelope.payload = b"forged".to_vec();
+            envelope
+        })
+        .collect();
+
+    for envelope in &bad {
+        assert!(!client.verify(envelope).unwrap());
+    }
+    // The oldest failure was evicted to stay within capacity
+    assert_eq!(cache.stats().len, 2);
+    assert!(!client.verify(&bad[0]).unwrap());
+    assert_eq!(cache.stats().hits, 0);
+    assert!(!client.verify(&bad[2]).unwrap());
+    assert_eq!(cache.stats().hits, 1);
+
+    tokio::time::sleep(Duration::from_millis(80)).await;
+    let before = cache.stats();
+    assert!(!client.verify(&bad[2]).unwrap());
+    let after = cache.stats();
+    assert_eq!(after.hits, before.hits);
+    assert_eq!(after.misses, before.misses + 1);
+}
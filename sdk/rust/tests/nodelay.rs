@@ -0,0 +1,42 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode, MockProxy};
+use securefabric_sdk::Client;
+
+#[test]
+fn nodelay_is_enabled_by_default() {
+    let builder = Client::builder("http://127.0.0.1:1");
+    assert!(format!("{builder:?}").contains("nodelay: true"));
+
+    let builder = builder.with_nodelay(false);
+    assert!(format!("{builder:?}").contains("nodelay: false"));
+}
+
+#[tokio::test]
+async fn connects_with_either_setting_directly_and_through_proxy() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let proxy = MockProxy::default();
+    let proxy_uri = common::spawn_proxy(proxy.clone()).await;
+
+    for nodelay in [true, false] {
+        for proxied in [false, true] {
+            let mut builder = Client::builder(&endpoint)
+                .env_proxy(false)
+                .with_nodelay(nodelay);
+            if proxied {
+                builder = builder.with_proxy(&proxy_uri, None);
+            }
+            let mut client = builder
+                .build()
+                .await
+                .unwrap()
+                .with_signing_key(signing_key(1));
+            let msg_id = client.send("latency", b"ping").await.unwrap();
+            assert_eq!(node.sent().last().unwrap().msg_id, msg_id);
+        }
+    }
+    assert_eq!(proxy.connects().len(), 2);
+}
@@ -0,0 +1,92 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{encrypted_envelope, signing_key, MockNode};
+use futures::StreamExt;
+use securefabric_sdk::nonce::NonceReuseAction;
+use securefabric_sdk::subscription::DecryptError;
+use securefabric_sdk::{Client, Error};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+const TOPIC_KEY: [u8; 32] = [9u8; 32];
+
+#[tokio::test]
+async fn second_envelope_with_the_same_nonce_is_rejected() {
+    let key = signing_key(1);
+    let nonce = [7u8; 24];
+    let node = MockNode::with_feed(vec![
+        encrypted_envelope(&key, "secrets", 1, nonce, &TOPIC_KEY, 1, b"one"),
+        encrypted_envelope(&key, "secrets", 2, nonce, &TOPIC_KEY, 1, b"two"),
+        encrypted_envelope(&key, "secrets", 3, [8u8; 24], &TOPIC_KEY, 1, b"three"),
+    ]);
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_encryption(TOPIC_KEY, 1)
+        .unwrap()
+        .with_nonce_tracking(16, NonceReuseAction::Reject);
+
+    let items: Vec<_> = client
+        .subscribe_decrypted(b"secrets")
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    assert_eq!(items.len(), 3);
+    assert_eq!(items[0].as_ref().unwrap().payload, b"one");
+    assert!(matches!(&items[1], Err(DecryptError::NonceReused { .. })));
+    assert_eq!(items[2].as_ref().unwrap().payload, b"three");
+}
+
+#[tokio::test]
+async fn warn_flags_repeats_and_still_decrypts() {
+    let key = signing_key(1);
+    let first = encrypted_envelope(&key, "secrets", 1, [7u8; 24], &TOPIC_KEY, 1, b"one");
+    let second = encrypted_envelope(&key, "secrets", 2, [7u8; 24], &TOPIC_KEY, 1, b"two");
+    let endpoint = common::spawn(MockNode::default()).await;
+    let flagged = Arc::new(AtomicUsize::new(0));
+    let counter = flagged.clone();
+    let client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_encryption(TOPIC_KEY, 1)
+        .unwrap()
+        .with_nonce_tracking(16, NonceReuseAction::Warn)
+        .with_nonce_reuse_warning(move |envelope| {
+            assert_eq!(envelope.seq, 2);
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+    assert_eq!(client.decrypt(&first).unwrap(), b"one");
+    assert_eq!(client.decrypt(&second).unwrap(), b"two");
+    assert_eq!(flagged.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn window_forgets_old_nonces() {
+    let key = signing_key(1);
+    let envelope =
+        |seq, nonce| encrypted_envelope(&key, "secrets", seq, nonce, &TOPIC_KEY, 1, b"x");
+    let endpoint = common::spawn(MockNode::default()).await;
+    let client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_encryption(TOPIC_KEY, 1)
+        .unwrap()
+        .with_nonce_tracking(2, NonceReuseAction::Reject);
+
+    client.decrypt(&envelope(1, [1u8; 24])).unwrap();
+    let error = client.decrypt(&envelope(2, [1u8; 24])).unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<Error>(),
+        Some(Error::NonceReused { key_version: 1, .. })
+    ));
+
+    client.decrypt(&envelope(3, [2u8; 24])).unwrap();
+    client.decrypt(&envelope(4, [3u8; 24])).unwrap();
+    // [1; 24] has left the two-nonce window
+    client.decrypt(&envelope(5, [1u8; 24])).unwrap();
+}
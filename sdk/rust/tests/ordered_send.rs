@@ -0,0 +1,80 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode};
+use securefabric_sdk::Client;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn ordered_sends_from_clones_take_consecutive_seqs() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_ordered_send(true);
+
+    let tasks: Vec<_> = (0..64)
+        .map(|i| {
+            let mut client = client.clone();
+            tokio::spawn(async move {
+                client
+                    .send("orders", format!("msg-{i}").as_bytes())
+                    .await
+                    .unwrap()
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    // Several sends are in flight at once, so the node may finish them in any order
+    let mut seqs: Vec<u64> = node.sent().iter().map(|e| e.seq).collect();
+    seqs.sort_unstable();
+    assert_eq!(seqs, (1..=64).collect::<Vec<_>>());
+    for envelope in node.sent() {
+        assert!(client.verify(&envelope).unwrap());
+        assert!(client.verify_msg_id(&envelope));
+    }
+}
+
+#[tokio::test]
+async fn ordered_sends_are_pipelined() {
+    let node = MockNode::default();
+    let gate = node.hold_sends();
+    let endpoint = common::spawn(node.clone()).await;
+    let client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_ordered_send(true);
+
+    let tasks: Vec<_> = (0..3)
+        .map(|i| {
+            let mut client = client.clone();
+            tokio::spawn(async move { client.send("orders", &[i]).await.unwrap() })
+        })
+        .collect();
+
+    // Every send reaches the node while the first is still unanswered
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while node.state.send_attempts.load(Ordering::SeqCst) < 3 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .expect("sends were not in flight together");
+    assert!(node.sent().is_empty());
+
+    gate.add_permits(3);
+    for task in tasks {
+        task.await.unwrap();
+    }
+    let mut seqs: Vec<u64> = node.sent().iter().map(|e| e.seq).collect();
+    seqs.sort_unstable();
+    assert_eq!(seqs, [1, 2, 3]);
+}
@@ -0,0 +1,114 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use futures::StreamExt;
+use prost::Message;
+use securefabric_sdk::codec::CodecError;
+use securefabric_sdk::digest::{
+    attach_payload_digest, payload_digest, payload_digest_matches, DigestError,
+};
+use securefabric_sdk::{Client, Envelope};
+
+#[tokio::test]
+async fn publisher_attaches_digest_of_carried_payload() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut client = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_payload_digest(true);
+    client.send("blobs", b"large payload").await.unwrap();
+
+    let mut encrypted = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_encryption([7u8; 32], 1)
+        .unwrap()
+        .with_payload_digest(true);
+    encrypted.send("blobs", b"large payload").await.unwrap();
+
+    let sent = node.sent();
+    assert_eq!(sent[0].payload_digest, payload_digest(b"large payload"));
+    // Encrypted envelopes digest the ciphertext they carry
+    assert_eq!(sent[1].payload_digest, payload_digest(&sent[1].payload));
+    for envelope in &sent {
+        assert_eq!(payload_digest_matches(envelope), Some(true));
+    }
+    // The digest is not signed, so attaching it leaves the signature valid
+    assert!(client.verify(&sent[0]).unwrap());
+
+    // Digests are off by default
+    let mut plain = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+    plain.send("blobs", b"large payload").await.unwrap();
+    assert_eq!(payload_digest_matches(&node.sent()[2]), None);
+}
+
+fn feed() -> Vec<Envelope> {
+    let key = signing_key(1);
+    let mut good = signed_envelope(&key, "blobs", 1, b"intact");
+    attach_payload_digest(&mut good);
+    let mut corrupted = signed_envelope(&key, "blobs", 2, b"intact");
+    attach_payload_digest(&mut corrupted);
+    corrupted.payload[0] ^= 1;
+    // Unsigned envelopes are checked too
+    let mut unsigned = Envelope {
+        msg_id: "unsigned".to_string(),
+        payload: b"no signature".to_vec(),
+        ..Default::default()
+    };
+    attach_payload_digest(&mut unsigned);
+    let undigested = signed_envelope(&key, "blobs", 3, b"no digest");
+    vec![good, corrupted, unsigned, undigested]
+}
+
+async fn checked(require: bool) -> Vec<Result<Envelope, DigestError>> {
+    let node = MockNode::with_feed(feed());
+    let mut client = Client::new(common::spawn(node).await).await.unwrap();
+    client
+        .subscribe_digest_checked(b"blobs", require)
+        .await
+        .unwrap()
+        .collect()
+        .await
+}
+
+#[tokio::test]
+async fn subscriber_rejects_mismatched_digests() {
+    let feed = feed();
+    let items = checked(false).await;
+    assert_eq!(items.len(), 4);
+    assert_eq!(items[0].as_ref().unwrap(), &feed[0]);
+    assert!(matches!(
+        &items[1],
+        Err(DigestError::Mismatch { msg_id }) if *msg_id == feed[1].msg_id
+    ));
+    assert_eq!(items[2].as_ref().unwrap(), &feed[2]);
+    assert_eq!(items[3].as_ref().unwrap(), &feed[3]);
+
+    let items = checked(true).await;
+    assert!(items[0].is_ok() && items[2].is_ok());
+    assert!(matches!(
+        &items[3],
+        Err(DigestError::Missing { msg_id }) if *msg_id == feed[3].msg_id
+    ));
+}
+
+#[test]
+fn codec_rejects_malformed_digest() {
+    let mut envelope = feed().remove(0);
+    envelope.payload_digest.truncate(16);
+    assert_eq!(
+        Envelope::try_from_bytes(&envelope.encode_to_vec()),
+        Err(CodecError::InvalidLength {
+            field: "payload_digest",
+            len: 16
+        })
+    );
+}
@@ -0,0 +1,58 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::MockNode;
+use securefabric_sdk::{Client, Error};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+#[tokio::test]
+async fn ping_measures_plausible_round_trip() {
+    let node = MockNode::default();
+    *node.state.ping_delay.lock().unwrap() = Duration::from_millis(20);
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let rtt = client.ping().await.unwrap();
+    assert!(rtt >= Duration::from_millis(20), "rtt {rtt:?}");
+    assert!(rtt < Duration::from_secs(2), "rtt {rtt:?}");
+
+    let stats = client.ping_n(10).await.unwrap();
+    assert_eq!(stats.completed, 10);
+    assert_eq!(stats.timed_out, 0);
+    assert!(stats.min >= Duration::from_millis(20));
+    assert!(stats.min <= stats.avg && stats.avg <= stats.max);
+    assert!(stats.p99 <= stats.max);
+}
+
+#[tokio::test]
+async fn ping_timeout_is_distinct_from_errors() {
+    let node = MockNode::default();
+    *node.state.ping_delay.lock().unwrap() = Duration::from_millis(200);
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_ping_timeout(Duration::from_millis(20));
+
+    let err = client.ping().await.unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<Error>(),
+        Some(&Error::Timeout {
+            after: Duration::from_millis(20)
+        })
+    );
+    assert!(client.ping_n(2).await.is_err());
+
+    // A node that rejects the RPC fails with its status instead
+    let node = MockNode::default();
+    node.state.ping_unavailable.store(true, Ordering::SeqCst);
+    let mut client = Client::new(common::spawn(node).await).await.unwrap();
+    let err = client.ping().await.unwrap_err();
+    assert!(err.downcast_ref::<Error>().is_none());
+    assert_eq!(
+        err.downcast_ref::<tonic::Status>().map(|s| s.code()),
+        Some(tonic::Code::Unavailable)
+    );
+}
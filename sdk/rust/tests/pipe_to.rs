@@ -0,0 +1,77 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use securefabric_sdk::{Client, Error};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tonic::{Code, Status};
+
+#[tokio::test]
+async fn forwards_verified_envelopes_into_the_channel() {
+    let key = signing_key(1);
+    let mut tampered = signed_envelope(&key, "events", 3, b"three");
+    tampered.payload = b"forged".to_vec();
+    let node = MockNode::with_feed(vec![
+        signed_envelope(&key, "events", 1, b"one"),
+        signed_envelope(&key, "events", 2, b"two"),
+        tampered,
+        signed_envelope(&key, "events", 4, b"four"),
+    ]);
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let (tx, mut rx) = mpsc::channel(1);
+    let handle = client.pipe_to(b"events", tx).await.unwrap();
+
+    let mut payloads = Vec::new();
+    while let Some(envelope) = rx.recv().await {
+        payloads.push(envelope.payload);
+    }
+    assert_eq!(payloads, [&b"one"[..], b"two", b"four"]);
+    assert_eq!(handle.await.unwrap(), 3);
+}
+
+#[tokio::test]
+async fn stops_when_the_receiver_is_dropped() {
+    let node = MockNode::default();
+    let _live = node.live_feed();
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let (tx, rx) = mpsc::channel(8);
+    let handle = client.pipe_to(b"events", tx).await.unwrap();
+    assert!(!handle.is_finished());
+
+    // The subscription is idle; dropping the receiver alone ends the task
+    drop(rx);
+    let forwarded = tokio::time::timeout(Duration::from_secs(5), handle)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(forwarded, 0);
+}
+
+#[tokio::test]
+async fn subscription_errors_surface_through_the_handle() {
+    let key = signing_key(1);
+    let node = MockNode::with_feed(vec![signed_envelope(&key, "events", 1, b"one")]);
+    *node.state.feed_error.lock().unwrap() = Some(Status::permission_denied("topic revoked"));
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let (tx, mut rx) = mpsc::channel(8);
+    let handle = client.pipe_to(b"events", tx).await.unwrap();
+
+    assert_eq!(rx.recv().await.unwrap().payload, b"one");
+    let error = handle.await.unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<Error>(),
+        Some(&Error::Rpc {
+            code: Code::PermissionDenied,
+            message: "topic revoked".into()
+        })
+    );
+    assert!(rx.recv().await.is_none());
+}
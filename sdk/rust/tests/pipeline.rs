@@ -0,0 +1,106 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key};
+use ed25519_dalek::Signer;
+use securefabric_sdk::crypto::pipeline::{verify_dedup, RejectReason};
+use securefabric_sdk::keyring::{fingerprint, Keyring};
+use securefabric_sdk::msg_id;
+
+#[test]
+fn verify_dedup_streams_results_in_order_and_drops_duplicates() {
+    let alice = signing_key(1);
+    let mallory = signing_key(3);
+    let keyring = Keyring::new();
+    keyring.insert(alice.verifying_key());
+
+    let first = signed_envelope(&alice, "archive", 1, b"one");
+    let second = signed_envelope(&alice, "archive", 2, b"two");
+
+    let mut forged = signed_envelope(&alice, "archive", 3, b"three");
+    forged.payload = b"tampered".to_vec();
+    let mut bad_id = signed_envelope(&alice, "archive", 4, b"four");
+    bad_id.msg_id = "00".repeat(32);
+    let unknown = signed_envelope(&mallory, "archive", 1, b"let me in");
+
+    // A forgery reusing the genuine envelope's msg_id must not mark it as seen
+    let genuine = signed_envelope(&alice, "archive", 5, b"five");
+    let mut imitation = genuine.clone();
+    imitation.payload = b"fake".to_vec();
+
+    let input = vec![
+        first.clone(),
+        forged.clone(),
+        first.clone(),
+        bad_id.clone(),
+        second.clone(),
+        unknown.clone(),
+        imitation,
+        genuine.clone(),
+        second.clone(),
+    ];
+    let results: Vec<_> = verify_dedup(input.into_iter(), &keyring).collect();
+
+    assert_eq!(
+        results,
+        vec![
+            Ok(first),
+            Err(RejectReason::BadSignature {
+                msg_id: forged.msg_id
+            }),
+            Err(RejectReason::BadMsgId {
+                msg_id: bad_id.msg_id
+            }),
+            Ok(second),
+            Err(RejectReason::UnknownSender {
+                msg_id: unknown.msg_id,
+                fingerprint: fingerprint(mallory.verifying_key().as_bytes()),
+            }),
+            Err(RejectReason::BadSignature {
+                msg_id: genuine.msg_id.clone()
+            }),
+            Ok(genuine),
+        ]
+    );
+}
+
+#[test]
+fn verify_dedup_pulls_input_lazily() {
+    let alice = signing_key(1);
+    let keyring = Keyring::new();
+    keyring.insert(alice.verifying_key());
+
+    // An endless input still yields results one batch at a time
+    let mut seq = 0;
+    let endless = std::iter::from_fn(|| {
+        seq += 1;
+        Some(signed_envelope(&alice, "archive", seq, b"again"))
+    });
+    let accepted = verify_dedup(endless, &keyring)
+        .take(1000)
+        .filter(Result::is_ok)
+        .count();
+    assert_eq!(accepted, 1000);
+}
+
+#[test]
+fn resequenced_copies_count_as_duplicates() {
+    let alice = signing_key(1);
+    let keyring = Keyring::new();
+    keyring.insert(alice.verifying_key());
+
+    // A sender that leaves seq out of the signed AAD, archived twice under different seqs
+    let mut original = signed_envelope(&alice, "archive", 1, b"one");
+    original.aad = br#"{"key_version":0,"topic":"archive"}"#.to_vec();
+    original.sig = alice
+        .sign(&[original.aad.as_slice(), &original.payload].concat())
+        .to_bytes()
+        .to_vec();
+    let mut copy = original.clone();
+    copy.seq = 2;
+    copy.msg_id = msg_id::compute(&msg_id::Blake3, &copy);
+
+    let results: Vec<_> = verify_dedup([original.clone(), copy].into_iter(), &keyring).collect();
+    assert_eq!(results, vec![Ok(original)]);
+}
@@ -0,0 +1,87 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Run with `cargo test --features prometheus`.
+
+mod common;
+
+use common::{signing_key, MockNode};
+use securefabric_sdk::metrics::{self, PrometheusRecorder, SENT_ENVELOPE_BYTES, SENT_TOTAL};
+use securefabric_sdk::Client;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+async fn get(addr: SocketAddr, path: &str) -> String {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let request = format!("GET {path} HTTP/1.1\r\nHost: {addr}\r\n\r\n");
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+#[tokio::test]
+async fn scrape_shows_send_counter_after_send() {
+    let recorder = Arc::new(PrometheusRecorder::new());
+    let server = metrics::serve("127.0.0.1:0", recorder.clone())
+        .await
+        .unwrap();
+    let addr = server.local_addr();
+
+    let before = get(addr, "/metrics").await;
+    assert!(before.starts_with("HTTP/1.1 200 OK\r\n"), "{before}");
+    assert!(!before.contains(SENT_TOTAL));
+
+    let endpoint = common::spawn(MockNode::default()).await;
+    let mut client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_metrics(recorder)
+        .with_size_histograms(true);
+    client.send("metrics", b"one").await.unwrap();
+    client.send("metrics", b"two").await.unwrap();
+
+    let after = get(addr, "/metrics").await;
+    assert!(after.contains("Content-Type: text/plain; version=0.0.4"));
+    assert!(after.contains(&format!("# TYPE {SENT_TOTAL} counter\n")));
+    assert!(after.contains(&format!("\n{SENT_TOTAL} 2\n")), "{after}");
+    assert!(after.contains(&format!("{SENT_ENVELOPE_BYTES}_bucket{{le=\"+Inf\"}} 2\n")));
+    assert!(after.contains(&format!("{SENT_ENVELOPE_BYTES}_count 2\n")));
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn unknown_paths_and_shutdown() {
+    let server = metrics::serve("127.0.0.1:0", Arc::default()).await.unwrap();
+    let addr = server.local_addr();
+
+    assert!(get(addr, "/")
+        .await
+        .starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+    server.shutdown().await;
+    assert!(TcpStream::connect(addr).await.is_err());
+}
+
+#[test]
+fn histogram_buckets_are_cumulative() {
+    use securefabric_sdk::metrics::MetricsRecorder;
+
+    let recorder = PrometheusRecorder::new();
+    for size in [10.0, 64.0, 65.0, 5_000_000.0] {
+        recorder.record_histogram(SENT_ENVELOPE_BYTES, size);
+    }
+    let rendered = recorder.render();
+    for line in [
+        format!("{SENT_ENVELOPE_BYTES}_bucket{{le=\"64\"}} 2"),
+        format!("{SENT_ENVELOPE_BYTES}_bucket{{le=\"256\"}} 3"),
+        format!("{SENT_ENVELOPE_BYTES}_bucket{{le=\"1048576\"}} 3"),
+        format!("{SENT_ENVELOPE_BYTES}_bucket{{le=\"+Inf\"}} 4"),
+        format!("{SENT_ENVELOPE_BYTES}_sum 5000139"),
+    ] {
+        assert!(rendered.lines().any(|l| l == line), "{line}\n{rendered}");
+    }
+}
@@ -0,0 +1,87 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::MockNode;
+use securefabric_sdk::capabilities::ProtocolVersion;
+use securefabric_sdk::{Client, Error};
+
+#[tokio::test]
+async fn connect_fails_on_an_incompatible_major_version() {
+    let node = MockNode::default();
+    *node.state.protocol_version.lock().unwrap() = "2.0".to_string();
+    let endpoint = common::spawn(node).await;
+
+    let error = Client::builder(endpoint)
+        .with_protocol_version(1, 3)
+        .build()
+        .await
+        .map(|_| ())
+        .unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<Error>(),
+        Some(&Error::ProtocolMismatch {
+            pinned: ProtocolVersion::new(1, 3),
+            reported: ProtocolVersion::new(2, 0),
+        })
+    );
+    assert_eq!(
+        error.to_string(),
+        "node speaks protocol 2.0, incompatible with pinned protocol 1.3"
+    );
+}
+
+#[tokio::test]
+async fn connects_to_a_compatible_minor_version_and_sends_the_pin() {
+    let node = MockNode::default();
+    let state = node.state.clone();
+    *state.protocol_version.lock().unwrap() = "1.7".to_string();
+    let endpoint = common::spawn(node).await;
+
+    let mut client = Client::builder(endpoint)
+        .with_protocol_version(1, 3)
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(
+        client.capabilities().await.unwrap().protocol_version(),
+        Some(ProtocolVersion::new(1, 7))
+    );
+    assert_eq!(
+        *state.pinned_protocol_versions.lock().unwrap(),
+        [Some("1.3".to_string())]
+    );
+}
+
+#[tokio::test]
+async fn nodes_without_a_version_are_accepted() {
+    let endpoint = common::spawn(MockNode::default()).await;
+    let mut client = Client::builder(endpoint)
+        .with_protocol_version(1, 0)
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(
+        client.capabilities().await.unwrap().protocol_version(),
+        None
+    );
+}
+
+#[tokio::test]
+async fn lazy_clients_check_on_warmup() {
+    let node = MockNode::default();
+    *node.state.protocol_version.lock().unwrap() = "0.9".to_string();
+    let endpoint = common::spawn(node).await;
+
+    let mut client = Client::builder(endpoint)
+        .connect_lazy(true)
+        .with_protocol_version(1, 0)
+        .build()
+        .await
+        .unwrap();
+    let error = client.warmup().await.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<Error>(),
+        Some(Error::ProtocolMismatch { .. })
+    ));
+}
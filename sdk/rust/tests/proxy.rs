@@ -0,0 +1,103 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode, MockProxy, TestPki};
+use securefabric_sdk::{Client, ProxyAuth, TlsConfig};
+
+fn authority(endpoint: &str) -> &str {
+    endpoint.split_once("://").unwrap().1
+}
+
+#[tokio::test]
+async fn tunnels_through_proxy_with_basic_auth() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let proxy = MockProxy::requiring("Basic YWxpY2U6c2VjcmV0"); // alice:secret
+    let proxy_uri = common::spawn_proxy(proxy.clone()).await;
+
+    let mut client = Client::builder(&endpoint)
+        .with_proxy(&proxy_uri, Some(ProxyAuth::basic("alice", "secret")))
+        .build()
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+
+    let msg_id = client.send("proxied", b"hello").await.unwrap();
+    assert_eq!(node.sent()[0].msg_id, msg_id);
+    assert_eq!(proxy.connects(), vec![authority(&endpoint).to_string()]);
+}
+
+#[tokio::test]
+async fn proxy_rejecting_credentials_fails_connect() {
+    let endpoint = common::spawn(MockNode::default()).await;
+    let proxy = MockProxy::requiring("Basic YWxpY2U6c2VjcmV0");
+    let proxy_uri = common::spawn_proxy(proxy.clone()).await;
+
+    let Err(err) = Client::builder(&endpoint)
+        .with_proxy(&proxy_uri, Some(ProxyAuth::basic("alice", "wrong")))
+        .build()
+        .await
+    else {
+        panic!("connected with rejected proxy credentials");
+    };
+    assert!(format!("{err:#}").contains("407"), "{err:#}");
+    assert!(proxy.connects().is_empty());
+}
+
+#[tokio::test]
+async fn mtls_runs_end_to_end_inside_the_tunnel() {
+    let pki = TestPki::generate();
+    let node = MockNode::default();
+    let endpoint = common::spawn_mtls(node.clone(), &pki).await;
+    let proxy = MockProxy::default();
+    let proxy_uri = common::spawn_proxy(proxy.clone()).await;
+
+    let tls = TlsConfig::new()
+        .with_ca_pem(&pki.ca_pem)
+        .with_identity(&pki.client_cert_pem, &pki.client_key_pem)
+        .with_domain("localhost");
+    let mut client = Client::builder(&endpoint)
+        .tls(tls)
+        .with_proxy(&proxy_uri, None)
+        .build()
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+
+    let msg_id = client.send("proxied", b"secret").await.unwrap();
+    assert_eq!(node.sent()[0].msg_id, msg_id);
+    assert_eq!(proxy.connects(), vec![authority(&endpoint).to_string()]);
+}
+
+// The only test in this binary that relies on the environment, as it is
+// shared by every test in the process
+#[tokio::test]
+async fn honours_https_proxy_and_no_proxy_from_env() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let proxy = MockProxy::requiring("Basic Ym9iOnBAc3M="); // bob:p@ss
+    let proxy_uri = common::spawn_proxy(proxy.clone()).await;
+
+    // Credentials embedded in the variable are percent-decoded
+    let with_credentials = proxy_uri.replace("http://", "http://bob:p%40ss@");
+    std::env::set_var("HTTPS_PROXY", &with_credentials);
+    std::env::remove_var("NO_PROXY");
+    Client::new(&endpoint).await.unwrap();
+    assert_eq!(proxy.connects().len(), 1);
+
+    // Opting out ignores the variable
+    Client::builder(&endpoint)
+        .env_proxy(false)
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(proxy.connects().len(), 1);
+
+    std::env::set_var("NO_PROXY", "example.com, .127.0.0.1");
+    Client::new(&endpoint).await.unwrap();
+    assert_eq!(proxy.connects().len(), 1);
+
+    std::env::remove_var("HTTPS_PROXY");
+    std::env::remove_var("NO_PROXY");
+}
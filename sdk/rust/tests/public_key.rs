@@ -0,0 +1,71 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use base64::Engine;
+use ed25519_dalek::pkcs8::spki::der::pem::LineEnding;
+use ed25519_dalek::pkcs8::EncodePrivateKey;
+use securefabric_sdk::crypto::scheme::VerifyingKey;
+use securefabric_sdk::crypto::{Keypair, PublicKeyBundle};
+use securefabric_sdk::keyring::fingerprint;
+
+const SEED: [u8; 32] = [0x42; 32];
+
+#[test]
+fn exports_round_trip_through_importers() {
+    let secp = k256::ecdsa::SigningKey::from_slice(&[7u8; 32]).unwrap();
+    let bundles = [
+        Keypair::from_bytes(&SEED).public_only(),
+        PublicKeyBundle::from(VerifyingKey::from(*secp.verifying_key())),
+    ];
+    for bundle in bundles {
+        let key = bundle.verifying_key();
+        assert_eq!(VerifyingKey::from_hex(&bundle.to_hex()).unwrap(), key);
+        assert_eq!(VerifyingKey::from_base64(&bundle.to_base64()).unwrap(), key);
+        assert_eq!(VerifyingKey::from_pem(&bundle.to_pem()).unwrap(), key);
+        assert!(bundle.to_pem().starts_with("-----BEGIN PUBLIC KEY-----\n"));
+        assert_eq!(bundle.fingerprint(), fingerprint(&key.to_bytes()));
+    }
+
+    let keypair = Keypair::from_bytes(&SEED);
+    assert_eq!(keypair.public_only().to_hex(), keypair.verifying_key_hex());
+}
+
+#[test]
+fn public_bundle_contains_no_seed_bytes() {
+    let keypair = Keypair::from_bytes(&SEED);
+    let bundle = keypair.public_only();
+    let seed_hex = hex::encode(SEED);
+    let seed_base64 = base64::engine::general_purpose::STANDARD.encode(SEED);
+
+    let pem_body: String = bundle
+        .to_pem()
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+        .collect();
+    let der = base64::engine::general_purpose::STANDARD
+        .decode(pem_body)
+        .unwrap();
+    assert!(!der.windows(SEED.len()).any(|window| window == SEED));
+
+    for export in [
+        bundle.to_hex(),
+        bundle.to_base64(),
+        bundle.to_pem(),
+        format!("{bundle:?}"),
+    ] {
+        assert!(!export.contains(&seed_hex), "{export}");
+        assert!(!export.contains(&seed_base64), "{export}");
+    }
+}
+
+#[test]
+fn importers_reject_private_and_malformed_keys() {
+    let keypair = Keypair::from_bytes(&SEED);
+    let private_pem = keypair.signing_key.to_pkcs8_pem(LineEnding::LF).unwrap();
+    assert!(VerifyingKey::from_pem(&private_pem).is_err());
+
+    assert!(VerifyingKey::from_hex(&"ab".repeat(31)).is_err());
+    assert!(VerifyingKey::from_hex("not hex").is_err());
+    assert!(VerifyingKey::from_base64("*").is_err());
+    // 33 bytes that are not a secp256k1 point
+    assert!(VerifyingKey::from_hex(&"05".repeat(33)).is_err());
+}
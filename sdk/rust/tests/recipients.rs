@@ -0,0 +1,179 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode};
+use securefabric_sdk::sealed::{PublicKey, StaticSecret};
+use securefabric_sdk::{Client, Error, Recipient};
+
+#[tokio::test]
+async fn default_recipient_applied_and_overridden() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+
+    let default_to = signing_key(2).verifying_key().to_bytes().to_vec();
+    let explicit_to = signing_key(3).verifying_key().to_bytes().to_vec();
+    client.set_default_recipient("orders", default_to.clone());
+
+    client.send_to_default("orders", b"first").await.unwrap();
+    client
+        .send_to("orders", &explicit_to, b"second")
+        .await
+        .unwrap();
+
+    let sent = node.sent();
+    assert_eq!(sent[0].to, default_to);
+    assert_eq!(sent[1].to, explicit_to);
+
+    // The recipient is bound into the signed AAD
+    for envelope in &sent {
+        assert!(client.verify(envelope).unwrap());
+        let aad: serde_json::Value = serde_json::from_slice(&envelope.aad).unwrap();
+        assert_eq!(aad["to"], hex::encode(&envelope.to));
+    }
+}
+
+#[tokio::test]
+async fn send_to_default_without_default_errors() {
+    let endpoint = common::spawn(MockNode::default()).await;
+    let mut client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+
+    let err = client.send_to_default("orders", b"x").await.unwrap_err();
+    assert!(err.to_string().contains("No default recipient"));
+
+    client.set_default_recipient("orders", vec![7u8; 32]);
+    assert_eq!(
+        client.clear_default_recipient("orders"),
+        Some(vec![7u8; 32])
+    );
+    assert!(client.send_to_default("orders", b"x").await.is_err());
+}
+
+#[tokio::test]
+async fn each_recipient_kind_round_trips() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+
+    let ed25519 = signing_key(2).verifying_key().to_bytes().to_vec();
+    let recipients = [
+        Recipient::Broadcast,
+        Recipient::Direct(ed25519.clone()),
+        Recipient::Direct(vec![2u8; 33]),
+        Recipient::Group("ops-team".into()),
+    ];
+    for to in &recipients {
+        client.send_to_recipient("orders", to, b"x").await.unwrap();
+    }
+
+    let sent = node.sent();
+    assert!(sent[0].to.is_empty());
+    assert_eq!(sent[1].to, ed25519);
+    assert_eq!(sent[3].to, b"group:ops-team");
+    for (envelope, to) in sent.iter().zip(&recipients) {
+        assert!(client.verify(envelope).unwrap());
+        assert_eq!(&envelope.recipient().unwrap(), to);
+    }
+
+    // Raw wire bytes go through the same rules
+    client
+        .send_to("orders", b"group:ops-team", b"y")
+        .await
+        .unwrap();
+    assert_eq!(
+        node.sent()[4].recipient().unwrap(),
+        Recipient::Group("ops-team".into())
+    );
+}
+
+#[tokio::test]
+async fn malformed_recipients_rejected() {
+    let endpoint = common::spawn(MockNode::default()).await;
+    let mut client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+
+    let mut prefixed = b"group:".to_vec();
+    prefixed.resize(32, 7);
+    for to in [
+        Recipient::Direct(vec![7u8; 31]),
+        Recipient::Direct(prefixed.clone()),
+        Recipient::Group(String::new()),
+    ] {
+        let err = client
+            .send_to_recipient("orders", &to, b"x")
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                err.downcast_ref::<Error>(),
+                Some(Error::InvalidRecipient { .. })
+            ),
+            "{to:?}: {err:#}"
+        );
+    }
+    for to in [&[7u8; 5][..], b"group:", b"group:\xff"] {
+        let err = client.send_to("orders", to, b"x").await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::InvalidRecipient { .. })
+        ));
+        assert!(Recipient::from_wire(to).is_err());
+    }
+}
+
+#[tokio::test]
+async fn sealing_must_match_recipient() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+    let alice = PublicKey::from(&StaticSecret::from([10u8; 32]));
+    let bob = PublicKey::from(&StaticSecret::from([11u8; 32]));
+    let direct = Recipient::Direct(signing_key(2).verifying_key().to_bytes().to_vec());
+
+    for (to, sealed_for) in [
+        (&Recipient::Broadcast, &[alice][..]),
+        (&direct, &[alice, bob][..]),
+    ] {
+        let err = client
+            .send_sealed("orders", to, sealed_for, b"x")
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::InvalidRecipient { .. })
+        ));
+    }
+    assert!(node.sent().is_empty());
+
+    client
+        .send_sealed("orders", &direct, &[alice, alice], b"x")
+        .await
+        .unwrap();
+    client
+        .send_sealed(
+            "orders",
+            &Recipient::Group("ops".into()),
+            &[alice, bob],
+            b"x",
+        )
+        .await
+        .unwrap();
+    let sent = node.sent();
+    assert_eq!(sent[0].recipient().unwrap(), direct);
+    assert_eq!(sent[1].recipient().unwrap(), Recipient::Group("ops".into()));
+}
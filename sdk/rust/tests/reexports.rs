@@ -0,0 +1,24 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The stable wire types must stay importable from the crate root.
+
+use securefabric_sdk::{pb, Envelope, SendReq, SubscribeReq};
+
+#[test]
+fn root_types_are_the_wire_types() {
+    let envelope: pb::Envelope = Envelope {
+        topic: "demo".to_string(),
+        seq: 1,
+        ..Default::default()
+    };
+    let req: pb::SendReq = SendReq {
+        envelope: Some(envelope.clone()),
+    };
+    let subscribe: pb::SubscribeReq = SubscribeReq {
+        topic: b"demo".to_vec(),
+        ..Default::default()
+    };
+
+    assert_eq!(req.envelope.unwrap(), envelope);
+    assert_eq!(subscribe.shard_count, 0);
+}
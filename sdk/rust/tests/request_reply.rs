@@ -0,0 +1,89 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::signing_key;
+use futures::StreamExt;
+use securefabric_sdk::fabric::InMemoryFabric;
+use securefabric_sdk::request::{CORRELATION_ID, REPLY_TO};
+use securefabric_sdk::{Client, Error};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+async fn client(fabric: &InMemoryFabric, seed: u8) -> Client {
+    fabric
+        .client()
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(seed))
+}
+
+#[tokio::test]
+async fn returns_the_reply_echoing_the_correlation_id() {
+    let fabric = InMemoryFabric::new();
+    let mut requester = client(&fabric, 1).await;
+    let mut responder = client(&fabric, 2).await;
+
+    let mut requests = responder.subscribe(b"rpc.echo").await.unwrap();
+    let echo = tokio::spawn(async move {
+        let request = requests.next().await.unwrap().unwrap();
+        let headers = request.headers();
+        let reply_to = headers[REPLY_TO].clone();
+
+        // A reply to some other request on the same topic is skipped
+        let mut other = BTreeMap::new();
+        other.insert(CORRELATION_ID.to_string(), "someone-else".to_string());
+        responder
+            .send_with_headers(&reply_to, &other, b"not yours")
+            .await
+            .unwrap();
+
+        let mut reply = BTreeMap::new();
+        reply.insert(CORRELATION_ID.to_string(), headers[CORRELATION_ID].clone());
+        let mut payload = request.payload.clone();
+        payload.reverse();
+        responder
+            .send_with_headers(&reply_to, &reply, &payload)
+            .await
+            .unwrap();
+        headers[CORRELATION_ID].clone()
+    });
+
+    let reply = requester
+        .request("rpc.echo", b"ping", "rpc.replies", Duration::from_secs(5))
+        .await
+        .unwrap();
+    let correlation_id = echo.await.unwrap();
+    assert_eq!(reply.payload, b"gnip");
+    assert_eq!(reply.topic, "rpc.replies");
+    assert_eq!(reply.headers()[CORRELATION_ID], correlation_id);
+    assert_eq!(correlation_id.len(), 32);
+    assert!(requester.active_subscriptions().is_empty());
+}
+
+#[tokio::test]
+async fn times_out_without_a_responder() {
+    let fabric = InMemoryFabric::new();
+    let mut requester = client(&fabric, 1).await;
+
+    let error = requester
+        .request(
+            "rpc.echo",
+            b"ping",
+            "rpc.replies",
+            Duration::from_millis(100),
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<Error>(),
+        Some(&Error::Timeout {
+            after: Duration::from_millis(100)
+        })
+    );
+    assert!(requester.active_subscriptions().is_empty());
+
+    let request = &fabric.retained(b"rpc.echo")[0];
+    assert_eq!(request.headers()[REPLY_TO], "rpc.replies");
+    assert!(request.headers().contains_key(CORRELATION_ID));
+}
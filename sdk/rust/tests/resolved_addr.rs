@@ -0,0 +1,57 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode, TestPki};
+use securefabric_sdk::{Client, TlsConfig};
+use std::net::SocketAddr;
+
+fn socket_addr(endpoint: &str) -> SocketAddr {
+    endpoint.split("://").nth(1).unwrap().parse().unwrap()
+}
+
+#[tokio::test]
+async fn connects_to_numeric_address_without_dns() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+
+    // The domain does not resolve, so the connection must use the address
+    let mut client = Client::new_addr(socket_addr(&endpoint), "node.invalid", None)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+    let msg_id = client.send("fixed", b"hello").await.unwrap();
+    assert_eq!(node.sent()[0].msg_id, msg_id);
+
+    let lazy = Client::builder("http://node.invalid:1")
+        .with_resolved_addr(socket_addr(&endpoint))
+        .connect_lazy(true)
+        .build()
+        .await
+        .unwrap();
+    lazy.with_signing_key(signing_key(1))
+        .send("fixed", b"lazy")
+        .await
+        .unwrap();
+    assert_eq!(node.sent().len(), 2);
+}
+
+#[tokio::test]
+async fn validates_tls_against_domain_not_address() {
+    let pki = TestPki::generate();
+    let node = MockNode::default();
+    let addr = socket_addr(&common::spawn_tls(node.clone(), &pki).await);
+    let tls = TlsConfig::new().with_ca_pem(&pki.ca_pem);
+
+    let mut client = Client::new_addr(addr, "localhost", Some(tls.clone()))
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+    client.send("fixed", b"hello").await.unwrap();
+    assert_eq!(node.sent().len(), 1);
+
+    // The certificate is issued for localhost only
+    assert!(Client::new_addr(addr, "node.invalid", Some(tls))
+        .await
+        .is_err());
+}
@@ -0,0 +1,78 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode};
+use securefabric_sdk::Client;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use tonic::Code;
+
+/// Send once and return how many attempts reached the node
+async fn attempts_for_send(client: &mut Client, node: &MockNode) -> (usize, bool) {
+    let before = node.state.send_attempts.load(Ordering::SeqCst);
+    let ok = client.send("budget", b"x").await.is_ok();
+    (node.state.send_attempts.load(Ordering::SeqCst) - before, ok)
+}
+
+#[tokio::test]
+async fn retries_taper_off_under_sustained_failure() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_send_retries(3, Duration::from_millis(1))
+        .with_retry_budget(0.5, 5);
+
+    node.state.sends_unavailable.store(true, Ordering::SeqCst);
+    let mut attempts = Vec::new();
+    for _ in 0..8 {
+        let (count, ok) = attempts_for_send(&mut client, &node).await;
+        assert!(!ok);
+        attempts.push(count);
+    }
+    // 5 tokens: 3 retries, then 2, then the budget is empty
+    assert_eq!(attempts, [4, 3, 1, 1, 1, 1, 1, 1]);
+
+    let err = client.send("budget", b"x").await.unwrap_err();
+    assert!(format!("{err:#}").contains("retry budget exhausted"));
+    assert_eq!(
+        err.downcast_ref::<tonic::Status>().map(|s| s.code()),
+        Some(Code::Unavailable)
+    );
+
+    // Each success earns half a token, so four successes buy two retries
+    node.state.sends_unavailable.store(false, Ordering::SeqCst);
+    for _ in 0..4 {
+        assert_eq!(attempts_for_send(&mut client, &node).await, (1, true));
+    }
+    node.state.sends_unavailable.store(true, Ordering::SeqCst);
+    assert_eq!(attempts_for_send(&mut client, &node).await, (3, false));
+    assert_eq!(attempts_for_send(&mut client, &node).await, (1, false));
+}
+
+#[tokio::test]
+async fn retries_recover_from_transient_failure_without_budget() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_send_retries(5, Duration::from_millis(20));
+
+    node.state.sends_unavailable.store(true, Ordering::SeqCst);
+    let state = node.state.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        state.sends_unavailable.store(false, Ordering::SeqCst);
+    });
+
+    let msg_id = client.send("budget", b"x").await.unwrap();
+    let sent = node.sent();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].msg_id, msg_id);
+    assert!(node.state.send_attempts.load(Ordering::SeqCst) > 1);
+}
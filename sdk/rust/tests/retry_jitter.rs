@@ -0,0 +1,71 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode};
+use securefabric_sdk::Client;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+const SAMPLES: usize = 32;
+
+/// Time `SAMPLES` sends that each fail once, retry once and fail again
+async fn retry_waits(configure: impl FnOnce(Client) -> Client) -> Vec<Duration> {
+    let node = MockNode::default();
+    node.state.sends_unavailable.store(true, Ordering::SeqCst);
+    let endpoint = common::spawn(node.clone()).await;
+    let client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+    let mut client = configure(client);
+
+    let mut waits = Vec::new();
+    for _ in 0..SAMPLES {
+        let start = Instant::now();
+        assert!(client.send("jitter", b"x").await.is_err());
+        waits.push(start.elapsed());
+    }
+    assert_eq!(node.state.send_attempts.load(Ordering::SeqCst), 2 * SAMPLES);
+    waits
+}
+
+/// Assert `waits` stay within `bound` and spread over both ends of it
+fn assert_spread(waits: &[Duration], bound: Duration) {
+    let slack = Duration::from_millis(50);
+    assert!(waits.iter().all(|wait| *wait <= bound + slack), "{waits:?}");
+    assert!(waits.iter().any(|wait| *wait < bound * 2 / 5), "{waits:?}");
+    assert!(waits.iter().any(|wait| *wait > bound * 3 / 5), "{waits:?}");
+}
+
+#[tokio::test]
+async fn full_jitter_spreads_waits_up_to_the_backoff() {
+    let backoff = Duration::from_millis(100);
+    let waits = retry_waits(|client| {
+        client
+            .with_send_retries(1, backoff)
+            .with_retry_jitter(Duration::ZERO)
+    })
+    .await;
+    assert_spread(&waits, backoff);
+}
+
+#[tokio::test]
+async fn initial_delay_spreads_the_first_retry() {
+    let initial = Duration::from_millis(100);
+    // Jitter configured before retries still applies
+    let waits = retry_waits(|client| {
+        client
+            .with_retry_jitter(initial)
+            .with_send_retries(1, Duration::ZERO)
+    })
+    .await;
+    assert_spread(&waits, initial);
+}
+
+#[tokio::test]
+async fn waits_are_fixed_without_jitter() {
+    let backoff = Duration::from_millis(20);
+    let waits = retry_waits(|client| client.with_send_retries(1, backoff)).await;
+    assert!(waits.iter().all(|wait| *wait >= backoff), "{waits:?}");
+}
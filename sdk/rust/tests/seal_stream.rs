@@ -0,0 +1,74 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use securefabric_sdk::crypto::stream::{open_stream, SealStream, NONCE_PREFIX_LEN, SEGMENT_LEN};
+use securefabric_sdk::crypto::TAG_LEN;
+
+const KEY: [u8; 32] = [7; 32];
+
+fn seal_in_chunks(plaintext: &[u8], chunk_len: usize) -> Vec<u8> {
+    let mut stream = SealStream::new(&KEY).unwrap();
+    let mut sealed = Vec::new();
+    for chunk in plaintext.chunks(chunk_len) {
+        sealed.extend(stream.push(chunk).unwrap());
+    }
+    sealed.extend(stream.finalize().unwrap());
+    sealed
+}
+
+#[test]
+fn chunked_seal_opens_as_one() {
+    let plaintext: Vec<u8> = (0..3 * SEGMENT_LEN + 123).map(|i| i as u8).collect();
+    for chunk_len in [1000, SEGMENT_LEN, SEGMENT_LEN + 1, plaintext.len()] {
+        let sealed = seal_in_chunks(&plaintext, chunk_len);
+        assert_eq!(
+            sealed.len(),
+            NONCE_PREFIX_LEN + plaintext.len() + 4 * TAG_LEN,
+            "{chunk_len}"
+        );
+        assert_eq!(
+            open_stream(&KEY, &sealed).unwrap(),
+            plaintext,
+            "{chunk_len}"
+        );
+    }
+}
+
+#[test]
+fn segment_aligned_and_empty_inputs() {
+    let aligned = vec![1u8; 2 * SEGMENT_LEN];
+    let sealed = seal_in_chunks(&aligned, SEGMENT_LEN);
+    assert_eq!(sealed.len(), NONCE_PREFIX_LEN + aligned.len() + 2 * TAG_LEN);
+    assert_eq!(open_stream(&KEY, &sealed).unwrap(), aligned);
+
+    let sealed = SealStream::new(&KEY).unwrap().finalize().unwrap();
+    assert_eq!(sealed.len(), NONCE_PREFIX_LEN + TAG_LEN);
+    assert!(open_stream(&KEY, &sealed).unwrap().is_empty());
+}
+
+#[test]
+fn truncation_and_tampering_detected() {
+    let plaintext = vec![9u8; 2 * SEGMENT_LEN + 10];
+    let sealed = seal_in_chunks(&plaintext, 4096);
+
+    // Dropping the last segment leaves a stream that ends on a non-final segment
+    let truncated = &sealed[..NONCE_PREFIX_LEN + 2 * (SEGMENT_LEN + TAG_LEN)];
+    assert!(open_stream(&KEY, truncated).is_err());
+    assert!(open_stream(&KEY, &sealed[..sealed.len() - 1]).is_err());
+
+    let mut tampered = sealed.clone();
+    tampered[NONCE_PREFIX_LEN + 5] ^= 1;
+    assert!(open_stream(&KEY, &tampered).is_err());
+
+    // Swapping two full segments breaks their counters
+    let mut reordered = sealed[..NONCE_PREFIX_LEN].to_vec();
+    let segments: Vec<&[u8]> = sealed[NONCE_PREFIX_LEN..]
+        .chunks(SEGMENT_LEN + TAG_LEN)
+        .collect();
+    for segment in [segments[1], segments[0], segments[2]] {
+        reordered.extend_from_slice(segment);
+    }
+    assert!(open_stream(&KEY, &reordered).is_err());
+
+    assert!(open_stream(&[0u8; 32], &sealed).is_err());
+    assert_eq!(open_stream(&KEY, &sealed).unwrap(), plaintext);
+}
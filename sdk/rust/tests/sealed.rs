@@ -0,0 +1,133 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode};
+use prost::Message;
+use securefabric_sdk::crypto::SEALED_KEY_VERSION;
+use securefabric_sdk::sealed::{PublicKey, StaticSecret};
+use securefabric_sdk::{Client, Envelope, Error, Recipient};
+
+fn recipient(seed: u8) -> (StaticSecret, PublicKey) {
+    let secret = StaticSecret::from([seed; 32]);
+    let public = PublicKey::from(&secret);
+    (secret, public)
+}
+
+#[tokio::test]
+async fn each_recipient_opens_and_outsider_cannot() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut sender = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+    let (alice, alice_public) = recipient(10);
+    let (bob, bob_public) = recipient(11);
+    let (carol, _) = recipient(12);
+
+    let payload: Vec<u8> = (0..256 * 1024).map(|i| (i % 253) as u8).collect();
+    sender
+        .send_sealed(
+            "reports",
+            &Recipient::Group("auditors".into()),
+            &[alice_public, bob_public, alice_public],
+            &payload,
+        )
+        .await
+        .unwrap();
+
+    let sent = node.sent().remove(0);
+    assert_eq!(sent.key_version, SEALED_KEY_VERSION);
+    assert_eq!(sent.wrapped_keys.len(), 2);
+    // One ciphertext for everyone: payload plus a single tag
+    assert_eq!(sent.payload.len(), payload.len() + 16);
+    let sent = Envelope::try_from_bytes(&sent.encode_to_vec()).unwrap();
+
+    for secret in [alice, bob] {
+        let client = Client::new(&endpoint)
+            .await
+            .unwrap()
+            .with_recipient_key(secret);
+        assert!(client.verify(&sent).unwrap());
+        assert_eq!(client.open_sealed(&sent).unwrap(), payload);
+    }
+
+    let outsider = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_recipient_key(carol);
+    assert!(outsider.verify(&sent).unwrap());
+    let err = outsider.open_sealed(&sent).unwrap_err();
+    assert_eq!(err.downcast_ref::<Error>(), Some(&Error::NotRecipient));
+}
+
+#[tokio::test]
+async fn tampered_sealed_envelopes_rejected() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut sender = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(2));
+    let (alice, alice_public) = recipient(20);
+    let (_, bob_public) = recipient(21);
+    sender
+        .send_sealed(
+            "reports",
+            &Recipient::Direct(signing_key(20).verifying_key().to_bytes().to_vec()),
+            &[alice_public],
+            b"for alice",
+        )
+        .await
+        .unwrap();
+    let sent = node.sent().remove(0);
+    let alice = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_recipient_key(alice);
+
+    // Swapping in another ephemeral key is caught by the signed AAD
+    let mut swapped = sent.clone();
+    swapped.ephemeral_key = bob_public.as_bytes().to_vec();
+    assert!(alice.open_sealed(&swapped).is_err());
+
+    // Relabelling a slot for a recipient not in the signed list is refused
+    let mut relabelled = sent.clone();
+    relabelled.wrapped_keys[0].recipient = bob_public.as_bytes().to_vec();
+    let err = alice.open_sealed(&relabelled).unwrap_err();
+    assert_eq!(err.downcast_ref::<Error>(), Some(&Error::NotRecipient));
+
+    let mut corrupted = sent.clone();
+    corrupted.wrapped_keys[0].wrapped[0] ^= 1;
+    assert!(alice.open_sealed(&corrupted).is_err());
+
+    let mut forged = sent.clone();
+    forged.payload[0] ^= 1;
+    assert!(alice.open_sealed(&forged).is_err());
+
+    assert_eq!(alice.open_sealed(&sent).unwrap(), b"for alice");
+}
+
+#[tokio::test]
+async fn sealing_needs_a_recipient() {
+    let endpoint = common::spawn(MockNode::default()).await;
+    let mut sender = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(3));
+    let team = Recipient::Group("auditors".into());
+    assert!(sender
+        .send_sealed("reports", &team, &[], b"nobody")
+        .await
+        .is_err());
+
+    let (_, public) = recipient(30);
+    sender
+        .send_sealed("reports", &team, &[public], b"x")
+        .await
+        .unwrap();
+    let client = Client::new(&endpoint).await.unwrap();
+    let plain = common::signed_envelope(&signing_key(3), "reports", 1, b"x");
+    assert!(client.open_sealed(&plain).is_err());
+}
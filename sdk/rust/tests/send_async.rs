@@ -0,0 +1,85 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode};
+use securefabric_sdk::{Client, Error, OverflowPolicy};
+use std::collections::HashSet;
+use std::time::Duration;
+
+async fn client(node: &MockNode) -> Client {
+    let endpoint = common::spawn(node.clone()).await;
+    Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+}
+
+#[tokio::test]
+async fn handles_resolve_after_background_delivery() {
+    let node = MockNode::default();
+    let client = client(&node).await;
+    let gate = node.hold_sends();
+
+    let mut handles = Vec::new();
+    for i in 0..50u8 {
+        handles.push(client.send_async("ui", &[], &[i]).await.unwrap());
+    }
+    // Every send was handed off while the node was still holding them
+    assert!(handles.iter().all(|handle| !handle.is_finished()));
+    assert!(node.sent().is_empty());
+
+    gate.add_permits(50);
+    let mut msg_ids = HashSet::new();
+    for handle in handles {
+        msg_ids.insert(handle.await.unwrap());
+    }
+    assert_eq!(msg_ids.len(), 50);
+    let sent: HashSet<_> = node.sent().into_iter().map(|e| e.msg_id).collect();
+    assert_eq!(sent, msg_ids);
+}
+
+#[tokio::test]
+async fn full_queue_applies_backpressure() {
+    let node = MockNode::default();
+    let client = client(&node)
+        .await
+        .with_send_queue(2, OverflowPolicy::RejectNew)
+        .unwrap();
+    let gate = node.hold_sends();
+
+    let first = client.send_async("ui", &[], b"1").await.unwrap();
+    let second = client.send_async("ui", &[], b"2").await.unwrap();
+    let error = client.send_async("ui", &[], b"3").await.unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<Error>(),
+        Some(&Error::QueueFull { capacity: 2 })
+    );
+
+    let blocking = client
+        .clone()
+        .with_send_queue(1, OverflowPolicy::Block)
+        .unwrap();
+    let held = blocking.send_async("ui", &[], b"4").await.unwrap();
+    let waiting = tokio::spawn(async move { blocking.send_async("ui", &[], b"5").await });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!waiting.is_finished());
+
+    gate.add_permits(4);
+    first.await.unwrap();
+    second.await.unwrap();
+    held.await.unwrap();
+    waiting.await.unwrap().unwrap().await.unwrap();
+    assert_eq!(node.sent().len(), 4);
+}
+
+#[tokio::test]
+async fn failures_surface_through_the_handle() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node).await;
+    let unsigned = Client::new(endpoint).await.unwrap();
+
+    let handle = unsigned.send_async("ui", &[], b"lost").await.unwrap();
+    let error = handle.await.unwrap_err();
+    assert!(error.to_string().contains("No signing key"), "{error}");
+}
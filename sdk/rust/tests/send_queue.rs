@@ -0,0 +1,134 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode};
+use securefabric_sdk::{Client, Error, OverflowPolicy};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+async fn queued_client(node: &MockNode, capacity: usize, policy: OverflowPolicy) -> Client {
+    let endpoint = common::spawn(node.clone()).await;
+    Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_send_queue(capacity, policy)
+        .unwrap()
+}
+
+fn spawn_send(client: &Client, payload: &'static [u8]) -> JoinHandle<anyhow::Result<String>> {
+    let mut client = client.clone();
+    tokio::spawn(async move { client.send("jobs", payload).await })
+}
+
+/// Wait until `n` sends occupy the queue and have had time to reach the node
+async fn wait_queued(client: &Client, n: usize) {
+    while client.queued_sends() < n {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    tokio::time::sleep(Duration::from_millis(50)).await;
+}
+
+fn queue_error(result: anyhow::Result<String>) -> Error {
+    result
+        .unwrap_err()
+        .downcast_ref::<Error>()
+        .cloned()
+        .expect("typed queue error")
+}
+
+#[tokio::test]
+async fn reject_new_fails_fast_when_full() {
+    let node = MockNode::default();
+    let gate = node.hold_sends();
+    let mut client = queued_client(&node, 1, OverflowPolicy::RejectNew).await;
+
+    let first = spawn_send(&client, b"a");
+    wait_queued(&client, 1).await;
+
+    assert_eq!(
+        queue_error(client.send("jobs", b"b").await),
+        Error::QueueFull { capacity: 1 }
+    );
+
+    gate.add_permits(2);
+    first.await.unwrap().unwrap();
+    assert_eq!(client.queued_sends(), 0);
+    client.send("jobs", b"c").await.unwrap();
+}
+
+#[tokio::test]
+async fn block_waits_for_a_free_slot() {
+    let node = MockNode::default();
+    let gate = node.hold_sends();
+    let client = queued_client(&node, 1, OverflowPolicy::Block).await;
+
+    let first = spawn_send(&client, b"a");
+    wait_queued(&client, 1).await;
+    let second = spawn_send(&client, b"b");
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    assert!(!second.is_finished());
+    assert_eq!(client.queued_sends(), 1);
+
+    gate.add_permits(2);
+    first.await.unwrap().unwrap();
+    second.await.unwrap().unwrap();
+
+    let payloads: Vec<_> = node.sent().into_iter().map(|e| e.payload).collect();
+    assert_eq!(payloads, vec![b"a".to_vec(), b"b".to_vec()]);
+}
+
+#[tokio::test]
+async fn drop_oldest_keeps_ordered_sends_already_on_the_wire() {
+    let node = MockNode::default();
+    let gate = node.hold_sends();
+    let mut client = queued_client(&node, 2, OverflowPolicy::DropOldest)
+        .await
+        .with_ordered_send(true);
+
+    // `b` does not wait for `a` to be answered, so both are on the wire
+    let a = spawn_send(&client, b"a");
+    wait_queued(&client, 1).await;
+    let b = spawn_send(&client, b"b");
+    wait_queued(&client, 2).await;
+
+    assert_eq!(
+        queue_error(client.send("jobs", b"c").await),
+        Error::QueueFull { capacity: 2 }
+    );
+
+    gate.add_permits(2);
+    a.await.unwrap().unwrap();
+    b.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn drop_oldest_rejects_when_every_send_is_on_the_wire() {
+    let node = MockNode::default();
+    let gate = node.hold_sends();
+    let mut client = queued_client(&node, 1, OverflowPolicy::DropOldest).await;
+
+    let first = spawn_send(&client, b"a");
+    wait_queued(&client, 1).await;
+
+    assert_eq!(
+        queue_error(client.send("jobs", b"b").await),
+        Error::QueueFull { capacity: 1 }
+    );
+
+    gate.add_permits(1);
+    first.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn zero_capacity_rejected() {
+    let endpoint = common::spawn(MockNode::default()).await;
+    let client = Client::new(endpoint).await.unwrap();
+    let err = client
+        .with_send_queue(0, OverflowPolicy::Block)
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("non-zero"), "{err}");
+}
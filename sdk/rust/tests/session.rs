@@ -0,0 +1,99 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use securefabric_sdk::session::SessionState;
+use securefabric_sdk::{msg_id, Client};
+use tokio_stream::StreamExt;
+
+#[tokio::test]
+async fn restored_client_resumes_without_gaps_or_duplicates() {
+    let producer = signing_key(1);
+    let feed = (1..=10)
+        .map(|seq| signed_envelope(&producer, "events", seq, b"x"))
+        .collect();
+    let node = MockNode::with_feed(feed);
+    let endpoint = common::spawn(node.clone()).await;
+
+    let mut first = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(2));
+    let mut stream = first.subscribe(b"events").await.unwrap();
+    for _ in 0..4 {
+        stream.next().await.unwrap().unwrap();
+    }
+    drop(stream);
+    first.send("replies", b"a").await.unwrap();
+    first.send("replies", b"b").await.unwrap();
+
+    // Move the session through its serialized form, as a migration would
+    let json = serde_json::to_string(&first.export_state()).unwrap();
+    let state: SessionState = serde_json::from_str(&json).unwrap();
+
+    let mut second = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(2));
+    second.restore_state(state.clone()).unwrap();
+    assert_eq!(second.export_state(), state);
+
+    let seqs: Vec<u64> = second
+        .subscribe(b"events")
+        .await
+        .unwrap()
+        .map(|item| item.unwrap().seq)
+        .collect()
+        .await;
+    assert_eq!(seqs, (5..=10).collect::<Vec<_>>());
+
+    second.send("replies", b"c").await.unwrap();
+    let reply_seqs: Vec<u64> = node.sent().iter().map(|e| e.seq).collect();
+    assert_eq!(reply_seqs, vec![1, 2, 3]);
+}
+
+#[tokio::test]
+async fn restore_rejects_malformed_state() {
+    let endpoint = common::spawn(MockNode::default()).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let mut state = SessionState::default();
+    state
+        .offsets
+        .entry("not-hex".to_string())
+        .or_default()
+        .insert("00".to_string(), 1);
+    assert!(client.restore_state(state).is_err());
+}
+
+#[tokio::test]
+async fn forged_high_seq_does_not_move_the_offset() {
+    let producer = signing_key(1);
+    let mut feed: Vec<_> = (1..=3)
+        .map(|seq| signed_envelope(&producer, "events", seq, b"x"))
+        .collect();
+    // A relay renumbers a genuine envelope, recomputing its msg_id
+    let mut renumbered = feed[2].clone();
+    renumbered.seq = u64::MAX;
+    renumbered.msg_id = msg_id::compute(&msg_id::Blake3, &renumbered);
+    // Someone else signs an envelope claiming the producer's key
+    let mut forged = signed_envelope(&signing_key(9), "events", u64::MAX - 1, b"x");
+    forged.pubkey = producer.verifying_key().to_bytes().to_vec();
+    forged.msg_id = msg_id::compute(&msg_id::Blake3, &forged);
+    feed.extend([renumbered, forged]);
+    let endpoint = common::spawn(MockNode::with_feed(feed)).await;
+
+    let mut client = Client::new(&endpoint).await.unwrap();
+    let received: Vec<_> = client.subscribe(b"events").await.unwrap().collect().await;
+    assert_eq!(received.len(), 5);
+
+    let producer_hex = hex::encode(producer.verifying_key().to_bytes());
+    let state = client.export_state();
+    assert_eq!(state.offsets[&hex::encode("events")][&producer_hex], 3);
+    let stats = client.subscription_stats(b"events").unwrap();
+    assert_eq!(
+        stats.last_seq[producer.verifying_key().as_bytes().as_slice()],
+        3
+    );
+}
@@ -0,0 +1,79 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use futures::StreamExt;
+use securefabric_sdk::capabilities::Feature;
+use securefabric_sdk::subscription::shard_for;
+use securefabric_sdk::{Client, Error};
+use std::collections::HashMap;
+
+#[tokio::test]
+async fn sharded_subscribe_merges_shards_preserving_per_shard_order() {
+    // Pick two senders that hash to different shards of two
+    let keys: Vec<_> = (1..=16u8).map(signing_key).collect();
+    let a = &keys[0];
+    let a_shard = shard_for(a.verifying_key().as_bytes(), 2);
+    let b = keys
+        .iter()
+        .find(|k| shard_for(k.verifying_key().as_bytes(), 2) != a_shard)
+        .unwrap();
+
+    let mut feed = Vec::new();
+    for seq in 1..=20 {
+        feed.push(signed_envelope(a, "trades", seq, b"a"));
+        feed.push(signed_envelope(b, "trades", seq, b"b"));
+    }
+    let endpoint = common::spawn(MockNode::with_feed(feed)).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let items: Vec<_> = client
+        .subscribe_sharded(b"trades", 2)
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    assert_eq!(items.len(), 40);
+
+    let mut per_shard: HashMap<u32, Vec<(Vec<u8>, u64)>> = HashMap::new();
+    for (shard, item) in items {
+        let envelope = item.unwrap();
+        assert_eq!(shard, shard_for(&envelope.pubkey, 2));
+        per_shard
+            .entry(shard)
+            .or_default()
+            .push((envelope.pubkey, envelope.seq));
+    }
+
+    assert_eq!(per_shard.len(), 2);
+    for envelopes in per_shard.values() {
+        let seqs: Vec<u64> = envelopes.iter().map(|(_, seq)| *seq).collect();
+        assert_eq!(seqs, (1..=20).collect::<Vec<_>>());
+    }
+    assert_eq!(client.subscription_stats(b"trades").unwrap().received, 40);
+}
+
+#[tokio::test]
+async fn zero_shards_rejected() {
+    let endpoint = common::spawn(MockNode::default()).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+    assert!(client.subscribe_sharded(b"trades", 0).await.is_err());
+}
+
+#[tokio::test]
+async fn unsupported_without_shards() {
+    let node = MockNode::default().without("shards");
+    let state = node.state.clone();
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let error = client.subscribe_sharded(b"trades", 2).await.err().unwrap();
+    assert_eq!(
+        error.downcast_ref::<Error>(),
+        Some(&Error::Unsupported {
+            feature: Feature::Shards
+        })
+    );
+    assert!(state.subscribe_requests.lock().unwrap().is_empty());
+}
@@ -0,0 +1,87 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use futures::StreamExt;
+use securefabric_sdk::subscription::{EndReason, StreamEnd};
+use securefabric_sdk::{Client, Error};
+
+#[tokio::test]
+async fn two_consumers_each_receive_every_envelope() {
+    let key = signing_key(1);
+    let feed: Vec<_> = (1..=5)
+        .map(|seq| signed_envelope(&key, "events", seq, &[seq as u8]))
+        .collect();
+    let endpoint = common::spawn(MockNode::with_feed(feed)).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let shared = client.subscribe_shared(b"events").await.unwrap();
+    assert_eq!(shared.topic(), b"events");
+    let first = shared.subscribe();
+    let second = shared.subscribe();
+    assert_eq!(shared.consumer_count(), 2);
+
+    let (first, second) = tokio::join!(
+        first.map(|item| item.unwrap().seq).collect::<Vec<_>>(),
+        second.map(|item| item.unwrap().seq).collect::<Vec<_>>(),
+    );
+    assert_eq!(first, [1, 2, 3, 4, 5]);
+    assert_eq!(second, [1, 2, 3, 4, 5]);
+    assert_eq!(client.subscription_stats(b"events").unwrap().received, 5);
+}
+
+#[tokio::test]
+async fn lagging_consumer_skips_without_stalling_others() {
+    let key = signing_key(1);
+    let node = MockNode::default();
+    let live = node.live_feed();
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let shared = client
+        .subscribe_shared_with_capacity(b"events", 2)
+        .await
+        .unwrap();
+    let mut fast = shared.subscribe();
+    let mut slow = shared.subscribe();
+
+    // The fast consumer reads each envelope before the next is sent
+    for seq in 1..=5 {
+        live.send(signed_envelope(&key, "events", seq, b"x"))
+            .unwrap();
+        assert_eq!(fast.next().await.unwrap().unwrap().seq, seq);
+    }
+    drop(live);
+    assert!(fast.next().await.is_none());
+
+    assert_eq!(
+        slow.next().await.unwrap().unwrap_err(),
+        Error::Lagged { skipped: 3 }
+    );
+    let rest: Vec<u64> = slow.map(|item| item.unwrap().seq).collect().await;
+    assert_eq!(rest, [4, 5]);
+}
+
+#[tokio::test]
+async fn reports_why_the_node_ended_the_stream() {
+    let key = signing_key(1);
+    let node = MockNode::with_feed(vec![signed_envelope(&key, "events", 1, b"x")]);
+    *node.state.end_reason.lock().unwrap() = Some("evicted");
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let shared = client.subscribe_shared(b"events").await.unwrap();
+    let consumer = shared.subscribe();
+    assert_eq!(consumer.count().await, 1);
+    assert_eq!(
+        shared.stream_end(),
+        Some(StreamEnd {
+            reason: EndReason::Evicted
+        })
+    );
+
+    // Consumers subscribing after the end receive nothing
+    assert_eq!(shared.consumer_count(), 0);
+    assert!(shared.subscribe().next().await.is_none());
+}
@@ -0,0 +1,151 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use k256::ecdsa::Signature;
+use prost::Message;
+use securefabric_sdk::crypto::scheme::{SigningKey, VerifyingKey};
+use securefabric_sdk::crypto::SignatureScheme;
+use securefabric_sdk::keyring::Keyring;
+use securefabric_sdk::{conformance, Client, Envelope};
+
+fn secp256k1_key(seed: u8) -> k256::ecdsa::SigningKey {
+    k256::ecdsa::SigningKey::from_slice(&[seed; 32]).unwrap()
+}
+
+#[test]
+fn conformance_vectors() {
+    let vectors: serde_json::Value = serde_json::from_str(conformance::VECTORS).unwrap();
+    let schemes = &vectors["signature_schemes"];
+
+    let mut checked = 0;
+    for (name, scheme) in [
+        ("ed25519", SignatureScheme::Ed25519),
+        ("secp256k1", SignatureScheme::Secp256k1),
+    ] {
+        for vector in schemes[name].as_array().unwrap() {
+            let field = |key: &str| hex::decode(vector[key].as_str().unwrap()).unwrap();
+            let description = vector["description"].as_str().unwrap();
+            let secret = field("secret_key");
+            let message = field("message");
+            let signature = field("signature");
+
+            assert_eq!(vector["wire"], scheme.wire_value(), "{description}");
+            let key: SigningKey = match scheme {
+                SignatureScheme::Ed25519 => {
+                    ed25519_dalek::SigningKey::from_bytes(&secret.try_into().unwrap()).into()
+                }
+                SignatureScheme::Secp256k1 => {
+                    k256::ecdsa::SigningKey::from_slice(&secret).unwrap().into()
+                }
+                SignatureScheme::Ed25519ph => unreachable!("checked in tests/ed25519ph.rs"),
+            };
+            let public = key.verifying_key();
+            assert_eq!(public.to_bytes(), field("public_key"), "{description}");
+            assert_eq!(
+                VerifyingKey::from_bytes(scheme, &field("public_key")).unwrap(),
+                public
+            );
+
+            assert_eq!(key.sign(&message), signature, "{description}");
+            assert!(public.verify(&message, &signature), "{description}");
+            let mut tampered = message.clone();
+            tampered.push(0);
+            assert!(!public.verify(&tampered, &signature), "{description}");
+            checked += 1;
+        }
+    }
+    assert_eq!(checked, 3);
+}
+
+#[test]
+fn high_s_secp256k1_signature_rejected() {
+    let key = SigningKey::from(secp256k1_key(7));
+    let signature = key.sign(b"message");
+    let low = Signature::from_slice(&signature).unwrap();
+    let high = Signature::from_scalars(low.r().to_bytes(), (-*low.s()).to_bytes()).unwrap();
+
+    assert!(key.verifying_key().verify(b"message", &signature));
+    assert!(!key.verifying_key().verify(b"message", &high.to_bytes()));
+}
+
+#[tokio::test]
+async fn both_schemes_round_trip_through_the_client() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+
+    let mut ed = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+    let mut secp = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(secp256k1_key(1));
+    ed.send("demo", b"from ed25519").await.unwrap();
+    secp.send("demo", b"from secp256k1").await.unwrap();
+
+    let sent = node.sent();
+    assert_eq!(
+        SignatureScheme::of(&sent[0]),
+        Some(SignatureScheme::Ed25519)
+    );
+    assert_eq!(sent[0].pubkey.len(), 32);
+    assert_eq!(
+        SignatureScheme::of(&sent[1]),
+        Some(SignatureScheme::Secp256k1)
+    );
+    assert_eq!(sent[1].pubkey.len(), 33);
+
+    let receiver = Client::new(&endpoint).await.unwrap();
+    for envelope in &sent {
+        let decoded = Envelope::try_from_bytes(&envelope.encode_to_vec()).unwrap();
+        assert!(receiver.verify(&decoded).unwrap());
+    }
+
+    let keyring = Keyring::new();
+    keyring.insert(*secp256k1_key(1).verifying_key());
+    let receiver = receiver.with_keyring(keyring);
+    assert!(receiver.verify(&sent[1]).unwrap());
+    assert!(receiver.verify(&sent[0]).is_err());
+}
+
+#[tokio::test]
+async fn scheme_mismatch_rejected() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut secp = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(secp256k1_key(2));
+    secp.send("demo", b"hello").await.unwrap();
+    let secp_envelope = node.sent().remove(0);
+    let client = Client::new(&endpoint).await.unwrap();
+
+    // An Ed25519 envelope relabelled as secp256k1
+    let mut relabelled = signed_envelope(&signing_key(2), "demo", 1, b"hello");
+    relabelled.sig_scheme = SignatureScheme::Secp256k1.wire_value();
+    assert!(!client.verify(&relabelled).unwrap());
+
+    // A secp256k1 envelope relabelled as Ed25519
+    let mut relabelled = secp_envelope.clone();
+    relabelled.sig_scheme = SignatureScheme::Ed25519.wire_value();
+    assert!(!client.verify(&relabelled).unwrap());
+    assert!(Envelope::try_from_bytes(&relabelled.encode_to_vec()).is_err());
+
+    // An unknown scheme is never accepted
+    let mut unknown = secp_envelope.clone();
+    unknown.sig_scheme = 9;
+    assert_eq!(SignatureScheme::of(&unknown), None);
+    assert!(!client.verify(&unknown).unwrap());
+
+    // A trusted secp256k1 key does not vouch for its bytes under another scheme
+    let keyring = Keyring::new();
+    keyring.insert(*secp256k1_key(2).verifying_key());
+    let client = client.with_keyring(keyring);
+    assert!(client.verify(&secp_envelope).unwrap());
+    let mut relabelled = secp_envelope;
+    relabelled.sig_scheme = SignatureScheme::Ed25519.wire_value();
+    assert!(!client.verify(&relabelled).unwrap());
+}
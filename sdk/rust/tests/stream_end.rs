@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use futures::StreamExt;
+use securefabric_sdk::handler::{async_trait, MessageHandler, RunSummary};
+use securefabric_sdk::pb::Envelope;
+use securefabric_sdk::subscription::{EndReason, StreamEnd};
+use securefabric_sdk::Client;
+use tonic::Status;
+
+fn node_ending_with(reason: &'static str) -> MockNode {
+    let key = signing_key(1);
+    let node = MockNode::with_feed(vec![
+        signed_envelope(&key, "jobs", 1, b"a"),
+        signed_envelope(&key, "jobs", 2, b"b"),
+    ]);
+    *node.state.end_reason.lock().unwrap() = Some(reason);
+    node
+}
+
+#[tokio::test]
+async fn reports_eviction_and_does_not_reconnect() {
+    let node = node_ending_with("evicted");
+    let state = node.state.clone();
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let mut subscription = client.subscribe(b"jobs").await.unwrap();
+    let mut received = 0;
+    while let Some(item) = subscription.next().await {
+        item.unwrap();
+        assert_eq!(subscription.stream_end(), None);
+        received += 1;
+    }
+    assert_eq!(received, 2);
+    assert_eq!(
+        subscription.stream_end(),
+        Some(&StreamEnd {
+            reason: EndReason::Evicted
+        })
+    );
+    assert!(subscription.next().await.is_none());
+    assert_eq!(state.subscribe_requests.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn unknown_reasons_are_kept() {
+    let node = node_ending_with("maintenance");
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let mut subscription = client.subscribe(b"jobs").await.unwrap().typed();
+    while subscription.next().await.is_some() {}
+    let end = subscription.stream_end().unwrap();
+    assert_eq!(end.reason, EndReason::Other("maintenance".to_string()));
+    assert_eq!(end.reason.wire_name(), "maintenance");
+}
+
+#[tokio::test]
+async fn errors_and_plain_ends_have_no_reason() {
+    let node = MockNode::default();
+    *node.state.feed_error.lock().unwrap() = Some(Status::unavailable("node restarting"));
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+    let mut subscription = client.subscribe(b"jobs").await.unwrap();
+    assert!(subscription.next().await.unwrap().is_err());
+    assert!(subscription.next().await.is_none());
+    assert_eq!(subscription.stream_end(), None);
+
+    let endpoint = common::spawn(MockNode::default()).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+    let mut subscription = client.subscribe(b"jobs").await.unwrap();
+    assert!(subscription.next().await.is_none());
+    assert_eq!(subscription.stream_end(), None);
+}
+
+struct Accept;
+
+#[async_trait]
+impl MessageHandler for Accept {
+    async fn handle(&self, _envelope: Envelope) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn run_summary_carries_the_reason() {
+    let node = node_ending_with("rebalanced");
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let summary = client.run(b"jobs", Accept).await.unwrap();
+    assert_eq!(
+        summary,
+        RunSummary {
+            handled: 2,
+            end: Some(StreamEnd {
+                reason: EndReason::Rebalanced
+            }),
+            ..Default::default()
+        }
+    );
+}
@@ -0,0 +1,143 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use futures::StreamExt;
+use securefabric_sdk::capabilities::Feature;
+use securefabric_sdk::fabric::InMemoryFabric;
+use securefabric_sdk::{Client, Error};
+
+#[tokio::test]
+async fn merges_three_topics_into_one_stream() {
+    let fabric = InMemoryFabric::new();
+    let mut receiver = fabric.client().await.unwrap();
+    let subscription = receiver
+        .subscribe_many(&["alpha", "beta", "gamma"])
+        .await
+        .unwrap();
+    assert_eq!(subscription.topic(), b"alpha");
+    assert_eq!(
+        subscription.topics(),
+        [b"alpha".to_vec(), b"beta".to_vec(), b"gamma".to_vec()]
+    );
+
+    let mut sender = fabric
+        .client()
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+    for i in 0..3u8 {
+        for topic in ["alpha", "beta", "gamma", "other"] {
+            sender.send(topic, &[i]).await.unwrap();
+        }
+    }
+
+    let received: Vec<(String, Vec<u8>)> = subscription
+        .take(9)
+        .map(|item| {
+            let envelope = item.unwrap();
+            (envelope.topic, envelope.payload)
+        })
+        .collect()
+        .await;
+    for topic in ["alpha", "beta", "gamma"] {
+        let payloads: Vec<&[u8]> = received
+            .iter()
+            .filter(|(t, _)| t == topic)
+            .map(|(_, payload)| payload.as_slice())
+            .collect();
+        assert_eq!(payloads, [[0], [1], [2]], "{topic}");
+        let stats = receiver.subscription_stats(topic.as_bytes()).unwrap();
+        assert_eq!(stats.received, 3);
+    }
+    assert!(received.iter().all(|(topic, _)| topic != "other"));
+}
+
+#[tokio::test]
+async fn sends_every_topic_in_one_request() {
+    let key = signing_key(1);
+    let node = MockNode::with_feed(vec![
+        signed_envelope(&key, "alpha", 1, b"a"),
+        signed_envelope(&key, "other", 2, b"o"),
+        signed_envelope(&key, "beta", 3, b"b"),
+    ]);
+    let state = node.state.clone();
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let subscription = client
+        .subscribe_many(&[b"alpha".as_slice(), b"beta"])
+        .await
+        .unwrap();
+    let topics: Vec<String> = subscription.map(|item| item.unwrap().topic).collect().await;
+    assert_eq!(topics, ["alpha", "beta"]);
+
+    let requests = state.subscribe_requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert!(requests[0].topic.is_empty());
+    assert_eq!(requests[0].topics, [b"alpha".to_vec(), b"beta".to_vec()]);
+}
+
+#[tokio::test]
+async fn in_memory_fabric_replays_each_topic_backlog() {
+    let fabric = InMemoryFabric::new();
+    let mut sender = fabric
+        .client()
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+    for i in 0..2u8 {
+        sender.send("alpha", &[i]).await.unwrap();
+        sender.send("beta", &[i]).await.unwrap();
+    }
+
+    let mut receiver = fabric.client().await.unwrap();
+    let subscription = receiver.subscribe_many(&["beta", "alpha"]).await.unwrap();
+    let received: Vec<(String, Vec<u8>)> = subscription
+        .take(4)
+        .map(|item| {
+            let envelope = item.unwrap();
+            (envelope.topic, envelope.payload)
+        })
+        .collect()
+        .await;
+    assert_eq!(
+        received,
+        [
+            ("beta".to_string(), vec![0]),
+            ("beta".to_string(), vec![1]),
+            ("alpha".to_string(), vec![0]),
+            ("alpha".to_string(), vec![1]),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn rejects_an_empty_topic_list() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let topics: [&str; 0] = [];
+    assert!(client.subscribe_many(&topics).await.is_err());
+}
+
+#[tokio::test]
+async fn unsupported_without_multi_topic() {
+    let node = MockNode::default().without("multi_topic");
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let error = client
+        .subscribe_many(&["alpha", "beta"])
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(
+        error.downcast_ref::<Error>(),
+        Some(&Error::Unsupported {
+            feature: Feature::MultiTopic
+        })
+    );
+}
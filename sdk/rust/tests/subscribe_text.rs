@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use futures::StreamExt;
+use securefabric_sdk::subscription::TextError;
+use securefabric_sdk::Client;
+
+#[tokio::test]
+async fn subscribe_text_yields_utf8_payloads_and_flags_invalid_ones() {
+    let key = signing_key(1);
+    let invalid = signed_envelope(&key, "chat", 2, b"caf\xc3");
+    let feed = vec![
+        signed_envelope(&key, "chat", 1, "héllo".as_bytes()),
+        invalid.clone(),
+        signed_envelope(&key, "chat", 3, b"still here"),
+    ];
+    let endpoint = common::spawn(MockNode::with_feed(feed)).await;
+    let mut client = Client::new(&endpoint).await.unwrap();
+
+    let items: Vec<_> = client
+        .subscribe_text(b"chat")
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    assert_eq!(items.len(), 3);
+
+    let (envelope, text) = items[0].as_ref().unwrap();
+    assert_eq!(text, "héllo");
+    assert_eq!(envelope.payload, "héllo".as_bytes());
+    assert!(client.verify(envelope).unwrap());
+
+    match &items[1] {
+        Err(TextError::NotUtf8 {
+            msg_id,
+            valid_up_to,
+        }) => {
+            assert_eq!(msg_id, &invalid.msg_id);
+            assert_eq!(*valid_up_to, 3);
+        }
+        other => panic!("expected NotUtf8, got {other:?}"),
+    }
+
+    // An invalid payload does not end the stream
+    assert_eq!(items[2].as_ref().unwrap().1, "still here");
+}
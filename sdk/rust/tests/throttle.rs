@@ -0,0 +1,116 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use futures::{StreamExt, TryStreamExt};
+use securefabric_sdk::subscription::EndReason;
+use securefabric_sdk::Client;
+use std::sync::atomic::Ordering;
+use tokio::time::Instant;
+
+const RATE: u32 = 20;
+const MESSAGES: u64 = 8;
+
+fn slow_node() -> MockNode {
+    let key = signing_key(1);
+    let feed = (1..=MESSAGES)
+        .map(|seq| signed_envelope(&key, "slow", seq, b"work"))
+        .collect();
+    MockNode::with_feed(feed)
+}
+
+/// Drain a throttled subscription, asserting it stays at or below [`RATE`]
+async fn assert_paced(client: &mut Client) {
+    let mut stream = client.subscribe_throttled(b"slow", RATE).await.unwrap();
+    let mut arrivals = Vec::new();
+    while let Some(envelope) = stream.next().await {
+        envelope.unwrap();
+        arrivals.push(Instant::now());
+    }
+    assert_eq!(arrivals.len(), MESSAGES as usize);
+
+    let elapsed = arrivals[arrivals.len() - 1] - arrivals[0];
+    let observed = (arrivals.len() - 1) as f64 / elapsed.as_secs_f64();
+    // Allow for timer granularity
+    assert!(
+        observed <= f64::from(RATE) * 1.05,
+        "observed {observed:.1} envelopes/s, requested {RATE}"
+    );
+}
+
+#[tokio::test]
+async fn node_paces_when_it_supports_rate_limits() {
+    let node = slow_node();
+    let state = node.state.clone();
+    let mut client = Client::new(common::spawn(node).await).await.unwrap();
+
+    assert_paced(&mut client).await;
+    let requests = state.subscribe_requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].max_rate, RATE);
+}
+
+#[tokio::test]
+async fn falls_back_to_client_pacing() {
+    // Capabilities already rule out node-side pacing
+    let node = slow_node().without("rate_limit");
+    let state = node.state.clone();
+    let mut client = Client::new(common::spawn(node).await).await.unwrap();
+    assert_paced(&mut client).await;
+    let max_rates: Vec<_> = state
+        .subscribe_requests
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|req| req.max_rate)
+        .collect();
+    assert_eq!(max_rates, [0]);
+
+    // A node that cannot say rejects the field and the SDK retries without it
+    let node = slow_node().without("rate_limit");
+    node.state
+        .capabilities_unimplemented
+        .store(true, Ordering::SeqCst);
+    let state = node.state.clone();
+    let mut client = Client::new(common::spawn(node).await).await.unwrap();
+    assert_paced(&mut client).await;
+    let max_rates: Vec<_> = state
+        .subscribe_requests
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|req| req.max_rate)
+        .collect();
+    assert_eq!(max_rates, [RATE, 0]);
+}
+
+#[tokio::test]
+async fn rejects_zero_rate() {
+    let mut client = Client::new(common::spawn(slow_node()).await).await.unwrap();
+    assert!(client.subscribe_throttled(b"slow", 0).await.is_err());
+}
+
+#[tokio::test]
+async fn throttled_subscription_keeps_stream_end_and_typed_errors() {
+    let node = slow_node();
+    *node.state.end_reason.lock().unwrap() = Some("evicted");
+    let mut client = Client::new(common::spawn(node).await).await.unwrap();
+
+    let mut subscription = client.subscribe_throttled(b"slow", 1000).await.unwrap();
+    while let Some(envelope) = subscription.next().await {
+        envelope.unwrap();
+    }
+    assert_eq!(
+        subscription.stream_end().map(|end| &end.reason),
+        Some(&EndReason::Evicted)
+    );
+
+    let typed = client
+        .subscribe_throttled(b"slow", 1000)
+        .await
+        .unwrap()
+        .typed();
+    let envelopes: Vec<_> = typed.try_collect().await.unwrap();
+    assert_eq!(envelopes.len(), MESSAGES as usize);
+}
@@ -0,0 +1,35 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode, TestPki};
+use securefabric_sdk::Client;
+
+#[tokio::test]
+async fn server_auth_tls_without_client_certificate() {
+    let pki = TestPki::generate();
+    let node = MockNode::default();
+    let endpoint = common::spawn_tls(node.clone(), &pki).await;
+
+    let mut client = Client::with_tls(&endpoint, Some(pki.ca_pem.as_bytes()), "localhost")
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_bearer("YOUR_TOKEN_HERE");
+
+    let msg_id = client.send("tls", b"hello").await.unwrap();
+    assert_eq!(node.sent()[0].msg_id, msg_id);
+}
+
+#[tokio::test]
+async fn server_auth_tls_rejects_untrusted_ca() {
+    let pki = TestPki::generate();
+    let endpoint = common::spawn_tls(MockNode::default(), &pki).await;
+
+    let other = TestPki::generate();
+    assert!(
+        Client::with_tls(&endpoint, Some(other.ca_pem.as_bytes()), "localhost")
+            .await
+            .is_err()
+    );
+}
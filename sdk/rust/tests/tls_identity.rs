@@ -0,0 +1,71 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::TestPki;
+use securefabric_sdk::tls::{validate_identity, IdentityError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[test]
+fn matching_identity_reports_certificate_details() {
+    let pki = TestPki::generate();
+    let info = validate_identity(&pki.client_cert_pem, &pki.client_key_pem, &pki.ca_pem).unwrap();
+
+    assert_eq!(info.chain_len, 1);
+    assert!(info.subject.starts_with("CN="), "{}", info.subject);
+    assert!(info.issuer.starts_with("CN="), "{}", info.issuer);
+    assert!(info.not_before <= SystemTime::now());
+    assert!(info.not_after > SystemTime::now());
+}
+
+#[test]
+fn mismatched_key_is_rejected() {
+    let pki = TestPki::generate();
+    assert_eq!(
+        validate_identity(&pki.client_cert_pem, &pki.server_key_pem, &pki.ca_pem),
+        Err(IdentityError::KeyMismatch)
+    );
+}
+
+#[test]
+fn expired_certificate_is_rejected() {
+    let ca_key = rcgen::KeyPair::generate().unwrap();
+    let mut ca_params = rcgen::CertificateParams::new(Vec::new()).unwrap();
+    ca_params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+    let ca = ca_params.self_signed(&ca_key).unwrap();
+
+    let key = rcgen::KeyPair::generate().unwrap();
+    let mut params = rcgen::CertificateParams::new(vec!["client".to_string()]).unwrap();
+    params.not_before = rcgen::date_time_ymd(2020, 1, 1);
+    params.not_after = rcgen::date_time_ymd(2021, 1, 1);
+    let cert = params.signed_by(&key, &ca, &ca_key).unwrap();
+
+    assert_eq!(
+        validate_identity(cert.pem(), key.serialize_pem(), ca.pem()),
+        Err(IdentityError::Expired {
+            not_after: UNIX_EPOCH + Duration::from_secs(1_609_459_200)
+        })
+    );
+}
+
+#[test]
+fn other_ca_and_missing_materials_are_rejected() {
+    let pki = TestPki::generate();
+    let other = TestPki::generate();
+    assert!(matches!(
+        validate_identity(&pki.client_cert_pem, &pki.client_key_pem, &other.ca_pem),
+        Err(IdentityError::Untrusted { .. })
+    ));
+    assert_eq!(
+        validate_identity(&pki.client_cert_pem, "", &pki.ca_pem),
+        Err(IdentityError::Missing {
+            what: "private key"
+        })
+    );
+    assert_eq!(
+        validate_identity(&pki.client_key_pem, &pki.client_key_pem, &pki.ca_pem),
+        Err(IdentityError::Missing {
+            what: "certificate"
+        })
+    );
+}
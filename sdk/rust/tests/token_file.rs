@@ -0,0 +1,119 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode};
+use securefabric_sdk::Client;
+
+#[tokio::test]
+async fn rotated_token_file_is_used_by_next_send() {
+    let path = std::env::temp_dir().join(format!("sf-token-{}", std::process::id()));
+    std::fs::write(&path, "token-v1\n").unwrap();
+
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_bearer_file(&path)
+        .unwrap();
+
+    client.send("auth", b"one").await.unwrap();
+    std::fs::write(&path, "token-v2-rotated\n").unwrap();
+    client.send("auth", b"two").await.unwrap();
+
+    // A briefly missing file keeps the last token
+    std::fs::remove_file(&path).unwrap();
+    client.send("auth", b"three").await.unwrap();
+
+    let seen = node.state.send_authorization.lock().unwrap().clone();
+    assert_eq!(
+        seen,
+        [
+            Some("Bearer token-v1".to_string()),
+            Some("Bearer token-v2-rotated".to_string()),
+            Some("Bearer token-v2-rotated".to_string()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn missing_token_file_is_an_error() {
+    let endpoint = common::spawn(MockNode::default()).await;
+    let path = std::env::temp_dir().join(format!("sf-token-missing-{}", std::process::id()));
+
+    let err = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_bearer_file(&path)
+        .err()
+        .unwrap();
+    let message = format!("{err:#}");
+    assert!(message.contains(&path.display().to_string()), "{message}");
+}
+
+#[tokio::test]
+async fn token_with_embedded_newline_is_rejected() {
+    let path = std::env::temp_dir().join(format!("sf-token-newline-{}", std::process::id()));
+    std::fs::write(&path, "token\nv1\n").unwrap();
+
+    let endpoint = common::spawn(MockNode::default()).await;
+    let err = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_bearer_file(&path)
+        .err()
+        .unwrap();
+    let message = format!("{err:#}");
+    assert!(message.contains("not a valid header value"), "{message}");
+    assert!(!message.contains("token\nv1"), "{message}");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn invalid_rotated_token_keeps_the_last_good_one() {
+    let path = std::env::temp_dir().join(format!("sf-token-rotate-bad-{}", std::process::id()));
+    std::fs::write(&path, "token-v1\n").unwrap();
+
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_bearer_file(&path)
+        .unwrap();
+
+    std::fs::write(&path, "token\nv2-broken\n").unwrap();
+    client.send("auth", b"one").await.unwrap();
+    client.send("auth", b"two").await.unwrap();
+    std::fs::write(&path, "token-v3\n").unwrap();
+    client.send("auth", b"three").await.unwrap();
+
+    let seen = node.state.send_authorization.lock().unwrap().clone();
+    assert_eq!(
+        seen,
+        [
+            Some("Bearer token-v1".to_string()),
+            Some("Bearer token-v1".to_string()),
+            Some("Bearer token-v3".to_string()),
+        ]
+    );
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn invalid_static_token_fails_requests() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1))
+        .with_bearer("token\nv1");
+
+    let err = client.send("auth", b"one").await.unwrap_err();
+    assert!(format!("{err:#}").contains("not a valid header value"));
+    assert!(node.state.sent.lock().unwrap().is_empty());
+}
@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode};
+use securefabric_sdk::topic::{TemplateError, TopicTemplate};
+use securefabric_sdk::Client;
+
+#[test]
+fn renders_placeholders() {
+    let template: TopicTemplate = "sensors.{id}.{kind}".parse().unwrap();
+    assert_eq!(template.placeholders().collect::<Vec<_>>(), ["id", "kind"]);
+    let topic = template.render(&[("kind", "temp"), ("id", "42")]).unwrap();
+    assert_eq!(topic.as_str(), "sensors.42.temp");
+
+    let template = TopicTemplate::parse("site-{site}.{site}-backup").unwrap();
+    assert_eq!(template.placeholders().count(), 1);
+    let topic = template.render(&[("site", "ljubljana")]).unwrap();
+    assert_eq!(topic.to_string(), "site-ljubljana.ljubljana-backup");
+
+    let fixed = TopicTemplate::parse("system.events.audit").unwrap();
+    assert_eq!(fixed.render(&[]).unwrap().as_str(), "system.events.audit");
+}
+
+#[test]
+fn missing_and_unknown_placeholders_rejected() {
+    let template = TopicTemplate::parse("sensors.{id}.temp").unwrap();
+    assert_eq!(
+        template.render(&[]),
+        Err(TemplateError::Missing {
+            name: "id".to_string()
+        })
+    );
+    assert_eq!(
+        template.render(&[("id", "1"), ("room", "2")]),
+        Err(TemplateError::Unknown {
+            name: "room".to_string()
+        })
+    );
+}
+
+#[test]
+fn values_cannot_inject_separators() {
+    let template = TopicTemplate::parse("sensors.{id}.temp").unwrap();
+    for value in ["1.humidity", ".", "", "{id}", "a}"] {
+        assert_eq!(
+            template.render(&[("id", value)]),
+            Err(TemplateError::InvalidValue {
+                name: "id".to_string(),
+                value: value.to_string()
+            }),
+            "{value:?}"
+        );
+    }
+}
+
+#[test]
+fn malformed_templates_rejected() {
+    for (template, offset) in [
+        ("sensors.{id", 8),
+        ("sensors.{}.temp", 8),
+        ("sensors.id}.temp", 10),
+        ("sensors.{a{b}.temp", 8),
+    ] {
+        assert!(
+            matches!(
+                TopicTemplate::parse(template),
+                Err(TemplateError::Malformed { offset: at, .. }) if at == offset
+            ),
+            "{template}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn send_templated_sends_to_rendered_topic() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut client = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+    let template = TopicTemplate::parse("sensors.{id}.temp").unwrap();
+
+    client
+        .send_templated(&template, &[("id", "7")], b"21.5")
+        .await
+        .unwrap();
+    let err = client
+        .send_templated(&template, &[("id", "7.x")], b"21.5")
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<TemplateError>(),
+        Some(TemplateError::InvalidValue { .. })
+    ));
+
+    let sent = node.sent();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].topic, "sensors.7.temp");
+}
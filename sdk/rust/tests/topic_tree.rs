@@ -0,0 +1,83 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use securefabric_sdk::topic_tree::TopicTree;
+
+fn tree() -> TopicTree {
+    [
+        ("sensors.kitchen.temp", 10),
+        ("sensors.kitchen.humidity", 5),
+        ("sensors.hall.temp", 7),
+        ("sensors", 1),
+        ("alerts", 3),
+    ]
+    .into_iter()
+    .collect()
+}
+
+#[test]
+fn groups_topics_by_segment() {
+    let tree = tree();
+    let roots: Vec<_> = tree.root().children().map(|node| node.segment()).collect();
+    assert_eq!(roots, ["alerts", "sensors"]);
+
+    let sensors = tree.get("sensors").unwrap();
+    assert!(sensors.is_topic());
+    let rooms: Vec<_> = sensors.children().map(|node| node.path()).collect();
+    assert_eq!(rooms, ["sensors.hall", "sensors.kitchen"]);
+
+    let kitchen = tree.get("sensors.kitchen").unwrap();
+    assert!(!kitchen.is_topic());
+    assert_eq!(kitchen.segment(), "kitchen");
+    assert_eq!(
+        kitchen.child("temp").map(|node| node.path()),
+        Some("sensors.kitchen.temp")
+    );
+    assert!(tree.get("sensors.garage").is_none());
+    assert!(tree.get("sensors.kitchen.temp.max").is_none());
+}
+
+#[test]
+fn aggregates_counts_per_subtree() {
+    let tree = tree();
+    assert_eq!(tree.root().topic_count(), 5);
+    assert_eq!(tree.root().total_messages(), 26);
+
+    let sensors = tree.get("sensors").unwrap();
+    assert_eq!(sensors.messages(), 1);
+    assert_eq!(sensors.total_messages(), 23);
+    assert_eq!(sensors.topic_count(), 4);
+
+    let kitchen = tree.get("sensors.kitchen").unwrap();
+    assert_eq!(kitchen.messages(), 0);
+    assert_eq!(kitchen.total_messages(), 15);
+    assert_eq!(kitchen.topic_count(), 2);
+
+    // Repeated topics add up without counting twice
+    let mut tree = tree.clone();
+    tree.insert("alerts", 2);
+    let alerts = tree.get("alerts").unwrap();
+    assert_eq!((alerts.messages(), alerts.topic_count()), (5, 1));
+    assert_eq!(tree.root().topic_count(), 5);
+    assert_eq!(tree.root().total_messages(), 28);
+}
+
+#[test]
+fn iterates_depth_first_in_segment_order() {
+    let tree = tree();
+    let paths: Vec<_> = tree.iter().map(|node| node.path()).collect();
+    assert_eq!(
+        paths,
+        [
+            "alerts",
+            "sensors",
+            "sensors.hall",
+            "sensors.hall.temp",
+            "sensors.kitchen",
+            "sensors.kitchen.humidity",
+            "sensors.kitchen.temp",
+        ]
+    );
+    let topics: Vec<_> = tree.topics().map(|node| node.path()).collect();
+    assert_eq!(topics.len(), 5);
+    assert!(!topics.contains(&"sensors.kitchen"));
+}
@@ -0,0 +1,77 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode};
+use securefabric_sdk::Client;
+use std::io::Write;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+fn payloads(node: &MockNode) -> Vec<Vec<u8>> {
+    node.sent().into_iter().map(|e| e.payload).collect()
+}
+
+#[tokio::test]
+async fn publishes_each_written_line() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+
+    let mut writer = client.topic_writer("logs");
+    writeln!(writer, "one").unwrap();
+    writer.write_all(b"two\r\nthr").unwrap();
+    writer.write_all(b"ee\n\nfour").unwrap();
+    assert_eq!(writer.finish().await.unwrap(), 5);
+
+    assert_eq!(
+        payloads(&node),
+        [&b"one"[..], b"two", b"three", b"", b"four"]
+    );
+    assert!(node.sent().iter().all(|e| e.topic == "logs"));
+}
+
+#[tokio::test]
+async fn dropping_the_writer_publishes_the_partial_line() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+
+    let mut writer = client.topic_writer("logs");
+    writer.write_all(b"complete\npartial").unwrap();
+    drop(writer);
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while node.sent().len() < 2 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(payloads(&node), [&b"complete"[..], b"partial"]);
+}
+
+#[tokio::test]
+async fn async_writer_publishes_lines_on_flush() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+
+    let mut writer = client.async_topic_writer("logs");
+    writer.write_all(b"one\ntwo\nthree").await.unwrap();
+    writer.flush().await.unwrap();
+    assert_eq!(payloads(&node), [&b"one"[..], b"two", b"three"]);
+
+    writer.write_all(b"four\n").await.unwrap();
+    writer.shutdown().await.unwrap();
+    assert_eq!(payloads(&node).len(), 4);
+}
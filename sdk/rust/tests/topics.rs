@@ -0,0 +1,51 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::MockNode;
+use securefabric_sdk::{Client, Error};
+
+const INVALID: &[u8] = b"caf\xe9";
+
+#[tokio::test]
+async fn strict_mode_accepts_utf8_and_rejects_invalid_bytes() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut client = Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_strict_utf8_topics(true);
+
+    let subscription = client.subscribe("café".as_bytes()).await.unwrap();
+    assert_eq!(subscription.topic_str(), Some("café"));
+    client.ack("café".as_bytes(), Vec::new()).await.unwrap();
+
+    let err = client.subscribe(INVALID).await.err().unwrap();
+    assert_eq!(
+        err.downcast_ref::<Error>(),
+        Some(&Error::InvalidTopic { valid_up_to: 3 })
+    );
+    let err = client.ack(INVALID, Vec::new()).await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::InvalidTopic { .. })
+    ));
+
+    // Rejected before reaching the node
+    assert_eq!(node.state.subscribe_requests.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn raw_byte_topics_allowed_by_default() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let subscription = client.subscribe(INVALID).await.unwrap();
+    assert_eq!(subscription.topic(), INVALID);
+    assert_eq!(subscription.topic_str(), None);
+    assert_eq!(
+        node.state.subscribe_requests.lock().unwrap()[0].topic,
+        INVALID
+    );
+}
@@ -0,0 +1,82 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use futures::TryStreamExt;
+use securefabric_sdk::{Client, Error};
+use tonic::{Code, Status};
+
+#[tokio::test]
+async fn stream_errors_surface_as_sdk_errors() {
+    let key = signing_key(1);
+    let node = MockNode::with_feed(vec![
+        signed_envelope(&key, "events", 1, b"one"),
+        signed_envelope(&key, "events", 2, b"two"),
+    ]);
+    *node.state.feed_error.lock().unwrap() = Some(Status::permission_denied("topic revoked"));
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let mut subscription = client.subscribe_typed(b"events").await.unwrap();
+    assert_eq!(
+        subscription.try_next().await.unwrap().unwrap().payload,
+        b"one"
+    );
+    assert_eq!(
+        subscription.try_next().await.unwrap().unwrap().payload,
+        b"two"
+    );
+    let error: Error = subscription.try_next().await.unwrap_err();
+    assert_eq!(
+        error,
+        Error::Rpc {
+            code: Code::PermissionDenied,
+            message: "topic revoked".into()
+        }
+    );
+}
+
+#[tokio::test]
+async fn refused_subscriptions_fail_with_sdk_errors() {
+    let node = MockNode::default();
+    *node.state.subscribe_error.lock().unwrap() = Some(Status::unauthenticated("bad token"));
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let error = client
+        .subscribe_typed(b"events")
+        .await
+        .map(|_| ())
+        .unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<Error>(),
+        Some(&Error::Rpc {
+            code: Code::Unauthenticated,
+            message: "bad token".into()
+        })
+    );
+    assert!(error.downcast_ref::<Status>().is_none());
+}
+
+#[tokio::test]
+async fn typed_subscription_is_a_try_stream() {
+    let node = MockNode::default();
+    *node.state.feed_error.lock().unwrap() = Some(Status::unavailable("draining"));
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(endpoint).await.unwrap();
+
+    let envelopes: Result<Vec<_>, Error> = client
+        .subscribe_typed(b"events")
+        .await
+        .unwrap()
+        .try_collect()
+        .await;
+    assert!(matches!(
+        envelopes,
+        Err(Error::Rpc {
+            code: Code::Unavailable,
+            ..
+        })
+    ));
+}
@@ -0,0 +1,146 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use ed25519_dalek::Signer;
+use securefabric_sdk::keyring::Keyring;
+use securefabric_sdk::validate::ValidationReport;
+use securefabric_sdk::{msg_id, Client, Envelope, Error};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const VALID: ValidationReport = ValidationReport {
+    signature: true,
+    msg_id: true,
+    expired: false,
+    replayed: false,
+};
+
+fn fresh_envelope(seq: u64) -> Envelope {
+    let mut envelope = signed_envelope(&signing_key(1), "checks", seq, b"payload");
+    envelope.timestamp_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    envelope
+}
+
+async fn client() -> Client {
+    let endpoint = common::spawn(MockNode::default()).await;
+    Client::new(endpoint)
+        .await
+        .unwrap()
+        .with_max_envelope_age(Duration::from_secs(60))
+        .with_replay_window(16)
+}
+
+fn invalid_report(client: &Client, envelope: &Envelope) -> ValidationReport {
+    let err = client.validate_strict(envelope).unwrap_err();
+    match err.downcast_ref::<Error>() {
+        Some(Error::Invalid { report }) => *report,
+        other => panic!("unexpected error {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn intact_envelope_passes_every_check() {
+    let client = client().await;
+    let envelope = fresh_envelope(1);
+    assert_eq!(client.validate(&envelope), VALID);
+
+    let envelope = fresh_envelope(2);
+    client.validate_strict(&envelope).unwrap();
+}
+
+#[tokio::test]
+async fn each_failed_check_is_reported_on_its_own() {
+    let client = client().await;
+
+    let mut tampered = fresh_envelope(1);
+    tampered.payload = b"tampered".to_vec();
+    let expected = ValidationReport {
+        signature: false,
+        ..VALID
+    };
+    assert_eq!(client.validate(&tampered), expected);
+    assert_eq!(invalid_report(&client, &tampered), expected);
+
+    let mut wrong_id = fresh_envelope(2);
+    wrong_id.msg_id = "00".repeat(32);
+    let expected = ValidationReport {
+        msg_id: false,
+        ..VALID
+    };
+    assert_eq!(client.validate(&wrong_id), expected);
+    assert_eq!(invalid_report(&client, &wrong_id), expected);
+
+    let mut stale = fresh_envelope(3);
+    stale.timestamp_ms -= 120_000;
+    let expected = ValidationReport {
+        expired: true,
+        ..VALID
+    };
+    assert_eq!(client.validate(&stale), expected);
+    let mut undated = fresh_envelope(4);
+    undated.timestamp_ms = 0;
+    assert!(client.validate(&undated).expired);
+
+    let replay = fresh_envelope(5);
+    assert_eq!(client.validate(&replay), VALID);
+    let expected = ValidationReport {
+        replayed: true,
+        ..VALID
+    };
+    assert_eq!(client.validate(&replay), expected);
+    assert_eq!(invalid_report(&client, &replay), expected);
+    assert_eq!(invalid_report(&client, &replay).to_string(), "replayed");
+}
+
+#[tokio::test]
+async fn resequenced_copy_is_flagged_as_replayed() {
+    let client = client().await;
+    // A sender that leaves seq out of the signed AAD
+    let mut original = fresh_envelope(7);
+    original.aad = br#"{"key_version":0,"topic":"checks"}"#.to_vec();
+    original.sig = signing_key(1)
+        .sign(&[original.aad.as_slice(), &original.payload].concat())
+        .to_bytes()
+        .to_vec();
+    assert_eq!(client.validate(&original), VALID);
+
+    // A relay replays it under a new seq and a recomputed msg_id
+    let mut copy = original.clone();
+    copy.seq = 8;
+    copy.msg_id = msg_id::compute(&msg_id::Blake3, &copy);
+    assert_eq!(
+        client.validate(&copy),
+        ValidationReport {
+            replayed: true,
+            ..VALID
+        }
+    );
+}
+
+#[tokio::test]
+async fn optional_checks_are_off_by_default() {
+    let endpoint = common::spawn(MockNode::default()).await;
+    let client = Client::new(endpoint).await.unwrap();
+
+    // Fixture envelopes carry no timestamp
+    let envelope = signed_envelope(&signing_key(1), "checks", 1, b"payload");
+    assert_eq!(client.validate(&envelope), VALID);
+    assert_eq!(client.validate(&envelope), VALID);
+}
+
+#[tokio::test]
+async fn signature_errors_surface_from_strict_validation() {
+    let client = client().await.with_keyring(Keyring::new());
+    let envelope = fresh_envelope(1);
+
+    assert!(!client.validate(&envelope).signature);
+    let err = client.validate_strict(&envelope).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::UnknownSender { .. })
+    ));
+}
@@ -0,0 +1,69 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use securefabric_sdk::{Client, Error};
+
+async fn client() -> Client {
+    Client::new(common::spawn(MockNode::default()).await)
+        .await
+        .unwrap()
+}
+
+fn error(result: anyhow::Result<&[u8]>) -> Error {
+    result
+        .unwrap_err()
+        .downcast_ref::<Error>()
+        .cloned()
+        .expect("typed error")
+}
+
+#[tokio::test]
+async fn returns_the_payload_of_a_valid_envelope() {
+    let client = client().await;
+    let envelope = signed_envelope(&signing_key(1), "orders", 1, b"order #1");
+    assert_eq!(
+        client.verified_payload(&envelope).unwrap(),
+        b"order #1".as_slice()
+    );
+}
+
+#[tokio::test]
+async fn rejects_an_invalid_signature() {
+    let client = client().await;
+    let mut envelope = signed_envelope(&signing_key(1), "orders", 1, b"order #1");
+    envelope.payload = b"order #2".to_vec();
+    assert_eq!(
+        error(client.verified_payload(&envelope)),
+        Error::InvalidSignature {
+            msg_id: envelope.msg_id.clone()
+        }
+    );
+}
+
+#[tokio::test]
+async fn rejects_an_unsigned_envelope() {
+    let client = client().await;
+    let mut envelope = signed_envelope(&signing_key(1), "orders", 1, b"order #1");
+    envelope.sig.clear();
+    assert_eq!(
+        error(client.verified_payload(&envelope)),
+        Error::Unsigned {
+            msg_id: envelope.msg_id.clone()
+        }
+    );
+}
+
+#[tokio::test]
+async fn rejects_a_mismatched_msg_id() {
+    let client = client().await;
+    let mut envelope = signed_envelope(&signing_key(1), "orders", 1, b"order #1");
+    envelope.msg_id = "00".repeat(32);
+    assert_eq!(
+        error(client.verified_payload(&envelope)),
+        Error::InvalidMsgId {
+            msg_id: envelope.msg_id.clone()
+        }
+    );
+}
@@ -0,0 +1,84 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode};
+use k256::ecdsa::Signature;
+use securefabric_sdk::crypto::scheme::{SigningKey, VerifyingKey};
+use securefabric_sdk::crypto::VerifyMode;
+use securefabric_sdk::Client;
+
+/// The Ed25519 identity point, a small-order public key
+const IDENTITY: [u8; 32] = {
+    let mut point = [0u8; 32];
+    point[0] = 1;
+    point
+};
+
+#[tokio::test]
+async fn non_canonical_ed25519_signature_needs_permissive_mode() {
+    let endpoint = common::spawn(MockNode::default()).await;
+
+    // With a small-order key and R, the signature (R, 0) satisfies the
+    // cofactorless equation for every message
+    let mut envelope = common::signed_envelope(&signing_key(1), "interop", 1, b"anything");
+    envelope.pubkey = IDENTITY.to_vec();
+    envelope.sig = [IDENTITY, [0u8; 32]].concat();
+
+    let strict = Client::new(&endpoint).await.unwrap();
+    assert!(!strict.verify(&envelope).unwrap());
+
+    let permissive = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_verify_mode(VerifyMode::Permissive);
+    assert!(permissive.verify(&envelope).unwrap());
+    envelope.payload = b"something else".to_vec();
+    assert!(permissive.verify(&envelope).unwrap());
+}
+
+#[tokio::test]
+async fn high_s_secp256k1_signature_needs_permissive_mode() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+    let mut sender = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_signing_key(k256::ecdsa::SigningKey::from_slice(&[7; 32]).unwrap());
+    sender.send("interop", b"hello").await.unwrap();
+
+    let mut envelope = node.sent().remove(0);
+    let low = Signature::from_slice(&envelope.sig).unwrap();
+    let high = Signature::from_scalars(low.r().to_bytes(), (-*low.s()).to_bytes()).unwrap();
+    envelope.sig = high.to_bytes().to_vec();
+
+    let strict = Client::new(&endpoint).await.unwrap();
+    assert!(!strict.verify(&envelope).unwrap());
+
+    let permissive = Client::new(&endpoint)
+        .await
+        .unwrap()
+        .with_verify_mode(VerifyMode::Permissive);
+    assert!(permissive.verify(&envelope).unwrap());
+
+    // Permissive mode still rejects signatures that do not verify at all
+    envelope.payload = b"tampered".to_vec();
+    assert!(!permissive.verify(&envelope).unwrap());
+}
+
+#[test]
+fn verify_defaults_to_strict() {
+    let key = SigningKey::from(signing_key(3));
+    let signature = key.sign(b"message");
+    let vk: VerifyingKey = key.verifying_key();
+
+    for mode in [VerifyMode::Strict, VerifyMode::Permissive] {
+        assert!(vk.verify_with_mode(b"message", &signature, mode));
+    }
+    assert_eq!(VerifyMode::default(), VerifyMode::Strict);
+
+    let weak = VerifyingKey::from(ed25519_dalek::VerifyingKey::from_bytes(&IDENTITY).unwrap());
+    let forged = [IDENTITY, [0u8; 32]].concat();
+    assert!(!weak.verify(b"message", &forged));
+    assert!(weak.verify_with_mode(b"message", &forged, VerifyMode::Permissive));
+}
@@ -0,0 +1,53 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signing_key, MockNode};
+use securefabric_sdk::Client;
+use std::sync::atomic::Ordering;
+
+#[tokio::test]
+async fn warmup_establishes_lazy_channel() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+
+    let mut client = Client::builder(endpoint)
+        .connect_lazy(true)
+        .build()
+        .await
+        .unwrap()
+        .with_signing_key(signing_key(1));
+    tokio::task::yield_now().await;
+    assert_eq!(node.state.connections.load(Ordering::SeqCst), 0);
+
+    client.warmup().await.unwrap();
+    assert_eq!(node.state.connections.load(Ordering::SeqCst), 1);
+    assert_eq!(node.state.pings.load(Ordering::SeqCst), 1);
+
+    // The first send reuses the warmed connection
+    client.send("demo", b"hello").await.unwrap();
+    assert_eq!(node.state.connections.load(Ordering::SeqCst), 1);
+    assert_eq!(node.sent().len(), 1);
+}
+
+#[tokio::test]
+async fn builder_warms_up_during_build() {
+    let node = MockNode::default();
+    let endpoint = common::spawn(node.clone()).await;
+
+    let _client = Client::builder(endpoint)
+        .connect_lazy(true)
+        .warmup(true)
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(node.state.connections.load(Ordering::SeqCst), 1);
+    assert_eq!(node.state.pings.load(Ordering::SeqCst), 1);
+
+    let dead = Client::builder("http://127.0.0.1:1")
+        .connect_lazy(true)
+        .warmup(true)
+        .build()
+        .await;
+    assert!(dead.is_err());
+}
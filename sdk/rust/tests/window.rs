@@ -0,0 +1,100 @@
+// SPDX-License-Identifier: Apache-2.0
+
+mod common;
+
+use common::{signed_envelope, signing_key, MockNode};
+use futures::StreamExt;
+use securefabric_sdk::window::WindowError;
+use securefabric_sdk::Client;
+use std::time::{Duration, Instant};
+
+const LONG: Duration = Duration::from_secs(30);
+
+fn seqs(batch: &[securefabric_sdk::Envelope]) -> Vec<u64> {
+    batch.iter().map(|envelope| envelope.seq).collect()
+}
+
+#[tokio::test]
+async fn flushes_on_count_with_partial_final_batch() {
+    let key = signing_key(1);
+    let feed = (1..=5)
+        .map(|seq| signed_envelope(&key, "demo", seq, b"hello"))
+        .collect();
+    let endpoint = common::spawn(MockNode::with_feed(feed)).await;
+    let mut client = Client::new(&endpoint).await.unwrap();
+
+    let started = Instant::now();
+    let batches: Vec<Vec<u64>> = client
+        .subscribe_windowed(b"demo", 2, LONG)
+        .await
+        .unwrap()
+        .map(|batch| seqs(&batch.unwrap()))
+        .collect()
+        .await;
+    assert_eq!(batches, vec![vec![1, 2], vec![3, 4], vec![5]]);
+    assert!(started.elapsed() < LONG, "final batch waited for the timer");
+}
+
+#[tokio::test]
+async fn flushes_on_delay_from_first_envelope() {
+    let key = signing_key(2);
+    let node = MockNode::default();
+    let live = node.live_feed();
+    let endpoint = common::spawn(node).await;
+    let mut client = Client::new(&endpoint).await.unwrap();
+    let delay = Duration::from_millis(100);
+    let mut windows = client
+        .subscribe_windowed(b"demo", 100, delay)
+        .await
+        .unwrap();
+
+    live.send(signed_envelope(&key, "demo", 1, b"a")).unwrap();
+    live.send(signed_envelope(&key, "demo", 2, b"b")).unwrap();
+    let started = Instant::now();
+    let batch = windows.next().await.unwrap().unwrap();
+    assert_eq!(seqs(&batch), vec![1, 2]);
+    assert!(started.elapsed() >= delay - Duration::from_millis(20));
+
+    // Nothing is flushed while no envelope is pending
+    assert!(tokio::time::timeout(delay * 3, windows.next())
+        .await
+        .is_err());
+
+    live.send(signed_envelope(&key, "demo", 3, b"c")).unwrap();
+    drop(live);
+    let batch = windows.next().await.unwrap().unwrap();
+    assert_eq!(seqs(&batch), vec![3]);
+    assert!(windows.next().await.is_none());
+}
+
+#[tokio::test]
+async fn invalid_envelopes_are_reported_outside_batches() {
+    let key = signing_key(3);
+    let mut forged = signed_envelope(&key, "demo", 2, b"real");
+    forged.payload = b"forged".to_vec();
+    let feed = vec![
+        signed_envelope(&key, "demo", 1, b"one"),
+        forged,
+        signed_envelope(&key, "demo", 3, b"three"),
+    ];
+    let endpoint = common::spawn(MockNode::with_feed(feed)).await;
+    let mut client = Client::new(&endpoint).await.unwrap();
+
+    let mut windows = client.subscribe_windowed(b"demo", 2, LONG).await.unwrap();
+    assert!(matches!(
+        windows.next().await.unwrap(),
+        Err(WindowError::BadSignature { .. })
+    ));
+    assert_eq!(seqs(&windows.next().await.unwrap().unwrap()), vec![1, 3]);
+    assert!(windows.next().await.is_none());
+}
+
+#[tokio::test]
+async fn zero_window_size_rejected() {
+    let endpoint = common::spawn(MockNode::default()).await;
+    let mut client = Client::new(&endpoint).await.unwrap();
+    assert!(client
+        .subscribe_windowed(b"demo", 0, Duration::from_millis(10))
+        .await
+        .is_err());
+}